@@ -0,0 +1,57 @@
+use crossbeam_queue::SegQueue;
+use rayon::iter::*;
+use std::time::SystemTime;
+use xarc::collections::Queue;
+
+#[cfg(not(target_os = "windows"))]
+#[global_allocator]
+static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
+
+fn main() {
+    let block_size = 512;
+    let num_blocks = 512;
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for i in 0..num_blocks {
+        ranges.push((i * block_size, (i + 1) * block_size));
+    }
+
+    let cqueue = SegQueue::new();
+
+    let c0 = SystemTime::now();
+    ranges.par_iter().for_each(|(begin, end)| {
+        for i in *begin..*end {
+            cqueue.push(i);
+        }
+    });
+    let c1 = SystemTime::now();
+    ranges.par_iter().for_each(|(begin, end)| {
+        for _ in *begin..*end {
+            let _ = cqueue.pop().unwrap();
+        }
+    });
+    let c2 = SystemTime::now();
+
+    let queue = Queue::new();
+
+    let t0 = SystemTime::now();
+    ranges.par_iter().for_each(|(begin, end)| {
+        for i in *begin..*end {
+            queue.push(i);
+        }
+    });
+    let t1 = SystemTime::now();
+    ranges.par_iter().for_each(|(begin, end)| {
+        for _ in *begin..*end {
+            let _ = queue.try_pop().unwrap();
+        }
+    });
+    let t2 = SystemTime::now();
+
+    assert!(queue.is_empty());
+
+    println!("Crossbeam Push Time: {} µs\r\nCrossbeam Pop Time: {} µs\r\nPush Time: {} µs\r\nPop Time: {} µs",
+        c1.duration_since(c0).unwrap().as_micros(),
+        c2.duration_since(c1).unwrap().as_micros(),
+        t1.duration_since(t0).unwrap().as_micros(),
+        t2.duration_since(t1).unwrap().as_micros());
+}
@@ -0,0 +1,38 @@
+use rayon::iter::*;
+use std::time::SystemTime;
+use xarc::collections::Stack;
+
+#[cfg(not(target_os = "windows"))]
+#[global_allocator]
+static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
+
+fn main() {
+    let block_size = 512;
+    let num_blocks = 512;
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for i in 0..num_blocks {
+        ranges.push((i * block_size, (i + 1) * block_size));
+    }
+
+    let stack = Stack::new();
+
+    let t0 = SystemTime::now();
+    ranges.par_iter().for_each(|(begin, end)| {
+        for i in *begin..*end {
+            stack.push(i);
+        }
+    });
+    let t1 = SystemTime::now();
+    ranges.par_iter().for_each(|(begin, end)| {
+        for _ in *begin..*end {
+            let _ = stack.try_pop().unwrap();
+        }
+    });
+    let t2 = SystemTime::now();
+
+    assert!(stack.is_empty());
+
+    println!("Push Time: {} µs\r\nPop Time: {} µs",
+      t1.duration_since(t0).unwrap().as_micros(),
+      t2.duration_since(t1).unwrap().as_micros());
+}
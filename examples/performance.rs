@@ -6,6 +6,7 @@ use xarc::{AtomicXarc, Xarc};
 fn main() {
     xarc_st_performance_test();
     xarc_mt_performance_test();
+    xarc_cas_loop_performance_test();
 }
 
 fn xarc_st_performance_test() {
@@ -66,3 +67,32 @@ fn xarc_mt_performance_test() {
       t1.duration_since(t0).unwrap().as_micros(),
       t2.duration_since(t1).unwrap().as_micros());
 }
+
+fn xarc_cas_loop_performance_test() {
+    println!("xarc_cas_loop_performance_test:");
+
+    let shared = AtomicXarc::new(42);
+
+    let mut values: Vec<i64> = Vec::new();
+    for i in 0..500000 {
+        values.push(i);
+    }
+
+    let t0 = SystemTime::now();
+    values.iter().for_each(|x| {
+        let _guard = pin();
+        let new = Xarc::new(*x);
+        let _ = shared.cas_loop(|_| Some(new.clone()), Ordering::AcqRel, Ordering::Acquire);
+    });
+    let t1 = SystemTime::now();
+    values.par_iter().for_each(|x| {
+        let _guard = pin();
+        let new = Xarc::new(*x);
+        let _ = shared.cas_loop(|_| Some(new.clone()), Ordering::AcqRel, Ordering::Acquire);
+    });
+    let t2 = SystemTime::now();
+
+    println!("Sequential Time: {} µs\r\nParallel Time: {} µs",
+      t1.duration_since(t0).unwrap().as_micros(),
+      t2.duration_since(t1).unwrap().as_micros());
+}
@@ -2,15 +2,15 @@ use crossbeam_epoch::pin;
 use crossbeam_queue::SegQueue;
 use crossbeam_utils::Backoff;
 use rayon::iter::*;
-use std::{cell::UnsafeCell, mem, sync::atomic::Ordering, time::SystemTime};
-use xarc::{AtomicXarc, Xarc};
+use std::{sync::atomic::Ordering, time::SystemTime};
+use xarc::{AtomicXarc, Takeable, Xarc};
 
 #[cfg(not(target_os = "windows"))]
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
 struct Node<T: Send> {
-    value: AtomicXarc<UnsafeCell<Option<T>>>,
+    value: AtomicXarc<Takeable<T>>,
     next: AtomicXarc<Node<T>>,
 }
 
@@ -40,7 +40,7 @@ impl<T: Send> Queue<T> {
     pub fn push(&self, value: T) {
         let _guard = pin();
         let backoff = Backoff::new();
-        let value = Xarc::new(UnsafeCell::new(Some(value)));
+        let value = Xarc::new(Takeable::new(value));
         let mut new_tail = Xarc::new(Node::null());
         let mut current_tail = self.tail.load(Ordering::Relaxed);
         loop {
@@ -83,9 +83,7 @@ impl<T: Send> Queue<T> {
                 }
             }
             match self.head.compare_exchange(&current_head, &next, Ordering::Release, Ordering::Relaxed) {
-                Ok(_) => unsafe {
-                    return mem::take(&mut *current_head.maybe_deref().unwrap().value.load(Ordering::Acquire).maybe_deref().unwrap().get())
-                },
+                Ok(_) => return current_head.maybe_deref().unwrap().value.load(Ordering::Acquire).maybe_deref().unwrap().take(),
                 Err(head) => {
                     current_head = head;
                     backoff.spin();
@@ -146,7 +144,7 @@ fn main() {
     });
     let t2 = SystemTime::now();
 
-    assert_eq!(queue.is_empty(), true);
+    assert!(queue.is_empty());
 
     println!("Crossbeam Push Time: {} µs\r\nCrossbeam Pop Time: {} µs\r\nPush Time: {} µs\r\nPop Time: {} µs",
         c1.duration_since(c0).unwrap().as_micros(),
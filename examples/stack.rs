@@ -1,22 +1,22 @@
 use crossbeam_epoch::pin;
 use crossbeam_utils::Backoff;
 use rayon::iter::*;
-use std::{cell::UnsafeCell, mem, sync::atomic::Ordering, time::SystemTime};
-use xarc::{AtomicXarc, Xarc};
+use std::{sync::atomic::Ordering, time::SystemTime};
+use xarc::{AtomicXarc, Takeable, Xarc};
 
 #[cfg(not(target_os = "windows"))]
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
 struct Node<T: Send> {
-    value: UnsafeCell<Option<T>>,
+    value: Takeable<T>,
     next: Xarc<Node<T>>,
 }
 
 impl<T: Send> Node<T> {
     fn new(value: T, next: Xarc<Node<T>>) -> Self {
         Self {
-            value: UnsafeCell::new(Some(value)),
+            value: Takeable::new(value),
             next,
         }
     }
@@ -71,9 +71,7 @@ impl<T: Send> Stack<T> {
                 },
             }
         }
-        unsafe {
-            mem::take(&mut *current.maybe_deref().unwrap().value.get())
-        }
+        current.maybe_deref().unwrap().value.take()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -106,7 +104,7 @@ fn main() {
     });
     let t2 = SystemTime::now();
 
-    assert_eq!(stack.is_empty(), true);
+    assert!(stack.is_empty());
 
     println!("Push Time: {} µs\r\nPop Time: {} µs",
       t1.duration_since(t0).unwrap().as_micros(),
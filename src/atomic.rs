@@ -1,8 +1,11 @@
-use super::{internal::*, pointer::*};
+use super::{cow_write::CowWriteGuard, dump::DebugState, error::AllocError, help, internal::*, mcas, order::*, ordering, pointee::*, pointer::*, repin::pin, strategy::{Eager, Strategy}};
 use alloc::boxed::Box;
-use core::{ptr, sync::atomic::{AtomicPtr, Ordering}};
-use crossbeam_epoch::pin;
-use crossbeam_utils::{Backoff, CachePadded};
+use core::{marker::PhantomData, mem, ptr, sync::atomic::{AtomicPtr, Ordering}};
+use crossbeam_utils::{Backoff, CachePadded, atomic::AtomicConsume};
+#[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+use super::error::WaitTimeoutError;
+#[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+use std::time::{Duration, Instant};
 
 /// `AtomicXarc` provides atomic storage for `Xarc` atomically refcounted smart pointers.
 /// 
@@ -23,32 +26,205 @@ use crossbeam_utils::{Backoff, CachePadded};
 /// assert_eq!(*atomic.compare_exchange(&same, &Xarc::null(), Ordering::AcqRel, Ordering::Acquire)
 ///             .unwrap().maybe_deref().unwrap(), 42);
 /// ```
+/// 
+/// The `Strategy` used to protect loads can be chosen with the second type parameter. See `ReadMostly`.
 #[derive(Debug)]
-pub struct AtomicXarc<T: Send> {
-    pub(crate) ptr: CachePadded<AtomicPtr<XarcData<T>>>,
+pub struct AtomicXarc<T: ?Sized + Send + Pointee, S: Strategy = Eager> {
+    pub(crate) ptr: CachePadded<AtomicPtr<XarcHeader>>,
+    phantom: PhantomData<(Xarc<T>, S)>,
 }
 
 impl<T: Send> AtomicXarc<T> {
     /// Initialize the atomic smart pointer with `value`.
     #[must_use]
     pub fn new(value: T) -> Self {
-        AtomicXarc {
-            ptr: CachePadded::new(AtomicPtr::new(Box::into_raw(Box::new(XarcData::new(value))))),
+        AtomicXarc::init(thin(Box::into_raw(Box::new(XarcData::new(value)))))
+    }
+
+    /// Initialize the atomic smart pointer with `value`, returning an error rather than aborting if allocation fails.
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        XarcData::new(value).try_allocate().map(|ptr| AtomicXarc::init(thin(ptr)))
+    }
+
+    /// Initialize the atomic smart pointer with `value` and a hook to run when it is reclaimed.
+    /// See `Xarc::with_on_reclaim`.
+    #[must_use]
+    pub fn with_on_reclaim(value: T, on_reclaim: fn(T)) -> Self {
+        AtomicXarc::init(thin(Box::into_raw(Box::new(XarcData::with_on_reclaim(value, on_reclaim)))))
+    }
+}
+
+impl<T: Send, S: Strategy> AtomicXarc<T, S> {
+    /// Load the address of the current value, or null, without taking a reference.
+    /// 
+    /// The value may be reclaimed as soon as it is replaced, so the result must never be dereferenced.
+    /// It is only good for identity comparisons, such as with `Xarc::as_raw` to check whether a cached value is still current.
+    /// Beware that a reclaimed address can be reused by a new value.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let config = AtomicXarc::new(42);
+    /// let cached = config.load(Ordering::Acquire);
+    /// assert_eq!(config.load_raw(Ordering::Relaxed), cached.as_raw());
+    /// let _ = config.swap(&Xarc::new(43), Ordering::AcqRel);
+    /// assert_ne!(config.load_raw(Ordering::Relaxed), cached.as_raw());
+    /// ```
+    #[must_use]
+    pub fn load_raw(&self, order: Ordering) -> *const T {
+        value_ptr(self.current(ordering::load(order)))
+    }
+
+    /// As an atomic operation, take the contents of `self`, leaving it null,
+    /// and move the value out if that was the last reference to it.
+    /// Otherwise, including when `self` was null, the reference is returned as an error.
+    /// Values with a hook to run on reclamation, or allocated from an `XarcPool`, are always returned as a reference.
+    ///
+    /// Taking the value blocks until every thread that was pinned at the time has unpinned,
+    /// since it may still be reading the value through `with`. Avoid it while other threads stay pinned for long.
+    ///
+    /// # Panics
+    /// - If the thread is pinned, in debug builds, since the wait would never finish.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::AtomicXarc;
+    ///
+    /// let slot = AtomicXarc::new(vec![1, 2, 3]);
+    /// assert_eq!(slot.load_owned(Ordering::AcqRel).unwrap(), [1, 2, 3]);
+    /// assert!(slot.load_owned(Ordering::AcqRel).unwrap_err().is_null());
+    ///
+    /// let slot = AtomicXarc::new(vec![4, 5, 6]);
+    /// let shared = slot.load(Ordering::Acquire);
+    /// assert_eq!(slot.load_owned(Ordering::AcqRel).unwrap_err(), shared);
+    /// ```
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load_owned(&self, order: Ordering) -> Result<T, Xarc<T>> {
+        let previous = self.swap(&Xarc::null(), order);
+        if claim_unlinked(previous.ptr) {
+            unsafe {
+                Ok(free(previous.into_ptr()))
+            }
+        }
+        else {
+            Err(previous)
+        }
+    }
+
+    /// Copy the current value into a `CowWriteGuard` that can be modified in place and then published with a compare-exchange.
+    ///
+    /// # Panics
+    /// - If `self` is null.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::AtomicXarc;
+    ///
+    /// let routes = AtomicXarc::new(vec!["/"]);
+    /// let mut guard = routes.write_cow();
+    /// guard.push("/health");
+    /// assert_eq!(*guard.commit().unwrap().maybe_deref().unwrap(), ["/"]);
+    ///
+    /// let mut stale = routes.write_cow();
+    /// stale.clear();
+    /// routes.update_cow(|current| current.push("/metrics"));
+    /// let conflict = stale.commit().unwrap_err();
+    /// assert!(conflict.value.is_empty());
+    /// assert_eq!(*conflict.current.maybe_deref().unwrap(), ["/", "/health", "/metrics"]);
+    /// assert_eq!(*routes.load(Ordering::Acquire).maybe_deref().unwrap(), ["/", "/health", "/metrics"]);
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn write_cow(&self) -> CowWriteGuard<'_, T, S> where T: Clone {
+        CowWriteGuard::new(self)
+    }
+
+    /// Copy the current value, modify the copy with `f` and publish it, starting over from the latest value if another thread replaced it first.
+    /// `f` may be called several times. Returns the value that was replaced.
+    ///
+    /// # Panics
+    /// - If `self` is null.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn update_cow<F: FnMut(&mut T)>(&self, mut f: F) -> Xarc<T> where T: Clone {
+        let backoff = Backoff::new();
+        loop {
+            let mut guard = self.write_cow();
+            f(&mut guard);
+            match guard.commit() {
+                Ok(previous) => return previous,
+                Err(_) => back_off(&backoff),
+            }
         }
     }
+}
 
+impl<T: ?Sized + Send + Pointee> AtomicXarc<T> {
     /// Initialize the atomic smart pointer with null.
     #[must_use]
-    pub fn null() -> Self {
+    pub const fn null() -> Self {
+        AtomicXarc::null_with_strategy(Eager)
+    }
+}
+
+impl<T: ?Sized + Send + Pointee, S: Strategy> AtomicXarc<T, S> {
+    /// Initialize the atomic smart pointer with null, using `strategy`.
+    #[must_use]
+    pub const fn null_with_strategy(strategy: S) -> Self {
+        mem::forget(strategy);
         AtomicXarc {
             ptr: CachePadded::new(AtomicPtr::new(ptr::null_mut())),
+            phantom: PhantomData,
         }
     }
 
+    /// Initialize the atomic smart pointer with `pointer`, using `strategy`.
+    #[must_use]
+    pub fn with_strategy(pointer: &Xarc<T>, strategy: S) -> Self {
+        mem::forget(strategy);
+        unguarded_increment(pointer.ptr);
+        AtomicXarc::init(pointer.ptr)
+    }
+
+    /// Move the reference out of the slot into an `Xarc`. Owning the slot makes this a plain read, with no atomic operation on the count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::AtomicXarc;
+    ///
+    /// let atomic = AtomicXarc::new(42);
+    /// let loaded = atomic.load(Ordering::Acquire);
+    /// assert_eq!(atomic.into_xarc(), loaded);
+    /// ```
     #[must_use]
-    pub(crate) fn init(ptr: *mut XarcData<T>) -> Self {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn into_xarc(mut self) -> Xarc<T> {
+        let pointer = self.take_mut();
+        // The slot is now null, so dropping it would only pin for nothing.
+        mem::forget(self);
+        pointer
+    }
+
+    /// Take the contents of `self`, leaving it null. Exclusive access makes this a plain read.
+    #[must_use]
+    pub(crate) fn take_mut(&mut self) -> Xarc<T> {
+        Xarc::init(mem::replace(self.ptr.get_mut(), ptr::null_mut()))
+    }
+
+    #[must_use]
+    pub(crate) fn init(ptr: *mut XarcHeader) -> Self {
+        mark_published(ptr);
         AtomicXarc {
             ptr: CachePadded::new(AtomicPtr::new(ptr)),
+            phantom: PhantomData,
         }
     }
 
@@ -56,6 +232,7 @@ impl<T: Send> AtomicXarc<T> {
     /// Returns the previous value of `self`.
     /// If the value does not equal `current` the operation failed.
     #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn compare_and_swap(&self, current: &Xarc<T>, new: &Xarc<T>, success: Ordering, failure: Ordering) -> Xarc<T> {
         match self.compare_exchange(current, new, success, failure) {
             Ok(ptr) => ptr,
@@ -65,15 +242,26 @@ impl<T: Send> AtomicXarc<T> {
 
     /// As an atomic operation, swap the contents of `self` with `new` if `self == current`.
     /// Returns the previous value of `self` in a Result indicating whether the operation succeeded or failed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn compare_exchange(&self, current: &Xarc<T>, new: &Xarc<T>, success: Ordering, failure: Ordering) -> Result<Xarc<T>, Xarc<T>> {
-        let guard = pin();
-        unguarded_increment(new.ptr);
-        match self.ptr.compare_exchange(current.ptr, new.ptr, success, failure) {
+        let (success, failure) = ordering::compare_exchange(success, failure);
+        #[cfg(feature = "schedule")]
+        super::schedule::turn();
+        let generation = help::generation();
+        publish(new.ptr);
+        match S::exclusive(&self.ptr, || self.compare_exchange_raw(current.ptr, new.ptr, success, failure, false)) {
             Ok(ptr) => {
-                Ok(Xarc::init(ptr))
+                let previous = Xarc::init(ptr);
+                S::replaced(ptr);
+                self.written(generation, new);
+                #[cfg(feature = "schedule")]
+                super::schedule::done(super::schedule::Op::CompareExchange, self.address(), new.ptr.addr());
+                Ok(previous)
             },
             Err(ptr) => {
-                decrement(new.ptr, &guard);
+                #[cfg(feature = "profiling")]
+                super::profile::record(self.address(), super::profile::Event::CasFailure);
+                release_reference::<T>(new.ptr);
                 Err(self.increment_or_reload(ptr, failure))
             },
         }
@@ -82,54 +270,562 @@ impl<T: Send> AtomicXarc<T> {
     /// As an atomic operation, swap the contents of `self` with `new` if `self == current` but with spurious failure of the comparison allowed.
     /// Returns the previous value of `self` in a Result indicating whether the operation succeeded or failed.
     /// Allowing spurious failure is a performance optimization that is reasonable when no additional loops are required for correctness.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn compare_exchange_weak(&self, current: &Xarc<T>, new: &Xarc<T>, success: Ordering, failure: Ordering) -> Result<Xarc<T>, Xarc<T>> {
-        let guard = pin();
-        unguarded_increment(new.ptr);
-        match self.ptr.compare_exchange_weak(current.ptr, new.ptr, success, failure) {
+        let (success, failure) = ordering::compare_exchange(success, failure);
+        #[cfg(feature = "fault-injection")]
+        if super::fault::spurious_failure() {
+            #[cfg(feature = "profiling")]
+            super::profile::record(self.address(), super::profile::Event::CasFailure);
+            return Err(self.load(failure));
+        }
+        #[cfg(feature = "schedule")]
+        super::schedule::turn();
+        let generation = help::generation();
+        publish(new.ptr);
+        match S::exclusive(&self.ptr, || self.compare_exchange_raw(current.ptr, new.ptr, success, failure, true)) {
             Ok(ptr) => {
-                Ok(Xarc::init(ptr))
+                let previous = Xarc::init(ptr);
+                S::replaced(ptr);
+                self.written(generation, new);
+                #[cfg(feature = "schedule")]
+                super::schedule::done(super::schedule::Op::CompareExchange, self.address(), new.ptr.addr());
+                Ok(previous)
             },
             Err(ptr) => {
-                decrement(new.ptr, &guard);
+                #[cfg(feature = "profiling")]
+                super::profile::record(self.address(), super::profile::Event::CasFailure);
+                release_reference::<T>(new.ptr);
                 Err(self.increment_or_reload(ptr, failure))
             },
         }
     }
 
-    /// Load the value into an `Xarc`.
-    /// The internal atomic operation is repeated as needed until successful.
-    #[must_use]
-    pub fn load(&self, order: Ordering) -> Xarc<T> {
-        let guard = pin();
+    /// Repeatedly load the contents of `self`, compute a replacement with `f`, and attempt to swap it in with `compare_exchange_weak`,
+    /// backing off between attempts, until either the swap succeeds or `f` declines by returning `None`.
+    /// Returns the value that was replaced on success or the current value that `f` declined to replace.
+    /// 
+    /// `f` may be called several times and should be free of side effects beyond computing the replacement.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let counter = AtomicXarc::new(0);
+    /// let previous = counter.cas_loop(|current| Some(Xarc::new(current.maybe_deref().unwrap() + 1)),
+    ///                                 Ordering::AcqRel, Ordering::Acquire).unwrap();
+    /// assert_eq!(*previous.maybe_deref().unwrap(), 0);
+    /// assert_eq!(*counter.load(Ordering::Acquire).maybe_deref().unwrap(), 1);
+    /// ```
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn cas_loop<F: FnMut(&Xarc<T>) -> Option<Xarc<T>>>(&self, mut f: F, success: Ordering, failure: Ordering) -> Result<Xarc<T>, Xarc<T>> {
+        let (success, failure) = ordering::compare_exchange(success, failure);
         let backoff = Backoff::new();
+        let mut current = self.load(failure);
         loop {
-            if let Ok(pointer) = Xarc::try_from(self.ptr.load(order), &guard) {
-                return pointer;
+            let new = match f(&current) {
+                Some(new) => new,
+                None => return Err(current),
+            };
+            #[cfg(feature = "fault-injection")]
+            super::fault::delay();
+            match self.compare_exchange_weak(&current, &new, success, failure) {
+                Ok(previous) => return Ok(previous),
+                Err(previous) => {
+                    current = previous;
+                    #[cfg(feature = "profiling")]
+                    if backoff.is_completed() {
+                        super::profile::record(self.address(), super::profile::Event::BackoffEscalation);
+                    }
+                    back_off(&backoff);
+                },
             }
-            else {
-                backoff.spin();
+        }
+    }
+
+    /// Attempt to load the value into an `Xarc` in a bounded number of steps, for real-time threads.
+    /// Makes at most `attempts` attempts, each of which increments the count with at most one compare-exchange,
+    /// and fails rather than retrying further if a writer or another load interferes every time.
+    /// With `Locked`, an attempt that finds the lock held fails rather than waiting for it.
+    /// 
+    /// Apart from pinning an epoch, each attempt takes a constant number of steps. See the crate documentation on real-time use.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::AtomicXarc;
+    /// 
+    /// let atomic = AtomicXarc::new(42);
+    /// assert_eq!(*atomic.try_load_bounded(Ordering::Acquire, 4).unwrap().maybe_deref().unwrap(), 42);
+    /// assert!(atomic.try_load_bounded(Ordering::Acquire, 0).is_err());
+    /// ```
+    #[allow(clippy::result_unit_err)]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn try_load_bounded(&self, order: Ordering, attempts: usize) -> Result<Xarc<T>, ()> {
+        let order = ordering::load(order);
+        // With `Locked`, finding the lock held counts as a failed attempt rather than waiting for it.
+        match (0..attempts).find_map(|_| S::try_exclusive(&self.ptr, || S::try_acquire_once(|| self.current(order)))?.ok()) {
+            Some(ptr) => Ok(Xarc::init(ptr)),
+            None => Err(()),
+        }
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new` if `self == current`, in a bounded number of steps.
+    /// Returns the previous value of `self` on success.
+    /// 
+    /// Unlike `compare_exchange`, a failure does not load the current value, which could take an unbounded number of retries.
+    /// Use `try_load_bounded` for that. With `Locked`, it fails rather than waiting if the lock is held,
+    /// and with `Eager` it fails rather than helping if a `compare_exchange_all` is in progress on the slot.
+    #[allow(clippy::result_unit_err)]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange_bounded(&self, current: &Xarc<T>, new: &Xarc<T>, success: Ordering, failure: Ordering) -> Result<Xarc<T>, ()> {
+        let (success, failure) = ordering::compare_exchange(success, failure);
+        #[cfg(feature = "schedule")]
+        super::schedule::turn();
+        let generation = help::generation();
+        publish(new.ptr);
+        match S::try_exclusive(&self.ptr, || self.ptr.compare_exchange(current.ptr, new.ptr, success, failure)) {
+            Some(Ok(ptr)) => {
+                let previous = Xarc::init(ptr);
+                S::replaced(ptr);
+                self.written(generation, new);
+                #[cfg(feature = "schedule")]
+                super::schedule::done(super::schedule::Op::CompareExchange, self.address(), new.ptr.addr());
+                Ok(previous)
+            },
+            // A held `Locked` lock fails the attempt like a lost race.
+            Some(Err(_)) | None => {
+                #[cfg(feature = "profiling")]
+                super::profile::record(self.address(), super::profile::Event::CasFailure);
+                release_reference::<T>(new.ptr);
+                Err(())
+            },
+        }
+    }
+
+    /// `cas_loop` in a bounded number of steps, making at most `attempts` attempts to load the current value and swap in a replacement.
+    /// Returns the value that was replaced on success, the current value that `f` declined to replace,
+    /// or None if every attempt was interfered with.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let counter = AtomicXarc::new(0);
+    /// let previous = counter.cas_loop_bounded(|current| Some(Xarc::new(current.maybe_deref().unwrap() + 1)),
+    ///                                         Ordering::AcqRel, Ordering::Acquire, 8).unwrap();
+    /// assert_eq!(*previous.maybe_deref().unwrap(), 0);
+    /// let declined = counter.cas_loop_bounded(|_| None, Ordering::AcqRel, Ordering::Acquire, 8).unwrap_err();
+    /// assert_eq!(*declined.unwrap().maybe_deref().unwrap(), 1);
+    /// ```
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn cas_loop_bounded<F: FnMut(&Xarc<T>) -> Option<Xarc<T>>>(&self, mut f: F, success: Ordering, failure: Ordering, attempts: usize) -> Result<Xarc<T>, Option<Xarc<T>>> {
+        let (success, failure) = ordering::compare_exchange(success, failure);
+        for _ in 0..attempts {
+            let current = match self.try_load_bounded(failure, 1) {
+                Ok(current) => current,
+                Err(()) => continue,
+            };
+            let new = match f(&current) {
+                Some(new) => new,
+                None => return Err(Some(current)),
+            };
+            if let Ok(previous) = self.compare_exchange_bounded(&current, &new, success, failure) {
+                return Ok(previous);
             }
         }
+        Err(None)
+    }
+
+    /// Load the value into an `Xarc`.
+    /// The internal atomic operation is repeated as needed until successful.
+    /// 
+    /// A load that keeps losing the race to writers asks them for help once backing off is exhausted,
+    /// and the next write to begin afterward hands it a reference to the value it stores,
+    /// so a load completes in a bounded number of steps even under a storm of swaps, provided that no more than 64 loads
+    /// across the process are asking for help at once. Beyond that, a load keeps backing off and retrying until a request frees up.
+    /// In exchange every successful write reads a count of outstanding requests, and scans the 64 of them while any are.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> Xarc<T> {
+        let order = ordering::load(order);
+        self.load_with(|| self.current(order))
+    }
+
+    /// Load the value into an `Xarc` using consume ordering.
+    /// The internal atomic operation is repeated as needed until successful.
+    /// 
+    /// Dereferencing the result is data-dependent on the loaded pointer, so on weakly ordered architectures
+    /// such as ARM and POWER this avoids the barrier an `Acquire` load requires.
+    /// On other architectures it is equivalent to `load(Ordering::Acquire)`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load_consume(&self) -> Xarc<T> {
+        self.load_with(|| mcas::read(&self.ptr, self.consume()))
     }
 
     /// Attempt to load the value into an `Xarc`.
     /// It can fail if, after the pointer has been loaded but before it is used, it is swapped out in another thread and destroyed.
     #[allow(clippy::result_unit_err)]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn try_load(&self, order: Ordering) -> Result<Xarc<T>, ()> {
-        let guard = pin();
-        Xarc::try_from(self.ptr.load(order), &guard)
+        let order = ordering::load(order);
+        match S::exclusive(&self.ptr, || S::try_acquire(|| self.current(order))) {
+            Ok(ptr) => Ok(Xarc::init(ptr)),
+            Err(()) => Err(()),
+        }
+    }
+
+    /// Attempt to load the value into an `Xarc` using consume ordering.
+    /// See `load_consume` and `try_load`.
+    #[allow(clippy::result_unit_err)]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn try_load_consume(&self) -> Result<Xarc<T>, ()> {
+        match S::exclusive(&self.ptr, || S::try_acquire(|| mcas::read(&self.ptr, self.consume()))) {
+            Ok(ptr) => Ok(Xarc::init(ptr)),
+            Err(()) => Err(()),
+        }
+    }
+
+    /// Run `f` on the current value by reference and return its result, without ever touching the count.
+    /// `f` receives None if the value is null.
+    /// 
+    /// An epoch is pinned for the duration of `f`, so the value cannot be reclaimed while it runs
+    /// but nothing else pinned can be reclaimed either. Keep `f` short, and `load` instead if the value must outlive it.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::AtomicXarc;
+    /// 
+    /// let atomic = AtomicXarc::new((42, "unused"));
+    /// assert_eq!(atomic.with(Ordering::Acquire, |value| value.map(|value| value.0)), Some(42));
+    /// ```
+    pub fn with<R, F: FnOnce(Option<&T>) -> R>(&self, order: Ordering, f: F) -> R {
+        let order = ordering::load(order);
+        let _guard = pin();
+        let ptr = self.current(order);
+        if ptr.is_null() {
+            f(None)
+        }
+        else {
+            unsafe {
+                f(Some(&(*fat::<T>(ptr)).value))
+            }
+        }
+    }
+
+    /// Check if the value is null without loading it.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let atomic = AtomicXarc::null();
+    /// assert!(atomic.is_null(Ordering::Relaxed));
+    /// let _ = atomic.swap(&Xarc::new(42), Ordering::AcqRel);
+    /// assert!(!atomic.is_null(Ordering::Relaxed));
+    /// ```
+    #[must_use]
+    pub fn is_null(&self, order: Ordering) -> bool {
+        self.current(ordering::load(order)).is_null()
+    }
+
+    /// Check if the value is the same allocation as `pointer` without loading it.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let value = Xarc::new(42);
+    /// let atomic = AtomicXarc::from(&value);
+    /// assert!(atomic.ptr_eq(&value, Ordering::Relaxed));
+    /// assert!(!atomic.ptr_eq(&Xarc::new(42), Ordering::Relaxed));
+    /// ```
+    #[must_use]
+    pub fn ptr_eq(&self, pointer: &Xarc<T>, order: Ordering) -> bool {
+        self.current(ordering::load(order)) == pointer.ptr
+    }
+
+    /// Describe the current state of `self` for logging, without taking a reference to the value.
+    /// See `DebugState`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let value = Xarc::new(42);
+    /// let atomic = AtomicXarc::from(&value);
+    /// let state = atomic.debug_state();
+    /// assert_ne!(state.address, 0);
+    /// assert_eq!(state.count, 2);
+    /// assert_eq!(state.strategy, "Eager");
+    /// ```
+    #[must_use]
+    pub fn debug_state(&self) -> DebugState {
+        let _guard = pin();
+        DebugState::new::<S>(self.address(), self.current(Ordering::Acquire))
     }
 
     /// As an atomic operation, swap the contents of `self` with `new`.
     /// Returns the previous value of `self`.
     #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn swap(&self, new: &Xarc<T>, order: Ordering) -> Xarc<T> {
-        unguarded_increment(new.ptr);
-        Xarc::init(self.ptr.swap(new.ptr, order))
+        let order = ordering::swap(order);
+        #[cfg(feature = "schedule")]
+        super::schedule::turn();
+        let generation = help::generation();
+        publish(new.ptr);
+        let previous = Xarc::init(S::exclusive(&self.ptr, || self.swap_raw(new.ptr, order)));
+        S::replaced(previous.ptr);
+        self.written(generation, new);
+        #[cfg(feature = "schedule")]
+        super::schedule::done(super::schedule::Op::Swap, self.address(), new.ptr.addr());
+        previous
+    }
+
+    /// `load` with an ordering checked at compile time. See `xarc::order`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load_ordered<O: LoadOrdering>(&self, _order: O) -> Xarc<T> {
+        self.load(O::ORDERING)
+    }
+
+    /// `swap` with an ordering checked at compile time. See `xarc::order`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap_ordered<O: RmwOrdering>(&self, new: &Xarc<T>, _order: O) -> Xarc<T> {
+        self.swap(new, O::ORDERING)
+    }
+
+    /// `compare_exchange` with orderings checked at compile time. See `xarc::order`.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange_ordered<O: RmwOrdering, F: FailureOrdering<O>>(&self, current: &Xarc<T>, new: &Xarc<T>, _success: O, _failure: F) -> Result<Xarc<T>, Xarc<T>> {
+        self.compare_exchange(current, new, O::ORDERING, F::ORDERING)
+    }
+
+    /// `compare_exchange_weak` with orderings checked at compile time. See `xarc::order`.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange_weak_ordered<O: RmwOrdering, F: FailureOrdering<O>>(&self, current: &Xarc<T>, new: &Xarc<T>, _success: O, _failure: F) -> Result<Xarc<T>, Xarc<T>> {
+        self.compare_exchange_weak(current, new, O::ORDERING, F::ORDERING)
+    }
+
+    /// `load` with `Acquire` ordering, which sees everything written before the value was stored.
+    ///
+    /// This and the other shorthands below cover the usual pattern of publishing values with release and reading them with acquire.
+    /// The methods taking an `Ordering` remain for code that needs something else.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::{AtomicXarc, Xarc};
+    ///
+    /// let atomic = AtomicXarc::new(0);
+    /// let current = atomic.load_acquire();
+    /// atomic.store_release(&Xarc::new(1));
+    /// assert!(atomic.compare_exchange_acq_rel(&current, &Xarc::new(2)).is_err());
+    /// assert_eq!(atomic.swap_acq_rel(&Xarc::new(3)).maybe_deref(), Some(&1));
+    /// assert_eq!(atomic.load_acquire().maybe_deref(), Some(&3));
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load_acquire(&self) -> Xarc<T> {
+        self.load(Ordering::Acquire)
+    }
+
+    /// Replace the value with `new` using `Release` ordering, dropping the previous value.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn store_release(&self, new: &Xarc<T>) {
+        drop(self.swap(new, Ordering::Release));
+    }
+
+    /// `swap` with `AcqRel` ordering.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap_acq_rel(&self, new: &Xarc<T>) -> Xarc<T> {
+        self.swap(new, Ordering::AcqRel)
+    }
+
+    /// `compare_exchange` with `AcqRel` ordering on success and `Acquire` ordering on failure.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange_acq_rel(&self, current: &Xarc<T>, new: &Xarc<T>) -> Result<Xarc<T>, Xarc<T>> {
+        self.compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+    }
+
+    /// Block the current thread until the contents of `self` differ from `current`, then load the new value.
+    /// Waiting threads are parked rather than spinning and are woken by any successful swap or compare-exchange.
+    /// 
+    /// Requires the `std` feature. Enabling it adds a fence to every successful write to any `AtomicXarc`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use std::{sync::Arc, thread};
+    /// use xarc::{AtomicXarc, Xarc};
+    /// 
+    /// let atomic = Arc::new(AtomicXarc::new(0));
+    /// let current = atomic.load(Ordering::Acquire);
+    /// let writer = {
+    ///     let atomic = atomic.clone();
+    ///     thread::spawn(move || {
+    ///         let _ = atomic.swap(&Xarc::new(1), Ordering::AcqRel);
+    ///     })
+    /// };
+    /// let changed = atomic.wait_until_changed(&current, Ordering::Acquire);
+    /// assert_eq!(*changed.maybe_deref().unwrap(), 1);
+    /// writer.join().unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn wait_until_changed(&self, current: &Xarc<T>, order: Ordering) -> Xarc<T> {
+        let order = ordering::load(order);
+        loop {
+            super::wait::wait_until(self.address(), || self.current(Ordering::SeqCst) != current.ptr, None);
+            let pointer = self.load(order);
+            if pointer != *current {
+                return pointer;
+            }
+        }
+    }
+
+    /// Block the current thread until the contents of `self` differ from `current` or `timeout` elapses.
+    /// See `wait_until_changed`.
+    /// 
+    /// Requires the `std` feature.
+    #[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn wait_until_changed_timeout(&self, current: &Xarc<T>, timeout: Duration, order: Ordering) -> Result<Xarc<T>, WaitTimeoutError> {
+        let order = ordering::load(order);
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            if !super::wait::wait_until(self.address(), || self.current(Ordering::SeqCst) != current.ptr, deadline) {
+                return Err(WaitTimeoutError);
+            }
+            let pointer = self.load(order);
+            if pointer != *current {
+                return Ok(pointer);
+            }
+        }
+    }
+
+    /// Answer loads that asked for help and wake waiting threads after successfully replacing the contents with `new`.
+    #[inline]
+    fn written(&self, generation: usize, new: &Xarc<T>) {
+        help::offer(self.address(), generation, new.ptr);
+        #[cfg(feature = "std")]
+        super::wait::notify(self.address());
+    }
+
+    /// Load the pointer, seeing through a `compare_exchange_all` in progress. See `mcas::read`.
+    #[must_use]
+    pub(crate) fn current(&self, order: Ordering) -> *mut XarcHeader {
+        mcas::read(&self.ptr, self.ptr.load(order))
+    }
+
+    /// Compare-exchange the pointer, first helping any `compare_exchange_all` in the way to finish, so that it fails only on a different value.
+    fn compare_exchange_raw(&self, current: *mut XarcHeader, new: *mut XarcHeader, success: Ordering, failure: Ordering, weak: bool) -> Result<*mut XarcHeader, *mut XarcHeader> {
+        loop {
+            let result = if weak {
+                self.ptr.compare_exchange_weak(current, new, success, failure)
+            }
+            else {
+                self.ptr.compare_exchange(current, new, success, failure)
+            };
+            match result {
+                Err(ptr) if mcas::is_pending(ptr) => mcas::finish(&self.ptr),
+                result => return result,
+            }
+        }
+    }
+
+    /// Swap the pointer. A slot that may hold a `compare_exchange_all` in progress is swapped with a compare-exchange
+    /// after helping it to finish, since swapping it out would lose the operation.
+    fn swap_raw(&self, new: *mut XarcHeader, order: Ordering) -> *mut XarcHeader {
+        if !S::TRANSACTIONAL {
+            return self.ptr.swap(new, order);
+        }
+        let mut current = self.ptr.load(Ordering::Relaxed);
+        loop {
+            if mcas::is_pending(current) {
+                mcas::finish(&self.ptr);
+                current = self.ptr.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.ptr.compare_exchange_weak(current, new, order, Ordering::Relaxed) {
+                Ok(previous) => return previous,
+                Err(ptr) => current = ptr,
+            }
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn address(&self) -> usize {
+        (&*self.ptr as *const AtomicPtr<XarcHeader>).addr()
+    }
+
+    #[must_use]
+    fn consume(&self) -> *mut XarcHeader {
+        if cfg!(feature = "force-seqcst") {
+            self.ptr.load(Ordering::SeqCst)
+        }
+        else {
+            self.ptr.load_consume()
+        }
+    }
+
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn load_with<F: Fn() -> *mut XarcHeader>(&self, load: F) -> Xarc<T> {
+        #[cfg(feature = "schedule")]
+        super::schedule::turn();
+        let backoff = Backoff::new();
+        let mut pending: Option<help::Pending> = None;
+        loop {
+            if let Some(answer) = pending.as_ref().and_then(help::Pending::answer) {
+                #[cfg(feature = "schedule")]
+                super::schedule::done(super::schedule::Op::Load, self.address(), answer.addr());
+                return Xarc::init(answer);
+            }
+            if let Ok(ptr) = S::exclusive(&self.ptr, || S::try_acquire(&load)) {
+                if let Some(answer) = pending.as_ref().and_then(help::Pending::withdraw) {
+                    drop(Xarc::<T>::init(answer));
+                }
+                #[cfg(feature = "schedule")]
+                super::schedule::done(super::schedule::Op::Load, self.address(), ptr.addr());
+                return Xarc::init(ptr);
+            }
+            #[cfg(feature = "profiling")]
+            super::profile::record(self.address(), super::profile::Event::LoadRetry);
+            // Once backing off stops helping, ask writers to hand over a reference so that the load cannot starve.
+            if pending.is_none() && backoff.is_completed() {
+                #[cfg(feature = "profiling")]
+                super::profile::record(self.address(), super::profile::Event::BackoffEscalation);
+                pending = help::request(self.address());
+            }
+            back_off(&backoff);
+        }
     }
 
     #[must_use]
-    fn increment_or_reload(&self, ptr: *mut XarcData<T>, order: Ordering) -> Xarc<T> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn increment_or_reload(&self, ptr: *mut XarcHeader, order: Ordering) -> Xarc<T> {
+        if S::LOADS_UNDER_LOCK {
+            return self.load(order);
+        }
         let guard = pin();
         if try_increment(ptr, &guard).is_ok() {
             Xarc::init(ptr)
@@ -140,21 +836,40 @@ impl<T: Send> AtomicXarc<T> {
     }
 }
 
-impl<T: Send> Drop for AtomicXarc<T> {
+impl<T: ?Sized + Send + Pointee, S: Strategy> Drop for AtomicXarc<T, S> {
     fn drop(&mut self) {
-        let ptr = self.ptr.load(Ordering::Relaxed);
-        decrement(ptr, &pin());
+        release_reference::<T>(self.ptr.load(ordering::force(Ordering::Relaxed)));
     }
 }
 
-impl<T: Send> From<&Xarc<T>> for AtomicXarc<T> {
-    #[must_use]
+#[cfg(feature = "defmt")]
+impl<T: ?Sized + Send + Pointee, S: Strategy> defmt::Format for AtomicXarc<T, S> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "AtomicXarc {{ ptr: {=usize:#x} }}", self.ptr.load(Ordering::Relaxed).addr());
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> From<&Xarc<T>> for AtomicXarc<T> {
     fn from(pointer: &Xarc<T>) -> Self {
         unguarded_increment(pointer.ptr);
         AtomicXarc::init(pointer.ptr)
     }
 }
 
+impl<T: ?Sized + Send + Pointee> From<Xarc<T>> for AtomicXarc<T> {
+    /// Move the reference into the slot without touching the count. See `Xarc::into_atomic`.
+    fn from(pointer: Xarc<T>) -> Self {
+        pointer.into_atomic()
+    }
+}
+
+impl<T: ?Sized + Send + Pointee, S: Strategy> From<AtomicXarc<T, S>> for Xarc<T> {
+    /// Move the reference out of the slot without touching the count. See `AtomicXarc::into_xarc`.
+    fn from(atomic: AtomicXarc<T, S>) -> Self {
+        atomic.into_xarc()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,4 +882,152 @@ mod tests {
         assert_eq!(*local.maybe_deref().unwrap(), 42);
     }
 
+    #[test]
+    #[cfg(not(feature = "no-reclaim"))]
+    fn xarc_on_reclaim_st_test() {
+        use core::sync::atomic::AtomicUsize;
+        static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+
+        let shared = AtomicXarc::with_on_reclaim(42, |value| {
+            RECLAIMED.fetch_add(value, Ordering::Relaxed);
+        });
+        let local = shared.load(Ordering::Acquire);
+        drop(shared);
+        drop(local);
+        assert!(crate::quiescent::flush_until(|| RECLAIMED.load(Ordering::Relaxed) == 42));
+    }
+
+    #[test]
+    fn xarc_unwrap_or_clone_st_test() {
+        #[derive(Debug, PartialEq)]
+        struct Clones(usize);
+        impl Clone for Clones {
+            fn clone(&self) -> Self {
+                Clones(self.0 + 1)
+            }
+        }
+
+        assert_eq!(Xarc::new(Clones(0)).unwrap_or_clone(), Clones(0));
+        let published = Xarc::new(Clones(0));
+        drop(AtomicXarc::from(&published));
+        assert_eq!(published.unwrap_or_clone(), Clones(1));
+    }
+
+    #[test]
+    fn xarc_recycle_published_st_test() {
+        let atomic = AtomicXarc::new(1);
+        let loaded = atomic.load(Ordering::Acquire);
+        drop(atomic.swap(&Xarc::null(), Ordering::AcqRel));
+        let address = loaded.as_raw();
+        let recycled = loaded.recycle(2);
+        assert_ne!(recycled.as_raw(), address);
+        assert_eq!(recycled.maybe_deref(), Some(&2));
+
+        let address = recycled.as_raw();
+        let recycled = recycled.recycle(3);
+        assert_eq!(recycled.as_raw(), address);
+        assert_eq!(Xarc::<i32>::null().recycle(4).maybe_deref(), Some(&4));
+    }
+
+    #[test]
+    fn xarc_debug_state_st_test() {
+        use crate::strategy::ReadMostly;
+
+        let shared = AtomicXarc::null_with_strategy(ReadMostly);
+        let state = shared.debug_state();
+        assert_eq!((state.address, state.count, state.strategy, state.debts), (0, 0, "ReadMostly", 0));
+        let value = Xarc::new(42);
+        let _ = shared.swap(&value, Ordering::AcqRel);
+        let loaded = shared.load(Ordering::Acquire);
+        let state = shared.debug_state();
+        assert_eq!(state.address, value.as_raw().cast::<u8>().addr() - mem::offset_of!(XarcData<i32>, value));
+        assert_eq!(state.count, 3);
+        drop(loaded);
+        assert_eq!(shared.debug_state().count, 2);
+    }
+
+    #[test]
+    fn xarc_slice_st_test() {
+        use alloc::{string::{String, ToString}, vec, vec::Vec};
+
+        let shared: AtomicXarc<[String]> = AtomicXarc::from(&Xarc::from(vec!["a".to_string(), "b".to_string()]));
+        let local = shared.load(Ordering::Acquire);
+        let _ = shared.swap(&Xarc::from(Vec::new()), Ordering::AcqRel);
+        assert_eq!(local.maybe_deref().unwrap(), &["a", "b"]);
+        assert!(shared.load(Ordering::Acquire).maybe_deref().unwrap().is_empty());
+        assert!(AtomicXarc::<str>::null().load(Ordering::Acquire).is_null());
+
+        let unit: Xarc<[()]> = (0..3).map(|_| ()).collect();
+        assert_eq!(unit.maybe_deref().unwrap().len(), 3);
+        assert_eq!(core::mem::size_of::<AtomicXarc<[String]>>(), core::mem::size_of::<AtomicXarc<String>>());
+    }
+
+    #[test]
+    fn xarc_dyn_st_test() {
+        use alloc::{string::{String, ToString}, vec};
+        use core::any::Any;
+
+        let any: Xarc<dyn Any + Send + Sync> = crate::xarc_dyn!(vec!["a".to_string()]);
+        let shared = AtomicXarc::from(&any);
+        let local = shared.load(Ordering::Acquire);
+        let _ = shared.swap(&crate::xarc_dyn!(42i64), Ordering::AcqRel);
+        assert_eq!(local.maybe_deref().unwrap().downcast_ref::<alloc::vec::Vec<String>>().unwrap(), &["a"]);
+        assert!(shared.load(Ordering::Acquire).downcast::<String>().is_err());
+        assert_eq!(*shared.load(Ordering::Acquire).downcast::<i64>().unwrap().maybe_deref().unwrap(), 42);
+        #[cfg(not(feature = "debug-owners"))]
+        assert_eq!(core::mem::size_of::<Xarc<dyn Any + Send + Sync>>(), core::mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn xarc_swap_storm_mt_test() {
+        use rayon::prelude::*;
+
+        let shared = AtomicXarc::new(0);
+        (0..100000).into_par_iter().for_each(|i| {
+            if i % 100 == 0 {
+                assert_eq!(*shared.load(Ordering::Acquire).maybe_deref().unwrap() % 2, 0);
+            }
+            else {
+                let _ = shared.swap(&Xarc::new(i * 2), Ordering::AcqRel);
+            }
+        });
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-reclaim"))]
+    fn xarc_long_chain_st_test() {
+        use core::sync::atomic::AtomicUsize;
+
+        static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+        struct Node {
+            _next: Xarc<Node>,
+        }
+        impl Drop for Node {
+            fn drop(&mut self) {
+                RECLAIMED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        const LENGTH: usize = 1_000_000;
+        let mut head = Xarc::null();
+        for _ in 0..LENGTH {
+            head = Xarc::new(Node {_next: head});
+        }
+        // Neither dropping the head nor reclaiming the chain may recurse through it, even on a small stack.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).stack_size(64 * 1024).build().unwrap();
+        pool.install(move || {
+            drop(head);
+            while RECLAIMED.load(Ordering::Relaxed) < LENGTH {
+                pin().flush();
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "XarcCount overflow!")]
+    fn xarc_count_overflow_st_test() {
+        let count = XarcCount::new(XarcCount::MAX);
+        let _ = count.try_increment();
+    }
+
 }
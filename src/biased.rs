@@ -0,0 +1,180 @@
+use super::{internal::*, pointer::*};
+use core::{fmt, marker::PhantomData, ptr};
+
+/// `BiasedXarc` is a dereferenceable smart pointer using biased reference counting.
+///
+/// The `BiasedXarc`s descended from one created with `new` all live on the thread that created it
+/// and share a non-atomic count kept in the header of the value, beside the atomic count.
+/// Together they hold a single strong reference in the atomic count, which is only touched when the
+/// value is created, when the last of them is dropped, and when an `Xarc` is produced to
+/// cross to another thread with `to_xarc`.
+/// Clones and drops on the owner thread are therefore plain non-atomic updates, with no allocation beyond the value's own.
+///
+/// A `BiasedXarc` converted from an `Xarc` instead holds a strong reference of its own, as does each of its clones.
+///
+/// `BiasedXarc` is neither `Send` nor `Sync`. Use `to_xarc` to hand the value to other threads or to store it in an `AtomicXarc`.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use xarc::BiasedXarc;
+///
+/// let owner = BiasedXarc::new(42);
+/// let local = owner.clone();
+/// let shared = owner.to_xarc();
+///
+/// thread::spawn(move || {
+///     assert_eq!(*shared.maybe_deref().unwrap(), 42);
+/// }).join().unwrap();
+/// assert_eq!(*local.maybe_deref().unwrap(), 42);
+/// ```
+pub struct BiasedXarc<T: Send> {
+    ptr: *mut XarcHeader,
+    biased: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Send> BiasedXarc<T> {
+    /// Initialize the smart pointer with `value`.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        let ptr = Xarc::new(value).into_ptr();
+        unsafe {
+            (*ptr).count.add_biased(1);
+        }
+        BiasedXarc {
+            ptr,
+            biased: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Initialize the smart pointer with null.
+    #[must_use]
+    pub fn null() -> Self {
+        BiasedXarc {
+            ptr: ptr::null_mut(),
+            biased: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Check if the smart pointer is null.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Dereference the pointer only if it is not null.
+    /// None will be returned if it is null.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        if self.is_null() {
+            None
+        }
+        else {
+            unsafe {
+                Some(&(*fat::<T>(self.ptr)).value)
+            }
+        }
+    }
+
+    /// Produce an `Xarc` pointing to the same value which can be sent to other threads.
+    /// This is the only operation that touches the atomic count.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn to_xarc(&self) -> Xarc<T> {
+        unguarded_increment(self.ptr);
+        Xarc::init(self.ptr)
+    }
+}
+
+impl<T: Send> Clone for BiasedXarc<T> {
+    fn clone(&self) -> Self {
+        if self.biased {
+            unsafe {
+                (*self.ptr).count.add_biased(1);
+            }
+        }
+        else {
+            unguarded_increment(self.ptr);
+        }
+        BiasedXarc {
+            ptr: self.ptr,
+            biased: self.biased,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Send> Drop for BiasedXarc<T> {
+    fn drop(&mut self) {
+        if self.biased && !unsafe { (*self.ptr).count.decrement_biased() } {
+            return;
+        }
+        if !self.ptr.is_null() {
+            release_reference::<T>(self.ptr);
+        }
+    }
+}
+
+impl<T: Send> Default for BiasedXarc<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: Send> From<Xarc<T>> for BiasedXarc<T> {
+    fn from(pointer: Xarc<T>) -> Self {
+        BiasedXarc {
+            ptr: pointer.into_ptr(),
+            biased: false,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Send> PartialEq for BiasedXarc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T: Send> Eq for BiasedXarc<T> {}
+
+impl<T: Send> fmt::Debug for BiasedXarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BiasedXarc").field("ptr", &self.ptr).field("biased", &self.biased).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn biased_xarc_count_st_test() {
+        let owner = BiasedXarc::new(7);
+        let clones: Vec<_> = (0..16).map(|_| owner.clone()).collect();
+        let shared = owner.to_xarc();
+        unsafe {
+            assert_eq!((*owner.ptr).count.add_biased(0), 17);
+            assert_eq!((*owner.ptr).count.load(), 2);
+        }
+        drop(clones);
+        drop(owner);
+        assert_eq!(*shared.maybe_deref().unwrap(), 7);
+        unsafe {
+            assert_eq!((*shared.ptr).count.load(), 1);
+        }
+
+        let unbiased = BiasedXarc::from(shared.clone());
+        let again = unbiased.clone();
+        assert_eq!(unbiased, again);
+        unsafe {
+            assert_eq!((*shared.ptr).count.load(), 3);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use super::{pointer::*, slice::*};
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt, hash::{Hash, Hasher}, ops::{Deref, RangeBounds}};
+
+/// `XarcBytes` is a cheaply cloneable and sliceable byte buffer whose storage is an `Xarc<[u8]>`.
+///
+/// Clones and slices share the storage, which is reclaimed once the last of them is gone, so framing a received message
+/// with `split_to` and `split_off`, or handing a window of it to another thread, never copies a byte.
+/// Since it is an ordinary value, the current buffer can be published as an `Xarc<XarcBytes>` and swapped through an `AtomicXarc`,
+/// such as a certificate that readers load while it is rotated.
+///
+/// With the `bytes` feature it implements `bytes::Buf`, consuming the buffer from the front.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Xarc, XarcBytes};
+///
+/// let mut frame = XarcBytes::from(&b"\x05hello\x05world"[..]);
+/// let mut words = Vec::new();
+/// while !frame.is_empty() {
+///     let length = usize::from(frame.split_to(1)[0]);
+///     words.push(frame.split_to(length));
+/// }
+/// assert_eq!(words, [&b"hello"[..], &b"world"[..]]);
+/// assert_eq!(words[0].owner(), words[1].owner());
+///
+/// let certificate = AtomicXarc::new(XarcBytes::from(vec![0u8; 1024]));
+/// let current = certificate.load(Ordering::Acquire);
+/// let _ = certificate.swap(&Xarc::new(XarcBytes::from(vec![1u8; 2048])), Ordering::AcqRel);
+/// assert_eq!(current.maybe_deref().map(|bytes| bytes.len()), Some(1024));
+/// ```
+#[derive(Clone)]
+pub struct XarcBytes {
+    bytes: XarcSlice<u8>,
+}
+
+impl XarcBytes {
+    /// Create an empty buffer without allocating.
+    #[must_use]
+    pub fn new() -> Self {
+        XarcBytes {
+            bytes: XarcSlice::from(Xarc::null()),
+        }
+    }
+
+    /// Copy `data` into a new buffer.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn copy_from_slice(data: &[u8]) -> Self {
+        Self::from(Xarc::from(data))
+    }
+
+    /// A buffer holding `range` of this one, sharing its storage.
+    ///
+    /// # Panics
+    /// - If `range` is out of bounds or decreasing, as slice indexing would.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Self {
+        XarcBytes {
+            bytes: self.bytes.slice(range),
+        }
+    }
+
+    /// Split the buffer in two at `at`, keeping `[0, at)` and returning `[at, len)`, both sharing its storage.
+    ///
+    /// # Panics
+    /// - If `at` is greater than the length.
+    #[must_use = "use `truncate` to drop the end of the buffer"]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let tail = self.slice(at..);
+        self.bytes.narrow(0, at);
+        tail
+    }
+
+    /// Split the buffer in two at `at`, returning `[0, at)` and keeping `[at, len)`, both sharing its storage.
+    ///
+    /// # Panics
+    /// - If `at` is greater than the length.
+    #[must_use = "use `advance` to drop the start of the buffer"]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn split_to(&mut self, at: usize) -> Self {
+        let head = self.slice(..at);
+        self.advance(at);
+        head
+    }
+
+    /// Drop the first `count` bytes.
+    ///
+    /// # Panics
+    /// - If `count` is greater than the length.
+    pub fn advance(&mut self, count: usize) {
+        let len = self.len();
+        assert!(count <= len, "cannot advance past the end of XarcBytes: {} > {}", count, len);
+        self.bytes.narrow(count, len);
+    }
+
+    /// Keep only the first `len` bytes. Does nothing if the buffer is no longer than that.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.bytes.narrow(0, len);
+        }
+    }
+
+    /// Drop every byte, keeping a reference to the storage until the buffer is dropped.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// The storage the buffer shares with its clones and slices.
+    #[must_use]
+    pub fn owner(&self) -> &Xarc<[u8]> {
+        self.bytes.owner()
+    }
+}
+
+impl Default for XarcBytes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for XarcBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl AsRef<[u8]> for XarcBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Borrow<[u8]> for XarcBytes {
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Xarc<[u8]>> for XarcBytes {
+    fn from(bytes: Xarc<[u8]>) -> Self {
+        XarcBytes {
+            bytes: XarcSlice::from(bytes),
+        }
+    }
+}
+
+impl From<XarcSlice<u8>> for XarcBytes {
+    fn from(bytes: XarcSlice<u8>) -> Self {
+        XarcBytes {
+            bytes,
+        }
+    }
+}
+
+impl From<Vec<u8>> for XarcBytes {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::from(Xarc::from(bytes))
+    }
+}
+
+impl From<&[u8]> for XarcBytes {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(bytes: &[u8]) -> Self {
+        Self::copy_from_slice(bytes)
+    }
+}
+
+impl From<&str> for XarcBytes {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(text: &str) -> Self {
+        Self::copy_from_slice(text.as_bytes())
+    }
+}
+
+impl PartialEq for XarcBytes {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl PartialEq<[u8]> for XarcBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<&[u8]> for XarcBytes {
+    fn eq(&self, other: &&[u8]) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for XarcBytes {}
+
+impl Hash for XarcBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl fmt::Debug for XarcBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b\"")?;
+        for byte in self.iter() {
+            for escaped in core::ascii::escape_default(*byte) {
+                fmt::Write::write_char(f, char::from(escaped))?;
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+/// Reads the buffer from the front, as `bytes::Bytes` does. `copy_to_bytes` copies, since the storage is not a `bytes::Bytes`.
+///
+/// # Examples
+///
+/// ```
+/// use bytes::Buf;
+/// use xarc::XarcBytes;
+///
+/// let mut buffer = XarcBytes::from(&[0u8, 0, 0, 42, 7][..]);
+/// assert_eq!(buffer.get_u32(), 42);
+/// assert_eq!(buffer.remaining(), 1);
+/// assert_eq!(buffer.chunk(), [7]);
+/// ```
+#[cfg(feature = "bytes")]
+impl ::bytes::Buf for XarcBytes {
+    fn remaining(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self
+    }
+
+    fn advance(&mut self, count: usize) {
+        XarcBytes::advance(self, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_bytes_mt_test() {
+        use alloc::vec::Vec;
+        use rayon::prelude::*;
+
+        let mut buffer = XarcBytes::from((0..=255u8).cycle().take(4096).collect::<Vec<u8>>());
+        let mut chunks = Vec::new();
+        while !buffer.is_empty() {
+            chunks.push(buffer.split_to(256));
+        }
+        assert_eq!(chunks.len(), 16);
+        chunks.par_iter_mut().for_each(|chunk| {
+            let tail = chunk.split_off(128);
+            assert!(chunk.iter().copied().eq(0..128));
+            assert!(tail.iter().copied().eq(128..=255));
+            assert_eq!(tail.owner(), chunk.owner());
+        });
+        assert!(chunks.iter().all(|chunk| chunk.len() == 128 && *chunk == chunks[0]));
+        buffer = chunks.swap_remove(0);
+        buffer.truncate(4);
+        assert_eq!(buffer, &[0, 1, 2, 3][..]);
+    }
+}
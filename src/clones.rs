@@ -0,0 +1,151 @@
+use super::{internal::*, pointee::*, pointer::*, repin::pin};
+use core::{fmt, iter::FusedIterator, marker::PhantomData};
+
+/// `XarcClones` hands out references to one value that were taken all at once by `Xarc::clone_n`.
+///
+/// The count was incremented by the number of references in a single atomic operation,
+/// so producing each `Xarc` costs nothing, rather than one contended increment of the same cache line per clone.
+/// References that are never taken are returned to the count in a single atomic operation when the `XarcClones` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::Xarc;
+///
+/// let message = Xarc::new(String::from("broadcast"));
+/// let handles: Vec<Xarc<String>> = message.clone_n(8).collect();
+/// assert_eq!(handles.len(), 8);
+/// assert!(handles.iter().all(|handle| *handle == message));
+///
+/// let mut unused = message.clone_n(4);
+/// let first = unused.next().unwrap();
+/// assert_eq!(unused.len(), 3);
+/// drop(unused);
+/// assert_eq!(first, message);
+/// ```
+pub struct XarcClones<T: ?Sized + Send + Pointee> {
+    ptr: *mut XarcHeader,
+    remaining: usize,
+    phantom: PhantomData<Xarc<T>>,
+}
+
+impl<T: ?Sized + Send + Pointee> XarcClones<T> {
+    /// Take `n` references to the value of `pointer` at once. See `Xarc::clone_n`.
+    #[must_use]
+    pub(crate) fn new(pointer: &Xarc<T>, n: usize) -> Self {
+        if n > 0 {
+            unguarded_increment_by(pointer.ptr, n);
+        }
+        XarcClones {
+            ptr: pointer.ptr,
+            remaining: n,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Iterator for XarcClones<T> {
+    type Item = Xarc<T>;
+
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn next(&mut self) -> Option<Xarc<T>> {
+        if self.remaining == 0 {
+            None
+        }
+        else {
+            self.remaining -= 1;
+            Some(Xarc::init(self.ptr))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> ExactSizeIterator for XarcClones<T> {}
+
+impl<T: ?Sized + Send + Pointee> FusedIterator for XarcClones<T> {}
+
+impl<T: ?Sized + Send + Pointee> Drop for XarcClones<T> {
+    fn drop(&mut self) {
+        if self.remaining > 0 {
+            decrement_by::<T>(self.ptr, self.remaining, &pin());
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> fmt::Debug for XarcClones<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XarcClones").field("ptr", &self.ptr).field("remaining", &self.remaining).finish()
+    }
+}
+
+unsafe impl<T: ?Sized + Send + Pointee> Send for XarcClones<T> {}
+unsafe impl<T: ?Sized + Send + Pointee> Sync for XarcClones<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "XarcCount overflow!")]
+    fn xarc_clone_n_overflow_st_test() {
+        let _ = Xarc::new(0).clone_n(usize::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "XarcCount overflow!")]
+    fn xarc_clone_n_overflow_by_one_st_test() {
+        let _ = Xarc::new(0).clone_n(XarcCount::MAX);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-reclaim"))]
+    fn xarc_clone_n_overflow_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use core::sync::atomic::{AtomicBool, Ordering};
+        use std::{panic, thread};
+        static RECLAIMED: AtomicBool = AtomicBool::new(false);
+
+        let value = Xarc::with_on_reclaim(0, |_| RECLAIMED.store(true, Ordering::Relaxed));
+        thread::scope(|scope| {
+            // Clones and drops of the value race with a clone_n that must fail without ever publishing an overflowed count.
+            let churn: Vec<_> = (0..4).map(|_| scope.spawn(|| {
+                for _ in 0..1000 {
+                    drop(value.clone());
+                }
+            })).collect();
+            for _ in 0..100 {
+                assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| value.clone_n(usize::MAX))).is_err());
+                assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| value.clone_n(XarcCount::MAX))).is_err());
+            }
+            churn.into_iter().for_each(|thread| thread.join().unwrap());
+        });
+        assert!(!RECLAIMED.load(Ordering::Relaxed));
+        assert_eq!(value.clone_n(3).count(), 3);
+        assert_eq!(unsafe { (*value.ptr).count.load() }, 1);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-reclaim"))]
+    fn xarc_clone_n_st_test() {
+        use alloc::vec::Vec;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+
+        let original = Xarc::with_on_reclaim(7, |value| {
+            RECLAIMED.fetch_add(value, Ordering::Relaxed);
+        });
+        let mut clones = original.clone_n(100);
+        let taken: Vec<Xarc<usize>> = clones.by_ref().take(60).collect();
+        assert_eq!(clones.len(), 40);
+        drop(clones);
+        drop(original);
+        assert!(taken.iter().all(|clone| *clone.maybe_deref().unwrap() == 7));
+        drop(taken);
+        assert!(crate::quiescent::flush_until(|| RECLAIMED.load(Ordering::Relaxed) == 7));
+        assert_eq!(Xarc::<usize>::null().clone_n(3).filter(Xarc::is_null).count(), 3);
+    }
+}
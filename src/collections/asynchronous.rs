@@ -0,0 +1,193 @@
+use super::{List, Queue, Stack};
+use crate::{atomic::*, pointer::*};
+use core::{fmt, future::Future, pin::Pin, sync::atomic::{AtomicBool, AtomicUsize, Ordering, fence}, task::{Context, Poll, Waker}};
+
+struct Registration {
+    /// The waker from the latest poll of the future.
+    waker: AtomicXarc<Waker>,
+    /// Set by every poll that goes on to wait and cleared by the push that wakes the task, so that a task is woken once per wait.
+    armed: AtomicBool,
+}
+
+/// The wakers of the tasks waiting for a collection to become nonempty.
+/// Each waiting future keeps a single registration in a `List` from its first poll until it completes or is dropped,
+/// updating the waker in it rather than registering again, and a push to the collection wakes every armed registration.
+pub(crate) struct Wakers {
+    registrations: List<Xarc<Registration>>,
+    /// The number of registrations, so that a push can skip the list when nobody is waiting.
+    waiting: AtomicUsize,
+}
+
+impl Wakers {
+    #[must_use]
+    pub(crate) fn new() -> Self {
+        Wakers {
+            registrations: List::new(),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Arm `registration` with `waker`, registering it first if this is the first time the future waits.
+    fn register(&self, registration: &mut Option<Xarc<Registration>>, waker: &Waker) {
+        match registration {
+            Some(registration) => {
+                let registration = registration.non_null();
+                if !registration.waker.with(Ordering::Acquire, |current| current.is_some_and(|current| current.will_wake(waker))) {
+                    let _ = registration.waker.swap(&Xarc::new(waker.clone()), Ordering::AcqRel);
+                }
+                registration.armed.store(true, Ordering::Relaxed);
+            },
+            None => {
+                let new = Xarc::new(Registration {
+                    waker: AtomicXarc::new(waker.clone()),
+                    armed: AtomicBool::new(true),
+                });
+                self.waiting.fetch_add(1, Ordering::Relaxed);
+                self.registrations.push_front(new.clone());
+                *registration = Some(new);
+            },
+        }
+    }
+
+    /// Remove `registration` from the list, once its future no longer waits.
+    fn unregister(&self, registration: &Xarc<Registration>) {
+        let mut cursor = self.registrations.cursor();
+        while let Some(current) = cursor.move_next() {
+            if current == registration {
+                let _ = cursor.remove();
+                break;
+            }
+        }
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Wake every armed registration. Called after every push.
+    pub(crate) fn wake_all(&self) {
+        // Pairs with the fence in `Pop::poll`: either the push sees the armed registration or the task sees the push.
+        fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        for registration in self.registrations.iter() {
+            let registration = registration.non_null();
+            if registration.armed.swap(false, Ordering::Relaxed) {
+                registration.waker.with(Ordering::Acquire, |waker| waker.unwrap().wake_by_ref());
+            }
+        }
+    }
+}
+
+enum Source<'a, T: Send> {
+    Stack(&'a Stack<T>),
+    Queue(&'a Queue<T>),
+}
+
+/// The future returned by `Stack::pop_async` and `Queue::pop_async`. It completes with the next value to be popped.
+///
+/// Requires the `async` feature.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Pop<'a, T: Send> {
+    source: Source<'a, T>,
+    registration: Option<Xarc<Registration>>,
+}
+
+impl<'a, T: Send> Pop<'a, T> {
+    pub(crate) fn stack(stack: &'a Stack<T>) -> Self {
+        Pop {
+            source: Source::Stack(stack),
+            registration: None,
+        }
+    }
+
+    pub(crate) fn queue(queue: &'a Queue<T>) -> Self {
+        Pop {
+            source: Source::Queue(queue),
+            registration: None,
+        }
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        match self.source {
+            Source::Stack(stack) => stack.try_pop(),
+            Source::Queue(queue) => queue.try_pop(),
+        }
+    }
+
+    fn wakers(&self) -> &'a Wakers {
+        match self.source {
+            Source::Stack(stack) => stack.wakers(),
+            Source::Queue(queue) => queue.wakers(),
+        }
+    }
+}
+
+impl<T: Send> Future for Pop<'_, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        if let Some(value) = self.try_pop() {
+            self.finish();
+            return Poll::Ready(value);
+        }
+        let wakers = self.wakers();
+        wakers.register(&mut self.registration, cx.waker());
+        // Pairs with the fence in `Wakers::wake_all`.
+        fence(Ordering::SeqCst);
+        match self.try_pop() {
+            Some(value) => {
+                self.finish();
+                Poll::Ready(value)
+            },
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T: Send> Pop<'_, T> {
+    /// Give up the registration, if there is one, now that the future no longer waits.
+    fn finish(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            self.wakers().unregister(&registration);
+        }
+    }
+}
+
+impl<T: Send> Drop for Pop<'_, T> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl<T: Send> fmt::Debug for Pop<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pop").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_registration_st_test() {
+        let stack = Stack::new();
+        let mut context = Context::from_waker(Waker::noop());
+        let mut pop = stack.pop_async();
+        for _ in 0..100 {
+            assert!(Pin::new(&mut pop).poll(&mut context).is_pending());
+        }
+        // Polling again updates the one registration rather than adding another.
+        assert_eq!(stack.wakers().waiting.load(Ordering::Relaxed), 1);
+        assert_eq!(stack.wakers().registrations.iter().count(), 1);
+
+        let mut other = stack.pop_async();
+        assert!(Pin::new(&mut other).poll(&mut context).is_pending());
+        drop(pop);
+        assert_eq!(stack.wakers().registrations.iter().count(), 1);
+
+        stack.push(7);
+        assert_eq!(Pin::new(&mut other).poll(&mut context), Poll::Ready(7));
+        assert_eq!(stack.wakers().waiting.load(Ordering::Relaxed), 0);
+        assert!(stack.wakers().registrations.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use super::Stack;
+use crate::{dump::CollectionDump, internal::thread_hint};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+use crossbeam_utils::CachePadded;
+
+/// The number of shards a `Bag` is split across by default.
+const SHARDS: usize = 8;
+
+/// `Bag` is an unordered concurrent collection tuned for recycling objects such as buffers or connections.
+///
+/// It is split into shards, each of which is a `Stack`. Threads put to and get from the shard
+/// suggested by their stack address first, so threads that mostly recycle their own objects rarely contend,
+/// and only look at the other shards when theirs is empty.
+/// No ordering is guaranteed between objects.
+/// Objects are moved out as they are taken, so unlike the other collections a `Bag` cannot be iterated or snapshotted.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::collections::Bag;
+///
+/// let pool = Bag::new();
+/// (0..1000).into_par_iter().for_each(|_| {
+///     let mut buffer = pool.try_get().unwrap_or_else(|| Vec::with_capacity(1024));
+///     buffer.clear();
+///     buffer.extend_from_slice(b"recycled");
+///     pool.put(buffer);
+/// });
+/// assert!(pool.try_get().is_some());
+/// ```
+pub struct Bag<T: Send> {
+    shards: Box<[CachePadded<Stack<T>>]>,
+}
+
+impl<T: Send> Bag<T> {
+    /// Create an empty bag with the default number of shards.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_shards(SHARDS)
+    }
+
+    /// Create an empty bag split across `shards` shards.
+    ///
+    /// # Panics
+    /// - If `shards` is 0.
+    #[must_use]
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "Bag requires at least one shard!");
+        Bag {
+            shards: (0..shards).map(|_| CachePadded::new(Stack::new())).collect::<Vec<_>>().into_boxed_slice(),
+        }
+    }
+
+    /// Put `value` in the bag.
+    pub fn put(&self, value: T) {
+        self.shards[thread_hint() % self.shards.len()].push(value);
+    }
+
+    /// Take any value out of the bag, or None if every shard appears empty.
+    #[must_use]
+    pub fn try_get(&self) -> Option<T> {
+        let start = thread_hint();
+        (0..self.shards.len()).find_map(|offset| self.shards[start.wrapping_add(offset) % self.shards.len()].try_pop())
+    }
+
+    /// Get the approximate number of values in the bag. See `Stack::len`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    /// Check if every shard appears empty. Other threads may put or get concurrently, so this is only a hint.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_empty())
+    }
+
+    /// Describe the state of the bag for logging, with the slots of every shard in turn. See `CollectionDump`.
+    #[must_use]
+    pub fn dump(&self) -> CollectionDump {
+        CollectionDump {
+            collection: "Bag",
+            len: self.len(),
+            slots: self.shards.iter().flat_map(|shard| shard.dump().slots).collect(),
+        }
+    }
+}
+
+impl<T: Send> Default for Bag<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> fmt::Debug for Bag<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bag").field("shards", &self.shards.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bag_recycle_mt_test() {
+        extern crate std;
+        use std::thread;
+        const THREADS: usize = 4;
+        const VALUES: usize = 1000;
+
+        // Fewer shards than threads, so threads share shards and fall back on the others when theirs runs dry.
+        let bag = Bag::with_shards(2);
+        let taken: Vec<Vec<usize>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS).map(|thread| {
+                let bag = &bag;
+                scope.spawn(move || {
+                    for value in thread * VALUES..(thread + 1) * VALUES {
+                        bag.put(value);
+                    }
+                    (0..VALUES).map_while(|_| bag.try_get()).collect()
+                })
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        // Every value comes out exactly once, whichever thread put it.
+        let mut all: Vec<usize> = taken.into_iter().flatten().chain(core::iter::from_fn(|| bag.try_get())).collect();
+        all.sort_unstable();
+        assert!(all.into_iter().eq(0..THREADS * VALUES));
+        assert!(bag.is_empty());
+    }
+}
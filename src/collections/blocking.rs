@@ -0,0 +1,21 @@
+use crate::wait;
+use core::sync::atomic::{Ordering, fence};
+use std::time::Instant;
+
+/// Pop with `try_pop` until it succeeds, parking while `is_empty` and waking on `wait::notify(address)`.
+/// Returns None if `deadline` passes first.
+pub(crate) fn pop<T, P: Fn() -> Option<T>, E: Fn() -> bool>(address: usize, deadline: Option<Instant>, try_pop: P, is_empty: E) -> Option<T> {
+    loop {
+        if let Some(value) = try_pop() {
+            return Some(value);
+        }
+        let pushed = || {
+            // Pairs with the fence in `wait::notify`: either the push sees us waiting or we see the push.
+            fence(Ordering::SeqCst);
+            !is_empty()
+        };
+        if !wait::wait_until(address, pushed, deadline) {
+            return None;
+        }
+    }
+}
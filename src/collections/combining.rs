@@ -0,0 +1,158 @@
+use crate::internal::{back_off, thread_hint};
+use alloc::{boxed::Box, vec::Vec};
+use core::{cell::UnsafeCell, sync::atomic::{AtomicBool, AtomicU8, Ordering}};
+use crossbeam_utils::{Backoff, CachePadded};
+
+/// The number of publication records, and so the number of threads that can wait on a combiner at once.
+const RECORDS: usize = 64;
+
+const FREE: u8 = 0;
+const CLAIMED: u8 = 1;
+const PENDING: u8 = 2;
+const DONE: u8 = 3;
+
+struct Record<R> {
+    state: AtomicU8,
+    /// The request while `PENDING`, then the response once `DONE`.
+    operation: UnsafeCell<Option<R>>,
+}
+
+/// A request to, or response from, a flat-combining collection.
+pub(crate) enum Operation<T> {
+    Push(T),
+    Pop,
+    Popped(Option<T>),
+}
+
+/// A flat-combining publication list.
+/// Threads publish requests, and whichever thread holds the combiner lock applies every published request on behalf of the others,
+/// so a heavily contended structure sees one thread at a time instead of a storm of failing compare-exchanges.
+/// Every request is applied to the structure `S` by the same `apply`, given when the combiner is created.
+///
+/// Waiting for a combiner is blocking, so collections only use this when asked to.
+pub(crate) struct Combiner<S, R> {
+    lock: CachePadded<AtomicBool>,
+    records: Box<[CachePadded<Record<R>>]>,
+    apply: fn(&S, R) -> R,
+}
+
+impl<S, R: Send> Combiner<S, R> {
+    #[must_use]
+    pub(crate) fn new(apply: fn(&S, R) -> R) -> Self {
+        Combiner {
+            lock: CachePadded::new(AtomicBool::new(false)),
+            records: (0..RECORDS).map(|_| CachePadded::new(Record {
+                state: AtomicU8::new(FREE),
+                operation: UnsafeCell::new(None),
+            })).collect::<Vec<_>>().into_boxed_slice(),
+            apply,
+        }
+    }
+
+    /// Publish `request` and wait for a combiner, possibly this thread, to apply it to `target` and replace it with the response.
+    /// `target` must be the structure that owns the combiner.
+    /// Returns the request unapplied if every record is in use, in which case the caller should apply it directly.
+    ///
+    /// # Panics
+    /// If applying the request panicked on another thread.
+    pub(crate) fn submit(&self, target: &S, request: R) -> Result<R, R> {
+        let start = thread_hint();
+        let record = match (0..RECORDS).map(|offset| &self.records[start.wrapping_add(offset) % RECORDS]).find(|record| {
+            record.state.load(Ordering::Relaxed) == FREE
+                && record.state.compare_exchange(FREE, CLAIMED, Ordering::Acquire, Ordering::Relaxed).is_ok()
+        }) {
+            Some(record) => record,
+            None => return Err(request),
+        };
+        unsafe {
+            *record.operation.get() = Some(request);
+        }
+        record.state.store(PENDING, Ordering::Release);
+
+        let backoff = Backoff::new();
+        loop {
+            if record.state.load(Ordering::Acquire) == DONE {
+                let response = unsafe {
+                    (*record.operation.get()).take()
+                };
+                record.state.store(FREE, Ordering::Release);
+                return Ok(response.expect("the thread combining this operation panicked"));
+            }
+            if !self.lock.load(Ordering::Relaxed) && self.lock.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+                self.combine(target, record);
+            }
+            else {
+                back_off(&backoff);
+            }
+        }
+    }
+
+    /// Apply every pending request, then release the lock. Must only be called while holding the lock.
+    fn combine<'a>(&'a self, target: &S, own: &'a Record<R>) {
+        let mut combining = Combining {
+            combiner: self,
+            own,
+            applying: None,
+        };
+        for record in self.records.iter() {
+            if record.state.load(Ordering::Acquire) == PENDING {
+                combining.applying = Some(record);
+                unsafe {
+                    let operation = &mut *record.operation.get();
+                    *operation = operation.take().map(|request| (self.apply)(target, request));
+                }
+                combining.applying = None;
+                record.state.store(DONE, Ordering::Release);
+            }
+        }
+    }
+}
+
+unsafe impl<S, R: Send> Sync for Combiner<S, R> {}
+
+/// Releases the combiner lock when dropped. If `apply` panicked, it first settles the records left behind,
+/// so that the other threads waiting on the combiner are neither stuck behind the lock nor left `PENDING` forever.
+struct Combining<'a, S, R> {
+    combiner: &'a Combiner<S, R>,
+    /// The record of the thread holding the lock.
+    own: &'a Record<R>,
+    /// The record being applied, whose request was consumed by `apply`.
+    applying: Option<&'a Record<R>>,
+}
+
+impl<S, R> Drop for Combining<'_, S, R> {
+    fn drop(&mut self) {
+        if let Some(applying) = self.applying {
+            // Its waiter finds no response and reports the panic.
+            applying.state.store(DONE, Ordering::Release);
+            // This thread is unwinding, so nobody will collect its own response. No other combiner can touch its record yet.
+            unsafe {
+                *self.own.operation.get() = None;
+            }
+            self.own.state.store(FREE, Ordering::Release);
+        }
+        self.combiner.lock.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combiner_apply_panic_st_test() {
+        extern crate std;
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        let combiner = Combiner::new(|_: &(), request: i32| {
+            assert!(request >= 0, "negative request");
+            request * 2
+        });
+        assert_eq!(combiner.submit(&(), 21).ok(), Some(42));
+        assert!(catch_unwind(AssertUnwindSafe(|| combiner.submit(&(), -1))).is_err());
+        // The lock and the record of the panicking thread were released, so later requests still complete.
+        assert!(!combiner.lock.load(Ordering::Relaxed));
+        assert!(combiner.records.iter().all(|record| record.state.load(Ordering::Relaxed) == FREE));
+        assert_eq!(combiner.submit(&(), 4).ok(), Some(8));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{atomic::*, pointer::*};
+use alloc::vec::Vec;
+use core::{fmt, iter::FromIterator, sync::atomic::Ordering};
+
+/// `CowVec` is a small list published through a single `AtomicXarc`, for data that is read far more often than it is modified,
+/// such as a registry of listeners.
+///
+/// Readers take a consistent `Xarc<[T]>` snapshot with `load`.
+/// Writers copy the current contents, modify the copy, and compare-exchange it into place, retrying if another writer got there first,
+/// so each modification is O(n) but never blocks readers or other writers.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::collections::CowVec;
+///
+/// let listeners = CowVec::new();
+/// listeners.push("alpha");
+/// listeners.push("beta");
+/// listeners.push("gamma");
+/// let snapshot = listeners.load();
+/// listeners.retain(|name| *name != "beta");
+/// assert_eq!(snapshot.maybe_deref(), Some(&["alpha", "beta", "gamma"][..]));
+/// assert_eq!(listeners.load().maybe_deref(), Some(&["alpha", "gamma"][..]));
+/// ```
+pub struct CowVec<T: Send> {
+    slot: AtomicXarc<[T]>,
+}
+
+impl<T: Send> CowVec<T> {
+    /// Create an empty list.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::from(Vec::new())
+    }
+
+    /// Load a snapshot of the current contents. It is unaffected by later modifications
+    /// and can be iterated with `Xarc::iter` for as long as it is held.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self) -> Xarc<[T]> {
+        self.slot.load(Ordering::Acquire)
+    }
+
+    /// Run `f` on the current contents without taking a reference to them.
+    pub fn with<R, F: FnOnce(&[T]) -> R>(&self, f: F) -> R {
+        self.slot.with(Ordering::Acquire, |values| f(values.unwrap()))
+    }
+
+    /// Get the number of elements currently in the list.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.with(<[T]>::len)
+    }
+
+    /// Check if the list is currently empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replace the contents with `values`, returning the previous contents. A null `Xarc` empties the list.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn replace<V: Into<Xarc<[T]>>>(&self, values: V) -> Xarc<[T]> {
+        let values = values.into();
+        let values = if values.is_null() { Xarc::from(Vec::new()) } else { values };
+        self.slot.swap(&values, Ordering::AcqRel)
+    }
+
+    /// Replace the contents with the result of `f`, retrying with the latest contents if another writer intervenes.
+    /// `f` may return None to leave the contents unchanged.
+    ///
+    /// Returns the contents that were replaced, or the current contents if `f` returned None.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn update<F: FnMut(&[T]) -> Option<Vec<T>>>(&self, mut f: F) -> Result<Xarc<[T]>, Xarc<[T]>> {
+        self.slot.cas_loop(|current| f(current.as_slice()).map(Xarc::from), Ordering::AcqRel, Ordering::Acquire)
+    }
+}
+
+impl<T: Clone + Send> CowVec<T> {
+    /// Append a clone of `value`.
+    pub fn push(&self, value: T) {
+        let _ = self.update(|current| {
+            let mut values = Vec::with_capacity(current.len() + 1);
+            values.extend_from_slice(current);
+            values.push(value.clone());
+            Some(values)
+        });
+    }
+
+    /// Remove every element for which `f` returns false. Returns the number of elements removed.
+    pub fn retain<F: FnMut(&T) -> bool>(&self, mut f: F) -> usize {
+        let mut removed = 0;
+        let _ = self.update(|current| {
+            let values: Vec<T> = current.iter().filter(|value| f(value)).cloned().collect();
+            removed = current.len() - values.len();
+            if removed == 0 {
+                None
+            }
+            else {
+                Some(values)
+            }
+        });
+        removed
+    }
+}
+
+impl<T: Send> Default for CowVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> From<Vec<T>> for CowVec<T> {
+    fn from(values: Vec<T>) -> Self {
+        CowVec {
+            slot: Xarc::from(values).into_atomic(),
+        }
+    }
+}
+
+impl<T: Send> FromIterator<T> for CowVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for CowVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with(|values| f.debug_list().entries(values).finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn cow_vec_replace_null_st_test() {
+        let list = CowVec::from(vec![1, 2, 3]);
+        assert_eq!(list.replace(Xarc::<[i32]>::null()).as_slice(), &[1, 2, 3]);
+        assert!(list.is_empty());
+        assert!(!list.load().is_null());
+        list.push(4);
+        assert_eq!(list.load().as_slice(), &[4]);
+        assert_eq!(alloc::format!("{:?}", list), "[4]");
+    }
+
+    #[test]
+    fn cow_vec_push_retain_mt_test() {
+        extern crate std;
+        use std::thread;
+
+        let list = CowVec::new();
+        thread::scope(|scope| {
+            for thread in 0..4 {
+                let list = &list;
+                scope.spawn(move || {
+                    for i in 0..250 {
+                        list.push(thread * 250 + i);
+                        // Each thread removes its own odd values, racing with the pushes of the others.
+                        if i % 50 == 49 {
+                            assert!(list.retain(|value| value % 2 == 0 || value / 250 != thread) > 0);
+                        }
+                    }
+                });
+            }
+        });
+        let mut values = list.load().as_slice().to_vec();
+        values.sort_unstable();
+        assert!(values.into_iter().eq((0..1000).filter(|value| value % 2 == 0)));
+    }
+}
@@ -0,0 +1,315 @@
+use super::{SlotKey, SlotMap};
+use crate::{atomic::*, pointer::*, weak::WeakXarc};
+use alloc::vec::Vec;
+use core::{fmt, ops::Deref, sync::atomic::{AtomicBool, Ordering}};
+
+/// One end of an edge, as recorded by the node at the other end.
+struct Edge<T: Send> {
+    key: SlotKey,
+    node: WeakXarc<GraphNode<T>>,
+}
+
+impl<T: Send> Edge<T> {
+    /// The node at this end, unless it has been removed from the graph.
+    #[must_use]
+    fn upgrade(&self) -> Option<Xarc<GraphNode<T>>> {
+        let node = self.node.upgrade();
+        if node.maybe_deref().is_none_or(GraphNode::is_removed) {
+            None
+        }
+        else {
+            Some(node)
+        }
+    }
+
+    #[must_use]
+    fn is_dead(&self) -> bool {
+        self.upgrade().is_none()
+    }
+}
+
+impl<T: Send> Clone for Edge<T> {
+    fn clone(&self) -> Self {
+        Edge {
+            key: self.key,
+            node: self.node.clone(),
+        }
+    }
+}
+
+type Edges<T> = [Edge<T>];
+
+/// A node of a `Graph`: its value and the edges leading out of it and into it.
+/// It dereferences to its value.
+pub struct GraphNode<T: Send> {
+    value: T,
+    /// Set once the node has been removed, so that edges to it stop being followed even while a handle keeps it alive.
+    removed: AtomicBool,
+    outgoing: AtomicXarc<Edges<T>>,
+    incoming: AtomicXarc<Edges<T>>,
+}
+
+impl<T: Send> GraphNode<T> {
+    /// The value of the node.
+    #[must_use]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Check whether the node has been removed from its graph.
+    #[must_use]
+    pub fn is_removed(&self) -> bool {
+        self.removed.load(Ordering::Acquire)
+    }
+}
+
+impl<T: Send> Deref for GraphNode<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for GraphNode<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphNode").field("value", &self.value).field("removed", &self.is_removed()).finish_non_exhaustive()
+    }
+}
+
+/// `Graph` is a directed graph whose nodes and edges can be added and removed concurrently, with cycles of any shape.
+///
+/// Nodes live in a `SlotMap` that holds the only strong reference the graph keeps to each, addressed by its `SlotKey`.
+/// Every node lists the edges leading out of it and, as back-edges, those leading into it,
+/// each list an immutable slice published through an `AtomicXarc` and replaced by a compare-exchange on every change.
+/// Edges hold `WeakXarc`s in both directions, so no cycle of nodes can keep itself alive:
+/// removing a node drops the graph's reference to it, and it is reclaimed as soon as no caller holds an `Xarc` to it,
+/// without any of the manual epoch management that unlinking a node from a cyclic structure usually needs.
+///
+/// Edges to a removed node are never followed again, and are dropped from a list the next time that list changes.
+/// Adding an edge updates the lists at its two ends one after the other, so a traversal may briefly see one without the other.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::collections::Graph;
+///
+/// let graph = Graph::new();
+/// let a = graph.add_node("a");
+/// let b = graph.add_node("b");
+/// let c = graph.add_node("c");
+/// assert!(graph.add_edge(a, b));
+/// assert!(graph.add_edge(b, c));
+/// assert!(graph.add_edge(c, a));
+///
+/// let successors: Vec<&str> = graph.successors(a).iter().filter_map(|(_, node)| node.maybe_deref()).map(|node| *node.value()).collect();
+/// assert_eq!(successors, ["b"]);
+/// assert_eq!(graph.predecessors(a)[0].0, c);
+///
+/// let removed = graph.remove_node(b).unwrap();
+/// assert!(removed.maybe_deref().unwrap().is_removed());
+/// assert!(graph.successors(a).is_empty());
+/// assert!(graph.predecessors(c).is_empty());
+/// assert_eq!(graph.len(), 2);
+/// ```
+pub struct Graph<T: Send> {
+    nodes: SlotMap<GraphNode<T>>,
+}
+
+impl<T: Send> Graph<T> {
+    /// Create an empty graph.
+    #[must_use]
+    pub fn new() -> Self {
+        Graph {
+            nodes: SlotMap::new(),
+        }
+    }
+
+    /// Add a node holding `value`, with no edges, and return its key.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn add_node(&self, value: T) -> SlotKey {
+        self.nodes.insert(GraphNode {
+            value,
+            removed: AtomicBool::new(false),
+            outgoing: AtomicXarc::null(),
+            incoming: AtomicXarc::null(),
+        })
+    }
+
+    /// Get the node for `key`, or None if it has been removed.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn node(&self, key: SlotKey) -> Option<Xarc<GraphNode<T>>> {
+        self.nodes.get(key)
+    }
+
+    /// Remove the node for `key`, and with it every edge leading out of it or into it.
+    /// Returns the node, or None if it had already been removed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn remove_node(&self, key: SlotKey) -> Option<Xarc<GraphNode<T>>> {
+        let node = self.nodes.remove(key)?;
+        let removed = node.maybe_deref()?;
+        removed.removed.store(true, Ordering::Release);
+        for edge in removed.outgoing.load(Ordering::Acquire).iter() {
+            if let Some(target) = edge.upgrade().as_ref().and_then(Xarc::maybe_deref) {
+                let _ = rewrite(&target.incoming, key, None);
+            }
+        }
+        for edge in removed.incoming.load(Ordering::Acquire).iter() {
+            if let Some(source) = edge.upgrade().as_ref().and_then(Xarc::maybe_deref) {
+                let _ = rewrite(&source.outgoing, key, None);
+            }
+        }
+        Some(node)
+    }
+
+    /// Add an edge from the node for `from` to the node for `to`.
+    /// Returns false if either node has been removed or the edge already exists.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn add_edge(&self, from: SlotKey, to: SlotKey) -> bool {
+        let (Some(source), Some(target)) = (self.nodes.get(from), self.nodes.get(to)) else {
+            return false;
+        };
+        let (Some(outgoing), Some(incoming)) = (
+            source.maybe_deref().map(|node| &node.outgoing),
+            target.maybe_deref().map(|node| &node.incoming),
+        ) else {
+            return false;
+        };
+        let forward = Edge {
+            key: to,
+            node: Xarc::downgrade(&target),
+        };
+        if !rewrite(outgoing, to, Some(forward)) {
+            return false;
+        }
+        let back = Edge {
+            key: from,
+            node: Xarc::downgrade(&source),
+        };
+        let _ = rewrite(incoming, from, Some(back));
+        true
+    }
+
+    /// Remove the edge from the node for `from` to the node for `to`. Returns false if there was no such edge.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn remove_edge(&self, from: SlotKey, to: SlotKey) -> bool {
+        let Some(source) = self.nodes.get(from) else {
+            return false;
+        };
+        if !source.maybe_deref().is_some_and(|source| rewrite(&source.outgoing, to, None)) {
+            return false;
+        }
+        if let Some(target) = self.nodes.get(to).as_ref().and_then(Xarc::maybe_deref) {
+            let _ = rewrite(&target.incoming, from, None);
+        }
+        true
+    }
+
+    /// Check whether there is an edge from the node for `from` to the node for `to`.
+    #[must_use]
+    pub fn contains_edge(&self, from: SlotKey, to: SlotKey) -> bool {
+        self.nodes.get(from).as_ref().and_then(Xarc::maybe_deref).is_some_and(|source| source.outgoing.load(Ordering::Acquire).iter()
+            .any(|edge| edge.key == to && edge.upgrade().is_some()))
+    }
+
+    /// The nodes that edges from the node for `key` lead to, with their keys. Empty if the node has been removed.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn successors(&self, key: SlotKey) -> Vec<(SlotKey, Xarc<GraphNode<T>>)> {
+        self.nodes.get(key).as_ref().and_then(Xarc::maybe_deref).map_or_else(Vec::new, |node| live(&node.outgoing))
+    }
+
+    /// The nodes with edges leading to the node for `key`, with their keys. Empty if the node has been removed.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn predecessors(&self, key: SlotKey) -> Vec<(SlotKey, Xarc<GraphNode<T>>)> {
+        self.nodes.get(key).as_ref().and_then(Xarc::maybe_deref).map_or_else(Vec::new, |node| live(&node.incoming))
+    }
+
+    /// Get the number of nodes in the graph.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check whether the graph has no nodes.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// The live ends of the edges in `edges`.
+#[must_use]
+fn live<T: Send>(edges: &AtomicXarc<Edges<T>>) -> Vec<(SlotKey, Xarc<GraphNode<T>>)> {
+    edges.load(Ordering::Acquire).iter()
+        .filter_map(|edge| edge.upgrade().map(|node| (edge.key, node)))
+        .collect()
+}
+
+/// Replace the list `edges` with a copy leaving out dead edges and the edge for `key`, then adding `with` if it is given.
+/// Returns whether that changed anything: whether `with` was added where no edge for `key` was,
+/// or, without `with`, whether the edge for `key` was there to remove.
+#[cfg_attr(feature = "debug-owners", track_caller)]
+fn rewrite<T: Send>(edges: &AtomicXarc<Edges<T>>, key: SlotKey, with: Option<Edge<T>>) -> bool {
+    loop {
+        let current = edges.load(Ordering::Acquire);
+        let existing = current.iter().any(|edge| edge.key == key && !edge.is_dead());
+        if existing == with.is_some() {
+            return false;
+        }
+        let kept: Vec<Edge<T>> = current.iter()
+            .filter(|edge| edge.key != key && !edge.is_dead())
+            .cloned()
+            .chain(with.clone())
+            .collect();
+        let new = if kept.is_empty() { Xarc::null() } else { Xarc::from(kept) };
+        if edges.compare_exchange(&current, &new, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            return true;
+        }
+    }
+}
+
+impl<T: Send> Default for Graph<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> fmt::Debug for Graph<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Graph").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_graph_mt_test() {
+        use alloc::vec::Vec;
+        use rayon::prelude::*;
+
+        let graph = Graph::new();
+        let keys: Vec<_> = (0..100).map(|i| graph.add_node(i)).collect();
+        (0..1000).into_par_iter().for_each(|i| {
+            let _ = graph.add_edge(keys[i % 100], keys[(i * 7 + 1) % 100]);
+        });
+        assert!((0..1000).all(|i| graph.contains_edge(keys[i % 100], keys[(i * 7 + 1) % 100])));
+
+        keys.par_iter().enumerate().filter(|(i, _)| i % 2 == 1).for_each(|(_, key)| {
+            let _ = graph.remove_node(*key);
+        });
+        assert_eq!(graph.len(), 50);
+        for key in &keys {
+            assert!(graph.successors(*key).iter().chain(graph.predecessors(*key).iter()).all(|(_, node)| node.maybe_deref().is_some_and(|node| *node.value() % 2 == 0)));
+        }
+
+        (0..1000).into_par_iter().for_each(|i| {
+            let _ = graph.remove_edge(keys[i % 100], keys[(i * 7 + 1) % 100]);
+        });
+        assert!(keys.iter().all(|key| graph.successors(*key).is_empty() && graph.predecessors(*key).is_empty()));
+    }
+}
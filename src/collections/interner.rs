@@ -0,0 +1,180 @@
+use super::length::Length;
+use crate::{atomic::*, internal::fnv_hash, pointer::*};
+use alloc::vec::Vec;
+use core::{fmt, sync::atomic::Ordering};
+
+/// The number of buckets an `Interner` spreads its strings across by default.
+const BUCKETS: usize = 256;
+
+/// An interned string. Entries are never unlinked before the `Interner` is dropped.
+struct Entry {
+    value: Xarc<str>,
+    next: Xarc<Entry>,
+}
+
+/// `Interner` deduplicates strings across threads, returning the same `Xarc<str>` for every equal string.
+///
+/// Strings are hashed into a fixed number of buckets, each of which is a list published through an `AtomicXarc`.
+/// Interning a new string links an entry onto the head of its bucket with a compare-exchange,
+/// so racing threads interning the same string agree on whichever entry was linked first.
+///
+/// Interned strings are kept until the `Interner` is dropped.
+/// For strings that should go away once nothing else references them, use a `WeakMap` from the string to its `Xarc<str>`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::{Xarc, collections::Interner};
+///
+/// let interner = Interner::new();
+/// let names: Vec<Xarc<str>> = (0..1000).into_par_iter().map(|i| interner.intern(["x", "y", "z"][i % 3])).collect();
+/// assert_eq!(interner.len(), 3);
+/// assert!(names.iter().all(|name| *name == interner.intern(name.maybe_deref().unwrap())));
+/// ```
+pub struct Interner {
+    buckets: Vec<AtomicXarc<Entry>>,
+    length: Length,
+}
+
+impl Interner {
+    /// Create an empty interner with the default number of buckets.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_buckets(BUCKETS)
+    }
+
+    /// Create an empty interner with `buckets` buckets.
+    /// The number of buckets is fixed, so choose it to suit the number of distinct strings expected.
+    ///
+    /// # Panics
+    /// - If `buckets` is 0.
+    #[must_use]
+    pub fn with_buckets(buckets: usize) -> Self {
+        assert!(buckets > 0, "Interner requires at least one bucket!");
+        Interner {
+            buckets: (0..buckets).map(|_| AtomicXarc::null()).collect(),
+            length: Length::new(),
+        }
+    }
+
+    /// Get the `Xarc<str>` for `value`, interning a copy of it first if no equal string has been interned yet.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn intern(&self, value: &str) -> Xarc<str> {
+        let head = self.bucket(value);
+        let mut new = None;
+        loop {
+            let current = head.load(Ordering::Acquire);
+            if let Some(entry) = find(&current, value) {
+                return entry.value.clone();
+            }
+            let new = new.get_or_insert_with(|| Xarc::<str>::from(value));
+            let entry = Xarc::new(Entry {
+                value: new.clone(),
+                next: current.clone(),
+            });
+            // If another thread linked an entry first, search again in case it was for the same string.
+            if head.compare_exchange(&current, &entry, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                self.length.add(1);
+                return new.clone();
+            }
+        }
+    }
+
+    /// Get the `Xarc<str>` for `value` if an equal string has been interned, without interning it.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get(&self, value: &str) -> Option<Xarc<str>> {
+        find(&self.bucket(value).load(Ordering::Acquire), value).map(|entry| entry.value.clone())
+    }
+
+    /// Get the number of distinct strings interned.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check whether no strings have been interned.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn bucket(&self, value: &str) -> &AtomicXarc<Entry> {
+        &self.buckets[fnv_hash(value) as usize % self.buckets.len()]
+    }
+}
+
+/// Find the entry for `value` in the bucket starting at `head`.
+/// The entries stay alive as long as `head` does, since entries are never unlinked.
+fn find<'a>(head: &'a Xarc<Entry>, value: &str) -> Option<&'a Entry> {
+    let mut next = head.maybe_deref();
+    while let Some(entry) = next {
+        if entry.value.maybe_deref() == Some(value) {
+            return Some(entry);
+        }
+        next = entry.next.maybe_deref();
+    }
+    None
+}
+
+impl Drop for Interner {
+    fn drop(&mut self) {
+        // Nothing else can reach the entries any more, so they are freed at once rather than one per epoch advance.
+        for bucket in self.buckets.iter_mut() {
+            let mut next = bucket.take_mut();
+            while let Some(entry) = unsafe { next.unwrap_unprotected() } {
+                next = entry.next;
+            }
+        }
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interner").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interner_race_mt_test() {
+        extern crate std;
+        use alloc::{format, string::String};
+        use std::thread;
+        const STRINGS: usize = 100;
+
+        // Few buckets, so that racing threads link different strings onto the same bucket as well as the same string.
+        let interner = Interner::with_buckets(4);
+        let strings: Vec<String> = (0..STRINGS).map(|i| format!("string {}", i)).collect();
+        let interned: Vec<Vec<Xarc<str>>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|thread| {
+                let (interner, strings) = (&interner, &strings);
+                scope.spawn(move || {
+                    let mut interned: Vec<_> = (0..STRINGS).map(|i| (i * 7 + thread * 13) % STRINGS)
+                        .map(|i| (i, interner.intern(&strings[i]))).collect();
+                    interned.sort_by_key(|(i, _)| *i);
+                    interned.into_iter().map(|(_, value)| value).collect()
+                })
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert_eq!(interner.len(), STRINGS);
+        for (i, string) in strings.iter().enumerate() {
+            // Every thread got the same allocation for the same string.
+            assert!(interned.iter().all(|values| values[i] == interned[0][i] && values[i].maybe_deref() == Some(string.as_str())));
+            assert_eq!(interner.get(string).as_ref(), Some(&interned[0][i]));
+        }
+        assert!(interner.get("missing").is_none());
+    }
+}
@@ -0,0 +1,33 @@
+use core::sync::atomic::{AtomicIsize, Ordering};
+use crossbeam_utils::CachePadded;
+
+/// A relaxed count of the values in a collection, for reporting an approximate length.
+///
+/// A pop can be counted before the push of the same value, so the count may briefly dip below zero.
+pub(crate) struct Length {
+    count: CachePadded<AtomicIsize>,
+}
+
+impl Length {
+    #[must_use]
+    pub(crate) const fn new() -> Self {
+        Length {
+            count: CachePadded::new(AtomicIsize::new(0)),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn add(&self, amount: usize) {
+        self.count.fetch_add(amount as isize, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn sub(&self, amount: usize) {
+        self.count.fetch_sub(amount as isize, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub(crate) fn get(&self) -> usize {
+        self.count.load(Ordering::Relaxed).max(0) as usize
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{atomic::*, internal::back_off, pointer::*};
+use core::{fmt, iter::{FromIterator, FusedIterator}, mem, ops::Deref, sync::atomic::Ordering};
+use crossbeam_utils::Backoff;
+
+struct Node<T: Send> {
+    /// None for the head sentinel and for markers.
+    value: Option<T>,
+    /// Set on the nodes that mark their predecessor as removed.
+    marker: bool,
+    next: AtomicXarc<Node<T>>,
+}
+
+impl<T: Send> Node<T> {
+    fn new(value: Option<T>, marker: bool, next: &Xarc<Node<T>>) -> Xarc<Self> {
+        Xarc::new(Node {
+            value,
+            marker,
+            next: AtomicXarc::from(next),
+        })
+    }
+}
+
+fn is_marker<T: Send>(node: &Xarc<Node<T>>) -> bool {
+    node.maybe_deref().is_some_and(|node| node.marker)
+}
+
+/// `List` is a lock-free singly linked list that can be traversed and edited anywhere through a `ListCursor`.
+///
+/// A node is removed by first linking a marker node in as its successor, which stops anything else from being linked after it,
+/// and then unlinking it. Nodes that a remove did not manage to unlink are unlinked by later traversals.
+/// Cursors and iterators hold `Xarc` handles, so the nodes they are on stay alive even after they are removed,
+/// and moving on from a removed node continues with what followed it.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::collections::List;
+///
+/// let list: List<i32> = (1..=5).collect();
+/// let mut cursor = list.cursor();
+/// while let Some(&value) = cursor.move_next() {
+///     if value % 2 == 0 {
+///         assert!(cursor.remove());
+///     }
+///     else {
+///         cursor.insert_after(value * 10).unwrap();
+///         let _ = cursor.move_next();
+///     }
+/// }
+/// assert_eq!(list.iter().map(|value| *value).collect::<Vec<_>>(), [1, 10, 3, 30, 5, 50]);
+/// ```
+pub struct List<T: Send> {
+    head: Xarc<Node<T>>,
+}
+
+impl<T: Send> List<T> {
+    /// Create an empty list.
+    #[must_use]
+    pub fn new() -> Self {
+        List {
+            head: Node::new(None, false, &Xarc::null()),
+        }
+    }
+
+    /// Insert `value` at the front of the list.
+    pub fn push_front(&self, value: T) {
+        // The head is never removed, so this cannot fail.
+        let _ = self.insert_after(&self.head, value);
+    }
+
+    /// Check if the list appears empty. Other threads may edit the list concurrently, so this is only a hint.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.advance(&self.head).is_null()
+    }
+
+    /// Get a cursor positioned before the first value.
+    #[must_use]
+    pub fn cursor(&self) -> ListCursor<'_, T> {
+        ListCursor {
+            list: self,
+            previous: Xarc::null(),
+            current: self.head.clone(),
+        }
+    }
+
+    /// Iterate over the values in the list. Values inserted or removed concurrently may or may not be visited.
+    #[must_use]
+    pub fn iter(&self) -> ListIter<'_, T> {
+        ListIter {
+            list: self,
+            current: self.head.clone(),
+        }
+    }
+
+    /// Get the first node after `from` that has not been removed, or null at the end of the list,
+    /// unlinking removed nodes along the way.
+    fn advance(&self, from: &Xarc<Node<T>>) -> Xarc<Node<T>> {
+        let node = from.maybe_deref().unwrap();
+        let mut next = node.next.load(Ordering::Acquire);
+        if is_marker(&next) {
+            // `from` has been removed itself. Continue with what followed it.
+            next = next.maybe_deref().unwrap().next.load(Ordering::Acquire);
+        }
+        loop {
+            let after = match next.maybe_deref() {
+                Some(candidate) => candidate.next.load(Ordering::Acquire),
+                None => return next,
+            };
+            if !is_marker(&after) {
+                return next;
+            }
+            let successor = after.maybe_deref().unwrap().next.load(Ordering::Acquire);
+            let _ = node.next.compare_exchange(&next, &successor, Ordering::AcqRel, Ordering::Relaxed);
+            next = successor;
+        }
+    }
+
+    /// Link `value` in after `node`. Fails if `node` has been removed.
+    fn insert_after(&self, node: &Xarc<Node<T>>, value: T) -> Result<(), T> {
+        let backoff = Backoff::new();
+        let node = node.maybe_deref().unwrap();
+        let mut new = Node::new(Some(value), false, &Xarc::null());
+        loop {
+            let next = node.next.load(Ordering::Acquire);
+            if is_marker(&next) {
+                // The node has not been published, so nobody else can be looking at it.
+                return Err(unsafe {
+                    new.unguarded_maybe_deref_mut().unwrap().value.take().unwrap()
+                });
+            }
+            let _ = new.maybe_deref().unwrap().next.swap(&next, Ordering::Relaxed);
+            if node.next.compare_exchange(&next, &new, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                return Ok(());
+            }
+            back_off(&backoff);
+        }
+    }
+
+    /// Remove `node`, which follows `previous` unless either has since been removed or something was inserted between them.
+    /// Returns false if `node` had already been removed.
+    fn remove(&self, previous: &Xarc<Node<T>>, node: &Xarc<Node<T>>) -> bool {
+        let backoff = Backoff::new();
+        let removed = node.maybe_deref().unwrap();
+        loop {
+            let next = removed.next.load(Ordering::Acquire);
+            if is_marker(&next) {
+                return false;
+            }
+            let marker = Node::new(None, true, &next);
+            if removed.next.compare_exchange(&next, &marker, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                // If this fails, a later traversal will unlink the node instead.
+                let _ = previous.maybe_deref().unwrap().next.compare_exchange(node, &next, Ordering::AcqRel, Ordering::Relaxed);
+                return true;
+            }
+            back_off(&backoff);
+        }
+    }
+}
+
+impl<T: Send> Drop for List<T> {
+    fn drop(&mut self) {
+        // Nodes still held by a `ListEntry` stop the teardown, and the rest of the chain is released through the epoch.
+        let mut next = mem::replace(&mut self.head, Xarc::null());
+        while let Some(mut node) = unsafe { next.unwrap_unprotected() } {
+            next = node.next.take_mut();
+        }
+    }
+}
+
+impl<T: Send> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let list = List::new();
+        let mut cursor = list.cursor();
+        for value in values {
+            let _ = cursor.insert_after(value);
+            let _ = cursor.move_next();
+        }
+        list
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Send> IntoIterator for &'a List<T> {
+    type Item = ListEntry<T>;
+    type IntoIter = ListIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+unsafe impl<T: Send + Sync> Sync for List<T> {}
+
+/// A position in a `List` from which values can be read, removed and inserted. See `List::cursor`.
+///
+/// A cursor starts before the first value, where `current` is None and `insert_after` inserts at the front.
+pub struct ListCursor<'a, T: Send> {
+    list: &'a List<T>,
+    previous: Xarc<Node<T>>,
+    current: Xarc<Node<T>>,
+}
+
+impl<T: Send> ListCursor<'_, T> {
+    /// Get the value the cursor is on, or None if it is before the first value.
+    /// The value remains readable even if it is removed concurrently.
+    #[must_use]
+    pub fn current(&self) -> Option<&T> {
+        self.current.maybe_deref().unwrap().value.as_ref()
+    }
+
+    /// Move to the next value and return it, or stay put and return None at the end of the list.
+    #[allow(clippy::should_implement_trait)]
+    pub fn move_next(&mut self) -> Option<&T> {
+        let next = self.list.advance(&self.current);
+        if next.is_null() {
+            return None;
+        }
+        self.previous = core::mem::replace(&mut self.current, next);
+        self.current()
+    }
+
+    /// Remove the value the cursor is on. The cursor stays on it, so `move_next` continues with what followed it.
+    /// Returns false if the cursor is before the first value or the value had already been removed.
+    pub fn remove(&mut self) -> bool {
+        !self.previous.is_null() && self.list.remove(&self.previous, &self.current)
+    }
+
+    /// Insert `value` after the value the cursor is on, without moving the cursor.
+    /// Returns `value` if the value the cursor is on has been removed.
+    pub fn insert_after(&self, value: T) -> Result<(), T> {
+        self.list.insert_after(&self.current, value)
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for ListCursor<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListCursor").field("current", &self.current()).finish()
+    }
+}
+
+/// A handle to a value in a `List`, which keeps it alive even if it is removed.
+pub struct ListEntry<T: Send> {
+    node: Xarc<Node<T>>,
+}
+
+impl<T: Send> Deref for ListEntry<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.node.maybe_deref().unwrap().value.as_ref().unwrap()
+    }
+}
+
+impl<T: Send> Clone for ListEntry<T> {
+    fn clone(&self) -> Self {
+        ListEntry {
+            node: self.node.clone(),
+        }
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for ListEntry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// An iterator over the values of a `List`. See `List::iter`.
+pub struct ListIter<'a, T: Send> {
+    list: &'a List<T>,
+    current: Xarc<Node<T>>,
+}
+
+impl<T: Send> Iterator for ListIter<'_, T> {
+    type Item = ListEntry<T>;
+
+    fn next(&mut self) -> Option<ListEntry<T>> {
+        if self.current.is_null() {
+            return None;
+        }
+        self.current = self.list.advance(&self.current);
+        if self.current.is_null() {
+            None
+        }
+        else {
+            Some(ListEntry {
+                node: self.current.clone(),
+            })
+        }
+    }
+}
+
+impl<T: Send> FusedIterator for ListIter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_cursor_remove_insert_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use core::sync::atomic::AtomicUsize;
+        use std::thread;
+
+        let list: List<usize> = (0..200).collect();
+        let removed = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            // Two threads race to remove the even values, and each remove may succeed only once.
+            for _ in 0..2 {
+                let (list, removed) = (&list, &removed);
+                scope.spawn(move || {
+                    let mut cursor = list.cursor();
+                    while let Some(&value) = cursor.move_next() {
+                        if value < 1000 && value % 2 == 0 && cursor.remove() {
+                            removed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+            // Two more insert after the odd values, which are never removed, next to the nodes being removed.
+            for residue in [1, 3] {
+                let list = &list;
+                scope.spawn(move || {
+                    let mut cursor = list.cursor();
+                    while let Some(&value) = cursor.move_next() {
+                        if value < 1000 && value % 4 == residue {
+                            assert!(cursor.insert_after(value + 1000).is_ok());
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(removed.load(Ordering::Relaxed), 100);
+        let values: Vec<usize> = list.iter().map(|value| *value).collect();
+        let expected: Vec<usize> = (0..200).filter(|value| value % 2 == 1).flat_map(|value| [value, value + 1000]).collect();
+        assert_eq!(values, expected);
+    }
+}
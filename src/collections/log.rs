@@ -0,0 +1,295 @@
+use crate::{atomic::*, dump::CollectionDump, pointer::*};
+use alloc::vec;
+use core::{array, fmt, mem, sync::atomic::{AtomicUsize, Ordering}};
+
+/// The number of entries stored in each chunk of a `Log`.
+const CHUNK: usize = 64;
+
+struct Chunk<T: Send> {
+    /// The index of the first entry in this chunk.
+    base: usize,
+    slots: [AtomicXarc<T>; CHUNK],
+    next: AtomicXarc<Chunk<T>>,
+}
+
+impl<T: Send> Chunk<T> {
+    fn new(base: usize) -> Self {
+        Chunk {
+            base,
+            slots: array::from_fn(|_| AtomicXarc::null()),
+            next: AtomicXarc::null(),
+        }
+    }
+
+    /// Get the chunk after this one, appending it first if necessary.
+    #[must_use]
+    fn next_or_append(&self) -> Xarc<Self> {
+        let next = self.next.load(Ordering::Acquire);
+        if !next.is_null() {
+            return next;
+        }
+        let new = Xarc::new(Chunk::new(self.base + CHUNK));
+        match self.next.compare_exchange(&Xarc::null(), &new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => new,
+            Err(current) => current,
+        }
+    }
+}
+
+/// `Log` is a lock-free append-only log of `Xarc`s, for event sourcing and tracing buffers.
+///
+/// Entries are stored in fixed-size chunks linked together with `AtomicXarc`, and the log keeps a pointer to its last chunk
+/// so that appending does not walk the whole log.
+/// Entries are handed out as `Xarc`s, so they can be kept after the log is dropped.
+///
+/// `tail` follows the log from an index onward. The iterator holds a reference to the chunk it is reading,
+/// which keeps that chunk and everything after it alive, so it does not borrow the log
+/// and can be resumed after it catches up with the writers.
+///
+/// An index is reserved before its entry is written, so `len` can briefly count entries that `get` does not return yet.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::collections::Log;
+///
+/// let log = Log::new();
+/// let mut tail = log.tail(0);
+/// (0..1000).into_par_iter().for_each(|i| {
+///     let index = log.append(i);
+///     assert_eq!(*log.get(index).unwrap().maybe_deref().unwrap(), i);
+/// });
+/// assert_eq!(log.len(), 1000);
+/// assert_eq!(tail.by_ref().map(|entry| *entry.maybe_deref().unwrap()).sum::<i32>(), (0..1000).sum());
+///
+/// log.append(1000);
+/// assert_eq!(*tail.next().unwrap().maybe_deref().unwrap(), 1000);
+/// assert_eq!(tail.position(), 1001);
+/// ```
+pub struct Log<T: Send> {
+    head: Xarc<Chunk<T>>,
+    last: AtomicXarc<Chunk<T>>,
+    len: AtomicUsize,
+}
+
+impl<T: Send> Log<T> {
+    /// Create an empty log.
+    #[must_use]
+    pub fn new() -> Self {
+        let head = Xarc::new(Chunk::new(0));
+        Log {
+            last: AtomicXarc::from(&head),
+            head,
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Append `value` and return its index.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn append(&self, value: T) -> usize {
+        let value = Xarc::new(value);
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        let last = self.last.load(Ordering::Acquire);
+        let mut chunk = if last.maybe_deref().unwrap().base <= index {
+            last.clone()
+        }
+        else {
+            self.head.clone()
+        };
+        while chunk.maybe_deref().unwrap().base + CHUNK <= index {
+            chunk = chunk.maybe_deref().unwrap().next_or_append();
+        }
+        if chunk.maybe_deref().unwrap().base > last.maybe_deref().unwrap().base {
+            // Another append may have moved it further already, in which case it is left alone.
+            let _ = self.last.compare_exchange(&last, &chunk, Ordering::AcqRel, Ordering::Relaxed);
+        }
+        let chunk = chunk.maybe_deref().unwrap();
+        let _ = chunk.slots[index - chunk.base].swap(&value, Ordering::Release);
+        index
+    }
+
+    /// Get the entry at `index`, or None if it has not been appended yet.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get(&self, index: usize) -> Option<Xarc<T>> {
+        let last = self.last.load(Ordering::Acquire);
+        let mut chunk = if last.maybe_deref().unwrap().base <= index {
+            last
+        }
+        else {
+            self.head.clone()
+        };
+        while chunk.maybe_deref()?.base + CHUNK <= index {
+            chunk = chunk.maybe_deref().unwrap().next.load(Ordering::Acquire);
+        }
+        let chunk = chunk.maybe_deref()?;
+        let value = chunk.slots[index - chunk.base].load(Ordering::Acquire);
+        if value.is_null() {
+            None
+        }
+        else {
+            Some(value)
+        }
+    }
+
+    /// Get the number of indices that have been reserved by `append`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Check if nothing has been appended yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Describe the state of the log for logging. See `CollectionDump`.
+    #[must_use]
+    pub fn dump(&self) -> CollectionDump {
+        CollectionDump {
+            collection: "Log",
+            len: self.len(),
+            slots: vec![("last", self.last.debug_state())],
+        }
+    }
+
+    /// Follow the log from `index` onward. See `LogTail`.
+    #[must_use]
+    pub fn tail(&self, index: usize) -> LogTail<T> {
+        LogTail {
+            chunk: self.head.clone(),
+            position: index,
+        }
+    }
+}
+
+impl<T: Send> Drop for Log<T> {
+    fn drop(&mut self) {
+        // Chunks still held by a `LogTail` stop the teardown, and the rest of the chain is released through the epoch.
+        drop(self.last.take_mut());
+        let mut next = mem::replace(&mut self.head, Xarc::null());
+        while let Some(mut chunk) = unsafe { next.unwrap_unprotected() } {
+            next = chunk.next.take_mut();
+        }
+    }
+}
+
+impl<T: Send> Default for Log<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> fmt::Debug for Log<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Log").field("len", &self.len()).finish()
+    }
+}
+
+impl<T: Send> Extend<T> for Log<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.append(value);
+        }
+    }
+}
+
+/// An iterator that follows a `Log` from some index onward. See `Log::tail`.
+///
+/// It yields entries in index order and returns None when it reaches an entry that has not been written yet,
+/// after which it can be called again to pick up entries appended since.
+/// Entries are never skipped, so an index that was reserved but not yet written holds it up until the entry arrives.
+pub struct LogTail<T: Send> {
+    chunk: Xarc<Chunk<T>>,
+    position: usize,
+}
+
+impl<T: Send> LogTail<T> {
+    /// The index of the next entry to be yielded.
+    #[must_use]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl<T: Send> Iterator for LogTail<T> {
+    type Item = Xarc<T>;
+
+    fn next(&mut self) -> Option<Xarc<T>> {
+        loop {
+            let chunk = self.chunk.maybe_deref().unwrap();
+            if self.position < chunk.base + CHUNK {
+                break;
+            }
+            let next = chunk.next.load(Ordering::Acquire);
+            if next.is_null() {
+                return None;
+            }
+            self.chunk = next;
+        }
+        let chunk = self.chunk.maybe_deref().unwrap();
+        let value = chunk.slots[self.position - chunk.base].load(Ordering::Acquire);
+        if value.is_null() {
+            None
+        }
+        else {
+            self.position += 1;
+            Some(value)
+        }
+    }
+}
+
+impl<T: Send> fmt::Debug for LogTail<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogTail").field("position", &self.position).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_tail_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use std::thread;
+        const WRITERS: usize = 3;
+        const APPENDS: usize = 500;
+
+        let log = Log::<usize>::new();
+        let read = thread::scope(|scope| {
+            // The reader starts before anything is appended and keeps catching up with the writers.
+            let reader = scope.spawn(|| {
+                let mut tail = log.tail(0);
+                let mut read = Vec::new();
+                while read.len() < WRITERS * APPENDS {
+                    match tail.next() {
+                        Some(entry) => read.extend(entry.maybe_deref()),
+                        None => thread::yield_now(),
+                    }
+                    assert_eq!(tail.position(), read.len());
+                }
+                read
+            });
+            for writer in 0..WRITERS {
+                let log = &log;
+                scope.spawn(move || {
+                    for i in 0..APPENDS {
+                        let value = writer * APPENDS + i;
+                        let index = log.append(value);
+                        assert_eq!(log.get(index).as_ref().and_then(Xarc::maybe_deref), Some(&value));
+                    }
+                });
+            }
+            reader.join().unwrap()
+        });
+        // The tail visits entries in index order.
+        assert!(read.iter().enumerate().all(|(index, value)| log.get(index).as_ref().and_then(Xarc::maybe_deref) == Some(value)));
+        let mut values = read;
+        values.sort_unstable();
+        assert!(values.into_iter().eq(0..WRITERS * APPENDS));
+    }
+}
@@ -0,0 +1,273 @@
+use super::length::Length;
+use crate::{atomic::*, internal::fnv_hash, pointer::*};
+use alloc::vec::Vec;
+use core::{fmt, hash::Hash, sync::atomic::{AtomicBool, Ordering}};
+#[cfg(not(feature = "std"))]
+use crate::internal::back_off;
+#[cfg(not(feature = "std"))]
+use crossbeam_utils::Backoff;
+#[cfg(feature = "std")]
+use crate::wait;
+#[cfg(feature = "std")]
+use core::sync::atomic::fence;
+
+/// The number of buckets a `MemoMap` spreads its keys across by default.
+const BUCKETS: usize = 256;
+
+/// A memoized key. Entries are never unlinked before the `MemoMap` is dropped.
+struct Entry<K: Send, V: Send> {
+    key: K,
+    /// Set while some caller is computing the value.
+    claimed: AtomicBool,
+    value: AtomicXarc<V>,
+    next: AtomicXarc<Entry<K, V>>,
+}
+
+/// `MemoMap` memoizes one value per key, computing each at most once even when callers race on the same key.
+///
+/// The first caller of `get_or_compute` for a key links an entry for it onto its bucket with a compare-exchange and computes the value.
+/// Callers that lose the race wait for the value rather than computing it again: with `std` they park, and otherwise they spin.
+/// If the computation panics, the entry is released and one of the waiting callers computes the value with its own function instead,
+/// so a value is only ever published once.
+///
+/// Values are kept until the `MemoMap` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use rayon::prelude::*;
+/// use xarc::collections::MemoMap;
+///
+/// let memo = MemoMap::new();
+/// let computed = AtomicUsize::new(0);
+/// let lengths: Vec<usize> = (0..1000).into_par_iter().map(|i| {
+///     *memo.get_or_compute(i % 10, || {
+///         computed.fetch_add(1, Ordering::Relaxed);
+///         (i % 10).to_string().len()
+///     }).maybe_deref().unwrap()
+/// }).collect();
+/// assert!(lengths.iter().all(|length| *length == 1));
+/// assert_eq!(computed.load(Ordering::Relaxed), 10);
+/// assert_eq!(memo.len(), 10);
+/// ```
+pub struct MemoMap<K: Hash + Eq + Send + Sync, V: Send> {
+    buckets: Vec<AtomicXarc<Entry<K, V>>>,
+    length: Length,
+}
+
+impl<K: Hash + Eq + Send + Sync, V: Send> MemoMap<K, V> {
+    /// Create an empty map with the default number of buckets.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_buckets(BUCKETS)
+    }
+
+    /// Create an empty map with `buckets` buckets.
+    /// The number of buckets is fixed, so choose it to suit the number of keys expected.
+    ///
+    /// # Panics
+    /// - If `buckets` is 0.
+    #[must_use]
+    pub fn with_buckets(buckets: usize) -> Self {
+        assert!(buckets > 0, "MemoMap requires at least one bucket!");
+        MemoMap {
+            buckets: (0..buckets).map(|_| AtomicXarc::null()).collect(),
+            length: Length::new(),
+        }
+    }
+
+    /// Get the value for `key`, computing it with `f` first if no caller has yet.
+    /// `f` is called at most once per key across all callers, unless an earlier call panicked.
+    /// If another caller is computing the value, wait for it.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get_or_compute<F: FnOnce() -> V>(&self, key: K, f: F) -> Xarc<V> {
+        let head = self.bucket(&key);
+        if let Some(entry) = find(head, &key) {
+            return wait_for(entry, f);
+        }
+        let new = Xarc::new(Entry {
+            key,
+            claimed: AtomicBool::new(true),
+            value: AtomicXarc::null(),
+            next: AtomicXarc::null(),
+        });
+        let entry = unsafe {
+            // `new` is kept alive by this function until it returns, and by the bucket once it is linked.
+            &*new.as_raw()
+        };
+        loop {
+            let current = head.load(Ordering::Acquire);
+            if let Some(existing) = find(head, &entry.key) {
+                return wait_for(existing, f);
+            }
+            let _ = entry.next.swap(&current, Ordering::Relaxed);
+            // If another caller linked an entry first, search again in case it was for the same key.
+            if head.compare_exchange(&current, &new, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                self.length.add(1);
+                return compute(entry, f);
+            }
+        }
+    }
+
+    /// Get the value for `key` if it has been computed, without computing or waiting for it.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get(&self, key: &K) -> Option<Xarc<V>> {
+        let value = find(self.bucket(key), key)?.value.load(Ordering::Acquire);
+        if value.is_null() {
+            None
+        }
+        else {
+            Some(value)
+        }
+    }
+
+    /// Get the number of keys that have been computed or are being computed.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check whether no keys have been computed or are being computed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn bucket(&self, key: &K) -> &AtomicXarc<Entry<K, V>> {
+        &self.buckets[fnv_hash(key) as usize % self.buckets.len()]
+    }
+}
+
+/// Wait for another caller to compute the value for `entry`, computing it with `f` if that caller panicked.
+#[cfg_attr(feature = "debug-owners", track_caller)]
+fn wait_for<K: Send, V: Send, F: FnOnce() -> V>(entry: &Entry<K, V>, f: F) -> Xarc<V> {
+    #[cfg(not(feature = "std"))]
+    let backoff = Backoff::new();
+    loop {
+        let value = entry.value.load(Ordering::Acquire);
+        if !value.is_null() {
+            return value;
+        }
+        if entry.claimed.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+            return compute(entry, f);
+        }
+        #[cfg(feature = "std")]
+        wait::wait_until(address(entry), || {
+            // Pairs with the fence in `wait::notify`: either the writer sees us waiting or we see its write.
+            fence(Ordering::SeqCst);
+            !entry.value.is_null(Ordering::Acquire) || !entry.claimed.load(Ordering::Acquire)
+        }, None);
+        #[cfg(not(feature = "std"))]
+        back_off(&backoff);
+    }
+}
+
+/// Compute the value for `entry` with `f` and publish it, releasing the claim on `entry` instead if `f` panics.
+#[cfg_attr(feature = "debug-owners", track_caller)]
+fn compute<K: Send, V: Send, F: FnOnce() -> V>(entry: &Entry<K, V>, f: F) -> Xarc<V> {
+    struct Release<'a, K: Send, V: Send>(&'a Entry<K, V>);
+
+    impl<K: Send, V: Send> Drop for Release<'_, K, V> {
+        fn drop(&mut self) {
+            self.0.claimed.store(false, Ordering::Release);
+            #[cfg(feature = "std")]
+            wait::notify(address(self.0));
+        }
+    }
+
+    let release = Release(entry);
+    let value = Xarc::new(f());
+    core::mem::forget(release);
+    let _ = entry.value.swap(&value, Ordering::AcqRel);
+    #[cfg(feature = "std")]
+    wait::notify(address(entry));
+    value
+}
+
+/// Find the entry for `key` in the bucket starting at `head`.
+fn find<'a, K: Eq + Send, V: Send>(head: &'a AtomicXarc<Entry<K, V>>, key: &K) -> Option<&'a Entry<K, V>> {
+    let mut next = head.load_raw(Ordering::Acquire);
+    while let Some(entry) = unsafe {
+        // Entries live as long as the bucket since they are never unlinked.
+        next.as_ref()
+    } {
+        if entry.key == *key {
+            return Some(entry);
+        }
+        next = entry.next.load_raw(Ordering::Acquire);
+    }
+    None
+}
+
+#[cfg(feature = "std")]
+#[must_use]
+fn address<K: Send, V: Send>(entry: &Entry<K, V>) -> usize {
+    (entry as *const Entry<K, V>).addr()
+}
+
+impl<K: Hash + Eq + Send + Sync, V: Send> Drop for MemoMap<K, V> {
+    fn drop(&mut self) {
+        // Nothing else can reach the entries any more, so they are freed at once rather than one per epoch advance.
+        for bucket in self.buckets.iter_mut() {
+            let mut next = bucket.take_mut();
+            while let Some(mut entry) = unsafe { next.unwrap_unprotected() } {
+                next = entry.next.take_mut();
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq + Send + Sync, V: Send> Default for MemoMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Send + Sync, V: Send> fmt::Debug for MemoMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoMap").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memo_map_panic_retry_mt_test() {
+        extern crate std;
+        use core::sync::atomic::AtomicUsize;
+        use std::{panic, thread, time::Duration};
+
+        let memo = MemoMap::new();
+        let started = AtomicBool::new(false);
+        let computed = AtomicUsize::new(0);
+        let values: Vec<usize> = thread::scope(|scope| {
+            let failing = scope.spawn(|| panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                memo.get_or_compute(0, || {
+                    started.store(true, Ordering::Release);
+                    // Give the other callers time to find the entry claimed and wait for it.
+                    thread::sleep(Duration::from_millis(20));
+                    panic!("computation failed");
+                })
+            })));
+            while !started.load(Ordering::Acquire) {
+                thread::yield_now();
+            }
+            let waiting: Vec<_> = (0..4).map(|_| scope.spawn(|| memo.get_or_compute(0, || {
+                computed.fetch_add(1, Ordering::Relaxed);
+                7
+            }))).collect();
+            assert!(failing.join().unwrap().is_err());
+            waiting.into_iter().map(|handle| *handle.join().unwrap().maybe_deref().unwrap()).collect()
+        });
+        // Exactly one of the waiting callers took over the computation, and the others got its value.
+        assert!(values.iter().all(|value| *value == 7));
+        assert_eq!(computed.load(Ordering::Relaxed), 1);
+        assert_eq!(memo.len(), 1);
+        assert_eq!(memo.get(&0).as_ref().and_then(Xarc::maybe_deref), Some(&7));
+    }
+}
@@ -0,0 +1,73 @@
+//! Concurrent collections built on `Xarc` and `AtomicXarc`.
+//!
+//! Dropping a collection frees its internal nodes at once rather than deferring each through the epoch,
+//! since nothing else can reach them. Values stored as `Xarc`s are still released as usual.
+
+#[cfg(feature = "async")]
+mod asynchronous;
+mod bag;
+#[cfg(feature = "std")]
+mod blocking;
+mod combining;
+mod cow;
+mod graph;
+mod interner;
+mod length;
+mod list;
+mod log;
+mod memo;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod queue;
+mod slot_map;
+mod stack;
+mod trie;
+mod vec;
+mod weak_map;
+
+#[cfg(feature = "async")]
+pub use asynchronous::Pop;
+pub use bag::Bag;
+pub use cow::CowVec;
+pub use graph::{Graph, GraphNode};
+pub use interner::Interner;
+pub use list::{List, ListCursor, ListEntry, ListIter};
+pub use log::{Log, LogTail};
+pub use memo::MemoMap;
+pub use queue::Queue;
+pub use slot_map::{SlotKey, SlotMap};
+pub use stack::Stack;
+pub use trie::Trie;
+pub use vec::{ConcurrentVec, ConcurrentVecIter, ConcurrentVecSnapshot};
+pub use weak_map::WeakMap;
+
+#[cfg(all(test, not(feature = "no-reclaim")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collection_teardown_st_test() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let stack = Stack::new();
+        let queue = Queue::new();
+        for _ in 0..100_000 {
+            stack.push(Counted);
+            queue.push(Counted);
+        }
+        drop((stack, queue));
+        // Under `fifo-reclaim`, nothing is reclaimed out of turn, so the nodes wait behind everything retired before them.
+        #[cfg(feature = "fifo-reclaim")]
+        while DROPPED.load(Ordering::Relaxed) < 200_000 {
+            crossbeam_epoch::pin().flush();
+        }
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 200_000);
+    }
+}
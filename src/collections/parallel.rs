@@ -0,0 +1,99 @@
+use super::{Bag, ConcurrentVec, CowVec, Queue, Stack};
+use alloc::vec::Vec;
+use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator, repeat};
+
+impl<T: Send> ParallelExtend<T> for Stack<T> {
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, values: I) {
+        let stack = &*self;
+        values.into_par_iter().for_each(|value| stack.push(value));
+    }
+}
+
+impl<T: Send> ParallelExtend<T> for Queue<T> {
+    /// Each worker links its share of the values into a batch and appends it with `push_iter`.
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, values: I) {
+        let queue = &*self;
+        values.into_par_iter().fold(Vec::new, |mut batch, value| {
+            batch.push(value);
+            batch
+        }).for_each(|batch| queue.push_iter(batch));
+    }
+}
+
+impl<T: Send> ParallelExtend<T> for Bag<T> {
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, values: I) {
+        let bag = &*self;
+        values.into_par_iter().for_each(|value| bag.put(value));
+    }
+}
+
+impl<T: Send + Sync> ParallelExtend<T> for ConcurrentVec<T> {
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, values: I) {
+        let vec = &*self;
+        values.into_par_iter().for_each(|value| {
+            vec.push(value);
+        });
+    }
+}
+
+impl<T: Clone + Send + Sync> ParallelExtend<T> for CowVec<T> {
+    /// The values are collected in parallel and then published with a single update.
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, values: I) {
+        let values: Vec<T> = values.into_par_iter().collect();
+        let _ = self.update(|current| Some(current.iter().cloned().chain(values.iter().cloned()).collect()));
+    }
+}
+
+impl<T: Send> Stack<T> {
+    /// Pop values in parallel across rayon's worker threads until the stack appears empty.
+    /// Values pushed concurrently may or may not be drained.
+    ///
+    /// Requires the `rayon` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use xarc::collections::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.par_extend(0..1000);
+    /// assert_eq!(stack.par_drain().sum::<i32>(), (0..1000).sum());
+    /// assert!(stack.is_empty());
+    /// ```
+    pub fn par_drain(&self) -> impl ParallelIterator<Item = T> + '_ {
+        repeat(()).map(move |_| self.try_pop()).while_some()
+    }
+}
+
+impl<T: Send> Queue<T> {
+    /// Pop values in parallel across rayon's worker threads until the queue appears empty.
+    /// Values are not drained in order. Values pushed concurrently may or may not be drained.
+    ///
+    /// Requires the `rayon` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use xarc::collections::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.par_extend(0..1000);
+    /// assert_eq!(queue.par_drain().sum::<i32>(), (0..1000).sum());
+    /// assert!(queue.is_empty());
+    /// ```
+    pub fn par_drain(&self) -> impl ParallelIterator<Item = T> + '_ {
+        repeat(()).map(move |_| self.try_pop()).while_some()
+    }
+}
+
+impl<T: Send> Bag<T> {
+    /// Take values in parallel across rayon's worker threads until the bag appears empty.
+    /// Values put concurrently may or may not be drained.
+    ///
+    /// Requires the `rayon` feature.
+    pub fn par_drain(&self) -> impl ParallelIterator<Item = T> + '_ {
+        repeat(()).map(move |_| self.try_get()).while_some()
+    }
+}
@@ -0,0 +1,385 @@
+use super::{combining::{Combiner, Operation}, length::Length};
+use crate::{atomic::*, dump::CollectionDump, internal::back_off, pointer::*, takeable::Takeable};
+use alloc::{vec, vec::Vec};
+use core::{fmt, sync::atomic::Ordering};
+use crossbeam_utils::Backoff;
+#[cfg(feature = "async")]
+use super::asynchronous::{Pop, Wakers};
+#[cfg(feature = "std")]
+use super::blocking;
+#[cfg(feature = "std")]
+use crate::wait;
+#[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+use crate::error::WaitTimeoutError;
+#[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+use std::time::{Duration, Instant};
+
+struct Node<T: Send> {
+    value: Takeable<T>,
+    next: AtomicXarc<Node<T>>,
+}
+
+impl<T: Send> Node<T> {
+    fn new(value: Option<T>) -> Xarc<Self> {
+        Xarc::new(Node {
+            value: Takeable::from(value),
+            next: AtomicXarc::null(),
+        })
+    }
+
+    /// Take the value out of a node that the caller has made the sentinel.
+    fn take(&self) -> Option<T> {
+        self.value.take()
+    }
+}
+
+/// `Queue` is a lock-free first-in first-out queue, the classic Michael-Scott queue.
+///
+/// `head` points to a sentinel node whose successor holds the next value to pop, and `tail` points at or near the last node.
+/// A popped node becomes the new sentinel, so only the thread that popped it ever takes its value.
+///
+/// `push_iter` and `pop_many` move whole batches with a single update of the tail or head,
+/// amortizing the cost of contention for pipeline-style producers and consumers.
+/// Like `Stack`, a queue created with `with_flat_combining` has one thread at a time apply the operations of every waiting thread.
+///
+/// Values are moved out as they are popped, so a `Queue` cannot be iterated or snapshotted.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::collections::Queue;
+///
+/// let queue = Queue::new();
+/// (0..1000).into_par_iter().for_each(|i| queue.push(i));
+/// let sum: i32 = (0..1000).into_par_iter().map(|_| queue.try_pop().unwrap()).sum();
+/// assert_eq!(sum, (0..1000).sum());
+/// assert!(queue.is_empty());
+/// ```
+pub struct Queue<T: Send> {
+    head: AtomicXarc<Node<T>>,
+    tail: AtomicXarc<Node<T>>,
+    combiner: Option<Combiner<Self, Operation<T>>>,
+    length: Length,
+    #[cfg(feature = "async")]
+    wakers: Wakers,
+}
+
+impl<T: Send> Queue<T> {
+    /// Create an empty queue.
+    #[must_use]
+    pub fn new() -> Self {
+        let sentinel = Node::new(None);
+        Queue {
+            head: AtomicXarc::from(&sentinel),
+            tail: AtomicXarc::from(&sentinel),
+            combiner: None,
+            length: Length::new(),
+            #[cfg(feature = "async")]
+            wakers: Wakers::new(),
+        }
+    }
+
+    /// Create an empty queue in flat-combining mode, for extreme contention. See `Queue`.
+    #[must_use]
+    pub fn with_flat_combining() -> Self {
+        let mut queue = Self::new();
+        queue.combiner = Some(Combiner::new(Self::apply));
+        queue
+    }
+
+    /// Push `value` onto the back of the queue.
+    pub fn push(&self, value: T) {
+        let value = match &self.combiner {
+            Some(combiner) => match combiner.submit(self, Operation::Push(value)) {
+                Ok(_) => return,
+                Err(Operation::Push(value)) => value,
+                Err(_) => unreachable!(),
+            },
+            None => value,
+        };
+        let node = Node::new(Some(value));
+        self.append(&node, &node, 1);
+    }
+
+    /// Push every value of `values` onto the back of the queue, in order and without interleaving with other pushes.
+    /// The values are linked together first and then appended with a single update of the tail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::collections::Queue;
+    ///
+    /// let queue = Queue::new();
+    /// queue.push_iter(0..10);
+    /// assert_eq!(queue.len(), 10);
+    /// let mut values = Vec::new();
+    /// assert_eq!(queue.pop_many(&mut values, 4), 4);
+    /// assert_eq!(values, [0, 1, 2, 3]);
+    /// assert_eq!(queue.pop_many(&mut values, 100), 6);
+    /// assert_eq!(values, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    /// ```
+    pub fn push_iter<I: IntoIterator<Item = T>>(&self, values: I) {
+        let mut values = values.into_iter();
+        let first = match values.next() {
+            Some(value) => Node::new(Some(value)),
+            None => return,
+        };
+        let (last, count) = values.fold((first.clone(), 1), |(last, count), value| {
+            let node = Node::new(Some(value));
+            // The chain has not been published, so nobody else can be looking at it.
+            let _ = last.non_null().next.swap(&node, Ordering::Relaxed);
+            (node, count + 1)
+        });
+        self.append(&first, &last, count);
+    }
+
+    /// Pop the value at the front of the queue, or None if the queue appears empty.
+    #[must_use]
+    pub fn try_pop(&self) -> Option<T> {
+        if let Some(combiner) = &self.combiner {
+            if let Ok(Operation::Popped(value)) = combiner.submit(self, Operation::Pop) {
+                return value;
+            }
+        }
+        self.try_pop_directly()
+    }
+
+    /// Pop the value at the front of the queue, blocking until one is pushed if the queue is empty.
+    /// Waiting threads are parked rather than spinning.
+    ///
+    /// Requires the `std` feature. Enabling it adds a fence to every push.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{sync::Arc, thread};
+    /// use xarc::collections::Queue;
+    ///
+    /// let queue = Arc::new(Queue::new());
+    /// let producer = {
+    ///     let queue = queue.clone();
+    ///     thread::spawn(move || queue.push(42))
+    /// };
+    /// assert_eq!(queue.pop(), 42);
+    /// producer.join().unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn pop(&self) -> T {
+        blocking::pop(self.address(), None, || self.try_pop(), || self.is_empty()).unwrap()
+    }
+
+    /// Pop the value at the front of the queue, blocking until one is pushed or `timeout` elapses. See `pop`.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<T, WaitTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        blocking::pop(self.address(), deadline, || self.try_pop(), || self.is_empty()).ok_or(WaitTimeoutError)
+    }
+
+    /// Pop the value at the front of the queue, waiting without blocking the thread until one is pushed if the queue is empty.
+    /// The future registers its waker once however often it is polled, and unregisters it when it completes or is dropped.
+    ///
+    /// Requires the `async` feature. Enabling it adds a fence to every push.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{future::Future, pin::pin, sync::Arc, task::{Context, Poll, Wake}, thread::{self, Thread}};
+    /// use xarc::collections::Queue;
+    ///
+    /// struct Unpark(Thread);
+    ///
+    /// impl Wake for Unpark {
+    ///     fn wake(self: Arc<Self>) {
+    ///         self.0.unpark();
+    ///     }
+    /// }
+    ///
+    /// let queue = Arc::new(Queue::new());
+    /// let producer = {
+    ///     let queue = queue.clone();
+    ///     thread::spawn(move || queue.push(42))
+    /// };
+    /// let waker = Arc::new(Unpark(thread::current())).into();
+    /// let mut context = Context::from_waker(&waker);
+    /// let mut pop = pin!(queue.pop_async());
+    /// let value = loop {
+    ///     match pop.as_mut().poll(&mut context) {
+    ///         Poll::Ready(value) => break value,
+    ///         Poll::Pending => thread::park(),
+    ///     }
+    /// };
+    /// assert_eq!(value, 42);
+    /// producer.join().unwrap();
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn pop_async(&self) -> Pop<'_, T> {
+        Pop::queue(self)
+    }
+
+    /// Pop up to `max` values from the front of the queue into `values` with a single update of the head.
+    /// Returns the number of values popped, which is 0 if the queue appears empty.
+    #[must_use]
+    pub fn pop_many(&self, values: &mut Vec<T>, max: usize) -> usize {
+        if self.combiner.is_some() {
+            return (0..max).map_while(|_| self.try_pop()).map(|value| values.push(value)).count();
+        }
+        self.pop_many_directly(values, max)
+    }
+
+    /// Get the approximate number of values in the queue.
+    /// It is maintained with relaxed counters, so it is cheap but may lag behind concurrent pushes and pops.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check if the queue appears empty. Other threads may push or pop concurrently, so this is only a hint.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.head.with(Ordering::Relaxed, |head| head.unwrap().next.is_null(Ordering::Relaxed))
+    }
+
+    /// Describe the state of the queue for logging. See `CollectionDump`.
+    #[must_use]
+    pub fn dump(&self) -> CollectionDump {
+        CollectionDump {
+            collection: "Queue",
+            len: self.len(),
+            slots: vec![("head", self.head.debug_state()), ("tail", self.tail.debug_state())],
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[must_use]
+    pub(crate) fn wakers(&self) -> &Wakers {
+        &self.wakers
+    }
+
+    #[cfg(feature = "std")]
+    #[must_use]
+    fn address(&self) -> usize {
+        (self as *const Self).addr()
+    }
+
+    /// Apply a request on behalf of a thread waiting on the combiner.
+    fn apply(&self, operation: Operation<T>) -> Operation<T> {
+        match operation {
+            Operation::Push(value) => {
+                let node = Node::new(Some(value));
+                self.append(&node, &node, 1);
+                Operation::Popped(None)
+            },
+            Operation::Pop => Operation::Popped(self.try_pop_directly()),
+            popped => popped,
+        }
+    }
+
+    /// Append the chain of `count` nodes from `first` to `last`.
+    fn append(&self, first: &Xarc<Node<T>>, last: &Xarc<Node<T>>, count: usize) {
+        let backoff = Backoff::new();
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            let node = tail.maybe_deref().unwrap();
+            match node.next.compare_exchange(&Xarc::null(), first, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => {
+                    self.length.add(count);
+                    let _ = self.tail.compare_exchange(&tail, last, Ordering::Release, Ordering::Relaxed);
+                    #[cfg(feature = "std")]
+                    wait::notify(self.address());
+                    #[cfg(feature = "async")]
+                    self.wakers.wake_all();
+                    return;
+                },
+                Err(next) => {
+                    // The tail is lagging, so help move it along before trying again.
+                    let _ = self.tail.compare_exchange(&tail, &next, Ordering::Release, Ordering::Relaxed);
+                    back_off(&backoff);
+                },
+            }
+        }
+    }
+
+    fn try_pop_directly(&self) -> Option<T> {
+        let backoff = Backoff::new();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            let next = head.maybe_deref().unwrap().next.load(Ordering::Acquire);
+            let node = next.maybe_deref()?;
+            if self.tail.ptr_eq(&head, Ordering::Relaxed) {
+                let _ = self.tail.compare_exchange(&head, &next, Ordering::Release, Ordering::Relaxed);
+            }
+            if self.head.compare_exchange(&head, &next, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                self.length.sub(1);
+                return node.take();
+            }
+            back_off(&backoff);
+        }
+    }
+
+    fn pop_many_directly(&self, values: &mut Vec<T>, max: usize) -> usize {
+        let backoff = Backoff::new();
+        let mut nodes = Vec::new();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            nodes.clear();
+            let mut last = head.clone();
+            while nodes.len() < max {
+                let next = last.maybe_deref().unwrap().next.load(Ordering::Acquire);
+                if next.is_null() {
+                    break;
+                }
+                nodes.push(next.clone());
+                last = next;
+            }
+            if nodes.is_empty() {
+                return 0;
+            }
+            // Move a lagging tail up to the new head so that it does not keep popped nodes alive.
+            let tail = self.tail.load(Ordering::Acquire);
+            if tail == head || nodes[..nodes.len() - 1].contains(&tail) {
+                let _ = self.tail.compare_exchange(&tail, &last, Ordering::Release, Ordering::Relaxed);
+            }
+            if self.head.compare_exchange(&head, &last, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                self.length.sub(nodes.len());
+                values.extend(nodes.iter().filter_map(|node| node.maybe_deref().unwrap().take()));
+                return nodes.len();
+            }
+            back_off(&backoff);
+        }
+    }
+}
+
+impl<T: Send> Drop for Queue<T> {
+    fn drop(&mut self) {
+        // Nothing else can reach the nodes any more, so they are freed at once rather than one per epoch advance.
+        drop(self.tail.take_mut());
+        let mut next = self.head.take_mut();
+        while let Some(mut node) = unsafe { next.unwrap_unprotected() } {
+            next = node.next.take_mut();
+        }
+    }
+}
+
+impl<T: Send> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        self.push_iter(values);
+    }
+}
+
+impl<T: Send> fmt::Debug for Queue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue").finish_non_exhaustive()
+    }
+}
+
+unsafe impl<T: Send> Sync for Queue<T> {}
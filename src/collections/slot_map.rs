@@ -0,0 +1,325 @@
+use super::{Stack, length::Length};
+use crate::{atomic::*, pointer::*};
+use alloc::boxed::Box;
+use core::{array, fmt, sync::atomic::{AtomicU64, AtomicUsize, Ordering}};
+
+/// The number of slots stored in the first chunk of a `SlotMap`. Each chunk after it is twice the size of the last.
+const CHUNK: usize = 64;
+
+/// The number of chunks it takes for a `SlotMap` to hold `usize::MAX` slots.
+const CHUNKS: usize = (usize::BITS - CHUNK.trailing_zeros()) as usize + 1;
+
+/// A key handed out by `SlotMap::insert`.
+///
+/// The generation distinguishes the value it was handed out for from any value inserted into the same slot later,
+/// so a stale key never reaches a newer value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SlotKey {
+    index: usize,
+    generation: u64,
+}
+
+impl SlotKey {
+    /// The index of the slot.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The generation of the slot when the value was inserted.
+    #[must_use]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+struct Slot<V: Send> {
+    /// Odd while the slot is occupied and even while it is vacant. Incremented on every insert and remove.
+    generation: AtomicU64,
+    /// Null exactly when the slot is vacant or a remove is finishing up.
+    value: AtomicXarc<V>,
+}
+
+struct Chunk<V: Send> {
+    slots: Box<[Slot<V>]>,
+}
+
+impl<V: Send> Chunk<V> {
+    fn new(len: usize) -> Self {
+        Chunk {
+            slots: (0..len).map(|_| Slot {
+                generation: AtomicU64::new(0),
+                value: AtomicXarc::null(),
+            }).collect(),
+        }
+    }
+}
+
+/// Find the chunk holding the slot at `index` and the offset of the slot within it.
+fn locate(index: usize) -> (usize, usize) {
+    let number = index / CHUNK + 1;
+    let chunk = (usize::BITS - 1 - number.leading_zeros()) as usize;
+    (chunk, index - CHUNK * ((1 << chunk) - 1))
+}
+
+/// `SlotMap` stores values in reusable slots addressed by generational `SlotKey`s, as an entity system would.
+///
+/// Every operation is lock-free. Ownership of a slot's value changes hands only by a compare-exchange on its `AtomicXarc`,
+/// and a handle to the expected value is held across it, so the value cannot be freed and replaced by another at the same address in between.
+/// Reusing a slot increments its generation, so a key for a removed value never matches the slot again, even though its index does.
+///
+/// Removed slots are kept on a free list for reuse. Slots are stored in chunks that double in size, found through a fixed directory,
+/// so a key finds its slot in constant time. Chunks are never freed before the map is.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::collections::SlotMap;
+///
+/// let entities = SlotMap::new();
+/// let player = entities.insert("player");
+/// let enemy = entities.insert("enemy");
+///
+/// assert_eq!(*entities.swap(enemy, "boss").unwrap().maybe_deref().unwrap(), "enemy");
+/// assert_eq!(*entities.remove(enemy).unwrap().maybe_deref().unwrap(), "boss");
+/// assert!(entities.get(enemy).is_none());
+///
+/// let reused = entities.insert("projectile");
+/// assert_eq!(reused.index(), enemy.index());
+/// assert!(entities.get(enemy).is_none());
+/// assert_eq!(*entities.get(reused).unwrap().maybe_deref().unwrap(), "projectile");
+/// assert_eq!(*entities.get(player).unwrap().maybe_deref().unwrap(), "player");
+/// ```
+pub struct SlotMap<V: Send> {
+    chunks: [AtomicXarc<Chunk<V>>; CHUNKS],
+    reserved: AtomicUsize,
+    free: Stack<usize>,
+    length: Length,
+}
+
+impl<V: Send> SlotMap<V> {
+    /// Create an empty map.
+    #[must_use]
+    pub fn new() -> Self {
+        SlotMap {
+            chunks: array::from_fn(|_| AtomicXarc::null()),
+            reserved: AtomicUsize::new(0),
+            free: Stack::new(),
+            length: Length::new(),
+        }
+    }
+
+    /// Insert `value` into a vacant slot and return its key.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn insert(&self, value: V) -> SlotKey {
+        let value = Xarc::new(value);
+        let index = self.free.try_pop().unwrap_or_else(|| self.reserved.fetch_add(1, Ordering::Relaxed));
+        let slot = self.slot_or_append(index);
+        // The slot is vacant and only this thread has its index, so nothing else can write to it.
+        let _ = slot.value.swap(&value, Ordering::AcqRel);
+        let generation = slot.generation.load(Ordering::Relaxed) + 1;
+        slot.generation.store(generation, Ordering::Release);
+        self.length.add(1);
+        SlotKey {
+            index,
+            generation,
+        }
+    }
+
+    /// Get the value for `key`, or None if it has been removed.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get(&self, key: SlotKey) -> Option<Xarc<V>> {
+        let slot = self.slot(key)?;
+        let value = slot.value.load(Ordering::Acquire);
+        // The slot may have been reused between the loads.
+        if value.is_null() || slot.generation.load(Ordering::Acquire) != key.generation {
+            None
+        }
+        else {
+            Some(value)
+        }
+    }
+
+    /// Check whether `key` still refers to a value.
+    #[must_use]
+    pub fn contains_key(&self, key: SlotKey) -> bool {
+        self.slot(key).is_some_and(|slot| !slot.value.is_null(Ordering::Acquire)
+            && slot.generation.load(Ordering::Acquire) == key.generation)
+    }
+
+    /// As an atomic operation, replace the value for `key` with `value`.
+    /// Returns the value that was replaced, or None if `key` has been removed, in which case `value` is dropped.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, key: SlotKey, value: V) -> Option<Xarc<V>> {
+        let value = Xarc::new(value);
+        self.replace(key, &value)
+    }
+
+    /// Remove the value for `key` and free its slot for reuse.
+    /// Returns the value that was removed, or None if `key` had already been removed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn remove(&self, key: SlotKey) -> Option<Xarc<V>> {
+        let removed = self.replace(key, &Xarc::null())?;
+        let slot = self.slot(key)?;
+        slot.generation.store(key.generation + 1, Ordering::Release);
+        self.length.sub(1);
+        self.free.push(key.index);
+        Some(removed)
+    }
+
+    /// Get the number of values in the map.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check whether the map holds no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replace the value for `key` with `new` with a compare-exchange, so that a remove can win the race only once.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn replace(&self, key: SlotKey, new: &Xarc<V>) -> Option<Xarc<V>> {
+        let slot = self.slot(key)?;
+        loop {
+            let current = slot.value.load(Ordering::Acquire);
+            // Checking the generation after the load, as in `get`, ensures that `current` belongs to the generation of `key`.
+            if current.is_null() || slot.generation.load(Ordering::Acquire) != key.generation {
+                return None;
+            }
+            // Holding `current` keeps its address from being reused, so a value inserted later never matches.
+            if slot.value.compare_exchange(&current, new, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                return Some(current);
+            }
+        }
+    }
+
+    /// Find the slot for `key`, or None if `key` could not have been handed out by this map.
+    fn slot(&self, key: SlotKey) -> Option<&Slot<V>> {
+        if key.generation & 1 == 0 || key.index >= self.reserved.load(Ordering::Acquire) {
+            return None;
+        }
+        let (chunk, offset) = locate(key.index);
+        // Chunks are never removed while the `SlotMap` is alive.
+        let chunk = unsafe {
+            self.chunks[chunk].load_raw(Ordering::Acquire).as_ref()?
+        };
+        Some(&chunk.slots[offset])
+    }
+
+    fn slot_or_append(&self, index: usize) -> &Slot<V> {
+        let (chunk, offset) = locate(index);
+        let mut raw = self.chunks[chunk].load_raw(Ordering::Acquire);
+        if raw.is_null() {
+            let new = Xarc::new(Chunk::new(CHUNK << chunk));
+            raw = match self.chunks[chunk].compare_exchange(&Xarc::null(), &new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => new.as_raw(),
+                Err(current) => current.as_raw(),
+            };
+        }
+        unsafe {
+            &(*raw).slots[offset]
+        }
+    }
+}
+
+impl<V: Send> Drop for SlotMap<V> {
+    fn drop(&mut self) {
+        // Nothing else can reach the chunks any more, so they are freed at once rather than one per epoch advance.
+        for chunk in self.chunks.iter_mut() {
+            drop(unsafe { chunk.take_mut().unwrap_unprotected() });
+        }
+    }
+}
+
+impl<V: Send> Default for SlotMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Send> fmt::Debug for SlotMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotMap").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_map_locate_st_test() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(CHUNK - 1), (0, CHUNK - 1));
+        assert_eq!(locate(CHUNK), (1, 0));
+        assert_eq!(locate(3 * CHUNK), (2, 0));
+        assert_eq!(locate(usize::MAX), (CHUNKS - 1, CHUNK - 1));
+    }
+
+    #[test]
+    fn slot_map_fill_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use std::thread;
+        const THREADS: usize = 4;
+        const INSERTS: usize = 2000;
+
+        let map = SlotMap::new();
+        let keys: Vec<Vec<SlotKey>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS).map(|thread| {
+                let map = &map;
+                scope.spawn(move || (0..INSERTS).map(|i| {
+                    let key = map.insert(thread * INSERTS + i);
+                    assert_eq!(map.get(key).as_ref().and_then(Xarc::maybe_deref), Some(&(thread * INSERTS + i)));
+                    key
+                }).collect())
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert_eq!(map.len(), THREADS * INSERTS);
+        for (thread, keys) in keys.iter().enumerate() {
+            for (i, &key) in keys.iter().enumerate() {
+                assert_eq!(map.get(key).as_ref().and_then(Xarc::maybe_deref), Some(&(thread * INSERTS + i)));
+            }
+        }
+    }
+
+    #[test]
+    fn slot_map_stale_key_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use std::thread;
+
+        let map = SlotMap::new();
+        // Threads keep inserting and removing, so slots are reused under keys that other threads have just made stale.
+        let stale: Vec<SlotKey> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|thread| {
+                let map = &map;
+                scope.spawn(move || (0..500).map(|i| {
+                    let value = thread * 1000 + i;
+                    let key = map.insert(value);
+                    assert_eq!(map.get(key).as_ref().and_then(Xarc::maybe_deref), Some(&value));
+                    assert_eq!(map.remove(key).as_ref().and_then(Xarc::maybe_deref), Some(&value));
+                    assert!(map.get(key).is_none() && !map.contains_key(key));
+                    assert!(map.swap(key, 0).is_none() && map.remove(key).is_none());
+                    key
+                }).collect::<Vec<_>>())
+            }).collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        });
+        assert!(map.is_empty());
+
+        // Refill every slot that was used. No stale key reaches the values now in them.
+        let live: Vec<SlotKey> = (0..map.reserved.load(Ordering::Relaxed)).map(|i| map.insert(i)).collect();
+        for key in &stale {
+            assert!(map.get(*key).is_none() && !map.contains_key(*key));
+            assert!(map.swap(*key, 0).is_none() && map.remove(*key).is_none());
+        }
+        assert_eq!(map.len(), live.len());
+        assert!(live.iter().all(|key| map.contains_key(*key)));
+    }
+}
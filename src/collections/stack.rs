@@ -0,0 +1,318 @@
+use super::{combining::{Combiner, Operation}, length::Length};
+use crate::{atomic::*, dump::CollectionDump, internal::{back_off, thread_hint}, pointer::*, takeable::Takeable};
+use alloc::vec;
+use core::{fmt, hint, sync::atomic::Ordering};
+use crossbeam_utils::{Backoff, CachePadded};
+#[cfg(feature = "async")]
+use super::asynchronous::{Pop, Wakers};
+#[cfg(feature = "std")]
+use super::blocking;
+#[cfg(feature = "std")]
+use crate::wait;
+#[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+use crate::error::WaitTimeoutError;
+#[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+use std::time::{Duration, Instant};
+
+/// The number of slots in the elimination array.
+const ELIMINATION_SLOTS: usize = 4;
+
+/// How long a push waits in the elimination array for a pop to take its value.
+const ELIMINATION_SPINS: usize = 64;
+
+struct Node<T: Send> {
+    value: Takeable<T>,
+    next: Xarc<Node<T>>,
+}
+
+impl<T: Send> Node<T> {
+    /// Take the value out of a node that the caller has unlinked.
+    fn take(&self) -> Option<T> {
+        self.value.take()
+    }
+}
+
+/// `Stack` is a lock-free last-in first-out stack, the classic Treiber stack.
+///
+/// Under contention a push and a pop that both failed to update the head can instead meet in a small elimination array,
+/// where the pop takes the value straight from the push and neither touches the head again.
+/// This keeps throughput from collapsing as the number of threads grows.
+///
+/// Values are moved out as they are popped, so a `Stack` cannot be iterated or snapshotted.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::collections::Stack;
+///
+/// let stack = Stack::new();
+/// (0..1000).into_par_iter().for_each(|i| stack.push(i));
+/// assert_eq!(stack.len(), 1000);
+/// let sum: i32 = (0..1000).into_par_iter().map(|_| stack.try_pop().unwrap()).sum();
+/// assert_eq!(sum, (0..1000).sum());
+/// assert!(stack.is_empty());
+/// ```
+pub struct Stack<T: Send> {
+    head: AtomicXarc<Node<T>>,
+    elimination: [CachePadded<AtomicXarc<Node<T>>>; ELIMINATION_SLOTS],
+    combiner: Option<Combiner<Self, Operation<T>>>,
+    length: Length,
+    #[cfg(feature = "async")]
+    wakers: Wakers,
+}
+
+impl<T: Send> Stack<T> {
+    /// Create an empty stack.
+    #[must_use]
+    pub fn new() -> Self {
+        Stack {
+            head: AtomicXarc::null(),
+            elimination: [(); ELIMINATION_SLOTS].map(|_| CachePadded::new(AtomicXarc::null())),
+            combiner: None,
+            length: Length::new(),
+            #[cfg(feature = "async")]
+            wakers: Wakers::new(),
+        }
+    }
+
+    /// Create an empty stack in flat-combining mode, for extreme contention. See `Stack`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use xarc::collections::Stack;
+    ///
+    /// let stack = Stack::with_flat_combining();
+    /// (0..1000).into_par_iter().for_each(|i| {
+    ///     stack.push(i);
+    ///     assert!(stack.try_pop().is_some());
+    /// });
+    /// assert!(stack.is_empty());
+    /// ```
+    #[must_use]
+    pub fn with_flat_combining() -> Self {
+        let mut stack = Self::new();
+        stack.combiner = Some(Combiner::new(Self::apply));
+        stack
+    }
+
+    /// Push `value` onto the stack.
+    pub fn push(&self, value: T) {
+        let value = match &self.combiner {
+            Some(combiner) => match combiner.submit(self, Operation::Push(value)) {
+                Ok(_) => return,
+                Err(Operation::Push(value)) => value,
+                Err(_) => unreachable!(),
+            },
+            None => value,
+        };
+        self.push_directly(value);
+    }
+
+    /// Pop the value on top of the stack, or None if the stack appears empty.
+    #[must_use]
+    pub fn try_pop(&self) -> Option<T> {
+        if let Some(combiner) = &self.combiner {
+            if let Ok(Operation::Popped(value)) = combiner.submit(self, Operation::Pop) {
+                return value;
+            }
+        }
+        self.try_pop_directly()
+    }
+
+    /// Pop the value on top of the stack, blocking until one is pushed if the stack is empty.
+    /// Waiting threads are parked rather than spinning.
+    ///
+    /// Requires the `std` feature. Enabling it adds a fence to every push.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{sync::Arc, thread};
+    /// use xarc::collections::Stack;
+    ///
+    /// let stack = Arc::new(Stack::new());
+    /// let producer = {
+    ///     let stack = stack.clone();
+    ///     thread::spawn(move || stack.push(42))
+    /// };
+    /// assert_eq!(stack.pop(), 42);
+    /// producer.join().unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn pop(&self) -> T {
+        blocking::pop(self.address(), None, || self.try_pop(), || self.is_empty()).unwrap()
+    }
+
+    /// Pop the value on top of the stack, blocking until one is pushed or `timeout` elapses. See `pop`.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(all(feature = "std", not(all(target_family = "wasm", target_os = "unknown"))))]
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<T, WaitTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        blocking::pop(self.address(), deadline, || self.try_pop(), || self.is_empty()).ok_or(WaitTimeoutError)
+    }
+
+    /// Pop the value on top of the stack, waiting without blocking the thread until one is pushed if the stack is empty.
+    /// See `Queue::pop_async`.
+    ///
+    /// Requires the `async` feature. Enabling it adds a fence to every push.
+    #[cfg(feature = "async")]
+    pub fn pop_async(&self) -> Pop<'_, T> {
+        Pop::stack(self)
+    }
+
+    /// Get the approximate number of values in the stack.
+    /// It is maintained with relaxed counters, so it is cheap but may lag behind concurrent pushes and pops.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check if the stack appears empty. Other threads may push or pop concurrently, so this is only a hint.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.head.is_null(Ordering::Relaxed)
+    }
+
+    /// Describe the state of the stack for logging. See `CollectionDump`.
+    #[must_use]
+    pub fn dump(&self) -> CollectionDump {
+        let mut slots = vec![("head", self.head.debug_state())];
+        slots.extend(self.elimination.iter().map(|slot| ("elimination", slot.debug_state())));
+        CollectionDump {
+            collection: "Stack",
+            len: self.len(),
+            slots,
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[must_use]
+    pub(crate) fn wakers(&self) -> &Wakers {
+        &self.wakers
+    }
+
+    #[cfg(feature = "std")]
+    #[must_use]
+    fn address(&self) -> usize {
+        (self as *const Self).addr()
+    }
+
+    /// Apply a request on behalf of a thread waiting on the combiner.
+    fn apply(&self, operation: Operation<T>) -> Operation<T> {
+        match operation {
+            Operation::Push(value) => {
+                self.push_directly(value);
+                Operation::Popped(None)
+            },
+            Operation::Pop => Operation::Popped(self.try_pop_directly()),
+            popped => popped,
+        }
+    }
+
+    fn push_directly(&self, value: T) {
+        let backoff = Backoff::new();
+        let mut new = Xarc::new(Node {
+            value: Takeable::new(value),
+            next: self.head.load(Ordering::Relaxed),
+        });
+        loop {
+            match self.head.compare_exchange_weak(&new.non_null().next, &new, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => {
+                    self.length.add(1);
+                    break;
+                },
+                Err(current) => {
+                    if self.offer(&new) {
+                        break;
+                    }
+                    // Whether or not the node was offered, nobody else holds a reference to it now.
+                    unsafe {
+                        new.unguarded_maybe_deref_mut().unwrap().next = current;
+                    }
+                    back_off(&backoff);
+                },
+            }
+        }
+        #[cfg(feature = "std")]
+        wait::notify(self.address());
+        #[cfg(feature = "async")]
+        self.wakers.wake_all();
+    }
+
+    fn try_pop_directly(&self) -> Option<T> {
+        let backoff = Backoff::new();
+        loop {
+            let current = self.head.load(Ordering::Acquire);
+            let node = current.maybe_deref()?;
+            match self.head.compare_exchange_weak(&current, &node.next, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => {
+                    self.length.sub(1);
+                    return node.take();
+                },
+                Err(_) => {
+                    if let Some(value) = self.take_offer() {
+                        return Some(value);
+                    }
+                    back_off(&backoff);
+                },
+            }
+        }
+    }
+
+    /// Offer `node` to a concurrent pop through the elimination array. Returns true if a pop took it.
+    fn offer(&self, node: &Xarc<Node<T>>) -> bool {
+        let slot = &self.elimination[thread_hint() % ELIMINATION_SLOTS];
+        if !slot.is_null(Ordering::Relaxed) || slot.compare_exchange(&Xarc::null(), node, Ordering::Release, Ordering::Relaxed).is_err() {
+            return false;
+        }
+        for _ in 0..ELIMINATION_SPINS {
+            if !slot.ptr_eq(node, Ordering::Relaxed) {
+                break;
+            }
+            hint::spin_loop();
+        }
+        // Either the node is withdrawn or a pop has already taken it.
+        slot.compare_exchange(node, &Xarc::null(), Ordering::Relaxed, Ordering::Relaxed).is_err()
+    }
+
+    /// Take a node offered by a concurrent push, if there is one.
+    fn take_offer(&self) -> Option<T> {
+        let start = thread_hint();
+        (0..ELIMINATION_SLOTS).find_map(|offset| {
+            let slot = &self.elimination[start.wrapping_add(offset) % ELIMINATION_SLOTS];
+            if slot.is_null(Ordering::Relaxed) {
+                return None;
+            }
+            slot.swap(&Xarc::null(), Ordering::Acquire).maybe_deref().and_then(Node::take)
+        })
+    }
+}
+
+impl<T: Send> Drop for Stack<T> {
+    fn drop(&mut self) {
+        // Nothing else can reach the nodes any more, so they are freed at once rather than one per epoch advance.
+        let mut next = self.head.take_mut();
+        while let Some(node) = unsafe { next.unwrap_unprotected() } {
+            next = node.next;
+        }
+    }
+}
+
+impl<T: Send> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> fmt::Debug for Stack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stack").finish_non_exhaustive()
+    }
+}
+
+unsafe impl<T: Send> Sync for Stack<T> {}
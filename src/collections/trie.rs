@@ -0,0 +1,257 @@
+use super::length::Length;
+use crate::{atomic::*, pointee::*, pointer::*};
+use alloc::vec;
+use core::{fmt, mem, sync::atomic::Ordering};
+
+/// The number of children of each node: one per nibble.
+const FANOUT: usize = 16;
+
+/// A node of a `Trie`, for one prefix of nibbles: the value for that prefix and the nodes for longer ones.
+struct TrieNode<V: ?Sized + Send + Pointee> {
+    value: AtomicXarc<V>,
+    children: [AtomicXarc<TrieNode<V>>; FANOUT],
+}
+
+impl<V: ?Sized + Send + Pointee> TrieNode<V> {
+    #[must_use]
+    fn new() -> Self {
+        TrieNode {
+            value: AtomicXarc::null(),
+            children: core::array::from_fn(|_| AtomicXarc::null()),
+        }
+    }
+
+    /// The child for `nibble`, or None if there is none.
+    #[must_use]
+    fn child(&self, nibble: u8) -> Option<Xarc<TrieNode<V>>> {
+        let child = self.children[usize::from(nibble)].load(Ordering::Acquire);
+        if child.is_null() {
+            None
+        }
+        else {
+            Some(child)
+        }
+    }
+
+    /// The child for `nibble`, installing an empty one with a compare-exchange if there is none.
+    /// When two threads race to install one, both go on with the child that won.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn child_or_insert(&self, nibble: u8) -> Xarc<TrieNode<V>> {
+        let slot = &self.children[usize::from(nibble)];
+        let child = slot.load(Ordering::Acquire);
+        if !child.is_null() {
+            return child;
+        }
+        let new = Xarc::new(TrieNode::new());
+        match slot.compare_exchange(&child, &new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => new,
+            Err(existing) => existing,
+        }
+    }
+}
+
+/// The nibbles of `key`, high nibble first.
+fn nibbles(key: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0xF])
+}
+
+/// `Trie` maps byte strings to values, with lock-free lookups by exact key or by longest matching prefix.
+///
+/// Keys are split into nibbles, and each node has one child per nibble, installed with a compare-exchange the first time a key needs it.
+/// The value for a key sits in an `AtomicXarc` of the node at the end of its path, so inserting and removing it is a single swap,
+/// and a lookup never waits, only walking down nodes that are never unlinked while the trie is alive.
+/// Removing a key empties its value but keeps the nodes on its path for the next insert,
+/// so the trie grows to cover every key it has held rather than shrinking.
+///
+/// `longest_prefix` finds the value for the longest key that is a prefix of a given one, as routing tables need.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::{Xarc, collections::Trie};
+///
+/// let routes = Trie::new();
+/// ["/", "/api/", "/api/v2/", "/static/"].par_iter().for_each(|route| {
+///     let _ = routes.insert(route.as_bytes(), &Xarc::new(*route));
+/// });
+/// assert_eq!(routes.len(), 4);
+///
+/// let (length, handler) = routes.longest_prefix(b"/api/v2/users").unwrap();
+/// assert_eq!((length, handler.maybe_deref()), (8, Some(&"/api/v2/")));
+/// assert_eq!(routes.longest_prefix(b"/api/v1/users").unwrap().1.maybe_deref(), Some(&"/api/"));
+/// assert_eq!(routes.longest_prefix(b"/index.html").unwrap().1.maybe_deref(), Some(&"/"));
+///
+/// assert_eq!(routes.remove(b"/api/").unwrap().maybe_deref(), Some(&"/api/"));
+/// assert_eq!(routes.longest_prefix(b"/api/v1/users").unwrap().1.maybe_deref(), Some(&"/"));
+/// assert!(routes.get(b"/api/").is_none());
+/// ```
+pub struct Trie<V: ?Sized + Send + Pointee> {
+    root: Xarc<TrieNode<V>>,
+    length: Length,
+}
+
+impl<V: ?Sized + Send + Pointee> Trie<V> {
+    /// Create an empty trie.
+    #[must_use]
+    pub fn new() -> Self {
+        Trie {
+            root: Xarc::new(TrieNode::new()),
+            length: Length::new(),
+        }
+    }
+
+    /// Get the value for `key`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get(&self, key: &[u8]) -> Option<Xarc<V>> {
+        let node = self.find(key)?;
+        let value = node.non_null().value.load(Ordering::Acquire);
+        if value.is_null() {
+            None
+        }
+        else {
+            Some(value)
+        }
+    }
+
+    /// Check whether the trie holds a value for `key`.
+    #[must_use]
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.find(key).is_some_and(|node| !node.non_null().value.is_null(Ordering::Acquire))
+    }
+
+    /// Get the value for the longest key that is a prefix of `key`, along with the length of that prefix in bytes.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn longest_prefix(&self, key: &[u8]) -> Option<(usize, Xarc<V>)> {
+        let mut node = self.root.clone();
+        let mut longest = None;
+        for (depth, nibble) in nibbles(key).enumerate() {
+            if depth % 2 == 0 {
+                let value = node.non_null().value.load(Ordering::Acquire);
+                if !value.is_null() {
+                    longest = Some((depth / 2, value));
+                }
+            }
+            node = match node.non_null().child(nibble) {
+                Some(child) => child,
+                None => return longest,
+            };
+        }
+        let value = node.non_null().value.load(Ordering::Acquire);
+        if value.is_null() {
+            longest
+        }
+        else {
+            Some((key.len(), value))
+        }
+    }
+
+    /// Map `key` to `value`, returning the value it replaced.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn insert(&self, key: &[u8], value: &Xarc<V>) -> Option<Xarc<V>> {
+        let node = nibbles(key).fold(self.root.clone(), |node, nibble| node.non_null().child_or_insert(nibble));
+        let previous = node.non_null().value.swap(value, Ordering::AcqRel);
+        if !value.is_null() {
+            self.length.add(1);
+        }
+        if previous.is_null() {
+            None
+        }
+        else {
+            self.length.sub(1);
+            Some(previous)
+        }
+    }
+
+    /// Remove the value for `key`, returning it.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn remove(&self, key: &[u8]) -> Option<Xarc<V>> {
+        let node = self.find(key)?;
+        if node.non_null().value.is_null(Ordering::Acquire) {
+            return None;
+        }
+        let previous = node.non_null().value.swap(&Xarc::null(), Ordering::AcqRel);
+        if previous.is_null() {
+            None
+        }
+        else {
+            self.length.sub(1);
+            Some(previous)
+        }
+    }
+
+    /// Get the number of keys with values.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check whether the trie holds no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The node at the end of the path for `key`, if the path exists.
+    #[must_use]
+    fn find(&self, key: &[u8]) -> Option<Xarc<TrieNode<V>>> {
+        nibbles(key).try_fold(self.root.clone(), |node, nibble| node.non_null().child(nibble))
+    }
+}
+
+impl<V: ?Sized + Send + Pointee> Drop for Trie<V> {
+    fn drop(&mut self) {
+        // Nothing else can reach the nodes any more, so they are freed at once rather than one per epoch advance.
+        let mut pending = vec![mem::replace(&mut self.root, Xarc::null())];
+        while let Some(next) = pending.pop() {
+            if let Some(mut node) = unsafe { next.unwrap_unprotected() } {
+                pending.extend(node.children.iter_mut().map(AtomicXarc::take_mut).filter(|child| !child.is_null()));
+            }
+        }
+    }
+}
+
+impl<V: ?Sized + Send + Pointee> Default for Trie<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: ?Sized + Send + Pointee> fmt::Debug for Trie<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Trie").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_trie_mt_test() {
+        use alloc::{format, string::String};
+        use rayon::prelude::*;
+
+        let trie = Trie::new();
+        (0..1000).into_par_iter().for_each(|i| {
+            let key = format!("{}", i);
+            let _ = trie.insert(key.as_bytes(), &Xarc::new(key.clone()));
+        });
+        assert_eq!(trie.len(), 1000);
+        assert!((0..1000).all(|i| trie.get(format!("{}", i).as_bytes()).as_ref().and_then(Xarc::maybe_deref) == Some(&format!("{}", i))));
+
+        let (length, value) = trie.longest_prefix(b"12345").unwrap();
+        assert_eq!((length, value.maybe_deref().map(String::as_str)), (3, Some("123")));
+        assert!(trie.longest_prefix(b"x").is_none());
+
+        (0..1000).into_par_iter().filter(|i| i % 2 == 1).for_each(|i| {
+            assert!(trie.remove(format!("{}", i).as_bytes()).is_some());
+        });
+        assert_eq!(trie.len(), 500);
+        assert_eq!(trie.longest_prefix(b"12345").unwrap().1.maybe_deref().map(String::as_str), Some("12"));
+        assert_eq!(trie.get(b"12").as_ref().and_then(Xarc::maybe_deref).cloned(), Some(String::from("12")));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{atomic::*, pointer::*};
+use alloc::boxed::Box;
+use core::{array, cell::UnsafeCell, fmt, iter::{FromIterator, FusedIterator}, mem::MaybeUninit, ptr, sync::atomic::{AtomicBool, AtomicUsize, Ordering}};
+
+/// The number of elements stored in the first chunk of a `ConcurrentVec`. Each chunk after it is twice the size of the last.
+const CHUNK: usize = 32;
+
+/// The number of chunks it takes for a `ConcurrentVec` to hold `usize::MAX` elements.
+const CHUNKS: usize = (usize::BITS - CHUNK.trailing_zeros()) as usize + 1;
+
+struct Slot<T> {
+    ready: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Chunk<T: Send> {
+    slots: Box<[Slot<T>]>,
+}
+
+impl<T: Send> Chunk<T> {
+    fn new(len: usize) -> Self {
+        Chunk {
+            slots: (0..len).map(|_| Slot {
+                ready: AtomicBool::new(false),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }).collect(),
+        }
+    }
+
+    fn get(&self, offset: usize) -> Option<&T> {
+        let slot = &self.slots[offset];
+        if slot.ready.load(Ordering::Acquire) {
+            unsafe {
+                Some((*slot.value.get()).assume_init_ref())
+            }
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl<T: Send> Drop for Chunk<T> {
+    fn drop(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if *slot.ready.get_mut() {
+                unsafe {
+                    ptr::drop_in_place(slot.value.get_mut().as_mut_ptr());
+                }
+            }
+        }
+    }
+}
+
+/// Find the chunk holding `index` and the offset of `index` within it.
+fn locate(index: usize) -> (usize, usize) {
+    let number = index / CHUNK + 1;
+    let chunk = (usize::BITS - 1 - number.leading_zeros()) as usize;
+    (chunk, index - CHUNK * ((1 << chunk) - 1))
+}
+
+/// `ConcurrentVec` is an append-only vector that can be read while other threads push to it.
+///
+/// Elements are stored in chunks that double in size, each published through its own `AtomicXarc` in a fixed directory,
+/// so `push` and `get` find an element's chunk in constant time however long the vector grows.
+/// Elements never move once pushed and references to them remain valid for as long as the `ConcurrentVec` is borrowed.
+/// It suits accumulating logs or telemetry from many threads.
+///
+/// An index is reserved before its element is written, so `len` can briefly count elements that `get` does not return yet.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::collections::ConcurrentVec;
+///
+/// let log = ConcurrentVec::new();
+/// (0..1000).into_par_iter().for_each(|i| {
+///     let index = log.push(i);
+///     assert_eq!(log.get(index), Some(&i));
+/// });
+/// assert_eq!(log.len(), 1000);
+/// assert_eq!(log.iter().sum::<i32>(), (0..1000).sum());
+/// ```
+pub struct ConcurrentVec<T: Send> {
+    chunks: [AtomicXarc<Chunk<T>>; CHUNKS],
+    len: AtomicUsize,
+}
+
+impl<T: Send> ConcurrentVec<T> {
+    /// Create an empty vector.
+    #[must_use]
+    pub fn new() -> Self {
+        ConcurrentVec {
+            chunks: array::from_fn(|_| AtomicXarc::null()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Append `value` and return its index.
+    pub fn push(&self, value: T) -> usize {
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        let (chunk, offset) = locate(index);
+        let slot = &self.chunk_or_append(chunk).slots[offset];
+        unsafe {
+            (*slot.value.get()).write(value);
+        }
+        slot.ready.store(true, Ordering::Release);
+        index
+    }
+
+    /// Get a reference to the element at `index`, or None if it has not been pushed yet.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let (chunk, offset) = locate(index);
+        self.chunk(chunk)?.get(offset)
+    }
+
+    /// Get the number of indices that have been reserved by `push`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Check if nothing has been pushed yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the elements in index order, skipping any that are still being written.
+    /// Elements pushed after iteration begins may or may not be visited. Use `snapshot` for a view without gaps.
+    #[must_use]
+    pub fn iter(&self) -> ConcurrentVecIter<'_, T> {
+        ConcurrentVecIter {
+            vec: self,
+            index: 0,
+            len: self.len(),
+        }
+    }
+
+    /// Take a snapshot of the longest prefix of elements that have all been written.
+    /// Since elements never change or move once written, the snapshot stays consistent while pushes continue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::collections::ConcurrentVec;
+    ///
+    /// let log: ConcurrentVec<_> = (0..100).collect();
+    /// let snapshot = log.snapshot();
+    /// log.push(100);
+    /// assert_eq!(snapshot.len(), 100);
+    /// assert_eq!(snapshot.iter().copied().max(), Some(99));
+    /// ```
+    #[must_use]
+    pub fn snapshot(&self) -> ConcurrentVecSnapshot<'_, T> {
+        let reserved = self.len();
+        let mut len = 0;
+        while len < reserved && self.get(len).is_some() {
+            len += 1;
+        }
+        ConcurrentVecSnapshot {
+            vec: self,
+            len,
+        }
+    }
+
+    fn chunk(&self, chunk: usize) -> Option<&Chunk<T>> {
+        // Chunks are never removed while the `ConcurrentVec` is alive.
+        unsafe {
+            self.chunks[chunk].load_raw(Ordering::Acquire).as_ref()
+        }
+    }
+
+    /// Get the chunk numbered `chunk`, appending it first if necessary.
+    fn chunk_or_append(&self, chunk: usize) -> &Chunk<T> {
+        if let Some(existing) = self.chunk(chunk) {
+            return existing;
+        }
+        let new = Xarc::new(Chunk::new(CHUNK << chunk));
+        let raw = match self.chunks[chunk].compare_exchange(&Xarc::null(), &new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => new.as_raw(),
+            Err(current) => current.as_raw(),
+        };
+        unsafe {
+            &*raw
+        }
+    }
+}
+
+impl<T: Send> Drop for ConcurrentVec<T> {
+    fn drop(&mut self) {
+        // Nothing else can reach the chunks any more, so they are freed at once rather than one per epoch advance.
+        for chunk in self.chunks.iter_mut() {
+            drop(unsafe { chunk.take_mut().unwrap_unprotected() });
+        }
+    }
+}
+
+impl<T: Send> Default for ConcurrentVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for ConcurrentVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Send> IntoIterator for &'a ConcurrentVec<T> {
+    type Item = &'a T;
+    type IntoIter = ConcurrentVecIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Send> Extend<T> for ConcurrentVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: Send> FromIterator<T> for ConcurrentVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+unsafe impl<T: Send + Sync> Sync for ConcurrentVec<T> {}
+
+/// An iterator over the elements of a `ConcurrentVec`. See `ConcurrentVec::iter`.
+pub struct ConcurrentVecIter<'a, T: Send> {
+    vec: &'a ConcurrentVec<T>,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, T: Send> Iterator for ConcurrentVecIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        while self.index < self.len {
+            let index = self.index;
+            self.index += 1;
+            if let Some(value) = self.vec.get(index) {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl<T: Send> FusedIterator for ConcurrentVecIter<'_, T> {}
+
+/// A consistent view of a prefix of a `ConcurrentVec`. See `ConcurrentVec::snapshot`.
+pub struct ConcurrentVecSnapshot<'a, T: Send> {
+    vec: &'a ConcurrentVec<T>,
+    len: usize,
+}
+
+impl<'a, T: Send> ConcurrentVecSnapshot<'a, T> {
+    /// Get the number of elements in the snapshot.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the snapshot is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a reference to the element at `index`, or None if it is outside of the snapshot.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        if index < self.len {
+            self.vec.get(index)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Iterate over the elements in the snapshot in index order.
+    #[must_use]
+    pub fn iter(&self) -> ConcurrentVecIter<'a, T> {
+        ConcurrentVecIter {
+            vec: self.vec,
+            index: 0,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Send> Clone for ConcurrentVecSnapshot<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Send> Copy for ConcurrentVecSnapshot<'_, T> {}
+
+impl<T: Send + fmt::Debug> fmt::Debug for ConcurrentVecSnapshot<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Send> IntoIterator for &ConcurrentVecSnapshot<'a, T> {
+    type Item = &'a T;
+    type IntoIter = ConcurrentVecIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_vec_locate_st_test() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(CHUNK - 1), (0, CHUNK - 1));
+        assert_eq!(locate(CHUNK), (1, 0));
+        assert_eq!(locate(3 * CHUNK - 1), (1, 2 * CHUNK - 1));
+        assert_eq!(locate(3 * CHUNK), (2, 0));
+        assert_eq!(locate(usize::MAX), (CHUNKS - 1, CHUNK - 1));
+    }
+
+    #[test]
+    fn concurrent_vec_fill_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use std::thread;
+        const THREADS: usize = 4;
+        const PUSHES: usize = 20_000;
+        let vec = ConcurrentVec::new();
+        thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let vec = &vec;
+                scope.spawn(move || {
+                    for i in 0..PUSHES {
+                        let value = thread * PUSHES + i;
+                        let index = vec.push(value);
+                        assert_eq!(vec.get(index), Some(&value));
+                        // Earlier elements stay where they were while later chunks are appended.
+                        if let Some(&first) = vec.get(0) {
+                            assert!(first % PUSHES == 0);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(vec.len(), THREADS * PUSHES);
+        assert_eq!(vec.snapshot().len(), THREADS * PUSHES);
+        let mut values: Vec<usize> = vec.iter().copied().collect();
+        values.sort_unstable();
+        assert!(values.into_iter().eq(0..THREADS * PUSHES));
+        assert!(vec.get(THREADS * PUSHES).is_none());
+    }
+}
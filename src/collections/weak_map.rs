@@ -0,0 +1,270 @@
+use super::length::Length;
+use crate::{atomic::*, internal::fnv_hash, pointee::*, pointer::*, weak::WeakXarc};
+use alloc::vec::Vec;
+use core::{fmt, hash::Hash, sync::atomic::Ordering};
+
+/// The number of buckets a `WeakMap` spreads its keys across by default.
+const BUCKETS: usize = 256;
+
+/// A key and a weak reference to its value.
+struct Entry<K: Send, V: ?Sized + Send + Pointee> {
+    key: K,
+    value: WeakXarc<V>,
+}
+
+impl<K: Send, V: ?Sized + Send + Pointee> Entry<K, V> {
+    #[must_use]
+    fn is_dead(&self) -> bool {
+        self.value.strong_count() == 0
+    }
+}
+
+type Bucket<K, V> = [Xarc<Entry<K, V>>];
+
+/// `WeakMap` maps keys to values that it does not keep alive, for caches whose entries should go away once nothing else uses them.
+///
+/// Values are held as `WeakXarc`s, so an entry dies when the last `Xarc` to its value is dropped and `get` stops returning it.
+/// Dead entries are purged lazily: any write to a bucket leaves them out, and `get` rewrites a bucket it finds one in.
+/// `purge` sweeps every bucket at once.
+///
+/// Each bucket is an immutable slice of entries published through an `AtomicXarc`,
+/// and every write replaces the slice of its bucket with a compare-exchange, so readers never wait.
+/// Values from an `XarcPool` cannot be downgraded and so cannot be stored.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::{Xarc, collections::WeakMap};
+///
+/// let cache = WeakMap::new();
+/// let held: Vec<Xarc<String>> = (0..1000).into_par_iter()
+///     .map(|i| cache.get_or_insert_with(i % 10, || Xarc::new((i % 10).to_string())))
+///     .collect();
+/// assert_eq!(cache.len(), 10);
+/// assert_eq!(cache.get(&3).unwrap().maybe_deref().map(String::as_str), Some("3"));
+///
+/// drop(held);
+/// assert!(cache.get(&3).is_none());
+/// assert_eq!(cache.purge(), 9);
+/// assert!(cache.is_empty());
+/// ```
+pub struct WeakMap<K: Hash + Eq + Send + Sync, V: ?Sized + Send + Pointee> {
+    buckets: Vec<AtomicXarc<Bucket<K, V>>>,
+    length: Length,
+}
+
+impl<K: Hash + Eq + Send + Sync, V: ?Sized + Send + Pointee> WeakMap<K, V> {
+    /// Create an empty map with the default number of buckets.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_buckets(BUCKETS)
+    }
+
+    /// Create an empty map with `buckets` buckets.
+    /// The number of buckets is fixed, so choose it to suit the number of keys expected.
+    ///
+    /// # Panics
+    /// - If `buckets` is 0.
+    #[must_use]
+    pub fn with_buckets(buckets: usize) -> Self {
+        assert!(buckets > 0, "WeakMap requires at least one bucket!");
+        WeakMap {
+            buckets: (0..buckets).map(|_| AtomicXarc::null()).collect(),
+            length: Length::new(),
+        }
+    }
+
+    /// Get the value for `key` if it is still alive, purging the bucket if it holds dead entries.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get(&self, key: &K) -> Option<Xarc<V>> {
+        let head = self.bucket(key);
+        let current = head.load(Ordering::Acquire);
+        if current.iter().any(|entry| entry.non_null().is_dead()) {
+            self.purge_bucket(head);
+        }
+        find(&current, key)
+    }
+
+    /// Map `key` to `value`, returning the value it replaced if that was still alive.
+    /// The map only keeps a weak reference to `value`.
+    ///
+    /// # Panics
+    /// - If `value` cannot be downgraded. See `Xarc::downgrade`.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn insert(&self, key: K, value: &Xarc<V>) -> Option<Xarc<V>> {
+        let head = self.bucket(&key);
+        let new = Xarc::new(Entry {
+            key,
+            value: Xarc::downgrade(value),
+        });
+        loop {
+            let current = head.load(Ordering::Acquire);
+            let previous = find(&current, &new.non_null().key);
+            let (entries, removed) = rewrite(&current, Some(&new.non_null().key), Some(&new));
+            if head.compare_exchange(&current, &entries, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                self.length.add(1);
+                self.length.sub(removed);
+                return previous;
+            }
+        }
+    }
+
+    /// Get the value for `key` if it is still alive, or map it to the value returned by `f` otherwise.
+    /// `f` is called at most once, but its value may be dropped unused if another thread inserts a value for `key` first.
+    ///
+    /// # Panics
+    /// - If the value returned by `f` cannot be downgraded. See `Xarc::downgrade`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get_or_insert_with<F: FnOnce() -> Xarc<V>>(&self, key: K, f: F) -> Xarc<V> {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let head = self.bucket(&key);
+        let value = f();
+        let new = Xarc::new(Entry {
+            key,
+            value: Xarc::downgrade(&value),
+        });
+        loop {
+            let current = head.load(Ordering::Acquire);
+            if let Some(existing) = find(&current, &new.non_null().key) {
+                return existing;
+            }
+            let (entries, removed) = rewrite(&current, None, Some(&new));
+            if head.compare_exchange(&current, &entries, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                self.length.add(1);
+                self.length.sub(removed);
+                return value;
+            }
+        }
+    }
+
+    /// Remove the entry for `key`, returning its value if that was still alive.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn remove(&self, key: &K) -> Option<Xarc<V>> {
+        let head = self.bucket(key);
+        loop {
+            let current = head.load(Ordering::Acquire);
+            if !current.iter().any(|entry| entry.non_null().key == *key || entry.non_null().is_dead()) {
+                return None;
+            }
+            let previous = find(&current, key);
+            let (entries, removed) = rewrite(&current, Some(key), None);
+            if head.compare_exchange(&current, &entries, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                self.length.sub(removed);
+                return previous;
+            }
+        }
+    }
+
+    /// Remove every entry whose value has been dropped, returning how many were removed.
+    pub fn purge(&self) -> usize {
+        self.buckets.iter().map(|head| self.purge_bucket(head)).sum()
+    }
+
+    /// Get the number of entries, including dead entries that have not been purged yet.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Check whether the map has no entries, alive or not yet purged.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn bucket(&self, key: &K) -> &AtomicXarc<Bucket<K, V>> {
+        &self.buckets[fnv_hash(key) as usize % self.buckets.len()]
+    }
+
+    /// Remove the dead entries from the bucket at `head`, returning how many were removed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn purge_bucket(&self, head: &AtomicXarc<Bucket<K, V>>) -> usize {
+        loop {
+            let current = head.load(Ordering::Acquire);
+            if !current.iter().any(|entry| entry.non_null().is_dead()) {
+                return 0;
+            }
+            let (entries, removed) = rewrite(&current, None, None);
+            if head.compare_exchange(&current, &entries, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                self.length.sub(removed);
+                return removed;
+            }
+        }
+    }
+}
+
+/// Upgrade the value for `key` in `entries`, if it is there and still alive.
+#[must_use]
+fn find<K: Eq + Send, V: ?Sized + Send + Pointee>(entries: &Xarc<Bucket<K, V>>, key: &K) -> Option<Xarc<V>> {
+    let value = entries.iter().find(|entry| entry.non_null().key == *key)?.non_null().value.upgrade();
+    if value.is_null() {
+        None
+    }
+    else {
+        Some(value)
+    }
+}
+
+/// Copy `entries` without the dead ones and without the entry for `without`, then append `with`.
+/// Returns the copy, or null if it is empty, and the number of entries left out.
+#[must_use]
+fn rewrite<K: Eq + Send, V: ?Sized + Send + Pointee>(entries: &Xarc<Bucket<K, V>>, without: Option<&K>, with: Option<&Xarc<Entry<K, V>>>) -> (Xarc<Bucket<K, V>>, usize) {
+    let kept: Vec<Xarc<Entry<K, V>>> = entries.iter()
+        .filter(|entry| !entry.non_null().is_dead() && without != Some(&entry.non_null().key))
+        .cloned()
+        .chain(with.cloned())
+        .collect();
+    let removed = entries.iter().len() + usize::from(with.is_some()) - kept.len();
+    if kept.is_empty() {
+        (Xarc::null(), removed)
+    }
+    else {
+        (Xarc::from(kept), removed)
+    }
+}
+
+impl<K: Hash + Eq + Send + Sync, V: ?Sized + Send + Pointee> Default for WeakMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Send + Sync, V: ?Sized + Send + Pointee> fmt::Debug for WeakMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeakMap").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_weak_map_mt_test() {
+        use alloc::vec::Vec;
+        use rayon::prelude::*;
+
+        let map = WeakMap::with_buckets(8);
+        let mut held: Vec<Xarc<usize>> = (0..1000).into_par_iter().map(|i| map.get_or_insert_with(i % 100, || Xarc::new(i % 100))).collect();
+        assert_eq!(map.len(), 100);
+        assert!(held.iter().all(|value| map.get(value.maybe_deref().unwrap()).as_ref() == Some(value)));
+
+        held.retain(|value| *value.maybe_deref().unwrap() % 2 == 0);
+        assert_eq!(map.get(&1), None);
+        let _ = map.purge();
+        assert_eq!(map.len(), 50);
+
+        let replacement = Xarc::new(1000);
+        assert_eq!(map.insert(0, &replacement).as_ref(), held.first());
+        assert_eq!(map.remove(&0), Some(replacement));
+        assert_eq!(map.remove(&0), None);
+        drop(held);
+        assert_eq!(map.purge(), 49);
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use super::{internal::XarcHeader, latency::Retired};
+use crossbeam_epoch::pin;
+use std::{cell::RefCell, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}}, thread_local, vec::Vec};
+
+/// The number of times `collect` flushes the epoch while waiting for deferred reclamations to become eligible.
+const FLUSHES: usize = 1024;
+
+/// A reclamation that has been deferred until it is safe and is now waiting for `collect`.
+struct Reclamation {
+    ptr: *mut XarcHeader,
+    reclaim: unsafe fn(*mut XarcHeader),
+    retired: Retired,
+}
+
+// The pointer is only ever handed to `reclaim`, by whichever thread calls `collect`.
+unsafe impl Send for Reclamation {}
+
+pub(crate) struct Queue {
+    reclamations: Mutex<Vec<Reclamation>>,
+    in_flight: AtomicUsize,
+    closed: AtomicBool,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Queue>>> = const { RefCell::new(None) };
+}
+
+/// `ManualCollector` puts reclamation under the control of a test.
+///
+/// While it is alive, no value whose count drops to zero on the thread that created it is reclaimed
+/// until `collect` is called, at which point everything eligible is reclaimed synchronously.
+/// This makes assertions about when values are dropped or `on_reclaim` hooks run deterministic.
+/// Values released on other threads are reclaimed as usual.
+///
+/// Dropping the collector reclaims whatever it was holding and restores the previous behavior.
+///
+/// Requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use xarc::{ManualCollector, Xarc};
+///
+/// static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+///
+/// let collector = ManualCollector::new();
+/// drop(Xarc::with_on_reclaim(42, |_| {
+///     RECLAIMED.fetch_add(1, Ordering::Relaxed);
+/// }));
+/// assert_eq!(RECLAIMED.load(Ordering::Relaxed), 0);
+/// assert_eq!(collector.collect(), 1);
+/// assert_eq!(RECLAIMED.load(Ordering::Relaxed), 1);
+/// ```
+#[derive(Debug)]
+pub struct ManualCollector {
+    queue: Arc<Queue>,
+    previous: Option<Arc<Queue>>,
+}
+
+impl ManualCollector {
+    /// Take control of reclamation on the current thread.
+    #[must_use]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let queue = Arc::new(Queue {
+            reclamations: Mutex::new(Vec::new()),
+            in_flight: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        });
+        let previous = CURRENT.with(|current| current.replace(Some(queue.clone())));
+        ManualCollector {
+            queue,
+            previous,
+        }
+    }
+
+    /// The number of values waiting to be reclaimed, including those still waiting on the epoch.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.queue.in_flight.load(Ordering::Acquire) + self.queue.lock().len()
+    }
+
+    /// Reclaim every value that can safely be reclaimed, including values released by the reclamations themselves.
+    /// Returns the number of values reclaimed.
+    ///
+    /// Values that another thread may still be reading remain pending, which only happens if that thread stays pinned throughout.
+    pub fn collect(&self) -> usize {
+        super::repin::release();
+        let mut reclaimed = 0;
+        loop {
+            for _ in 0..FLUSHES {
+                if self.queue.in_flight.load(Ordering::Acquire) == 0 {
+                    break;
+                }
+                pin().flush();
+            }
+            let reclamations = core::mem::take(&mut *self.queue.lock());
+            if reclamations.is_empty() {
+                return reclaimed;
+            }
+            reclaimed += reclamations.len();
+            for Reclamation {ptr, reclaim, retired} in reclamations {
+                retired.reclaiming();
+                unsafe {
+                    reclaim(ptr);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ManualCollector {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.replace(self.previous.take()));
+        self.queue.closed.store(true, Ordering::Release);
+        for Reclamation {ptr, reclaim, retired} in core::mem::take(&mut *self.queue.lock()) {
+            retired.reclaiming();
+            unsafe {
+                reclaim(ptr);
+            }
+        }
+    }
+}
+
+impl Queue {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Reclamation>> {
+        self.reclamations.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Called from the deferred function once `ptr` can safely be reclaimed.
+    pub(crate) fn push(&self, ptr: *mut XarcHeader, reclaim: unsafe fn(*mut XarcHeader), retired: Retired) {
+        if self.closed.load(Ordering::Acquire) {
+            retired.reclaiming();
+            unsafe {
+                reclaim(ptr);
+            }
+        }
+        else {
+            self.lock().push(Reclamation {ptr, reclaim, retired});
+        }
+        self.in_flight.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl core::fmt::Debug for Queue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Queue").field("in_flight", &self.in_flight).finish()
+    }
+}
+
+/// Check whether a `ManualCollector` is controlling reclamation on the current thread.
+#[must_use]
+pub(crate) fn is_current() -> bool {
+    CURRENT.try_with(|current| current.borrow().is_some()).unwrap_or(false)
+}
+
+/// The queue of the `ManualCollector` controlling reclamation on the current thread, if any.
+/// The caller must `push` to it exactly once.
+#[must_use]
+pub(crate) fn current() -> Option<Arc<Queue>> {
+    CURRENT.try_with(|current| {
+        current.borrow().clone().inspect(|queue| {
+            queue.in_flight.fetch_add(1, Ordering::Relaxed);
+        })
+    }).ok().flatten()
+}
@@ -0,0 +1,242 @@
+//! Types with the method names and semantics of the `arc-swap` crate, backed by `AtomicXarc` with the `ReadMostly` strategy.
+//!
+//! Replacing `arc_swap` with `xarc::compat::arcswap` and `Arc` with `Xarc` migrates the common subset of the API:
+//! `new`, `from_pointee`, `load`, `load_full`, `store`, `swap`, `rcu` and `compare_and_swap`.
+//! Every operation is sequentially consistent, as in `arc-swap`.
+//! `into_inner` on the `AtomicXarc` then exposes everything else, such as explicit orderings and bounded operations.
+//!
+//! `Cache`, `map` and the `Access` traits have no counterpart.
+//!
+//! # Examples
+//!
+//! ```
+//! use xarc::{Xarc, compat::arcswap::{ArcSwap, ArcSwapOption}};
+//!
+//! let config = ArcSwap::from_pointee(1);
+//! assert_eq!(config.load().maybe_deref(), Some(&1));
+//! config.store(Xarc::new(2));
+//! config.rcu(|current| Xarc::new(current.maybe_deref().unwrap() + 1));
+//! assert_eq!(config.load_full().maybe_deref(), Some(&3));
+//!
+//! let cache: ArcSwapOption<String> = ArcSwapOption::empty();
+//! assert!(cache.load().is_none());
+//! cache.store(Some(Xarc::new(String::from("hit"))));
+//! assert_eq!(cache.load_full().as_ref().and_then(Xarc::maybe_deref).map(String::as_str), Some("hit"));
+//! ```
+
+use crate::{atomic::*, pointer::*, strategy::ReadMostly};
+use core::{fmt, ops::Deref, sync::atomic::Ordering};
+
+/// A handle that can be stored in an `ArcSwapAny`, in place of `arc_swap::RefCnt`:
+/// either `Xarc<T>` or `Option<Xarc<T>>`, with `None` stored as null.
+/// This trait is sealed.
+pub trait RefCnt: sealed::Sealed {}
+
+mod sealed {
+    use crate::pointer::Xarc;
+
+    pub trait Sealed: Sized {
+        type Value: Send;
+
+        fn into_xarc(self) -> Xarc<Self::Value>;
+
+        fn from_xarc(pointer: Xarc<Self::Value>) -> Self;
+
+        fn with_xarc<R, F: FnOnce(&Xarc<Self::Value>) -> R>(&self, f: F) -> R;
+    }
+}
+
+impl<T: Send> RefCnt for Xarc<T> {}
+
+impl<T: Send> sealed::Sealed for Xarc<T> {
+    type Value = T;
+
+    fn into_xarc(self) -> Xarc<T> {
+        self
+    }
+
+    fn from_xarc(pointer: Xarc<T>) -> Self {
+        pointer
+    }
+
+    fn with_xarc<R, F: FnOnce(&Xarc<T>) -> R>(&self, f: F) -> R {
+        f(self)
+    }
+}
+
+impl<T: Send> RefCnt for Option<Xarc<T>> {}
+
+impl<T: Send> sealed::Sealed for Option<Xarc<T>> {
+    type Value = T;
+
+    fn into_xarc(self) -> Xarc<T> {
+        self.unwrap_or_else(Xarc::null)
+    }
+
+    fn from_xarc(pointer: Xarc<T>) -> Self {
+        pointer.into_option()
+    }
+
+    fn with_xarc<R, F: FnOnce(&Xarc<T>) -> R>(&self, f: F) -> R {
+        match self {
+            Some(pointer) => f(pointer),
+            None => f(&Xarc::null()),
+        }
+    }
+}
+
+/// The result of `ArcSwapAny::load`, dereferencing to the loaded handle.
+///
+/// In `arc-swap` this may borrow the value without taking a reference.
+/// Here it always holds one, so it can be kept for as long as needed.
+pub struct Guard<H: RefCnt> {
+    inner: H,
+}
+
+impl<H: RefCnt> Guard<H> {
+    /// Wrap a handle in a guard.
+    #[must_use]
+    pub fn from_inner(inner: H) -> Self {
+        Guard {
+            inner,
+        }
+    }
+
+    /// Take the handle out of the guard.
+    #[must_use]
+    pub fn into_inner(guard: Self) -> H {
+        guard.inner
+    }
+}
+
+impl<H: RefCnt> Deref for Guard<H> {
+    type Target = H;
+
+    fn deref(&self) -> &H {
+        &self.inner
+    }
+}
+
+impl<H: RefCnt + fmt::Debug> fmt::Debug for Guard<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// An atomic storage for a `RefCnt` handle with the API of `arc_swap::ArcSwapAny`. See the module documentation.
+pub struct ArcSwapAny<H: RefCnt> {
+    slot: AtomicXarc<H::Value, ReadMostly>,
+}
+
+/// An `ArcSwapAny` that always holds a value, like `arc_swap::ArcSwap`.
+pub type ArcSwap<T> = ArcSwapAny<Xarc<T>>;
+
+/// An `ArcSwapAny` that may be empty, like `arc_swap::ArcSwapOption`.
+pub type ArcSwapOption<T> = ArcSwapAny<Option<Xarc<T>>>;
+
+impl<H: RefCnt> ArcSwapAny<H> {
+    /// Store `value` in a new `ArcSwapAny`.
+    #[must_use]
+    pub fn new(value: H) -> Self {
+        ArcSwapAny {
+            slot: AtomicXarc::with_strategy(&value.into_xarc(), ReadMostly),
+        }
+    }
+
+    /// Load the current value into a `Guard`.
+    #[must_use]
+    pub fn load(&self) -> Guard<H> {
+        Guard::from_inner(self.load_full())
+    }
+
+    /// Load the current value.
+    #[must_use]
+    pub fn load_full(&self) -> H {
+        H::from_xarc(self.slot.load(Ordering::SeqCst))
+    }
+
+    /// Replace the current value with `value`.
+    pub fn store(&self, value: H) {
+        drop(self.swap(value));
+    }
+
+    /// Replace the current value with `value`, returning the previous value.
+    #[must_use]
+    pub fn swap(&self, value: H) -> H {
+        H::from_xarc(self.slot.swap(&value.into_xarc(), Ordering::SeqCst))
+    }
+
+    /// Replace the current value with `new` if it is the same allocation as `current`,
+    /// returning the value found either way. The swap succeeded if it is the same allocation as `current`.
+    pub fn compare_and_swap<C: Deref<Target = H>>(&self, current: C, new: H) -> Guard<H> {
+        let new = new.into_xarc();
+        let found = current.with_xarc(|current| self.slot.compare_and_swap(current, &new, Ordering::SeqCst, Ordering::SeqCst));
+        Guard::from_inner(H::from_xarc(found))
+    }
+
+    /// Repeatedly compute a replacement for the current value with `f` and attempt to swap it in until nothing interferes.
+    /// Returns the value that was replaced. `f` may be called several times.
+    pub fn rcu<R: Into<H>, F: FnMut(&H) -> R>(&self, mut f: F) -> H {
+        let replaced = self.slot.cas_loop(|current| {
+            Some(f(&H::from_xarc(current.clone())).into().into_xarc())
+        }, Ordering::SeqCst, Ordering::SeqCst);
+        match replaced {
+            Ok(previous) => H::from_xarc(previous),
+            Err(_) => unreachable!("the replacement is never declined"),
+        }
+    }
+
+    /// Give up the `arc-swap` API for the `AtomicXarc` behind it.
+    #[must_use]
+    pub fn into_inner(self) -> AtomicXarc<H::Value, ReadMostly> {
+        self.slot
+    }
+}
+
+impl<T: Send> ArcSwapAny<Xarc<T>> {
+    /// Store `value` in a new `ArcSwap`.
+    #[must_use]
+    pub fn from_pointee(value: T) -> Self {
+        Self::new(Xarc::new(value))
+    }
+}
+
+impl<T: Send> ArcSwapAny<Option<Xarc<T>>> {
+    /// Store `value`, if any, in a new `ArcSwapOption`.
+    #[must_use]
+    pub fn from_pointee<V: Into<Option<T>>>(value: V) -> Self {
+        Self::new(value.into().map(Xarc::new))
+    }
+
+    /// Create an empty `ArcSwapOption`.
+    #[must_use]
+    pub const fn const_empty() -> Self {
+        ArcSwapAny {
+            slot: AtomicXarc::null_with_strategy(ReadMostly),
+        }
+    }
+
+    /// Create an empty `ArcSwapOption`.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self::const_empty()
+    }
+}
+
+impl<H: RefCnt + Default> Default for ArcSwapAny<H> {
+    fn default() -> Self {
+        Self::new(H::default())
+    }
+}
+
+impl<H: RefCnt> From<H> for ArcSwapAny<H> {
+    fn from(value: H) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<H: RefCnt + fmt::Debug> fmt::Debug for ArcSwapAny<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ArcSwapAny").field(&self.load_full()).finish()
+    }
+}
@@ -0,0 +1,3 @@
+//! Types mirroring the APIs of other crates, so that code using them can migrate to `xarc` by changing its imports.
+
+pub mod arcswap;
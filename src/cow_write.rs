@@ -0,0 +1,150 @@
+use super::{atomic::*, pointer::*, strategy::*};
+use core::{fmt, ops::{Deref, DerefMut}, sync::atomic::Ordering};
+
+/// A private copy of the value of an `AtomicXarc`, returned by `AtomicXarc::write_cow`, to be published in its place.
+///
+/// It dereferences mutably to the copy. `commit` publishes the copy with a compare-exchange,
+/// which fails with a `CowConflict` if another thread replaced the value since the copy was taken.
+/// Dropping a guard whose copy was modified commits it too, discarding the copy on a conflict,
+/// so call `commit` to find out whether the write happened, or `AtomicXarc::update_cow` to retry until it does.
+/// `discard` drops the copy without publishing it.
+pub struct CowWriteGuard<'a, T: Send + Clone, S: Strategy = Eager> {
+    slot: &'a AtomicXarc<T, S>,
+    original: Xarc<T>,
+    /// The copy, taken out when it is committed or discarded.
+    copy: Option<T>,
+    modified: bool,
+}
+
+impl<'a, T: Send + Clone, S: Strategy> CowWriteGuard<'a, T, S> {
+    /// Copy the current value of `slot`. See `AtomicXarc::write_cow`.
+    ///
+    /// # Panics
+    /// - If `slot` is null.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub(crate) fn new(slot: &'a AtomicXarc<T, S>) -> Self {
+        let original = slot.load(Ordering::Acquire);
+        let copy = original.maybe_deref().expect("Copied a null AtomicXarc!").clone();
+        CowWriteGuard {
+            slot,
+            original,
+            copy: Some(copy),
+            modified: false,
+        }
+    }
+
+    /// The value the copy was taken from.
+    #[must_use]
+    pub fn original(&self) -> &Xarc<T> {
+        &self.original
+    }
+
+    /// Publish the copy if the value has not been replaced since it was taken, returning the value it replaced.
+    /// Otherwise, return the copy along with the value that replaced the original.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn commit(mut self) -> Result<Xarc<T>, CowConflict<T>> {
+        let copy = self.copy.take().unwrap();
+        self.publish(copy)
+    }
+
+    /// Drop the copy without publishing it.
+    pub fn discard(mut self) {
+        self.copy = None;
+    }
+
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn publish(&self, copy: T) -> Result<Xarc<T>, CowConflict<T>> {
+        let new = Xarc::new(copy);
+        match self.slot.compare_exchange(&self.original, &new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(previous) => Ok(previous),
+            Err(current) => Err(CowConflict {
+                value: new.unwrap_or_clone(),
+                current,
+            }),
+        }
+    }
+}
+
+impl<T: Send + Clone, S: Strategy> Deref for CowWriteGuard<'_, T, S> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.copy.as_ref().unwrap()
+    }
+}
+
+impl<T: Send + Clone, S: Strategy> DerefMut for CowWriteGuard<'_, T, S> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.modified = true;
+        self.copy.as_mut().unwrap()
+    }
+}
+
+impl<T: Send + Clone, S: Strategy> Drop for CowWriteGuard<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(copy) = self.copy.take() {
+            if self.modified {
+                let _ = self.publish(copy);
+            }
+        }
+    }
+}
+
+impl<T: Send + Clone + fmt::Debug, S: Strategy> fmt::Debug for CowWriteGuard<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CowWriteGuard").field("copy", &**self).field("modified", &self.modified).finish_non_exhaustive()
+    }
+}
+
+/// The error returned by `CowWriteGuard::commit` when another thread replaced the value after the copy was taken.
+pub struct CowConflict<T: Send> {
+    /// The copy that was not published.
+    pub value: T,
+    /// The value that replaced the one the copy was taken from.
+    pub current: Xarc<T>,
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for CowConflict<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CowConflict").field("value", &self.value).field("current", &self.current).finish()
+    }
+}
+
+impl<T: Send> fmt::Display for CowConflict<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the value was replaced after it was copied")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + fmt::Debug> std::error::Error for CowConflict<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_write_cow_mt_test() {
+        use alloc::vec::Vec;
+        use rayon::prelude::*;
+
+        let shared = AtomicXarc::new(Vec::new());
+        (0..1000).into_par_iter().for_each(|i| {
+            let _ = shared.update_cow(|current| current.push(i));
+        });
+        let mut values = shared.load(Ordering::Acquire).maybe_deref().unwrap().clone();
+        values.sort_unstable();
+        assert!(values.into_iter().eq(0..1000));
+
+        let mut guard = shared.write_cow();
+        guard.clear();
+        guard.discard();
+        assert_eq!(shared.load(Ordering::Acquire).maybe_deref().map(Vec::len), Some(1000));
+        {
+            let mut guard = shared.write_cow();
+            guard.truncate(10);
+        }
+        assert_eq!(shared.load(Ordering::Acquire).maybe_deref().map(Vec::len), Some(10));
+    }
+}
@@ -0,0 +1,77 @@
+//! Tracking of where live `Xarc` handles were created, enabled by the `debug-owners` feature.
+//!
+//! Every `Xarc` records the location of the call that produced it, whether `new`, `clone`, `load`, or any other constructor,
+//! and the registry forgets it when the handle is dropped or reset.
+//! References held by `AtomicXarc`, `WeightedXarc`, and the other handle types are counted but not located.
+
+use super::{internal::XarcHeader, pointee::*, pointer::*};
+use core::{panic::Location, sync::atomic::{AtomicUsize, Ordering}};
+use std::{eprintln, sync::Mutex, vec::Vec};
+
+struct Owner {
+    id: usize,
+    ptr: usize,
+    location: &'static Location<'static>,
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+static OWNERS: Mutex<Vec<Owner>> = Mutex::new(Vec::new());
+
+/// Record a new handle to `ptr` created at `location`. Returns the id to pass to `unregister`, or 0 for null.
+#[must_use]
+pub(crate) fn register(ptr: *mut XarcHeader, location: &'static Location<'static>) -> usize {
+    if ptr.is_null() {
+        return 0;
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    OWNERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(Owner {
+        id,
+        ptr: ptr.addr(),
+        location,
+    });
+    id
+}
+
+/// Forget the handle with id `id`.
+pub(crate) fn unregister(id: usize) {
+    if id == 0 {
+        return;
+    }
+    let mut owners = OWNERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(index) = owners.iter().position(|owner| owner.id == id) {
+        owners.swap_remove(index);
+    }
+}
+
+/// The locations where the live `Xarc` handles to the same value as `pointer`, including `pointer` itself, were created.
+#[must_use]
+pub fn owners<T: ?Sized + Send + Pointee>(pointer: &Xarc<T>) -> Vec<&'static Location<'static>> {
+    let owners = OWNERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    owners.iter().filter(|owner| owner.ptr == pointer.ptr.addr()).map(|owner| owner.location).collect()
+}
+
+/// Print the count of the value `pointer` points to and where each live `Xarc` handle to it was created to standard error.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::{Xarc, debug};
+///
+/// let node = Xarc::new(42);
+/// let retained = node.clone();
+/// debug::dump_owners(&node);
+/// assert_eq!(debug::owners(&node).len(), 2);
+/// # drop(retained);
+/// ```
+pub fn dump_owners<T: ?Sized + Send + Pointee>(pointer: &Xarc<T>) {
+    if pointer.ptr.is_null() {
+        eprintln!("Xarc is null");
+        return;
+    }
+    let owners = owners(pointer);
+    let count = unsafe { (*pointer.ptr).count.load() };
+    eprintln!("Xarc {:p}: {} references, {} held by Xarc handles created at:", pointer.ptr, count, owners.len());
+    for location in owners {
+        eprintln!("    {}", location);
+    }
+}
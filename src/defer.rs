@@ -0,0 +1,66 @@
+use super::pointer::*;
+
+/// Run `f` once every thread that is pinned now has unpinned, through the same reclamation as the values `xarc` releases.
+///
+/// This is for auxiliary structures kept alongside values published through `AtomicXarc`s, such as an index or a cache entry
+/// that readers reach through a borrowed pointer while they are pinned: retiring them with `defer` makes them outlive those readers
+/// exactly as the values do, without a second epoch instance to keep in step with the first.
+/// `f` runs wherever the value it was retired with would have been reclaimed, so it follows `ManualCollector`, `fifo-reclaim`
+/// and `reclaim-latency` as well, and under `no-reclaim` it is leaked and never runs.
+///
+/// `f` may run on any thread and at any later time, or not at all if the program exits first, hence `Send + 'static`.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static FREED: AtomicUsize = AtomicUsize::new(0);
+///
+/// let index = vec![1u32, 2, 3];
+/// xarc::defer(move || {
+///     FREED.fetch_add(index.len(), Ordering::Relaxed);
+/// });
+/// xarc::quiescent();
+/// ```
+#[cfg_attr(feature = "debug-owners", track_caller)]
+pub fn defer<F: FnOnce() + Send + 'static>(f: F) {
+    drop(Xarc::with_on_reclaim(f, |f| f()));
+}
+
+/// Drop `value` once every thread that is pinned now has unpinned. See `xarc::defer`.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::Xarc;
+///
+/// let retired: Box<[Xarc<u32>]> = vec![Xarc::new(1), Xarc::new(2)].into_boxed_slice();
+/// xarc::retire(retired);
+/// ```
+#[cfg_attr(feature = "debug-owners", track_caller)]
+pub fn retire<T: Send + 'static>(value: T) {
+    drop(Xarc::with_on_reclaim(value, drop));
+}
+
+#[cfg(all(test, not(feature = "no-reclaim")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_defer_st_test() {
+        use alloc::boxed::Box;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static DEFERRED: AtomicUsize = AtomicUsize::new(0);
+
+        let index: Box<[usize]> = Box::new([1, 2, 3]);
+        crate::defer(move || {
+            DEFERRED.fetch_add(index.iter().sum(), Ordering::Relaxed);
+        });
+        crate::pin_scope(|scope| scope.defer(|| {
+            DEFERRED.fetch_add(4, Ordering::Relaxed);
+        }));
+        crate::retire(Xarc::new(5));
+        assert!(crate::quiescent::flush_until(|| DEFERRED.load(Ordering::Relaxed) == 10));
+    }
+}
@@ -0,0 +1,73 @@
+use super::{help, internal::XarcHeader, isr, strategy};
+use alloc::vec::Vec;
+use core::any;
+
+/// A description of the state of an `AtomicXarc` at one moment, for logging in post-mortems. See `AtomicXarc::debug_state`.
+///
+/// The fields are read one after another while other threads may be changing them, so they need not be mutually consistent.
+/// Addresses are given as integers so that the description can be logged and compared across threads.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct DebugState {
+    /// The address of the allocation holding the current value, or 0 if it is null.
+    pub address: usize,
+    /// The reference count of the current value, or 0 if it is null.
+    pub count: usize,
+    /// The name of the `Strategy`.
+    pub strategy: &'static str,
+    /// The number of `ReadMostly` loads holding a debt on the current value that no writer has paid yet.
+    pub debts: usize,
+    /// The number of loads that have given up racing writers and are waiting to be handed a reference.
+    pub waiting: usize,
+    /// The number of releases that `IsrXarc`s have left for `reclaim_isr_deferred`, across the whole process.
+    pub isr_deferred: usize,
+}
+
+impl DebugState {
+    /// Describe an `AtomicXarc` at `atomic` holding `ptr`. The caller must keep `ptr` from being reclaimed.
+    #[must_use]
+    pub(crate) fn new<S>(atomic: usize, ptr: *mut XarcHeader) -> Self {
+        let strategy = any::type_name::<S>();
+        DebugState {
+            address: ptr.addr(),
+            count: if ptr.is_null() {
+                0
+            }
+            else {
+                unsafe {
+                    (*ptr).count.load()
+                }
+            },
+            strategy: strategy.rsplit("::").next().unwrap_or(strategy),
+            debts: strategy::debts(ptr),
+            waiting: help::waiting(atomic),
+            isr_deferred: isr::deferred(),
+        }
+    }
+}
+
+/// A description of the state of a collection at one moment, for logging in post-mortems.
+/// Returned by `dump` on the collections.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::collections::Queue;
+///
+/// let queue = Queue::new();
+/// queue.push(42);
+/// let dump = queue.dump();
+/// assert_eq!(dump.collection, "Queue");
+/// assert_eq!(dump.len, 1);
+/// assert!(dump.slots.iter().all(|(_, state)| state.count > 0));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct CollectionDump {
+    /// The name of the collection type.
+    pub collection: &'static str,
+    /// The number of values, as `len` would return.
+    pub len: usize,
+    /// The state of each `AtomicXarc` the collection is anchored by, such as a head or a tail, with its name.
+    pub slots: Vec<(&'static str, DebugState)>,
+}
@@ -0,0 +1,97 @@
+use core::fmt;
+
+/// The error returned when a timed wait expires before the awaited change occurs.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct WaitTimeoutError;
+
+impl fmt::Display for WaitTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out waiting for a change")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for WaitTimeoutError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "WaitTimeoutError");
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WaitTimeoutError {}
+
+/// The error returned when the allocator fails to provide memory for a new value.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for AllocError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "AllocError");
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+/// The error returned by `Transaction::read` when a slot the transaction touched earlier has been written since,
+/// so that the transaction must start again. See `xarc::transaction`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TransactionConflict;
+
+impl fmt::Display for TransactionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a slot read by the transaction was written by another thread")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for TransactionConflict {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "TransactionConflict");
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransactionConflict {}
+
+/// The error returned when a region of memory cannot be used as a `ShmSegment`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ShmError {
+    /// The region is not aligned to 16 bytes.
+    Misaligned,
+    /// The region is too small to hold the segment header and at least one block, or too large to address with 32-bit offsets.
+    BadSize,
+    /// The region does not hold a segment created by `ShmSegment::create` with the same length.
+    NotFormatted,
+}
+
+impl fmt::Display for ShmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShmError::Misaligned => "shared memory region is not aligned to 16 bytes",
+            ShmError::BadSize => "shared memory region has an unusable size",
+            ShmError::NotFormatted => "shared memory region does not hold a segment",
+        })
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ShmError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            ShmError::Misaligned => defmt::write!(f, "ShmError::Misaligned"),
+            ShmError::BadSize => defmt::write!(f, "ShmError::BadSize"),
+            ShmError::NotFormatted => defmt::write!(f, "ShmError::NotFormatted"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ShmError {}
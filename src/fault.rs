@@ -0,0 +1,144 @@
+//! Fault injection for testing lock-free code built on xarc, enabled by the `fault-injection` feature.
+//!
+//! Retry branches are rarely taken under normal scheduling, so code that depends on them may ship without ever running them.
+//! Once faults are injected, the crate takes its failure paths on purpose:
+//! - `AtomicXarc::compare_exchange_weak` fails spuriously, and so `cas_loop` retries.
+//! - Loads, `cas_loop` and count increments pause between loading a value and acting on it,
+//!   widening the window in which another thread can interfere.
+//! - Incrementing the count of a loaded value fails as though the value had been released, and so loads retry.
+//!
+//! Every fault is one that could occur naturally, so correct code behaves the same apart from running slower.
+//! Faults apply to every thread in the process until `clear` is called, and nothing is injected before `inject` is called.
+//!
+//! # Examples
+//!
+//! ```
+//! use core::sync::atomic::Ordering;
+//! use xarc::{AtomicXarc, Xarc, fault::{self, Faults}};
+//!
+//! fault::inject(Faults {
+//!     spurious_failure: 0.5,
+//!     increment_failure: 0.5,
+//!     ..Faults::none()
+//! });
+//!
+//! let counter = AtomicXarc::new(0);
+//! let mut attempts = 0;
+//! for _ in 0..100 {
+//!     let _ = counter.cas_loop(|current| {
+//!         attempts += 1;
+//!         Some(Xarc::new(current.maybe_deref().unwrap() + 1))
+//!     }, Ordering::AcqRel, Ordering::Acquire);
+//! }
+//! fault::clear();
+//!
+//! assert_eq!(*counter.load(Ordering::Acquire).maybe_deref().unwrap(), 100);
+//! assert!(attempts > 100);
+//! ```
+
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// The rates and sizes of the faults to inject. Rates are probabilities between 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Faults {
+    /// How often `compare_exchange_weak` fails spuriously.
+    /// At a rate of 1, `cas_loop` never completes.
+    pub spurious_failure: f64,
+    /// How often to pause between loading a value and acting on it.
+    pub delay: f64,
+    /// How many spin loop iterations a pause lasts. With `std`, a pause also yields to other threads.
+    pub delay_spins: u32,
+    /// How often incrementing the count of a loaded value fails.
+    /// At a rate of 1, a load of a value that is not null can only complete once a writer hands it a reference.
+    pub increment_failure: f64,
+}
+
+impl Faults {
+    /// Inject no faults.
+    #[must_use]
+    pub const fn none() -> Self {
+        Faults {
+            spurious_failure: 0.0,
+            delay: 0.0,
+            delay_spins: 0,
+            increment_failure: 0.0,
+        }
+    }
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Each rate is stored as a threshold that a random `u32` must fall below, so 2^32 always passes.
+static SPURIOUS_FAILURE: AtomicU64 = AtomicU64::new(0);
+static DELAY: AtomicU64 = AtomicU64::new(0);
+static DELAY_SPINS: AtomicU32 = AtomicU32::new(0);
+static INCREMENT_FAILURE: AtomicU64 = AtomicU64::new(0);
+
+static STATE: AtomicU64 = AtomicU64::new(0);
+
+/// Start injecting `faults`, replacing any injected before.
+pub fn inject(faults: Faults) {
+    SPURIOUS_FAILURE.store(threshold(faults.spurious_failure), Ordering::Relaxed);
+    DELAY.store(threshold(faults.delay), Ordering::Relaxed);
+    DELAY_SPINS.store(faults.delay_spins, Ordering::Relaxed);
+    INCREMENT_FAILURE.store(threshold(faults.increment_failure), Ordering::Relaxed);
+}
+
+/// Stop injecting faults.
+pub fn clear() {
+    inject(Faults::none());
+}
+
+/// Restart the sequence of random numbers that decides which operations fail.
+/// On a single thread, the same seed makes the same operations fail.
+pub fn seed(seed: u64) {
+    STATE.store(seed, Ordering::Relaxed);
+}
+
+/// Decide whether `compare_exchange_weak` should fail spuriously.
+#[must_use]
+pub(crate) fn spurious_failure() -> bool {
+    roll(&SPURIOUS_FAILURE)
+}
+
+/// Decide whether incrementing a count should fail.
+#[must_use]
+pub(crate) fn increment_failure() -> bool {
+    roll(&INCREMENT_FAILURE)
+}
+
+/// Pause between a load and the operation that depends on it, if the dice say so.
+pub(crate) fn delay() {
+    if roll(&DELAY) {
+        for _ in 0..DELAY_SPINS.load(Ordering::Relaxed) {
+            core::hint::spin_loop();
+        }
+        #[cfg(feature = "std")]
+        std::thread::yield_now();
+    }
+}
+
+#[must_use]
+fn threshold(rate: f64) -> u64 {
+    // NaN saturates to 0.
+    (rate.clamp(0.0, 1.0) * 4_294_967_296.0) as u64
+}
+
+#[must_use]
+fn roll(threshold: &AtomicU64) -> bool {
+    let threshold = threshold.load(Ordering::Relaxed);
+    threshold != 0 && u64::from(random()) < threshold
+}
+
+/// SplitMix64, stepped atomically so that threads draw from one sequence.
+#[must_use]
+fn random() -> u32 {
+    let mut z = STATE.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)) as u32
+}
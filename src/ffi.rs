@@ -0,0 +1,119 @@
+//! References to `Xarc` values that C and C++ code can hold, enabled by the `ffi` feature.
+//!
+//! `Xarc::into_foreign` turns a reference into an `XarcForeign`, a `#[repr(C)]` struct that a foreign component can copy,
+//! add references to with `xarc_add_ref` and release with `xarc_release`.
+//! These are counted in the same count as every `Xarc`, so the value stays alive for as long as either side holds it,
+//! and the Rust side keeps swapping it through `AtomicXarc`s as usual. `Xarc::from_foreign` takes a reference back.
+//! The value itself should be `#[repr(C)]` for foreign code to read it through the `value` field.
+//!
+//! The layout of `XarcForeign` and the exported symbols, for C and C++:
+//!
+//! ```c
+//! typedef struct xarc_foreign {
+//!     void *header;
+//!     void *value;
+//!     void (*release)(void *header);
+//! } xarc_foreign;
+//!
+//! void xarc_add_ref(const xarc_foreign *foreign);
+//! void xarc_release(const xarc_foreign *foreign);
+//! ```
+//!
+//! A `std::shared_ptr` can own one of the references with a deleter that releases it,
+//! after which C++ code copies and drops the `shared_ptr` without calling back into Rust until the last copy is gone:
+//!
+//! ```cpp
+//! template <typename T>
+//! std::shared_ptr<T> adopt(xarc_foreign foreign) {
+//!     return std::shared_ptr<T>(static_cast<T *>(foreign.value), [foreign](T *) { xarc_release(&foreign); });
+//! }
+//! ```
+//!
+//! `xarc_release` may run the value's destructor, deferred through the epoch like any other release, on the calling thread.
+//!
+//! # Examples
+//!
+//! ```
+//! use xarc::{Xarc, ffi};
+//!
+//! #[repr(C)]
+//! struct Frame {
+//!     width: u32,
+//!     height: u32,
+//! }
+//!
+//! let frame = Xarc::new(Frame {width: 640, height: 480});
+//! let foreign = frame.clone().into_foreign();
+//! unsafe {
+//!     // What a C++ component would do with its copy.
+//!     assert_eq!((*(foreign.value as *const Frame)).width, 640);
+//!     ffi::xarc_add_ref(&foreign);
+//!     ffi::xarc_release(&foreign);
+//!
+//!     let back: Xarc<Frame> = Xarc::from_foreign(foreign);
+//!     assert_eq!(back, frame);
+//! }
+//! ```
+
+use super::{internal::*, pointee::*, pointer::*};
+use core::{ffi::c_void, ptr};
+
+/// A reference to an `Xarc` value for foreign code, with the function that releases it. See the module documentation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct XarcForeign {
+    /// The allocation, to pass to `release`, or null.
+    pub header: *mut c_void,
+    /// The value, or null.
+    pub value: *mut c_void,
+    /// Releases one reference to the allocation, dropping the value as its type requires if that was the last.
+    pub release: unsafe extern "C" fn(*mut c_void),
+}
+
+/// Release one reference to `header` as an `Xarc<T>`.
+unsafe extern "C" fn release<T: ?Sized + Send + Pointee>(header: *mut c_void) {
+    drop(Xarc::<T>::init(header as *mut XarcHeader));
+}
+
+/// Add a reference to the value of `foreign`, to be released with `xarc_release`. Does nothing if it is null.
+///
+/// # Safety
+/// - `foreign` must point to an `XarcForeign` from `Xarc::into_foreign`, or a copy of one, whose references have not all been released.
+#[no_mangle]
+pub unsafe extern "C" fn xarc_add_ref(foreign: *const XarcForeign) {
+    unguarded_increment((*foreign).header as *mut XarcHeader);
+}
+
+/// Release one reference to the value of `foreign`, dropping the value if that was the last. Does nothing if it is null.
+///
+/// # Safety
+/// - `foreign` must point to an `XarcForeign` from `Xarc::into_foreign`, or a copy of one,
+///   and the reference being released must have come from `into_foreign` or `xarc_add_ref`.
+#[no_mangle]
+pub unsafe extern "C" fn xarc_release(foreign: *const XarcForeign) {
+    ((*foreign).release)((*foreign).header);
+}
+
+impl<T: ?Sized + Send + Pointee> Xarc<T> {
+    /// Hand the reference to foreign code as an `XarcForeign`. See `xarc::ffi`.
+    #[must_use]
+    pub fn into_foreign(self) -> XarcForeign {
+        let value = self.maybe_deref().map_or(ptr::null_mut(), |value| value as *const T as *mut c_void);
+        XarcForeign {
+            header: self.into_ptr() as *mut c_void,
+            value,
+            release: release::<T>,
+        }
+    }
+
+    /// Take back a reference handed to foreign code with `into_foreign` or added with `xarc_add_ref`.
+    ///
+    /// # Safety
+    /// - `foreign` must come from `into_foreign` on an `Xarc<T>` of this same `T`, or be a copy of one.
+    /// - The reference taken must not be released by foreign code as well.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub unsafe fn from_foreign(foreign: XarcForeign) -> Self {
+        Xarc::init(foreign.header as *mut XarcHeader)
+    }
+}
@@ -0,0 +1,124 @@
+use super::{internal::XarcHeader, latency::Retired};
+use alloc::collections::VecDeque;
+use crossbeam_epoch::Guard;
+use std::sync::{Mutex, MutexGuard};
+
+/// A value whose count has dropped to zero, waiting for its turn to be reclaimed.
+struct Reclamation {
+    ptr: *mut XarcHeader,
+    reclaim: unsafe fn(*mut XarcHeader),
+    retired: Retired,
+}
+
+// The pointer is only ever handed to `reclaim`, by whichever thread is draining.
+unsafe impl Send for Reclamation {}
+
+struct State {
+    /// Retired values not yet reclaimed, in the order they were retired.
+    queue: VecDeque<Reclamation>,
+    /// The number of values ever retired.
+    retired: u64,
+    /// The number of values ever reclaimed, which is the position in the order of the front of `queue`.
+    next: u64,
+    /// Values before this position in the order are past their grace period.
+    safe: u64,
+    /// Set while some thread is running reclamations, so that they run one at a time.
+    draining: bool,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    queue: VecDeque::new(),
+    retired: 0,
+    next: 0,
+    safe: 0,
+    draining: false,
+});
+
+fn lock() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Queue the value at `ptr`, whose count has just dropped to zero, fixing its place in the order of reclamation.
+///
+/// Rather than waiting for its own deferred function, which sits in this thread's bag until the bag fills or the thread flushes it,
+/// the value waits for a marker that is deferred and flushed to the global queue at once.
+/// A marker deferred after a value was queued outlives every reader of that value and of every value queued before it,
+/// so when it runs it marks that whole prefix as safe, and no thread can hold up the order by leaving its bag unflushed.
+pub(crate) fn retire(ptr: *mut XarcHeader, reclaim: unsafe fn(*mut XarcHeader), retired: Retired, guard: &Guard) {
+    let position = {
+        let mut state = lock();
+        state.queue.push_back(Reclamation {ptr, reclaim, retired});
+        state.retired += 1;
+        state.retired
+    };
+    unsafe {
+        guard.defer_unchecked(move || ready(position));
+    }
+    guard.flush();
+}
+
+/// Called from the deferred marker once the first `safe` values in the order can safely be reclaimed.
+/// Runs every reclamation whose turn has come, unless another thread is already doing so.
+fn ready(safe: u64) {
+    let mut state = lock();
+    state.safe = state.safe.max(safe);
+    if state.draining {
+        return;
+    }
+    state.draining = true;
+    let draining = Draining;
+    while state.next < state.safe {
+        let Some(Reclamation {ptr, reclaim, retired}) = state.queue.pop_front() else {
+            break;
+        };
+        state.next += 1;
+        // Reclaiming may release further values, which are queued here and need the lock.
+        drop(state);
+        retired.reclaiming();
+        unsafe {
+            reclaim(ptr);
+        }
+        state = lock();
+    }
+    drop(state);
+    drop(draining);
+}
+
+/// Lets the next thread drain once this one is done, even if a reclamation panics.
+struct Draining;
+
+impl Drop for Draining {
+    fn drop(&mut self) {
+        lock().draining = false;
+    }
+}
+
+/// The number of values that have been retired but not yet reclaimed.
+///
+/// Requires the `fifo-reclaim` feature, under which values are reclaimed strictly in the order that their counts dropped to zero,
+/// for payloads such as memory-mapped regions layered on each other whose destructors must run in order.
+/// A value whose reclamation is safe waits for every value retired before it, so a thread that stays pinned holds up the rest,
+/// just as it holds up the epoch.
+/// `ManualCollector` keeps its own order for the values it controls.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::pin;
+/// use std::sync::Mutex;
+/// use xarc::Xarc;
+///
+/// static ORDER: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+///
+/// for layer in 0..8 {
+///     drop(Xarc::with_on_reclaim(layer, |layer| ORDER.lock().unwrap().push(layer)));
+/// }
+/// while xarc::fifo_pending() > 0 {
+///     pin().flush();
+/// }
+/// assert_eq!(*ORDER.lock().unwrap(), (0..8).collect::<Vec<_>>());
+/// ```
+#[must_use]
+pub fn fifo_pending() -> usize {
+    lock().queue.len()
+}
@@ -0,0 +1,85 @@
+use super::{atomic::*, pointer::*};
+use core::sync::atomic::Ordering;
+
+/// `StaticAtomicXarc` is an `AtomicXarc` intended for global hot-swappable state.
+/// 
+/// It is const-constructible so that it can initialize a `static` and allocates nothing until the first store.
+/// Loads return `None` until a value has been stored.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::StaticAtomicXarc;
+/// 
+/// struct Config {
+///     verbose: bool,
+/// }
+/// 
+/// static GLOBAL_CONFIG: StaticAtomicXarc<Config> = StaticAtomicXarc::new();
+/// 
+/// assert!(GLOBAL_CONFIG.load(Ordering::Acquire).is_none());
+/// GLOBAL_CONFIG.store(Config {verbose: true}, Ordering::Release);
+/// assert!(GLOBAL_CONFIG.load(Ordering::Acquire).unwrap().maybe_deref().unwrap().verbose);
+/// ```
+#[derive(Debug)]
+pub struct StaticAtomicXarc<T: Send> {
+    slot: AtomicXarc<T>,
+}
+
+impl<T: Send> StaticAtomicXarc<T> {
+    /// Initialize the uninitialized atomic smart pointer without allocating.
+    #[must_use]
+    pub const fn new() -> Self {
+        StaticAtomicXarc {
+            slot: AtomicXarc::null(),
+        }
+    }
+
+    /// Check whether a value has been stored.
+    #[must_use]
+    pub fn is_initialized(&self, order: Ordering) -> bool {
+        !self.slot.is_null(order)
+    }
+
+    /// Load the value into an `Xarc`, or return `None` if no value has been stored yet.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> Option<Xarc<T>> {
+        self.slot.load(order).into_option()
+    }
+
+    /// Load the value into an `Xarc`, storing the result of `f` first if no value has been stored yet.
+    /// If several threads race to initialize, `f` may be called by more than one of them but only one result is kept.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> Xarc<T> {
+        if let Some(pointer) = self.load(Ordering::Acquire) {
+            return pointer;
+        }
+        let new = Xarc::new(f());
+        match self.slot.compare_exchange(&Xarc::null(), &new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => new,
+            Err(current) => current,
+        }
+    }
+
+    /// Allocate `value` and publish it, replacing any previously stored value.
+    pub fn store(&self, value: T, order: Ordering) {
+        let _ = self.slot.swap(&Xarc::new(value), order);
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`.
+    /// Returns the previously stored value, if any.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, new: &Xarc<T>, order: Ordering) -> Option<Xarc<T>> {
+        self.slot.swap(new, order).into_option()
+    }
+}
+
+impl<T: Send> Default for StaticAtomicXarc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,188 @@
+use super::internal::*;
+use core::{ptr, sync::atomic::{AtomicPtr, AtomicUsize, Ordering}};
+use crossbeam_utils::CachePadded;
+
+/// The record of a load that has given up on winning the race and asks writers for a reference instead.
+/// Records are preallocated and reused, so asking for help never allocates.
+struct Request {
+    /// The phase of the request in the low bits and, above them, how many times the record has been used,
+    /// so that a writer that read one use of the record cannot claim the next.
+    state: AtomicUsize,
+    /// The address of the `AtomicXarc` being loaded.
+    atomic: AtomicUsize,
+    /// The generation at which the request was made. Only writes that began later may answer it.
+    generation: AtomicUsize,
+    answer: AtomicPtr<XarcHeader>,
+}
+
+/// The record is unused.
+const FREE: usize = 0;
+/// The record belongs to a load, which is filling it in or has withdrawn it.
+const CLAIMED: usize = 1;
+/// The request is published for writers to answer.
+const OPEN: usize = 2;
+/// A writer is answering the request.
+const TAKEN: usize = 3;
+/// The request has been answered.
+const DONE: usize = 4;
+
+const PHASE: usize = 0b111;
+
+/// One use of a record, counted above the phase.
+const USE: usize = PHASE + 1;
+
+/// The number of loads that can ask for help at once.
+const REQUESTS: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: CachePadded<Request> = CachePadded::new(Request {
+    state: AtomicUsize::new(FREE),
+    atomic: AtomicUsize::new(0),
+    generation: AtomicUsize::new(0),
+    answer: AtomicPtr::new(ptr::null_mut()),
+});
+
+static REQUEST_LIST: [CachePadded<Request>; REQUESTS] = [EMPTY; REQUESTS];
+
+/// Incremented by every request, so that writers can tell whether their write began after a given request.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of published requests, so that writers can skip scanning when nobody needs help.
+static OUTSTANDING: AtomicUsize = AtomicUsize::new(0);
+
+/// A published request, owned by the load that made it.
+pub(crate) struct Pending {
+    request: &'static Request,
+    /// The state of the record while the request is open.
+    open: usize,
+}
+
+/// To be read by a writer before it attempts a write, and passed to `offer` if the write succeeds.
+#[must_use]
+pub(crate) fn generation() -> usize {
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// Ask writers to `atomic` for help. Returns None if every record is taken.
+#[must_use]
+pub(crate) fn request(atomic: usize) -> Option<Pending> {
+    let (request, free) = REQUEST_LIST.iter().find_map(|request| {
+        let state = request.state.load(Ordering::Relaxed);
+        let claimed = state & PHASE == FREE
+            && request.state.compare_exchange(state, state | CLAIMED, Ordering::Acquire, Ordering::Relaxed).is_ok();
+        claimed.then_some((&**request, state))
+    })?;
+    request.atomic.store(atomic, Ordering::Relaxed);
+    request.generation.store(GENERATION.fetch_add(1, Ordering::AcqRel), Ordering::Relaxed);
+    request.answer.store(ptr::null_mut(), Ordering::Relaxed);
+    OUTSTANDING.fetch_add(1, Ordering::Relaxed);
+    request.state.store(free | OPEN, Ordering::Release);
+    // A writer that reads the generation from here on synchronizes with this increment, so it sees the request
+    // and the count of outstanding requests without a fence of its own.
+    GENERATION.fetch_add(1, Ordering::Release);
+    Some(Pending {
+        request,
+        open: free | OPEN,
+    })
+}
+
+/// Having just replaced the contents of `atomic` with `ptr`, answer any request made before the write began.
+/// The caller must hold a reference to `ptr`.
+pub(crate) fn offer(atomic: usize, generation: usize, ptr: *mut XarcHeader) {
+    if OUTSTANDING.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    for request in REQUEST_LIST.iter() {
+        let state = request.state.load(Ordering::Acquire);
+        // The fields may already belong to the next use of the record, in which case claiming this use fails.
+        if state & PHASE == OPEN && request.atomic.load(Ordering::Relaxed) == atomic && request.generation.load(Ordering::Relaxed) < generation
+            && request.state.compare_exchange(state, state - OPEN + TAKEN, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+            unguarded_increment(ptr);
+            request.answer.store(ptr, Ordering::Relaxed);
+            request.state.store(state - OPEN + DONE, Ordering::Release);
+        }
+    }
+}
+
+/// The number of published requests for help with `atomic`, for `DebugState`.
+#[must_use]
+pub(crate) fn waiting(atomic: usize) -> usize {
+    if OUTSTANDING.load(Ordering::Relaxed) == 0 {
+        return 0;
+    }
+    REQUEST_LIST.iter().filter(|request| {
+        request.state.load(Ordering::Acquire) & PHASE == OPEN && request.atomic.load(Ordering::Relaxed) == atomic
+    }).count()
+}
+
+impl Pending {
+    /// The reference handed over by a writer, if one has been.
+    #[must_use]
+    pub(crate) fn answer(&self) -> Option<*mut XarcHeader> {
+        if self.request.state.load(Ordering::Acquire) == self.open - OPEN + DONE {
+            Some(self.request.answer.load(Ordering::Relaxed))
+        }
+        else {
+            None
+        }
+    }
+
+    /// Withdraw the request. Returns the reference handed over by a writer if it was answered in the meantime.
+    #[must_use]
+    pub(crate) fn withdraw(&self) -> Option<*mut XarcHeader> {
+        if self.request.state.compare_exchange(self.open, self.open - OPEN + CLAIMED, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            return None;
+        }
+        // A writer claimed the request and is about to finish answering it.
+        loop {
+            if let Some(answer) = self.answer() {
+                return Some(answer);
+            }
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        // A load only drops its request open if it panicked, in which case a reference handed over meanwhile is leaked.
+        if self.request.state.load(Ordering::Relaxed) & PHASE == OPEN {
+            let _ = self.withdraw();
+        }
+        OUTSTANDING.fetch_sub(1, Ordering::Relaxed);
+        self.request.state.store((self.open & !PHASE) + USE, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pointer::Xarc;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn help_records_st_test() {
+        // Requests take preallocated records, which other tests' loads may be holding, and are reusable once dropped.
+        let slot = 0u8;
+        let atomic = (&slot as *const u8).addr();
+        let pending: Vec<Pending> = (0..=REQUESTS).map_while(|_| request(atomic)).collect();
+        assert!(!pending.is_empty() && pending.len() <= REQUESTS);
+        assert_eq!(waiting(atomic), pending.len());
+
+        let value = Xarc::new(7);
+        offer(atomic, generation(), value.ptr);
+        for pending in &pending {
+            assert_eq!(pending.answer(), Some(value.ptr));
+            assert_eq!(pending.withdraw(), Some(value.ptr));
+            drop(Xarc::<i32>::init(value.ptr));
+        }
+        drop(pending);
+        assert_eq!(waiting(atomic), 0);
+
+        let again = request(atomic).unwrap();
+        // A write that began before the request cannot answer it.
+        offer(atomic, again.request.generation.load(Ordering::Relaxed), value.ptr);
+        assert!(again.answer().is_none());
+        assert!(again.withdraw().is_none());
+    }
+}
@@ -0,0 +1,102 @@
+use super::{atomic::*, pointer::*};
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+/// `AtomicXarcHistory` is an `AtomicXarc` that remembers the last `N` values it held.
+///
+/// The current value and its history are published together as one immutable `Xarc<[Xarc<T>]>`, newest first,
+/// so every swap records the value it replaced as part of the same atomic operation
+/// and `history` always returns a history that actually happened.
+/// Each swap copies up to `N` handles, so `N` is meant to be small, as for debugging, auditing or undoing the last push of a config.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarcHistory, Xarc};
+///
+/// let config: AtomicXarcHistory<&str, 2> = AtomicXarcHistory::new("first");
+/// let _ = config.swap(&Xarc::new("second"));
+/// let _ = config.swap(&Xarc::new("third"));
+/// let _ = config.swap(&Xarc::new("fourth"));
+///
+/// let history = config.history();
+/// let values: Vec<&str> = history.iter().map(|value| *value.maybe_deref().unwrap()).collect();
+/// assert_eq!(values, ["fourth", "third", "second"]);
+///
+/// assert_eq!(*config.undo().unwrap().maybe_deref().unwrap(), "fourth");
+/// assert_eq!(*config.load(Ordering::Acquire).maybe_deref().unwrap(), "third");
+/// ```
+#[derive(Debug)]
+pub struct AtomicXarcHistory<T: Send, const N: usize> {
+    /// The current value followed by up to `N` previous values, newest first. Never empty.
+    slot: AtomicXarc<[Xarc<T>]>,
+}
+
+impl<T: Send, const N: usize> AtomicXarcHistory<T, N> {
+    /// Initialize the atomic smart pointer with `value` and no history.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self::from(&Xarc::new(value))
+    }
+
+    /// Initialize the atomic smart pointer with null and no history.
+    #[must_use]
+    pub fn null() -> Self {
+        Self::from(&Xarc::null())
+    }
+
+    /// Load the current value into an `Xarc`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> Xarc<T> {
+        self.slot.with(order, |values| values.unwrap()[0].clone())
+    }
+
+    /// Load the current value followed by up to `N` previous values, newest first.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn history(&self) -> Xarc<[Xarc<T>]> {
+        self.slot.load(Ordering::Acquire)
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`, recording the previous value in the history.
+    /// If the history is full, its oldest value is dropped.
+    /// Returns the previous value.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, new: &Xarc<T>) -> Xarc<T> {
+        let previous = self.slot.cas_loop(|values| {
+            let values = values.maybe_deref().unwrap();
+            let mut next = Vec::with_capacity(values.len().min(N) + 1);
+            next.push(new.clone());
+            next.extend(values.iter().take(N).cloned());
+            Some(Xarc::from(next))
+        }, Ordering::AcqRel, Ordering::Acquire);
+        previous.unwrap().maybe_deref().unwrap()[0].clone()
+    }
+
+    /// As an atomic operation, restore the most recent value in the history, removing it from the history.
+    /// Returns the value that was replaced, or None if the history is empty.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn undo(&self) -> Option<Xarc<T>> {
+        let previous = self.slot.cas_loop(|values| {
+            let values = values.maybe_deref().unwrap();
+            if values.len() > 1 {
+                Some(Xarc::from(&values[1..]))
+            }
+            else {
+                None
+            }
+        }, Ordering::AcqRel, Ordering::Acquire);
+        previous.ok().map(|values| values.maybe_deref().unwrap()[0].clone())
+    }
+}
+
+impl<T: Send, const N: usize> From<&Xarc<T>> for AtomicXarcHistory<T, N> {
+    fn from(pointer: &Xarc<T>) -> Self {
+        AtomicXarcHistory {
+            slot: Xarc::from(&[pointer.clone()][..]).into_atomic(),
+        }
+    }
+}
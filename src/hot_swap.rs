@@ -0,0 +1,60 @@
+use super::{internal::back_off, ordering::force};
+use core::sync::atomic::{AtomicU64, Ordering, fence};
+use crossbeam_utils::{Backoff, CachePadded};
+
+/// The sequence that `#[derive(HotSwap)]` mirrors use to keep whole-snapshot loads consistent across their fields.
+///
+/// Writers hold the low bit while they swap fields, as in a seqlock, and whole-snapshot loads retry if a write overlapped them.
+/// Loads of a single field do not consult the sequence at all.
+#[derive(Debug, Default)]
+pub struct HotSwapSequence {
+    sequence: CachePadded<AtomicU64>,
+}
+
+impl HotSwapSequence {
+    /// Initialize the sequence with no write in progress.
+    #[must_use]
+    pub fn new() -> Self {
+        HotSwapSequence {
+            sequence: CachePadded::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Call `read` until it runs without overlapping a write.
+    pub fn read<R, F: FnMut() -> R>(&self, mut read: F) -> R {
+        let backoff = Backoff::new();
+        loop {
+            let sequence = self.sequence.load(force(Ordering::Acquire));
+            if sequence & 1 == 0 {
+                let result = read();
+                // Keeps the loads in `read` from being reordered after the second check.
+                fence(Ordering::Acquire);
+                if self.sequence.load(force(Ordering::Acquire)) == sequence {
+                    return result;
+                }
+            }
+            back_off(&backoff);
+        }
+    }
+
+    /// Call `write` while holding off other writes and whole-snapshot reads.
+    /// `write` should only swap fields and must not panic, since everything else waits on it.
+    pub fn write<R, F: FnOnce() -> R>(&self, write: F) -> R {
+        let backoff = Backoff::new();
+        let mut sequence = self.sequence.load(force(Ordering::Relaxed));
+        loop {
+            if sequence & 1 != 0 {
+                back_off(&backoff);
+                sequence = self.sequence.load(force(Ordering::Relaxed));
+                continue;
+            }
+            match self.sequence.compare_exchange_weak(sequence, sequence | 1, force(Ordering::Acquire), force(Ordering::Relaxed)) {
+                Ok(_) => break,
+                Err(s) => sequence = s,
+            }
+        }
+        let result = write();
+        self.sequence.store(sequence + 2, force(Ordering::Release));
+        result
+    }
+}
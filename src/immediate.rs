@@ -0,0 +1,151 @@
+use super::{atomic::*, pointer::*, strategy::Locked};
+use core::{fmt, sync::atomic::Ordering};
+
+/// `ImmediateXarc` is an `Xarc` for a value from an `ImmediateXarcPool`, which is reclaimed, and its node returned to the pool,
+/// the moment its last reference is released rather than after the epoch advances.
+///
+/// Nothing may read such a value without holding a reference of its own, so unlike an `Xarc` it can only be stored
+/// in an `AtomicImmediateXarc`, which takes every reference under a lock, and there is no way to turn it into an `Xarc`.
+/// Cloning, dropping, loading, swapping and compare-exchanging it never pins and never allocates.
+///
+/// Since the value is dropped inside the release of its last reference, dropping the head of a chain of `ImmediateXarc`s
+/// recurses through the chain. The depth is bounded by the capacity of the pools it was allocated from.
+pub struct ImmediateXarc<T: Send> {
+    inner: Xarc<T>,
+}
+
+impl<T: Send> ImmediateXarc<T> {
+    /// Initialize the smart pointer with null.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn null() -> Self {
+        Self::from_inner(Xarc::null())
+    }
+
+    /// Check if the pointer is null.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.inner.is_null()
+    }
+
+    /// Dereference the pointer only if it is not null.
+    /// None will be returned if it is null.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        self.inner.maybe_deref()
+    }
+
+    /// Wrap an `Xarc` allocated from an `ImmediateXarcPool`, or null.
+    #[must_use]
+    pub(crate) fn from_inner(inner: Xarc<T>) -> Self {
+        ImmediateXarc {
+            inner,
+        }
+    }
+}
+
+impl<T: Send> Clone for ImmediateXarc<T> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn clone(&self) -> Self {
+        Self::from_inner(self.inner.clone())
+    }
+}
+
+impl<T: Send> Default for ImmediateXarc<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: Send> PartialEq for ImmediateXarc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: Send> Eq for ImmediateXarc<T> {}
+
+impl<T: Send> fmt::Debug for ImmediateXarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImmediateXarc").field("ptr", &self.inner.ptr).finish()
+    }
+}
+
+/// `AtomicImmediateXarc` is an `AtomicXarc<T, Locked>` for `ImmediateXarc`s. See `ImmediateXarcPool`.
+///
+/// It offers no way to borrow the value without taking a reference, such as `AtomicXarc::with`,
+/// since the value may be reclaimed on the spot by whichever thread releases the last reference to it.
+pub struct AtomicImmediateXarc<T: Send> {
+    inner: AtomicXarc<T, Locked>,
+}
+
+impl<T: Send> AtomicImmediateXarc<T> {
+    /// Initialize the atomic smart pointer with `pointer`.
+    #[must_use]
+    pub fn new(pointer: &ImmediateXarc<T>) -> Self {
+        AtomicImmediateXarc {
+            inner: AtomicXarc::with_strategy(&pointer.inner, Locked),
+        }
+    }
+
+    /// Initialize the atomic smart pointer with null.
+    #[must_use]
+    pub const fn null() -> Self {
+        AtomicImmediateXarc {
+            inner: AtomicXarc::null_with_strategy(Locked),
+        }
+    }
+
+    /// Load the value into an `ImmediateXarc`. See `AtomicXarc::load`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> ImmediateXarc<T> {
+        ImmediateXarc::from_inner(self.inner.load(order))
+    }
+
+    /// Replace the current value with `new`.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn store(&self, new: &ImmediateXarc<T>, order: Ordering) {
+        let _ = self.swap(new, order);
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`, returning the previous value.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, new: &ImmediateXarc<T>, order: Ordering) -> ImmediateXarc<T> {
+        ImmediateXarc::from_inner(self.inner.swap(&new.inner, order))
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new` if `self == current`.
+    /// Returns the previous value of `self` in a Result indicating whether the operation succeeded or failed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange(&self, current: &ImmediateXarc<T>, new: &ImmediateXarc<T>, success: Ordering, failure: Ordering) -> Result<ImmediateXarc<T>, ImmediateXarc<T>> {
+        self.inner.compare_exchange(&current.inner, &new.inner, success, failure)
+            .map(ImmediateXarc::from_inner)
+            .map_err(ImmediateXarc::from_inner)
+    }
+
+    /// Check if the pointer is null without loading it.
+    #[must_use]
+    pub fn is_null(&self, order: Ordering) -> bool {
+        self.inner.is_null(order)
+    }
+}
+
+impl<T: Send> Default for AtomicImmediateXarc<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: Send> From<&ImmediateXarc<T>> for AtomicImmediateXarc<T> {
+    fn from(pointer: &ImmediateXarc<T>) -> Self {
+        Self::new(pointer)
+    }
+}
+
+impl<T: Send> fmt::Debug for AtomicImmediateXarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicImmediateXarc").field("ptr", &self.inner.ptr.load(Ordering::Relaxed)).finish()
+    }
+}
@@ -1,69 +1,700 @@
-use alloc::boxed::Box;
-use core::sync::atomic::{AtomicUsize, Ordering};
-use crossbeam_epoch::Guard;
-use crossbeam_utils::CachePadded;
+use super::{error::AllocError, ordering::force, pointee::*, pool::PoolHeader, repin};
+#[cfg(not(feature = "no-reclaim"))]
+use super::latency::Retired;
+use alloc::{alloc::{Layout, alloc, handle_alloc_error}, sync::Arc, vec::Vec};
+use core::{cell::Cell, hash::{Hash, Hasher}, mem::{self, MaybeUninit}, ptr, sync::atomic::{AtomicBool, Ordering, fence}};
+#[cfg(not(feature = "count-u32"))]
+use core::sync::atomic::AtomicUsize;
+#[cfg(feature = "count-u32")]
+use core::{convert::TryFrom, sync::atomic::AtomicU32};
+use crossbeam_epoch::{Guard, is_pinned, pin};
+use crossbeam_utils::{Backoff, CachePadded};
 
+/// The atomic integer holding the counts, narrowed by `count-u32` for headers that need to be small.
+#[cfg(not(feature = "count-u32"))]
+type AtomicCount = AtomicUsize;
+#[cfg(feature = "count-u32")]
+type AtomicCount = AtomicU32;
+
+#[cfg(not(feature = "count-u32"))]
+type RawCount = usize;
+#[cfg(feature = "count-u32")]
+type RawCount = u32;
+
+#[cfg(not(feature = "count-u32"))]
+#[inline]
+fn narrow(count: usize) -> RawCount {
+    count
+}
+
+/// # Panics
+/// - If `count` does not fit in 32 bits.
+#[cfg(feature = "count-u32")]
+#[inline]
+fn narrow(count: usize) -> RawCount {
+    match RawCount::try_from(count) {
+        Ok(count) => count,
+        Err(_) => panic!("XarcCount overflow!"),
+    }
+}
+
+#[cfg(not(feature = "count-u32"))]
+#[inline]
+const fn widen(count: RawCount) -> usize {
+    count
+}
+
+#[cfg(feature = "count-u32")]
+#[inline]
+const fn widen(count: RawCount) -> usize {
+    count as usize
+}
+
+/// The number of high bits of the count word holding the weak count: 16 of 64, but 12 of 32,
+/// so that a value can still have thousands of weak references, such as graph edges or weak map entries, where the word is narrow.
+const WEAK_BITS: u32 = if RawCount::BITS > 32 { RawCount::BITS / 4 } else { 12 };
+
+/// The number of low bits of the count word holding the strong count.
+const STRONG_BITS: u32 = RawCount::BITS - WEAK_BITS;
+
+/// One strong reference.
+const STRONG: RawCount = 1;
+
+/// One weak reference.
+const WEAK: RawCount = 1 << STRONG_BITS;
+
+const STRONG_MASK: RawCount = WEAK - 1;
+
+/// The strong and weak counts, packed into one word so that every change to either, and every decision on reclaiming
+/// the value or freeing the allocation, is a single atomic operation.
+///
+/// The strong references collectively hold one weak reference, released once the value has been dropped,
+/// so that the allocation is freed by whichever goes last: that release or the last `WeakXarc`.
+///
+/// The padding of the word also holds the non-atomic count of the `BiasedXarc`s on the thread that created the value,
+/// which collectively hold one strong reference.
 pub(crate) struct XarcCount {
-    count: CachePadded<AtomicUsize>,
+    count: CachePadded<Counts>,
+}
+
+struct Counts {
+    shared: AtomicCount,
+    /// Only ever touched by the thread that created the value with `BiasedXarc::new`. See `BiasedXarc`.
+    biased: Cell<usize>,
 }
 
 impl XarcCount {
+    /// The greatest strong count.
+    pub(crate) const MAX: usize = widen(STRONG_MASK);
+
+    /// # Panics
+    /// - If `count` exceeds `MAX`.
     #[must_use]
-    fn new() -> XarcCount {
+    pub(crate) fn new(count: usize) -> XarcCount {
+        if count > Self::MAX {
+            panic!("XarcCount overflow!");
+        }
+        let count = narrow(count);
         XarcCount {
-            count: CachePadded::new(AtomicUsize::new(1)),
+            count: CachePadded::new(Counts {
+                shared: AtomicCount::new(if count > 0 { count | WEAK } else { 0 }),
+                biased: Cell::new(0),
+            }),
+        }
+    }
+
+    /// The strong count.
+    #[must_use]
+    pub(crate) fn load(&self) -> usize {
+        widen(self.count.shared.load(Ordering::Relaxed) & STRONG_MASK)
+    }
+
+    /// The weak count, less the one held by the strong references while there are any.
+    #[must_use]
+    pub(crate) fn load_weak(&self) -> usize {
+        let count = self.count.shared.load(Ordering::Relaxed);
+        widen(count >> STRONG_BITS) - usize::from(count & STRONG_MASK > 0)
+    }
+
+    /// Check if there is exactly one strong reference and no weak references that could be upgraded to another.
+    #[must_use]
+    pub(crate) fn is_sole(&self) -> bool {
+        self.count.shared.load(Ordering::Relaxed) == STRONG | WEAK
+    }
+
+    /// Decrement the strong count, returning the strong count before.
+    #[must_use]
+    pub(crate) fn decrement(&self, amount: usize) -> usize {
+        widen(self.count.shared.fetch_sub(narrow(amount), force(Ordering::Release)) & STRONG_MASK)
+    }
+
+    /// Decrement the strong count unless that would take it to 0. Returns false, changing nothing, if this is the last reference.
+    #[must_use]
+    pub(crate) fn try_decrement_unless_last(&self) -> bool {
+        let mut count = self.count.shared.load(force(Ordering::Relaxed));
+        while count & STRONG_MASK > 1 {
+            match self.count.shared.compare_exchange_weak(count, count - STRONG, force(Ordering::Release), force(Ordering::Relaxed)) {
+                Ok(_) => return true,
+                Err(c) => count = c,
+            }
         }
+        false
     }
 
+    /// Take the count from one strong reference and no weak references to nothing at all,
+    /// failing, changing nothing, unless this is the last reference.
     #[must_use]
-    pub(crate) fn decrement(&self) -> usize {
-        self.count.fetch_sub(1, Ordering::Relaxed)
+    pub(crate) fn try_claim_last(&self) -> bool {
+        self.count.shared.compare_exchange(STRONG | WEAK, 0, force(Ordering::Acquire), force(Ordering::Relaxed)).is_ok()
     }
 
+    /// Increment the strong count unless it is 0. Returns the strong count before.
     pub(crate) fn try_increment(&self) -> Result<usize, usize> {
-        let mut count = self.count.load(Ordering::Relaxed);
-        while count > 0 {
-            match self.count.compare_exchange_weak(count, count + 1, Ordering::Relaxed, Ordering::Relaxed) {
-                Ok(c) => return Ok(c),
+        let mut count = self.count.shared.load(force(Ordering::Relaxed));
+        #[cfg(feature = "fault-injection")]
+        super::fault::delay();
+        while count & STRONG_MASK > 0 {
+            match self.count.shared.compare_exchange_weak(count, checked_increment(count, STRONG), force(Ordering::Relaxed), force(Ordering::Relaxed)) {
+                Ok(c) => return Ok(widen(c & STRONG_MASK)),
                 Err(c) => count = c,
             }
         }
-        Err(count)
+        Err(0)
+    }
+
+    /// Attempt to increment the strong count with a single compare-exchange, failing on contention as well as from 0.
+    pub(crate) fn try_increment_once(&self) -> Result<(), ()> {
+        let count = self.count.shared.load(force(Ordering::Relaxed));
+        if count & STRONG_MASK > 0 && self.count.shared.compare_exchange_weak(count, checked_increment(count, STRONG), force(Ordering::Relaxed), force(Ordering::Relaxed)).is_ok() {
+            Ok(())
+        }
+        else {
+            Err(())
+        }
+    }
+
+    /// Increment the strong count, returning the strong count before.
+    ///
+    /// The references are reserved with a compare-exchange, so a count that would overflow is never published,
+    /// even for a moment in which another thread could observe it.
+    ///
+    /// # Panics
+    /// - If the strong count would exceed `MAX`, leaving the count unchanged.
+    #[must_use]
+    fn unsafe_increment(&self, amount: usize) -> usize {
+        if amount > Self::MAX {
+            panic!("XarcCount overflow!");
+        }
+        let amount = narrow(amount);
+        let mut count = self.count.shared.load(force(Ordering::Relaxed));
+        loop {
+            match self.count.shared.compare_exchange_weak(count, checked_increment(count, amount), force(Ordering::Relaxed), force(Ordering::Relaxed)) {
+                Ok(c) => return widen(c & STRONG_MASK),
+                Err(c) => count = c,
+            }
+        }
+    }
+
+    /// Increment the weak count, which must not be 0.
+    pub(crate) fn increment_weak(&self) {
+        let count = self.count.shared.fetch_add(WEAK, force(Ordering::Relaxed));
+        if count >> STRONG_BITS == RawCount::MAX >> STRONG_BITS {
+            self.count.shared.fetch_sub(WEAK, force(Ordering::Relaxed));
+            panic!("XarcCount overflow!");
+        }
+    }
+
+    /// Decrement the weak count. Returns true if that released the allocation, which nothing refers to any longer.
+    #[must_use]
+    pub(crate) fn decrement_weak(&self) -> bool {
+        if self.count.shared.fetch_sub(WEAK, force(Ordering::Release)) == WEAK {
+            fence(force(Ordering::Acquire));
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Add `amount` to the count of `BiasedXarc`s, returning the count before.
+    ///
+    /// # Safety
+    /// - Only the `BiasedXarc`s descended from the one created with the value may call this, all on the thread that created it.
+    ///
+    /// # Panics
+    /// - If the count would overflow.
+    pub(crate) unsafe fn add_biased(&self, amount: usize) -> usize {
+        let biased = self.count.biased.get();
+        match biased.checked_add(amount) {
+            Some(count) => self.count.biased.set(count),
+            None => panic!("XarcCount overflow!"),
+        }
+        biased
     }
 
+    /// Subtract 1 from the count of `BiasedXarc`s. Returns true if that was the last of them,
+    /// so that the caller must release the strong reference they held together.
+    ///
+    /// # Safety
+    /// - As for `add_biased`.
     #[must_use]
-    fn unsafe_increment(&self) -> usize {
-        self.count.fetch_add(1, Ordering::Relaxed)
+    pub(crate) unsafe fn decrement_biased(&self) -> bool {
+        let biased = self.count.biased.get() - 1;
+        self.count.biased.set(biased);
+        biased == 0
+    }
+
+    /// Release the weak reference held by the strong references, after the last of them, if no other weak references remain.
+    #[cfg(not(feature = "no-reclaim"))]
+    #[must_use]
+    pub(crate) fn try_release_only_weak(&self) -> bool {
+        self.count.shared.compare_exchange(WEAK, 0, force(Ordering::Acquire), force(Ordering::Relaxed)).is_ok()
+    }
+}
+
+/// `count + amount`, panicking rather than carrying into the weak count.
+#[must_use]
+#[inline]
+fn checked_increment(count: RawCount, amount: RawCount) -> RawCount {
+    match (count & STRONG_MASK).checked_add(amount) {
+        Some(strong) if strong <= STRONG_MASK => count + amount,
+        _ => panic!("XarcCount overflow!"),
     }
 }
 
-pub(crate) struct XarcData<T: Send> {
+/// The part of every allocation that precedes the value.
+/// Its layout does not depend on `T`, so it can be read through a thin pointer.
+#[repr(C)]
+pub struct XarcHeader {
     pub(crate) count: XarcCount,
+    pub(crate) meta: XarcMeta,
+    /// A type-erased `fn(T)` to run on reclamation, or null.
+    on_reclaim: *const (),
+    /// The `XarcPool` the allocation belongs to, how to free an intrusive value, or null if it is an `XarcData` on the heap.
+    pub(crate) pool: *const PoolHeader,
+    /// Set once the value has been stored in an `AtomicXarc`, after which readers may hold it without a count.
+    published: AtomicBool,
+}
+
+impl XarcHeader {
+    /// A header embedded in a value that is not yet owned by any reference. See `XarcLink`.
+    #[must_use]
+    pub(crate) fn unowned() -> Self {
+        XarcHeader {
+            count: XarcCount::new(0),
+            meta: XarcMeta { len: 0 },
+            on_reclaim: ptr::null(),
+            pool: ptr::null(),
+            published: AtomicBool::new(false),
+        }
+    }
+
+    /// Take the first reference to the value the header is embedded in, to be freed through `release` once the last is gone.
+    pub(crate) fn own(&mut self, release: &'static PoolHeader) {
+        self.count = XarcCount::new(1);
+        self.pool = release;
+    }
+
+    /// The number of elements of a slice or bytes of a `str`.
+    #[must_use]
+    pub(crate) fn slice_len(&self) -> usize {
+        unsafe {
+            self.meta.len
+        }
+    }
+
+    /// Set the number of elements, to reuse the allocation of an array as a slice.
+    ///
+    /// # Safety
+    /// - The caller must hold the only reference to the allocation, as `is_unique` checks, since other references read the length without synchronization.
+    pub(crate) unsafe fn set_slice_len(&mut self, len: usize) {
+        self.meta.len = len;
+    }
+
+    #[must_use]
+    pub(crate) fn has_on_reclaim(&self) -> bool {
+        !self.on_reclaim.is_null()
+    }
+
+    /// Drop `value`, or hand it to the hook to run on reclamation if there is one.
+    pub(crate) fn dispose<T>(&self, value: T) {
+        if self.on_reclaim.is_null() {
+            drop(value);
+        }
+        else {
+            let on_reclaim: fn(T) = unsafe { mem::transmute(self.on_reclaim) };
+            on_reclaim(value);
+        }
+    }
+}
+
+/// What is needed to recover a fat pointer to the value from a thin pointer to the header.
+#[derive(Clone, Copy)]
+pub(crate) union XarcMeta {
+    /// The number of elements for slices and the number of bytes for `str`.
+    pub(crate) len: usize,
+    /// What a trait object needs that its thin pointer lacks.
+    pub(crate) fat: DynMeta,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct DynMeta {
+    /// A type-erased function converting the thin pointer into a fat pointer.
+    coerce: *const (),
+    /// The layout of the allocation, which cannot be read from the vtable once the value has been dropped without a reference to it.
+    layout: Layout,
+}
+
+/// The allocation behind every `Xarc`.
+/// Only the last field may mention `T` so that `XarcData<T>` can be unsized to `XarcData<dyn Trait>`.
+#[repr(C)]
+pub struct XarcData<T: ?Sized> {
+    pub(crate) header: XarcHeader,
     pub(crate) value: T,
 }
 
-impl<T: Send> XarcData<T> {
+impl<T> XarcData<T> {
     #[must_use]
     pub(crate) fn new(value: T) -> Self {
+        Self::with_count(value, 1)
+    }
+
+    #[must_use]
+    pub(crate) fn with_count(value: T, count: usize) -> Self {
+        Self::init(value, count, XarcMeta { len: 0 }, ptr::null())
+    }
+
+    #[must_use]
+    pub(crate) fn with_on_reclaim(value: T, on_reclaim: fn(T)) -> Self {
+        Self::init(value, 1, XarcMeta { len: 0 }, on_reclaim as *const ())
+    }
+
+    /// Prepare `value` to be pointed to as a `U`, typically a trait object.
+    /// `coerce` must be the identity unsizing coercion.
+    #[must_use]
+    pub(crate) fn unsize<U: ?Sized>(value: T, coerce: fn(*mut XarcData<T>) -> *mut XarcData<U>) -> Self {
+        let fat = DynMeta {
+            coerce: coerce as *const (),
+            layout: Layout::new::<Self>(),
+        };
+        Self::init(value, 1, XarcMeta { fat }, ptr::null())
+    }
+
+    #[must_use]
+    fn init(value: T, count: usize, meta: XarcMeta, on_reclaim: *const ()) -> Self {
         XarcData {
-            count: XarcCount::new(),
+            header: XarcHeader {
+                count: XarcCount::new(count),
+                meta,
+                on_reclaim,
+                pool: ptr::null(),
+                published: AtomicBool::new(false),
+            },
             value,
         }
     }
+
+    /// Move `self` into a new allocation, as `Box::new` would, but report failure rather than aborting.
+    pub(crate) fn try_allocate(self) -> Result<*mut Self, AllocError> {
+        let layout = Layout::new::<Self>();
+        unsafe {
+            let ptr = alloc(layout) as *mut Self;
+            if ptr.is_null() {
+                return Err(AllocError);
+            }
+            ptr.write(self);
+            Ok(ptr)
+        }
+    }
+
+    pub(crate) fn reclaim(self) {
+        self.header.dispose(self.value);
+    }
+
+    /// Replace the value in place, disposing of the old one as reclamation would.
+    pub(crate) fn replace(&mut self, value: T) {
+        let old = mem::replace(&mut self.value, value);
+        self.header.dispose(old);
+    }
+}
+
+impl<T> XarcData<[T]> {
+    /// Allocate a slice with a count of 1, moving the elements out of `values`.
+    #[must_use]
+    pub(crate) fn from_vec(mut values: Vec<T>) -> *mut Self {
+        let len = values.len();
+        unsafe {
+            let ptr = Self::allocate(len);
+            ptr::copy_nonoverlapping(values.as_ptr(), ptr::addr_of_mut!((*ptr).value) as *mut T, len);
+            values.set_len(0);
+            ptr
+        }
+    }
+
+    /// Allocate a slice with a count of 1, moving the elements out of `values`.
+    #[must_use]
+    pub(crate) fn from_array<const N: usize>(values: [T; N]) -> *mut Self {
+        unsafe {
+            let ptr = Self::allocate(N);
+            ptr::addr_of_mut!((*ptr).value).cast::<[T; N]>().write(values);
+            ptr
+        }
+    }
+
+    /// Allocate a slice of `len` uninitialized elements with a count of 1.
+    /// 
+    /// # Safety
+    /// - Every element must be initialized before the value is used or reclaimed.
+    #[must_use]
+    pub(crate) unsafe fn allocate(len: usize) -> *mut Self {
+        let layout = Self::layout(len);
+        let raw = alloc(layout);
+        if raw.is_null() {
+            handle_alloc_error(layout);
+        }
+        let ptr = ptr::slice_from_raw_parts_mut(raw as *mut T, len) as *mut Self;
+        ptr::addr_of_mut!((*ptr).header).write(XarcHeader {
+            count: XarcCount::new(1),
+            meta: XarcMeta { len },
+            on_reclaim: ptr::null(),
+            pool: ptr::null(),
+            published: AtomicBool::new(false),
+        });
+        ptr
+    }
+
+    /// The layout of an allocation of `len` elements, matching what `Box` computes from the fat pointer.
+    #[must_use]
+    pub(crate) fn layout(len: usize) -> Layout {
+        let uninit = MaybeUninit::<XarcData<[T; 0]>>::uninit();
+        let base = uninit.as_ptr();
+        let offset = unsafe { ptr::addr_of!((*base).value).addr() - base.addr() };
+        let size = mem::size_of::<T>().checked_mul(len).and_then(|size| size.checked_add(offset)).expect("Xarc slice is too large!");
+        Layout::from_size_align(size, mem::align_of::<XarcData<[T; 0]>>()).expect("Xarc slice is too large!").pad_to_align()
+    }
+}
+
+/// Discard the metadata of a possibly fat pointer.
+#[must_use]
+pub(crate) fn thin<T: ?Sized>(ptr: *mut XarcData<T>) -> *mut XarcHeader {
+    ptr as *mut XarcHeader
+}
+
+/// Recover the possibly fat pointer from a thin pointer, reading the metadata from the header if necessary.
+/// 
+/// # Safety
+/// - `ptr` must be non-null and the allocation must not have been reclaimed,
+///   so a guard must be held unless a reference is.
+#[must_use]
+pub(crate) unsafe fn fat<T: ?Sized + Pointee>(ptr: *mut XarcHeader) -> *mut XarcData<T> {
+    T::fat(ptr)
+}
+
+/// Recover a fat pointer to a trait object using the coercion stored by `XarcData::unsize`.
+/// 
+/// # Safety
+/// - See `fat`. The allocation must have been created with `XarcData::unsize` for this `T`.
+#[must_use]
+pub unsafe fn dyn_fat<T: ?Sized>(ptr: *mut XarcHeader) -> *mut XarcData<T> {
+    // Function pointers taking `*mut XarcData<C>` and `*mut XarcHeader` are ABI-compatible since both are thin pointers.
+    let coerce: fn(*mut XarcHeader) -> *mut XarcData<T> = mem::transmute((*ptr).meta.fat.coerce);
+    coerce(ptr)
+}
+
+/// The layout of an allocation holding a trait object, as stored by `XarcData::unsize`.
+///
+/// # Safety
+/// - See `dyn_fat`, except that the value may already have been dropped.
+#[must_use]
+pub unsafe fn dyn_layout(ptr: *mut XarcHeader) -> Layout {
+    (*ptr).meta.fat.layout
+}
+
+/// The address of the value in the allocation pointed to by `ptr`, or null.
+#[must_use]
+pub(crate) fn value_ptr<T>(ptr: *mut XarcHeader) -> *const T {
+    if ptr.is_null() {
+        ptr::null()
+    }
+    else {
+        unsafe {
+            ptr::addr_of!((*(ptr as *mut XarcData<T>)).value)
+        }
+    }
+}
+
+pub(crate) fn decrement<T: ?Sized + Pointee>(ptr: *mut XarcHeader, guard: &Guard) {
+    decrement_by::<T>(ptr, 1, guard);
+}
+
+#[cfg_attr(feature = "no-reclaim", allow(clippy::extra_unused_type_parameters))]
+pub(crate) fn decrement_by<T: ?Sized + Pointee>(ptr: *mut XarcHeader, amount: usize, guard: &Guard) {
+    unsafe {
+        if !ptr.is_null() && (*ptr).count.decrement(amount) == amount {
+            #[cfg(feature = "no-reclaim")]
+            {
+                let _ = guard;
+                super::leak::leak();
+            }
+            #[cfg(not(feature = "no-reclaim"))]
+            if is_immediate(ptr) {
+                fence(force(Ordering::Acquire));
+                Retired::now().reclaiming();
+                reclaim::<T>(ptr);
+            }
+            else {
+                defer_reclaim::<T>(ptr, guard);
+            }
+        }
+    }
+}
+
+/// Release a reference to the value at `ptr`, pinning the epoch only if the value would be reclaimed through it.
+pub(crate) fn release_reference<T: ?Sized + Pointee>(ptr: *mut XarcHeader) {
+    if is_immediate(ptr) {
+        // The guard is never used to defer anything, since the value is reclaimed on the spot.
+        decrement::<T>(ptr, unsafe { crossbeam_epoch::unprotected() });
+    }
+    else {
+        decrement::<T>(ptr, &repin::pin());
+    }
+}
+
+/// Check whether the value at `ptr` belongs to an `XarcPool` that reclaims its values as soon as their count reaches 0.
+#[must_use]
+fn is_immediate(ptr: *mut XarcHeader) -> bool {
+    unsafe {
+        !ptr.is_null() && !(*ptr).pool.is_null() && (*(*ptr).pool).immediate
+    }
+}
+
+/// Reclaim the allocation once no other thread can still be reading it, or hand it to the `ManualCollector` in control.
+#[cfg(not(feature = "no-reclaim"))]
+unsafe fn defer_reclaim<T: ?Sized + Pointee>(ptr: *mut XarcHeader, guard: &Guard) {
+    let retired = Retired::now();
+    #[cfg(feature = "std")]
+    if let Some(queue) = super::collector::current() {
+        guard.defer_unchecked(move || queue.push(ptr, reclaim::<T>, retired));
+        return;
+    }
+    #[cfg(feature = "fifo-reclaim")]
+    super::fifo::retire(ptr, reclaim::<T>, retired, guard);
+    #[cfg(not(feature = "fifo-reclaim"))]
+    guard.defer_unchecked(move || {
+        retired.reclaiming();
+        reclaim::<T>(ptr);
+    });
+}
+
+/// Release the last reference to the value at `ptr` and free the allocation at once rather than deferring it, returning the value.
+/// Returns None, changing nothing, if this is not the last reference or if the value must be reclaimed through the epoch anyway:
+/// because it belongs to an `XarcPool` or has a hook to run on reclamation, or because a `ManualCollector` or `fifo-reclaim` controls the order.
+///
+/// # Safety
+/// - No other thread may be reading the value without a count of its own, as loads in progress and `AtomicXarc::with` do.
+#[cfg(not(feature = "no-reclaim"))]
+pub(crate) unsafe fn reclaim_now<T>(ptr: *mut XarcHeader) -> Option<T> {
+    if ptr.is_null() || !(*ptr).pool.is_null() || !(*ptr).on_reclaim.is_null() || cfg!(feature = "fifo-reclaim") {
+        return None;
+    }
+    #[cfg(feature = "std")]
+    if super::collector::is_current() {
+        return None;
+    }
+    if !(*ptr).count.try_claim_last() {
+        return None;
+    }
+    Retired::now().reclaiming();
+    Some(free(ptr))
+}
+
+/// Values are only ever leaked under `no-reclaim`.
+#[cfg(feature = "no-reclaim")]
+pub(crate) unsafe fn reclaim_now<T>(_ptr: *mut XarcHeader) -> Option<T> {
+    None
+}
+
+/// Claim the value at `ptr`, which the caller holds the last reference to and has just unlinked, to move it out.
+/// Waits until nothing can still be reading it without a count, then frees the allocation without dropping the value.
+/// Returns false, changing nothing, if there are other references or the value must be reclaimed through the epoch
+/// because it belongs to an `XarcPool` or has a hook to run on reclamation.
+#[must_use]
+pub(crate) fn claim_unlinked(ptr: *mut XarcHeader) -> bool {
+    unsafe {
+        if ptr.is_null() || !(*ptr).pool.is_null() || !(*ptr).on_reclaim.is_null() || !(*ptr).count.try_claim_last() {
+            return false;
+        }
+    }
+    synchronize();
+    true
 }
 
-pub(crate) fn decrement<T: Send>(ptr: *mut XarcData<T>, guard: &Guard) {
+/// Claim the value at `ptr` to move it out if the caller's reference is the only way to reach it. See `is_unique`.
+/// Returns false, changing nothing, otherwise or if the value belongs to an `XarcPool` or has a hook to run on reclamation.
+#[must_use]
+pub(crate) fn claim_unique(ptr: *mut XarcHeader) -> bool {
     unsafe {
-        if !ptr.is_null() && (*ptr).count.decrement() == 1 {
-            let boxed = Box::from_raw(ptr);
-            guard.defer_unchecked(move || {
-                drop(boxed);
-            });
+        is_unique(ptr) && (*ptr).pool.is_null() && (*ptr).on_reclaim.is_null() && (*ptr).count.try_claim_last()
+    }
+}
+
+/// Move the value out of the allocation at `ptr` and free it.
+///
+/// # Safety
+/// - The count must have reached zero and nothing may be reading the value, which must be on the heap.
+#[must_use]
+pub(crate) unsafe fn free<T>(ptr: *mut XarcHeader) -> T {
+    let XarcData { value, .. } = *alloc::boxed::Box::from_raw(ptr as *mut XarcData<T>);
+    value
+}
+
+/// Wait until every thread that was pinned when this was called has unpinned.
+/// A value unlinked before the call can no longer be read through loads that hold no count once this returns.
+fn synchronize() {
+    repin::release();
+    debug_assert!(!is_pinned(), "xarc: waiting for the epoch to advance while the thread is pinned would never finish");
+    let done = Arc::new(AtomicBool::new(false));
+    let deferred = done.clone();
+    pin().defer(move || deferred.store(true, Ordering::Release));
+    let backoff = Backoff::new();
+    while !done.load(Ordering::Acquire) {
+        pin().flush();
+        back_off(&backoff);
+    }
+}
+
+/// Destroy the allocation once its count has reached zero and no other thread can still be reading it.
+/// If a `WeakXarc` remains, only the value is dropped and the last `WeakXarc` frees the allocation.
+#[cfg(not(feature = "no-reclaim"))]
+unsafe fn reclaim<T: ?Sized + Pointee>(ptr: *mut XarcHeader) {
+    let pool = (*ptr).pool;
+    if !pool.is_null() {
+        ((*pool).release)(pool, ptr);
+    }
+    else if (*ptr).count.try_release_only_weak() {
+        T::reclaim(alloc::boxed::Box::from_raw(fat::<T>(ptr)));
+    }
+    else {
+        T::drop_value(fat::<T>(ptr));
+        release_weak::<T>(ptr);
+    }
+}
+
+/// Release a weak reference to the allocation at `ptr`, freeing it without touching the value, which was already dropped, if it was the last.
+pub(crate) fn release_weak<T: ?Sized + Pointee>(ptr: *mut XarcHeader) {
+    unsafe {
+        if (*ptr).count.decrement_weak() {
+            // The value has been dropped, so its size comes from its type and the metadata in the header, never from a reference to it.
+            alloc::alloc::dealloc(ptr as *mut u8, T::layout(ptr));
         }
     }
 }
 
-pub(crate) fn try_increment<T: Send>(ptr: *mut XarcData<T>, _guard: &Guard) -> Result<(), ()> {
+pub(crate) fn try_increment(ptr: *mut XarcHeader, _guard: &Guard) -> Result<(), ()> {
+    #[cfg(feature = "fault-injection")]
+    if !ptr.is_null() && super::fault::increment_failure() {
+        return Err(());
+    }
     unsafe {
         if ptr.is_null() || (*ptr).count.try_increment().is_ok() {
             Ok(())
@@ -74,10 +705,102 @@ pub(crate) fn try_increment<T: Send>(ptr: *mut XarcData<T>, _guard: &Guard) -> R
     }
 }
 
-pub(crate) fn unguarded_increment<T: Send>(ptr: *mut XarcData<T>) {
+pub(crate) fn try_increment_once(ptr: *mut XarcHeader, _guard: &Guard) -> Result<(), ()> {
+    #[cfg(feature = "fault-injection")]
+    if !ptr.is_null() && super::fault::increment_failure() {
+        return Err(());
+    }
+    unsafe {
+        if ptr.is_null() {
+            Ok(())
+        }
+        else {
+            (*ptr).count.try_increment_once()
+        }
+    }
+}
+
+pub(crate) fn unguarded_increment(ptr: *mut XarcHeader) {
+    unguarded_increment_by(ptr, 1);
+}
+
+pub(crate) fn unguarded_increment_by(ptr: *mut XarcHeader, amount: usize) {
     unsafe {
-        if !ptr.is_null() && (*ptr).count.unsafe_increment() < 1 {
+        if !ptr.is_null() && (*ptr).count.unsafe_increment(amount) < 1 {
             panic!("Unguarded XarcCount increment from 0!");
         }
     }
 }
+
+/// Take a reference to the value at `ptr` for an `AtomicXarc` that it is being stored into.
+pub(crate) fn publish(ptr: *mut XarcHeader) {
+    unguarded_increment(ptr);
+    mark_published(ptr);
+}
+
+/// Note that the value at `ptr` is held by an `AtomicXarc`, so that it is never again treated as uniquely owned.
+pub(crate) fn mark_published(ptr: *mut XarcHeader) {
+    unsafe {
+        // Checking first keeps stores of values already published from writing to the header.
+        if !ptr.is_null() && !(*ptr).published.load(Ordering::Relaxed) {
+            (*ptr).published.store(true, force(Ordering::Release));
+        }
+    }
+}
+
+/// Check whether the reference to the value at `ptr` is the only way to reach it, so that it may be modified in place.
+/// A `WeakXarc` could be upgraded to another, and a value that has ever been stored in an `AtomicXarc`
+/// may still be read by loads that hold no count, so neither ever is.
+#[must_use]
+pub(crate) fn is_unique(ptr: *mut XarcHeader) -> bool {
+    unsafe {
+        if ptr.is_null() || !(*ptr).count.is_sole() {
+            return false;
+        }
+        // Synchronize with the release of every other reference, so that their reads of the value come first.
+        fence(force(Ordering::Acquire));
+        !(*ptr).published.load(force(Ordering::Acquire))
+    }
+}
+
+/// A number that tends to differ between threads and stay the same within one, derived from the address of the stack.
+/// `no_std` offers no thread id, so this is what spreads threads across shards and slots.
+#[inline]
+#[must_use]
+pub(crate) fn thread_hint() -> usize {
+    let local = 0u8;
+    (ptr::addr_of!(local).addr() >> 12).wrapping_mul(0x9E37_79B9)
+}
+
+/// Hash `value` with FNV-1a, since `core` has no default hasher. Used to pick buckets, not to resist collisions.
+#[must_use]
+pub(crate) fn fnv_hash<H: Hash + ?Sized>(value: &H) -> u64 {
+    struct Fnv(u64);
+
+    impl Hasher for Fnv {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01B3);
+            }
+        }
+    }
+
+    let mut hasher = Fnv(0xCBF2_9CE4_8422_2325);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Back off after a failed attempt.
+/// With the `std` feature this eventually yields to the scheduler rather than spinning.
+pub(crate) fn back_off(backoff: &Backoff) {
+    if cfg!(feature = "std") {
+        backoff.snooze();
+    }
+    else {
+        backoff.spin();
+    }
+}
@@ -0,0 +1,347 @@
+use super::{atomic::*, internal::*, pointer::*, pool::PoolHeader, strategy::{Eager, Strategy}};
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, ops::Deref, ptr, sync::atomic::Ordering};
+
+/// `RefCounted` is implemented by types that embed their own `XarcLink`, holding the count and the rest of the header,
+/// so that `IntrusiveXarc` and `AtomicIntrusiveXarc` can point at them directly rather than at an `XarcData` wrapping them.
+///
+/// The type decides where the link goes, so the count can be kept on a cache line of its own or next to the fields it is updated with,
+/// and a reference to the value is enough to recover a counted handle with `IntrusiveXarc::from_ref`.
+///
+/// # Safety
+/// - `LINK_OFFSET` must be the offset of a field of type `XarcLink`, as given by `core::mem::offset_of!`.
+///
+/// # Examples
+///
+/// ```
+/// use core::{mem, sync::atomic::Ordering};
+/// use xarc::{AtomicIntrusiveXarc, IntrusiveXarc, RefCounted, XarcLink};
+///
+/// struct Order {
+///     id: u64,
+///     link: XarcLink,
+/// }
+///
+/// unsafe impl RefCounted for Order {
+///     const LINK_OFFSET: usize = mem::offset_of!(Order, link);
+/// }
+///
+/// let book = AtomicIntrusiveXarc::new(Order {id: 1, link: XarcLink::new()});
+/// let order = book.load(Ordering::Acquire);
+/// assert_eq!(order.id, 1);
+///
+/// let _ = book.swap(&IntrusiveXarc::new(Order {id: 2, link: XarcLink::new()}), Ordering::AcqRel);
+/// let again = unsafe { IntrusiveXarc::from_ref(&*order) };
+/// assert_eq!(again, order);
+/// ```
+pub unsafe trait RefCounted: Send + Sized {
+    /// The offset in bytes of the `XarcLink` within the value.
+    const LINK_OFFSET: usize;
+}
+
+/// `XarcLink` is the header of an intrusive value: its count, and what is needed to reclaim it. See `RefCounted`.
+///
+/// A new link belongs to no reference until the value is moved into an `IntrusiveXarc`.
+#[repr(transparent)]
+pub struct XarcLink {
+    header: XarcHeader,
+}
+
+unsafe impl Send for XarcLink {}
+unsafe impl Sync for XarcLink {}
+
+impl XarcLink {
+    /// A link for a value that is about to be moved into an `IntrusiveXarc`.
+    #[must_use]
+    pub fn new() -> Self {
+        XarcLink {
+            header: XarcHeader::unowned(),
+        }
+    }
+}
+
+impl Default for XarcLink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for XarcLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XarcLink").finish_non_exhaustive()
+    }
+}
+
+/// Frees an intrusive value through the same path as a node of an `XarcPool`.
+struct Release<T>(PhantomData<T>);
+
+impl<T: RefCounted> Release<T> {
+    const HEADER: PoolHeader = PoolHeader {
+        release: release::<T>,
+        immediate: false,
+    };
+}
+
+unsafe fn release<T: RefCounted>(_pool: *const PoolHeader, ptr: *mut XarcHeader) {
+    drop(Box::from_raw(value_of::<T>(ptr)));
+}
+
+/// The address of the value that the link at `ptr` is embedded in.
+#[must_use]
+fn value_of<T: RefCounted>(ptr: *mut XarcHeader) -> *mut T {
+    ptr.cast::<u8>().wrapping_sub(T::LINK_OFFSET).cast()
+}
+
+/// The address of the link embedded in the value at `value`.
+#[must_use]
+fn link_of<T: RefCounted>(value: *const T) -> *mut XarcHeader {
+    value.cast::<u8>().wrapping_add(T::LINK_OFFSET) as *mut XarcHeader
+}
+
+/// `IntrusiveXarc` is an `Xarc` for a value that embeds its own header. See `RefCounted`.
+///
+/// It shares the implementation of `Xarc` and behaves the same way, with one allocation per value:
+/// the value itself, in a `Box` laid out as the type chooses.
+pub struct IntrusiveXarc<T: RefCounted> {
+    inner: Xarc<()>,
+    phantom: PhantomData<Xarc<T>>,
+}
+
+impl<T: RefCounted> IntrusiveXarc<T> {
+    /// Move `value` to the heap and take the first reference to it.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn new(value: T) -> Self {
+        let value = Box::into_raw(Box::new(value));
+        let link = link_of::<T>(value);
+        unsafe {
+            (*link).own(&Release::<T>::HEADER);
+        }
+        Self::init(link)
+    }
+
+    /// Initialize the smart pointer with null.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn null() -> Self {
+        Self::init(ptr::null_mut())
+    }
+
+    /// Take another reference to a value from a reference to it.
+    ///
+    /// # Safety
+    /// - `value` must have been borrowed, directly or not, from an `IntrusiveXarc`, rather than from a value that was never moved into one.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub unsafe fn from_ref(value: &T) -> Self {
+        let link = link_of::<T>(value);
+        debug_assert!(!(*link).pool.is_null(), "xarc: IntrusiveXarc::from_ref on a value that no IntrusiveXarc owns");
+        unguarded_increment(link);
+        Self::init(link)
+    }
+
+    /// Check if the pointer is null.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.inner.is_null()
+    }
+
+    /// Dereference the pointer only if it is not null.
+    /// None will be returned if it is null.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        if self.is_null() {
+            None
+        }
+        else {
+            unsafe {
+                Some(&*value_of::<T>(self.inner.ptr))
+            }
+        }
+    }
+
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn init(ptr: *mut XarcHeader) -> Self {
+        Self::from_inner(Xarc::init(ptr))
+    }
+
+    #[must_use]
+    fn from_inner(inner: Xarc<()>) -> Self {
+        IntrusiveXarc {
+            inner,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: RefCounted> Clone for IntrusiveXarc<T> {
+    fn clone(&self) -> Self {
+        Self::from_inner(self.inner.clone())
+    }
+}
+
+impl<T: RefCounted> Default for IntrusiveXarc<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: RefCounted> Deref for IntrusiveXarc<T> {
+    type Target = T;
+
+    /// Dereference the pointer.
+    ///
+    /// # Panics
+    /// - If the pointer is null. Use `maybe_deref` if it might be.
+    fn deref(&self) -> &T {
+        match self.maybe_deref() {
+            Some(value) => value,
+            None => panic!("Dereferenced a null IntrusiveXarc!"),
+        }
+    }
+}
+
+impl<T: RefCounted> PartialEq for IntrusiveXarc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: RefCounted> Eq for IntrusiveXarc<T> {}
+
+impl<T: RefCounted> fmt::Debug for IntrusiveXarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntrusiveXarc").field("ptr", &self.inner.ptr).finish()
+    }
+}
+
+/// `AtomicIntrusiveXarc` is an `AtomicXarc` for `IntrusiveXarc`s. See `RefCounted`.
+///
+/// It shares the implementation of `AtomicXarc`, including the `Strategy` chosen with the second type parameter.
+pub struct AtomicIntrusiveXarc<T: RefCounted, S: Strategy = Eager> {
+    inner: AtomicXarc<(), S>,
+    phantom: PhantomData<AtomicXarc<T, S>>,
+}
+
+impl<T: RefCounted> AtomicIntrusiveXarc<T> {
+    /// Initialize the atomic smart pointer with `value`.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self::from(&IntrusiveXarc::new(value))
+    }
+
+    /// Initialize the atomic smart pointer with null.
+    #[must_use]
+    pub const fn null() -> Self {
+        AtomicIntrusiveXarc::null_with_strategy(Eager)
+    }
+}
+
+impl<T: RefCounted, S: Strategy> AtomicIntrusiveXarc<T, S> {
+    /// Initialize the atomic smart pointer with null, using `strategy`.
+    #[must_use]
+    pub const fn null_with_strategy(strategy: S) -> Self {
+        AtomicIntrusiveXarc {
+            inner: AtomicXarc::null_with_strategy(strategy),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Initialize the atomic smart pointer with `pointer`, using `strategy`.
+    #[must_use]
+    pub fn with_strategy(pointer: &IntrusiveXarc<T>, strategy: S) -> Self {
+        AtomicIntrusiveXarc {
+            inner: AtomicXarc::with_strategy(&pointer.inner, strategy),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Load the value into an `IntrusiveXarc`. See `AtomicXarc::load`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> IntrusiveXarc<T> {
+        IntrusiveXarc::from_inner(self.inner.load(order))
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`, returning the previous value.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, new: &IntrusiveXarc<T>, order: Ordering) -> IntrusiveXarc<T> {
+        IntrusiveXarc::from_inner(self.inner.swap(&new.inner, order))
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new` if `self == current`.
+    /// Returns the previous value of `self` in a Result indicating whether the operation succeeded or failed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange(&self, current: &IntrusiveXarc<T>, new: &IntrusiveXarc<T>, success: Ordering, failure: Ordering) -> Result<IntrusiveXarc<T>, IntrusiveXarc<T>> {
+        self.inner.compare_exchange(&current.inner, &new.inner, success, failure)
+            .map(IntrusiveXarc::from_inner)
+            .map_err(IntrusiveXarc::from_inner)
+    }
+
+    /// Check if the pointer is null without loading it.
+    #[must_use]
+    pub fn is_null(&self, order: Ordering) -> bool {
+        self.inner.is_null(order)
+    }
+}
+
+impl<T: RefCounted> Default for AtomicIntrusiveXarc<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: RefCounted> From<&IntrusiveXarc<T>> for AtomicIntrusiveXarc<T> {
+    fn from(pointer: &IntrusiveXarc<T>) -> Self {
+        Self::with_strategy(pointer, Eager)
+    }
+}
+
+impl<T: RefCounted, S: Strategy> fmt::Debug for AtomicIntrusiveXarc<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicIntrusiveXarc").field("ptr", &self.inner.ptr.load(Ordering::Relaxed)).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_intrusive_mt_test() {
+        use core::mem;
+        use core::sync::atomic::AtomicUsize;
+        use rayon::prelude::*;
+
+        static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+        struct Node {
+            value: usize,
+            link: XarcLink,
+        }
+        unsafe impl RefCounted for Node {
+            const LINK_OFFSET: usize = mem::offset_of!(Node, link);
+        }
+        impl Drop for Node {
+            fn drop(&mut self) {
+                RECLAIMED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let shared = AtomicIntrusiveXarc::new(Node {value: 0, link: XarcLink::new()});
+        (0..10000).into_par_iter().for_each(|i| {
+            if i % 10 == 0 {
+                let _ = shared.swap(&IntrusiveXarc::new(Node {value: i, link: XarcLink::new()}), Ordering::AcqRel);
+            }
+            else {
+                let local = shared.load(Ordering::Acquire);
+                assert_eq!(local.value % 10, 0);
+                assert_eq!(unsafe { IntrusiveXarc::from_ref(&*local) }, local);
+            }
+        });
+        drop(shared);
+        let _ = crate::quiescent::flush_until(|| RECLAIMED.load(Ordering::Relaxed) == 1001);
+        assert!(RECLAIMED.load(Ordering::Relaxed) <= 1001);
+    }
+}
@@ -0,0 +1,299 @@
+use super::{atomic::*, internal::*, mcas, ordering, pointee::*, pointer::*, strategy::{ReadMostly, Strategy}};
+use core::{cell::UnsafeCell, fmt, marker::PhantomData, mem, ptr, sync::atomic::{AtomicBool, AtomicPtr, Ordering}};
+
+/// The number of `IsrXarc`s that can exist at once.
+const SLOTS: usize = 32;
+
+/// Where an `IsrXarc` leaves its reference if dropping it would release the last one.
+struct Slot {
+    /// Set while an `IsrXarc` owns the slot or a deferred release is waiting in it.
+    claimed: AtomicBool,
+    /// The pointer whose release is deferred, or null.
+    ptr: AtomicPtr<XarcHeader>,
+    /// Releases `ptr` as the right type. Written only by the owner of the slot before publishing `ptr`.
+    release: UnsafeCell<fn(*mut XarcHeader)>,
+}
+
+unsafe impl Sync for Slot {}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Slot = Slot {
+    claimed: AtomicBool::new(false),
+    ptr: AtomicPtr::new(ptr::null_mut()),
+    release: UnsafeCell::new(release_nothing),
+};
+
+static SLOT_LIST: [Slot; SLOTS] = [EMPTY; SLOTS];
+
+fn release_nothing(_ptr: *mut XarcHeader) {}
+
+fn release<T: ?Sized + Send + Pointee>(ptr: *mut XarcHeader) {
+    drop(Xarc::<T>::init(ptr));
+}
+
+/// Claim a free slot, starting the search at a position derived from the stack.
+fn claim() -> Option<usize> {
+    let start = thread_hint();
+    (0..SLOTS).map(|offset| start.wrapping_add(offset) % SLOTS).find(|&index| {
+        let slot = &SLOT_LIST[index];
+        !slot.claimed.load(Ordering::Relaxed)
+            && slot.claimed.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    })
+}
+
+/// `IsrXarc` is an `Xarc` that can be created, read and dropped in an interrupt handler.
+///
+/// An ordinary `Xarc` may pin an epoch when it is dropped, and dropping the last reference defers reclamation,
+/// which allocates and may run the destructor. Neither is acceptable in an interrupt handler.
+/// An `IsrXarc` instead claims one of a fixed number of slots when it is created,
+/// and if dropping it would release the last reference, it leaves the reference in its slot
+/// for `reclaim_isr_deferred` to release in thread context. Otherwise dropping it only decrements the count.
+///
+/// Creating an `IsrXarc` fails rather than waiting when every slot is claimed,
+/// and a slot holding a deferred release stays claimed until `reclaim_isr_deferred` is called, so call it regularly.
+///
+/// `AtomicXarc::try_swap_isr` and `AtomicXarc::try_load_isr` are the operations that produce them.
+/// Neither allocates, pins an epoch, runs a destructor or takes a lock.
+///
+/// # Signal handlers
+///
+/// The same operations are async-signal-safe, since they consist only of atomic operations on static arrays
+/// and never touch thread-local storage, so they may interrupt any other operation on the same thread without deadlocking.
+/// `AtomicXarc::try_with_isr` reads a `ReadMostly` slot from a signal handler in one call,
+/// which suits swapping logging configuration in normal code and reading it while handling `SIGSEGV`.
+/// Everything else in the crate may allocate, pin or lock, and must not be used in a signal handler.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, IsrXarc, ReadMostly, Xarc, reclaim_isr_deferred};
+///
+/// static SAMPLE: AtomicXarc<u32, ReadMostly> = AtomicXarc::null_with_strategy(ReadMostly);
+///
+/// // Prepared in thread context.
+/// let reading = IsrXarc::try_from_xarc(Xarc::new(42)).unwrap();
+///
+/// // In the interrupt handler.
+/// let previous = SAMPLE.try_swap_isr(&reading, Ordering::AcqRel).unwrap();
+/// drop(reading);
+/// let current = SAMPLE.try_load_isr(Ordering::Acquire).unwrap();
+/// assert_eq!(current.maybe_deref(), Some(&42));
+/// drop((previous, current));
+///
+/// // Back in thread context.
+/// let _ = SAMPLE.swap(&Xarc::null(), Ordering::AcqRel);
+/// assert_eq!(reclaim_isr_deferred(), 0);
+/// ```
+pub struct IsrXarc<T: ?Sized + Send + Pointee> {
+    ptr: *mut XarcHeader,
+    slot: usize,
+    phantom: PhantomData<Xarc<T>>,
+}
+
+impl<T: ?Sized + Send + Pointee> IsrXarc<T> {
+    /// Take over the reference held by `pointer`, or give it back if every slot is claimed.
+    /// Call this in thread context to prepare values for an interrupt handler to publish.
+    pub fn try_from_xarc(pointer: Xarc<T>) -> Result<Self, Xarc<T>> {
+        match claim() {
+            Some(slot) => Ok(IsrXarc {
+                ptr: pointer.into_ptr(),
+                slot,
+                phantom: PhantomData,
+            }),
+            None => Err(pointer),
+        }
+    }
+
+    /// Turn back into an ordinary `Xarc`, freeing the slot. The result must not be dropped in an interrupt handler.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn into_xarc(self) -> Xarc<T> {
+        let ptr = self.ptr;
+        SLOT_LIST[self.slot].claimed.store(false, Ordering::Release);
+        mem::forget(self);
+        Xarc::init(ptr)
+    }
+
+    /// Check if the pointer is null.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Dereference the pointer only if it is not null.
+    /// None will be returned if it is null.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        if !self.ptr.is_null() {
+            unsafe {
+                Some(&(*fat::<T>(self.ptr)).value)
+            }
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Drop for IsrXarc<T> {
+    fn drop(&mut self) {
+        let slot = &SLOT_LIST[self.slot];
+        if self.ptr.is_null() || unsafe { (*self.ptr).count.try_decrement_unless_last() } {
+            slot.claimed.store(false, Ordering::Release);
+        }
+        else {
+            unsafe {
+                *slot.release.get() = release::<T>;
+            }
+            slot.ptr.store(self.ptr, Ordering::Release);
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> fmt::Debug for IsrXarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IsrXarc").field("ptr", &self.ptr).finish()
+    }
+}
+
+unsafe impl<T: ?Sized + Send + Pointee> Send for IsrXarc<T> {}
+unsafe impl<T: ?Sized + Send + Pointee> Sync for IsrXarc<T> {}
+
+/// Release the last references that `IsrXarc`s left behind when they were dropped, running destructors as needed.
+/// Call this regularly in thread context, never in an interrupt handler.
+/// Returns the number of references released.
+pub fn reclaim_isr_deferred() -> usize {
+    let mut released = 0;
+    for slot in SLOT_LIST.iter() {
+        let ptr = slot.ptr.swap(ptr::null_mut(), Ordering::Acquire);
+        if !ptr.is_null() {
+            let release = unsafe {
+                *slot.release.get()
+            };
+            slot.claimed.store(false, Ordering::Release);
+            release(ptr);
+            released += 1;
+        }
+    }
+    released
+}
+
+/// The number of releases waiting for `reclaim_isr_deferred`, for `DebugState`.
+#[must_use]
+pub(crate) fn deferred() -> usize {
+    SLOT_LIST.iter().filter(|slot| !slot.ptr.load(Ordering::Relaxed).is_null()).count()
+}
+
+impl<T: ?Sized + Send + Pointee, S: Strategy> AtomicXarc<T, S> {
+    /// As an atomic operation, swap the contents of `self` with `new`, without allocating, pinning an epoch or running a destructor.
+    /// Returns the previous contents, or an error if every `IsrXarc` slot is claimed, in which case nothing changes.
+    /// With `Eager`, it also fails if another write or a `compare_exchange_all` gets in the way.
+    ///
+    /// Unlike `swap`, this does not answer loads that have asked writers for help or wake threads waiting for a change,
+    /// so those wait for the next ordinary write. See `IsrXarc`.
+    #[allow(clippy::result_unit_err)]
+    pub fn try_swap_isr(&self, new: &IsrXarc<T>, order: Ordering) -> Result<IsrXarc<T>, ()> {
+        let order = ordering::swap(order);
+        let slot = claim().ok_or(())?;
+        let Some(previous) = S::try_exclusive(&self.ptr, || {
+            publish(new.ptr);
+            if !S::TRANSACTIONAL {
+                return Some(self.ptr.swap(new.ptr, order));
+            }
+            // Helping a `compare_exchange_all` would pin, so one in progress fails the swap instead, as does losing a race.
+            let current = self.ptr.load(Ordering::Relaxed);
+            if mcas::is_pending(current) {
+                return None;
+            }
+            self.ptr.compare_exchange(current, new.ptr, order, Ordering::Relaxed).ok()
+        }).flatten() else {
+            SLOT_LIST[slot].claimed.store(false, Ordering::Release);
+            return Err(());
+        };
+        S::replaced(previous);
+        Ok(IsrXarc {
+            ptr: previous,
+            slot,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> AtomicXarc<T, ReadMostly> {
+    /// Make one attempt to load the value, without allocating, pinning an epoch or running a destructor.
+    /// Fails if a write intervenes, if every debt slot is taken or if every `IsrXarc` slot is claimed.
+    ///
+    /// Only `ReadMostly` can load without pinning, since its writers pay the debts of concurrent loads.
+    /// See `IsrXarc`.
+    #[allow(clippy::result_unit_err)]
+    pub fn try_load_isr(&self, order: Ordering) -> Result<IsrXarc<T>, ()> {
+        let order = ordering::load(order);
+        let slot = claim().ok_or(())?;
+        match ReadMostly::try_acquire_with_debt(&|| self.ptr.load(order)) {
+            Some(Ok(ptr)) => Ok(IsrXarc {
+                ptr,
+                slot,
+                phantom: PhantomData,
+            }),
+            _ => {
+                SLOT_LIST[slot].claimed.store(false, Ordering::Release);
+                Err(())
+            },
+        }
+    }
+
+    /// Run `f` on the current value by reference and return its result, for use in an interrupt or signal handler.
+    /// `f` receives None if the value is null.
+    /// Fails without calling `f` in the cases that `try_load_isr` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::{AtomicXarc, ReadMostly, Xarc};
+    ///
+    /// struct LogConfig {
+    ///     path: &'static str,
+    /// }
+    ///
+    /// static LOG_CONFIG: AtomicXarc<LogConfig, ReadMostly> = AtomicXarc::null_with_strategy(ReadMostly);
+    ///
+    /// // In normal code.
+    /// let _ = LOG_CONFIG.swap(&Xarc::new(LogConfig {path: "/var/log/crash"}), Ordering::AcqRel);
+    ///
+    /// // In the signal handler.
+    /// let path = LOG_CONFIG.try_with_isr(Ordering::Acquire, |config| config.map(|config| config.path));
+    /// assert_eq!(path, Ok(Some("/var/log/crash")));
+    /// ```
+    #[allow(clippy::result_unit_err)]
+    pub fn try_with_isr<R, F: FnOnce(Option<&T>) -> R>(&self, order: Ordering, f: F) -> Result<R, ()> {
+        let value = self.try_load_isr(order)?;
+        Ok(f(value.maybe_deref()))
+    }
+}
+
+#[cfg(all(test, not(feature = "no-reclaim")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_isr_deferred_st_test() {
+        use core::sync::atomic::AtomicUsize;
+        static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+
+        let shared = AtomicXarc::null_with_strategy(ReadMostly);
+        let new = IsrXarc::try_from_xarc(Xarc::with_on_reclaim(42, |value| {
+            RECLAIMED.fetch_add(value, Ordering::Relaxed);
+        })).unwrap();
+        assert!(shared.try_swap_isr(&new, Ordering::AcqRel).unwrap().is_null());
+        drop(new);
+        let previous = shared.try_swap_isr(&IsrXarc::try_from_xarc(Xarc::null()).unwrap(), Ordering::AcqRel).unwrap();
+        assert_eq!(previous.maybe_deref(), Some(&42));
+        // The last reference is left for thread context rather than released here.
+        drop(previous);
+        assert_eq!(RECLAIMED.load(Ordering::Relaxed), 0);
+        assert!(crate::isr::reclaim_isr_deferred() >= 1);
+        assert!(crate::quiescent::flush_until(|| RECLAIMED.load(Ordering::Relaxed) == 42));
+    }
+}
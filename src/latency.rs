@@ -0,0 +1,156 @@
+#[cfg(feature = "reclaim-latency")]
+use core::{convert::TryFrom, sync::atomic::{AtomicU64, Ordering}, time::Duration};
+#[cfg(feature = "reclaim-latency")]
+use std::time::Instant;
+
+/// When a value was retired, so that the time it waits to be reclaimed can be measured.
+/// Empty unless the `reclaim-latency` feature is enabled.
+#[derive(Clone, Copy)]
+pub(crate) struct Retired {
+    #[cfg(feature = "reclaim-latency")]
+    at: Instant,
+}
+
+impl Retired {
+    /// Note that a value has just been retired.
+    #[inline]
+    #[must_use]
+    pub(crate) fn now() -> Self {
+        Retired {
+            #[cfg(feature = "reclaim-latency")]
+            at: Instant::now(),
+        }
+    }
+
+    /// Record that the value is about to be reclaimed.
+    #[inline]
+    pub(crate) fn reclaiming(self) {
+        #[cfg(feature = "reclaim-latency")]
+        record(self.at.elapsed());
+    }
+}
+
+/// The number of buckets in the histogram. Bucket `i` counts latencies below `2^i` nanoseconds that do not fit in an earlier one.
+#[cfg(feature = "reclaim-latency")]
+const BUCKETS: usize = 64;
+
+#[cfg(feature = "reclaim-latency")]
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "reclaim-latency")]
+static HISTOGRAM: [AtomicU64; BUCKETS] = [ZERO; BUCKETS];
+#[cfg(feature = "reclaim-latency")]
+static TOTAL_NANOS: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "reclaim-latency")]
+static MAX_NANOS: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "reclaim-latency")]
+fn record(latency: Duration) {
+    let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+    HISTOGRAM[(u64::BITS - nanos.leading_zeros()).min(BUCKETS as u32 - 1) as usize].fetch_add(1, Ordering::Relaxed);
+    TOTAL_NANOS.fetch_add(nanos, Ordering::Relaxed);
+    MAX_NANOS.fetch_max(nanos, Ordering::Relaxed);
+}
+
+/// The distribution of the time between a value's count dropping to zero and its destructor starting to run.
+/// Returned by `reclaim_latency`.
+///
+/// This is how long released data, such as keys or credentials, lingers in memory.
+/// Latencies are kept in a histogram with power of two buckets, so percentiles are upper bounds within a factor of two.
+#[cfg(feature = "reclaim-latency")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReclaimLatency {
+    histogram: [u64; BUCKETS],
+    total_nanos: u64,
+    max_nanos: u64,
+}
+
+#[cfg(feature = "reclaim-latency")]
+impl ReclaimLatency {
+    /// The number of reclamations measured.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.histogram.iter().sum()
+    }
+
+    /// The longest latency measured.
+    #[must_use]
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_nanos)
+    }
+
+    /// The mean latency, or zero if nothing has been measured.
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        Duration::from_nanos(self.total_nanos.checked_div(self.count()).unwrap_or(0))
+    }
+
+    /// An upper bound on the latency that a fraction `p` of reclamations did not exceed, such as 0.99 for the 99th percentile.
+    /// Never more than `max`.
+    #[must_use]
+    pub fn percentile(&self, p: f64) -> Duration {
+        let target = (p.clamp(0.0, 1.0) * self.count() as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.histogram.iter().enumerate() {
+            seen += count;
+            if seen >= target.max(1) {
+                return Duration::from_nanos(upper_bound(bucket).min(self.max_nanos));
+            }
+        }
+        self.max()
+    }
+
+    /// The nonempty buckets of the histogram, as the latency each is bounded by and the number of reclamations in it.
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.histogram.iter().enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bucket, count)| (Duration::from_nanos(upper_bound(bucket)), *count))
+    }
+}
+
+#[cfg(feature = "reclaim-latency")]
+#[must_use]
+fn upper_bound(bucket: usize) -> u64 {
+    1u64.checked_shl(bucket as u32).map_or(u64::MAX, |bound| bound - 1)
+}
+
+/// Take a snapshot of the distribution of reclamation latencies measured since the process started or since `reset_reclaim_latency`.
+///
+/// Requires the `reclaim-latency` feature, under which every retirement reads the clock once more
+/// and every reclamation updates a few shared counters.
+///
+/// # Examples
+///
+/// ```
+/// use crossbeam_epoch::pin;
+/// use std::time::Duration;
+/// use xarc::Xarc;
+///
+/// drop(Xarc::new([0u8; 32]));
+/// while xarc::reclaim_latency().count() == 0 {
+///     pin().flush();
+/// }
+/// let latency = xarc::reclaim_latency();
+/// assert!(latency.percentile(0.99) <= latency.max());
+/// assert!(latency.max() < Duration::from_secs(60));
+/// ```
+#[cfg(feature = "reclaim-latency")]
+#[must_use]
+pub fn reclaim_latency() -> ReclaimLatency {
+    ReclaimLatency {
+        histogram: HISTOGRAM.each_ref().map(|count| count.load(Ordering::Relaxed)),
+        total_nanos: TOTAL_NANOS.load(Ordering::Relaxed),
+        max_nanos: MAX_NANOS.load(Ordering::Relaxed),
+    }
+}
+
+/// Forget every latency measured so far.
+#[cfg(feature = "reclaim-latency")]
+pub fn reset_reclaim_latency() {
+    for count in HISTOGRAM.iter() {
+        count.store(0, Ordering::Relaxed);
+    }
+    TOTAL_NANOS.store(0, Ordering::Relaxed);
+    MAX_NANOS.store(0, Ordering::Relaxed);
+}
@@ -0,0 +1,28 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static LEAKED: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn leak() {
+    LEAKED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of values whose count has dropped to zero and which have been leaked rather than reclaimed.
+/// 
+/// Requires the `no-reclaim` feature, under which no value is ever dropped, no `on_reclaim` hook ever runs,
+/// and `ManualCollector` is unavailable.
+/// This suits short-lived tools and fuzz targets that would rather not pay for reclamation at all,
+/// while still letting tests assert that values were released when expected.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use xarc::Xarc;
+/// 
+/// let before = xarc::leaked();
+/// drop(Xarc::new(42));
+/// assert!(xarc::leaked() > before);
+/// ```
+#[must_use]
+pub fn leaked() -> usize {
+    LEAKED.load(Ordering::Relaxed)
+}
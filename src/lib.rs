@@ -7,15 +7,163 @@
 //! be swapped into and out of `AtomicXarc`.
 //! `Xarc` is dereferenceable but cannot have its contents atomically swapped.
 //! `AtomicXarc` can have its contents atomically swapped but is not dereferenceable.
+//! 
+//! # Real-time use
+//! 
+//! Every operation is lock-free unless documented otherwise, but only some complete in a bounded number of steps.
+//! 
+//! - Bounded: `swap` with `ReadMostly`, `is_null`, `ptr_eq`, `load_raw`, `try_load_bounded`, `compare_exchange_bounded` and `cas_loop_bounded`.
+//!   A successful write also scans a fixed number of slots for loads that asked for help and, with `ReadMostly`, for debts.
+//!   With `Locked`, the bounded loads and compare-exchanges count finding the lock held as a failed attempt,
+//!   but `swap` waits for the lock like every other operation, so it blocks.
+//! - Unbounded but lock-free: `try_load`, `compare_exchange` and `compare_exchange_weak` on failure, `cas_loop` and the writes of `VersionedAtomicXarc`,
+//!   which retry compare-exchanges on the count or the pointer for as long as other threads keep interfering.
+//!   With the default `Eager`, `swap` is a compare-exchange loop as well, which helps any `compare_exchange_all` in progress on the slot
+//!   to finish rather than overwrite it, and so are `compare_exchange_all` and `transaction` themselves.
+//!   `load` asks writers for help once backing off is exhausted, so it is bounded by the next write rather than by contention,
+//!   as long as no more than 64 loads ask at once.
+//! - Blocking: every other operation on a `Locked` slot, `#[derive(HotSwap)]` and serialized `XarcRwLock` writes, `wait_until_changed`, `load_owned`, `pop` on the collections,
+//!   and `MemoMap` waiting for another caller's computation.
+//! 
+//! Pinning an epoch, which `Eager` loads and every drop of an `Xarc` do, may run a bounded batch of deferred destructors
+//! for values released elsewhere, and with `std` a write takes a lock if threads are waiting for a change to a slot that hashes to the same one of 64 buckets.
+//! A real-time thread should use `ReadMostly`, which loads without pinning while a debt slot is free,
+//! and hand the `Xarc`s it is done with to another thread to drop, or use `IsrXarc`, which never pins or drops.
+//! The `IsrXarc` operations are also the only ones that are async-signal-safe; `AtomicXarc::try_with_isr` reads a value from a signal handler.
 
 #![crate_name = "xarc"]
 
 #![no_std]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(all(any(feature = "reclaim-latency", feature = "schedule"), target_family = "wasm", target_os = "unknown"))]
+compile_error!("xarc: `reclaim-latency` and `schedule` need a clock, which `wasm32-unknown-unknown` does not provide");
 
 mod internal;
+mod ordering;
+mod pointee;
 mod atomic;
+mod biased;
+mod bytes;
+mod clones;
+pub mod collections;
+pub mod compat;
+#[cfg(all(feature = "std", not(feature = "no-reclaim")))]
+mod collector;
+mod cow_write;
+#[cfg(feature = "debug-owners")]
+pub mod debug;
+mod defer;
+mod dump;
+mod error;
+#[cfg(all(feature = "fifo-reclaim", not(feature = "no-reclaim")))]
+mod fifo;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod global;
+mod help;
+mod history;
+mod immediate;
+mod intrusive;
+mod isr;
+#[cfg(not(feature = "no-reclaim"))]
+mod latency;
+#[cfg(feature = "derive")]
+mod hot_swap;
+#[cfg(feature = "no-reclaim")]
+mod leak;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub mod numa;
+pub mod order;
+mod mcas;
+mod mutex;
+mod observers;
+mod option;
 mod pointer;
+mod pool;
+#[cfg(feature = "profiling")]
+pub mod profile;
+mod quiescent;
+mod registry;
+mod repin;
+mod rw_lock;
+mod scope;
+#[cfg(feature = "schedule")]
+pub mod schedule;
+mod sharded;
+mod shm;
+mod slice;
+mod snapshot;
+mod strategy;
+mod takeable;
+mod transaction;
+#[cfg(kani)]
+mod verification;
+mod versioned;
+#[cfg(feature = "std")]
+mod wait;
+mod weak;
+mod weighted;
+mod writer;
 
 pub use atomic::AtomicXarc;
+pub use biased::BiasedXarc;
+pub use bytes::XarcBytes;
+pub use clones::XarcClones;
+#[cfg(all(feature = "std", not(feature = "no-reclaim")))]
+pub use collector::ManualCollector;
+pub use cow_write::{CowConflict, CowWriteGuard};
+pub use defer::{defer, retire};
+pub use dump::{CollectionDump, DebugState};
+pub use error::{AllocError, ShmError, TransactionConflict, WaitTimeoutError};
+#[cfg(all(feature = "fifo-reclaim", not(feature = "no-reclaim")))]
+pub use fifo::fifo_pending;
+pub use global::StaticAtomicXarc;
+pub use history::AtomicXarcHistory;
+pub use immediate::{AtomicImmediateXarc, ImmediateXarc};
+pub use intrusive::{AtomicIntrusiveXarc, IntrusiveXarc, RefCounted, XarcLink};
+pub use isr::{IsrXarc, reclaim_isr_deferred};
+#[cfg(all(feature = "reclaim-latency", not(feature = "no-reclaim")))]
+pub use latency::{ReclaimLatency, reclaim_latency, reset_reclaim_latency};
+#[cfg(feature = "no-reclaim")]
+pub use leak::leaked;
+pub use mcas::compare_exchange_all;
+pub use mutex::{XarcMutex, XarcMutexGuard};
+pub use observers::{Observer, ObserverId, Observers};
+pub use option::AtomicOptionXarc;
+pub use pointee::Pointee;
 pub use pointer::Xarc;
+pub use pool::{ImmediateXarcPool, XarcPool};
+pub use quiescent::{ThreadRegistration, quiescent, quiescent_while, register_thread};
+pub use registry::Registry;
+#[cfg(feature = "std")]
+pub use repin::{repin_interval, set_repin_interval};
+pub use rw_lock::{XarcRwLock, XarcRwLockWriteGuard};
+pub use scope::{PinScope, pin_scope};
+pub use sharded::ShardedXarc;
+pub use shm::{AtomicXarcShm, SHM_ROOTS, ShmSegment, ShmValue, XarcShm};
+pub use slice::XarcSlice;
+pub use snapshot::snapshot;
+pub use strategy::{Eager, Locked, ReadMostly, Strategy};
+pub use takeable::Takeable;
+pub use transaction::{Transaction, transaction};
+pub use versioned::VersionedAtomicXarc;
+pub use weak::WeakXarc;
+pub use weighted::WeightedXarc;
+pub use writer::XarcSliceWriter;
+#[cfg(feature = "derive")]
+pub use xarc_derive::HotSwap;
+
+/// Implementation details used by `dyn_pointee!`, `xarc_dyn!` and `#[derive(HotSwap)]`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::internal::{XarcData, XarcHeader, dyn_fat, dyn_layout};
+    pub use crate::pointee::sealed::Sealed;
+    pub use alloc::alloc::Layout;
+    #[cfg(feature = "derive")]
+    pub use crate::hot_swap::HotSwapSequence;
+}
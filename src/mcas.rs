@@ -0,0 +1,378 @@
+use super::{atomic::*, help, internal::*, pointee::*, pointer::*, repin::pin};
+use alloc::{boxed::Box, vec::Vec};
+use core::{mem, sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering}};
+use crossbeam_epoch::Guard;
+use crossbeam_utils::Backoff;
+
+/// As a single atomic operation, replace the contents of every slot with its `new` value if every slot holds its `current` value.
+/// Each entry is `(slot, current, new)`. Returns whether the slots were replaced; if not, none of them changed.
+///
+/// This is the multi-word compare-exchange of Harris, Fraser and Pratt over `Eager` slots.
+/// A descriptor of the operation is installed in each slot in turn, in address order,
+/// and any other operation that comes across one helps it finish rather than waiting for the thread that began it,
+/// so that it is lock-free like `compare_exchange`. Meanwhile loads see through the descriptor to the value the slot holds
+/// without helping, so `load_raw`, `is_null` and `ptr_eq` stay bounded, but a `swap` or compare-exchange that meets one helps first.
+///
+/// Since the slots are only borrowed for the duration of the call, it waits before returning for any thread
+/// that is in the middle of helping it to finish doing so. A helper that is preempted delays that return, though nothing else.
+/// All accesses are sequentially consistent.
+///
+/// As with `compare_exchange`, slots are compared by address, so the same `Xarc` being swapped out and back into a slot goes undetected.
+///
+/// # Panics
+/// - If the same slot appears twice.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Xarc, compare_exchange_all};
+///
+/// let head = AtomicXarc::new(0);
+/// let tail = AtomicXarc::new(0);
+/// let (front, back) = (head.load(Ordering::Acquire), tail.load(Ordering::Acquire));
+///
+/// assert!(compare_exchange_all(&[(&head, &front, &Xarc::new(1)), (&tail, &back, &Xarc::new(1))]));
+/// assert!(!compare_exchange_all(&[(&head, &front, &Xarc::new(2)), (&tail, &tail.load(Ordering::Acquire), &Xarc::new(2))]));
+/// assert_eq!((head.load(Ordering::Acquire).maybe_deref(), tail.load(Ordering::Acquire).maybe_deref()), (Some(&1), Some(&1)));
+/// ```
+#[must_use]
+pub fn compare_exchange_all<T: ?Sized + Send + Pointee>(entries: &[(&AtomicXarc<T>, &Xarc<T>, &Xarc<T>)]) -> bool {
+    compare_exchange_entries(entries.iter().map(|(slot, current, new)| Entry {
+        slot: &*slot.ptr,
+        current: current.ptr,
+        new: new.ptr,
+        release: release_reference::<T>,
+    }).collect())
+}
+
+/// Tags a slot holding a `Descriptor` in place of its value.
+const DESCRIPTOR: usize = 0b01;
+/// Tags a slot holding a `Claim`, which becomes the `Descriptor` it names if that is still undecided.
+const CLAIM: usize = 0b10;
+const TAGS: usize = DESCRIPTOR | CLAIM;
+
+const _: () = assert!(mem::align_of::<XarcHeader>() > TAGS);
+
+const UNDECIDED: u8 = 0;
+const SUCCEEDED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Set in `Descriptor::helpers` once the thread that began the operation is done with it.
+const CLOSED: usize = 1;
+/// One thread helping, counted above `CLOSED`.
+const HELPER: usize = 2;
+
+/// A multi-word compare-exchange in progress.
+struct Descriptor {
+    status: AtomicU8,
+    /// The threads helping, and whether the thread that began the operation is done with it,
+    /// after which nobody may touch its slots, since they may have been dropped.
+    helpers: AtomicUsize,
+    /// Sorted by the address of the slot, so that operations install themselves in the same order and never help each other in a cycle.
+    entries: Vec<Entry>,
+}
+
+/// One slot of a `Descriptor`.
+pub(crate) struct Entry {
+    pub(crate) slot: *const AtomicPtr<XarcHeader>,
+    pub(crate) current: *mut XarcHeader,
+    pub(crate) new: *mut XarcHeader,
+    /// Drops the reference the slot held to `current`, with the type of the slot.
+    pub(crate) release: fn(*mut XarcHeader),
+}
+
+/// A claim on a slot by one attempt to install a `Descriptor` in it.
+///
+/// Installing the descriptor directly could put it back in a slot after the operation was decided and cleaned up,
+/// if the slot had come to hold the expected value again. The claim is settled by whoever finds it,
+/// into the descriptor if that is still undecided and back into the value otherwise.
+/// Each attempt allocates a claim of its own, so that a late settlement of one cannot apply to another.
+struct Claim {
+    descriptor: *const Descriptor,
+    entry: *const Entry,
+}
+
+/// Check if `ptr`, loaded from a slot, is a descriptor or claim rather than a value.
+#[must_use]
+pub(crate) fn is_pending(ptr: *mut XarcHeader) -> bool {
+    ptr.addr() & TAGS != 0
+}
+
+/// The value of `slot`, given `ptr` just loaded from it, seeing through any operation in progress without helping it.
+/// The result must only be dereferenced while pinned since before `ptr` was loaded.
+#[must_use]
+pub(crate) fn read(slot: &AtomicPtr<XarcHeader>, ptr: *mut XarcHeader) -> *mut XarcHeader {
+    if !is_pending(ptr) {
+        return ptr;
+    }
+    let _guard = pin();
+    // Whatever was loaded before pinning may already be gone.
+    let ptr = slot.load(Ordering::SeqCst);
+    unsafe {
+        match ptr.addr() & TAGS {
+            DESCRIPTOR => {
+                let descriptor = &*untag::<Descriptor>(ptr);
+                let entry = descriptor.entry(slot);
+                if descriptor.status.load(Ordering::SeqCst) == SUCCEEDED {
+                    entry.new
+                }
+                else {
+                    entry.current
+                }
+            },
+            CLAIM => (*(*untag::<Claim>(ptr)).entry).current,
+            _ => ptr,
+        }
+    }
+}
+
+/// Help whatever operation is in progress on `slot`, if any, so that it holds a value again.
+pub(crate) fn finish(slot: &AtomicPtr<XarcHeader>) {
+    let guard = pin();
+    unsafe {
+        resolve(slot.load(Ordering::SeqCst), &guard);
+    }
+}
+
+/// Run a multi-word compare-exchange over `entries`. See `compare_exchange_all`.
+#[must_use]
+pub(crate) fn compare_exchange_entries(mut entries: Vec<Entry>) -> bool {
+    entries.sort_unstable_by_key(|entry| entry.slot.addr());
+    assert!(entries.windows(2).all(|pair| pair[0].slot != pair[1].slot), "compare_exchange_all given the same slot twice!");
+    let generation = help::generation();
+    for entry in entries.iter() {
+        mark_published(entry.new);
+    }
+    let guard = pin();
+    let descriptor = Box::into_raw(Box::new(Descriptor {
+        status: AtomicU8::new(UNDECIDED),
+        helpers: AtomicUsize::new(0),
+        entries,
+    }));
+    unsafe {
+        let succeeded = run(&*descriptor, &guard);
+        (*descriptor).helpers.fetch_or(CLOSED, Ordering::SeqCst);
+        let backoff = Backoff::new();
+        while (*descriptor).helpers.load(Ordering::SeqCst) != CLOSED {
+            back_off(&backoff);
+        }
+        if succeeded {
+            for entry in (*descriptor).entries.iter().filter(|entry| entry.new != entry.current) {
+                let address = entry.slot.addr();
+                help::offer(address, generation, entry.new);
+                #[cfg(feature = "std")]
+                super::wait::notify(address);
+            }
+        }
+        // Threads that loaded the descriptor from a slot may still be reading it.
+        guard.defer_unchecked(move || drop(Box::from_raw(descriptor)));
+        succeeded
+    }
+}
+
+/// Help the operation, if any, that `ptr` shows to be in progress on the slot it was loaded from.
+///
+/// # Safety
+/// - The caller must have been pinned since `ptr` was loaded.
+unsafe fn resolve(ptr: *mut XarcHeader, guard: &Guard) {
+    match ptr.addr() & TAGS {
+        DESCRIPTOR => {
+            let descriptor = &*untag::<Descriptor>(ptr);
+            // Once closed, the descriptor has been removed from every slot and its other slots may be gone.
+            if descriptor.helpers.fetch_add(HELPER, Ordering::SeqCst) & CLOSED == 0 {
+                let _ = run(descriptor, guard);
+            }
+            descriptor.helpers.fetch_sub(HELPER, Ordering::SeqCst);
+        },
+        CLAIM => settle(untag(ptr)),
+        _ => {},
+    }
+}
+
+/// Install `descriptor` in each of its slots in turn, decide it, and replace it in each slot with the outcome.
+/// Returns whether it succeeded.
+///
+/// # Safety
+/// - The slots of `descriptor` must be alive: the caller began it, or is registered as helping it.
+unsafe fn run(descriptor: &Descriptor, guard: &Guard) -> bool {
+    let tagged = tag(descriptor, DESCRIPTOR);
+    if descriptor.status.load(Ordering::SeqCst) == UNDECIDED {
+        let mut outcome = SUCCEEDED;
+        'entries: for entry in descriptor.entries.iter() {
+            loop {
+                if descriptor.status.load(Ordering::SeqCst) != UNDECIDED {
+                    break 'entries;
+                }
+                let seen = install(descriptor, entry, guard);
+                if seen == entry.current || seen == tagged {
+                    break;
+                }
+                if !is_pending(seen) {
+                    outcome = FAILED;
+                    break 'entries;
+                }
+                // Another operation is installed in the slot. Help it out of the way, then try again.
+                resolve(seen, guard);
+            }
+        }
+        let _ = descriptor.status.compare_exchange(UNDECIDED, outcome, Ordering::SeqCst, Ordering::SeqCst);
+    }
+    let succeeded = descriptor.status.load(Ordering::SeqCst) == SUCCEEDED;
+    for entry in descriptor.entries.iter() {
+        if !succeeded {
+            let _ = (*entry.slot).compare_exchange(tagged, entry.current, Ordering::SeqCst, Ordering::SeqCst);
+            continue;
+        }
+        // Whoever replaces the descriptor moves the slot's reference from `current` to `new`.
+        // The reference to `new` must exist before the slot can be seen to hold it, since another operation could replace it at once.
+        unguarded_increment(entry.new);
+        if (*entry.slot).compare_exchange(tagged, entry.new, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+            (entry.release)(entry.current);
+        }
+        else {
+            (entry.release)(entry.new);
+        }
+    }
+    succeeded
+}
+
+/// Make one attempt to install `descriptor` in the slot of `entry`, settling any claims in the way.
+/// Returns what the slot held: `entry.current` if the claim was made, and otherwise whatever stopped it.
+///
+/// # Safety
+/// - As for `run`.
+unsafe fn install(descriptor: &Descriptor, entry: &Entry, guard: &Guard) -> *mut XarcHeader {
+    let slot = &*entry.slot;
+    let claim = Box::into_raw(Box::new(Claim {
+        descriptor,
+        entry,
+    }));
+    loop {
+        match slot.compare_exchange(entry.current, tag(claim, CLAIM), Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(current) => {
+                settle(claim);
+                guard.defer_unchecked(move || drop(Box::from_raw(claim)));
+                return current;
+            },
+            Err(seen) if seen.addr() & TAGS == CLAIM => settle(untag(seen)),
+            Err(seen) => {
+                // Never published, so nobody else can have seen it.
+                drop(Box::from_raw(claim));
+                return seen;
+            },
+        }
+    }
+}
+
+/// Replace `claim` in its slot with its descriptor if that is undecided, and with the value it replaced otherwise.
+///
+/// # Safety
+/// - The caller must have been pinned since `claim` was loaded from its slot, or made it.
+unsafe fn settle(claim: *const Claim) {
+    let descriptor = &*(*claim).descriptor;
+    let entry = &*(*claim).entry;
+    let value = if descriptor.status.load(Ordering::SeqCst) == UNDECIDED {
+        tag(descriptor, DESCRIPTOR)
+    }
+    else {
+        entry.current
+    };
+    let _ = (*entry.slot).compare_exchange(tag(claim, CLAIM), value, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+impl Descriptor {
+    /// The entry for `slot`, which must be one of the slots of the descriptor.
+    #[must_use]
+    fn entry(&self, slot: &AtomicPtr<XarcHeader>) -> &Entry {
+        let address = (slot as *const AtomicPtr<XarcHeader>).addr();
+        match self.entries.binary_search_by_key(&address, |entry| entry.slot.addr()) {
+            Ok(index) => &self.entries[index],
+            Err(_) => unreachable!("descriptor found in a slot it does not name"),
+        }
+    }
+}
+
+#[must_use]
+fn tag<T>(ptr: *const T, tag: usize) -> *mut XarcHeader {
+    ptr.map_addr(|address| address | tag).cast_mut().cast()
+}
+
+#[must_use]
+fn untag<T>(ptr: *mut XarcHeader) -> *const T {
+    ptr.map_addr(|address| address & !TAGS).cast_const().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_exchange_all_st_test() {
+        let slots: Vec<AtomicXarc<usize>> = (0..4).map(AtomicXarc::new).collect();
+        let current: Vec<Xarc<usize>> = slots.iter().map(|slot| slot.load(Ordering::Acquire)).collect();
+        let new: Vec<Xarc<usize>> = (10..14).map(Xarc::new).collect();
+        let entries: Vec<_> = slots.iter().zip(current.iter()).zip(new.iter()).map(|((slot, current), new)| (slot, current, new)).rev().collect();
+        assert!(compare_exchange_all(&entries));
+        assert!(!compare_exchange_all(&entries));
+        for (slot, value) in slots.iter().zip(10..14) {
+            assert_eq!(slot.load(Ordering::Acquire).maybe_deref(), Some(&value));
+        }
+        // Each slot moved its reference from the old value to the new one.
+        assert!(current.iter().all(|pointer| unsafe { (*pointer.ptr).count.load() } == 1));
+        assert!(new.iter().all(|pointer| unsafe { (*pointer.ptr).count.load() } == 2));
+        assert_eq!(slots[0].swap(&Xarc::new(20), Ordering::AcqRel), new[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "compare_exchange_all given the same slot twice!")]
+    fn compare_exchange_all_duplicate_st_test() {
+        let slot = AtomicXarc::new(0);
+        let current = slot.load(Ordering::Acquire);
+        let _ = compare_exchange_all(&[(&slot, &current, &Xarc::new(1)), (&slot, &current, &Xarc::new(2))]);
+    }
+
+    #[test]
+    fn compare_exchange_all_mt_test() {
+        extern crate std;
+        use std::thread;
+        const SLOTS: usize = 6;
+        const ROUNDS: usize = 2000;
+
+        // Each operation adds one to three of the slots at once, while another thread keeps replacing values with copies of themselves,
+        // which must neither lose an increment nor overwrite an operation in progress.
+        let slots: Vec<AtomicXarc<usize>> = (0..SLOTS).map(|_| AtomicXarc::new(0)).collect();
+        let done = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for thread in 0..4 {
+                let (slots, done) = (&slots, &done);
+                scope.spawn(move || {
+                    for round in 0..ROUNDS {
+                        let chosen = [(thread + round) % SLOTS, (thread + round + 1) % SLOTS, (thread + round * 3 + 3) % SLOTS];
+                        if chosen[2] == chosen[0] || chosen[2] == chosen[1] {
+                            continue;
+                        }
+                        loop {
+                            let current: Vec<Xarc<usize>> = chosen.iter().map(|&index| slots[index].load(Ordering::Acquire)).collect();
+                            let new: Vec<Xarc<usize>> = current.iter().map(|value| Xarc::new(value.maybe_deref().unwrap() + 1)).collect();
+                            let entries: Vec<_> = chosen.iter().zip(current.iter()).zip(new.iter()).map(|((&index, current), new)| (&slots[index], current, new)).collect();
+                            if compare_exchange_all(&entries) {
+                                break;
+                            }
+                        }
+                        done.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for round in 0..ROUNDS {
+                    let _ = slots[round % SLOTS].cas_loop(|current| Some(Xarc::new(*current.maybe_deref().unwrap())), Ordering::AcqRel, Ordering::Acquire);
+                    assert!(slots.iter().all(|slot| !slot.is_null(Ordering::Acquire)));
+                }
+            });
+        });
+        let total: usize = slots.iter().map(|slot| *slot.load(Ordering::Acquire).maybe_deref().unwrap()).sum();
+        assert_eq!(total, 3 * done.load(Ordering::Relaxed));
+        assert!(slots.iter().all(|slot| !is_pending(slot.ptr.load(Ordering::Relaxed))));
+    }
+}
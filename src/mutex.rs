@@ -0,0 +1,173 @@
+use super::{internal::back_off, ordering::force};
+use core::{cell::UnsafeCell, fmt, ops::{Deref, DerefMut}, sync::atomic::{AtomicBool, Ordering}};
+use crossbeam_utils::Backoff;
+
+/// `XarcMutex` stores a borrow flag next to a value so that whoever holds a reference to it, usually through an `Xarc`,
+/// can claim exclusive access at runtime and modify it in place.
+/// It is a safe alternative to `Xarc::unguarded_maybe_deref_mut` when one writer at a time updates a value
+/// that many readers keep a snapshot of.
+///
+/// `try_lock` claims the flag and returns a guard dereferencing mutably to the value, or None if the value is already claimed.
+/// `lock` spins until it can claim it. The flag is released when the guard is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::{Xarc, XarcMutex};
+///
+/// let stats = Xarc::new(XarcMutex::new(Vec::new()));
+/// let snapshot = stats.clone();
+///
+/// let mut guard = stats.maybe_deref().unwrap().try_lock().unwrap();
+/// guard.push(1);
+/// assert!(snapshot.maybe_deref().unwrap().try_lock().is_none());
+/// drop(guard);
+///
+/// snapshot.maybe_deref().unwrap().lock().push(2);
+/// assert_eq!(*stats.maybe_deref().unwrap().lock(), [1, 2]);
+/// ```
+pub struct XarcMutex<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for XarcMutex<T> {}
+unsafe impl<T: Send> Sync for XarcMutex<T> {}
+
+impl<T> XarcMutex<T> {
+    /// Store `value`, unclaimed.
+    #[must_use]
+    pub const fn new(value: T) -> Self {
+        XarcMutex {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Claim exclusive access to the value, or return None if it is already claimed.
+    #[must_use]
+    pub fn try_lock(&self) -> Option<XarcMutexGuard<'_, T>> {
+        if self.locked.swap(true, force(Ordering::Acquire)) {
+            None
+        }
+        else {
+            Some(XarcMutexGuard {
+                mutex: self,
+            })
+        }
+    }
+
+    /// Claim exclusive access to the value, spinning until whoever holds it lets go.
+    ///
+    /// This is not lock-free. Never call it while holding a guard for the same value, which would spin forever.
+    #[must_use]
+    pub fn lock(&self) -> XarcMutexGuard<'_, T> {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            while self.locked.load(force(Ordering::Relaxed)) {
+                back_off(&backoff);
+            }
+        }
+    }
+
+    /// Check if the value is currently claimed.
+    #[must_use]
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(force(Ordering::Relaxed))
+    }
+
+    /// Access the value. Exclusive access to the `XarcMutex` makes claiming it unnecessary.
+    #[must_use]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Take the value out.
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default> Default for XarcMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for XarcMutex<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for XarcMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_tuple("XarcMutex").field(&*guard).finish(),
+            None => f.write_str("XarcMutex(<locked>)"),
+        }
+    }
+}
+
+/// Exclusive access to the value in an `XarcMutex`, returned by `try_lock` and `lock`. The value is released when it is dropped.
+pub struct XarcMutexGuard<'a, T> {
+    mutex: &'a XarcMutex<T>,
+}
+
+impl<T> Deref for XarcMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe {
+            &*self.mutex.value.get()
+        }
+    }
+}
+
+impl<T> DerefMut for XarcMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {
+            &mut *self.mutex.value.get()
+        }
+    }
+}
+
+impl<T> Drop for XarcMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, force(Ordering::Release));
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for XarcMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_mutex_mt_test() {
+        use crate::atomic::AtomicXarc;
+        use rayon::prelude::*;
+
+        let shared = AtomicXarc::new(XarcMutex::new(0));
+        (0..10000).into_par_iter().for_each(|i| {
+            let local = shared.load(Ordering::Acquire);
+            if i % 2 == 0 {
+                *local.maybe_deref().unwrap().lock() += 1;
+            }
+            else if let Some(mut guard) = local.maybe_deref().unwrap().try_lock() {
+                *guard += 1;
+                *guard -= 1;
+            }
+        });
+        assert_eq!(*shared.load(Ordering::Acquire).maybe_deref().unwrap().lock(), 5000);
+    }
+}
@@ -0,0 +1,155 @@
+//! NUMA placement hints for `Xarc` allocations on Linux, enabled by the `numa` feature.
+//!
+//! Every clone and drop of an `Xarc` writes the count at the start of its allocation,
+//! so on a machine with several sockets a count whose memory lives on another node turns every one of them into cross-socket traffic.
+//! `Xarc::new_on_node` and `Xarc::new_local` place the allocation on a chosen node or on the node of the calling thread,
+//! and `Xarc::numa_node` reports where an allocation actually is.
+//!
+//! Placement is a hint: the allocation is rounded up to whole pages, which are asked to move to the node with `mbind`,
+//! and it is used wherever it ends up if the kernel declines, such as when the node does not exist or NUMA is not supported.
+//! The rounding makes these allocations unsuitable for large numbers of small values; they suit the few values that every thread hammers.
+//!
+//! Values placed this way are freed by this module rather than by the global allocator,
+//! so like values from an `XarcPool` they cannot be downgraded or moved out with `AtomicXarc::load_owned`.
+//!
+//! # Examples
+//!
+//! ```
+//! use xarc::{Xarc, numa};
+//!
+//! let config = Xarc::new_on_node(42, 0);
+//! assert_eq!(config.maybe_deref(), Some(&42));
+//! // Kernels without NUMA support report no placement.
+//! assert!(matches!(config.numa_node(), None | Some(0)));
+//!
+//! let local = Xarc::new_local(String::from("hot"));
+//! assert_eq!(local.maybe_deref().map(String::as_str), Some("hot"));
+//! if let (Some(node), Some(placed)) = (numa::current_node(), local.numa_node()) {
+//!     println!("allocated on node {} from node {}", placed, node);
+//! }
+//! ```
+
+use super::{internal::*, pointee::*, pointer::*, pool::PoolHeader};
+use alloc::{alloc::{Layout, alloc, dealloc, handle_alloc_error}, vec};
+use core::{marker::PhantomData, ptr};
+use libc::{c_int, c_uint, c_ulong, c_void};
+
+const MPOL_PREFERRED: c_int = 1;
+const MPOL_F_NODE: c_ulong = 1 << 0;
+const MPOL_F_ADDR: c_ulong = 1 << 1;
+const MPOL_MF_MOVE: c_uint = 1 << 1;
+
+/// The node of the CPU the calling thread is running on, or None if the kernel does not say.
+/// The thread may be migrated to another node at any time unless it is pinned to CPUs of one node.
+#[must_use]
+pub fn current_node() -> Option<usize> {
+    let mut cpu: c_uint = 0;
+    let mut node: c_uint = 0;
+    let result = unsafe {
+        libc::syscall(libc::SYS_getcpu, &mut cpu as *mut c_uint, &mut node as *mut c_uint, ptr::null_mut::<c_void>())
+    };
+    if result == 0 {
+        Some(node as usize)
+    }
+    else {
+        None
+    }
+}
+
+/// The node holding the page at `address`, or None if the kernel does not say.
+#[must_use]
+fn node_of(address: *const u8) -> Option<usize> {
+    let mut node: c_int = -1;
+    let result = unsafe {
+        libc::syscall(libc::SYS_get_mempolicy, &mut node as *mut c_int, ptr::null_mut::<c_ulong>(), 0 as c_ulong, address, MPOL_F_NODE | MPOL_F_ADDR)
+    };
+    if result == 0 && node >= 0 {
+        Some(node as usize)
+    }
+    else {
+        None
+    }
+}
+
+/// Ask for the pages of `layout` at `address` to be placed on `node`, moving any that are already in use. Failure is ignored.
+fn bind(address: *mut u8, layout: Layout, node: usize) {
+    let bits = c_ulong::BITS as usize;
+    let mut mask = vec![0 as c_ulong; node / bits + 1];
+    mask[node / bits] |= 1 << (node % bits);
+    unsafe {
+        // The kernel reads one bit fewer than it is told to.
+        libc::syscall(libc::SYS_mbind, address as *mut c_void, layout.size() as c_ulong, MPOL_PREFERRED, mask.as_ptr(), (mask.len() * bits + 1) as c_ulong, MPOL_MF_MOVE);
+    }
+}
+
+/// Whole pages, so that binding them moves nothing else.
+#[must_use]
+fn layout<T>() -> Layout {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as usize;
+    let data = Layout::new::<XarcData<T>>();
+    let align = page.max(data.align());
+    match Layout::from_size_align(data.size().div_ceil(align).max(1) * align, align) {
+        Ok(layout) => layout,
+        Err(_) => handle_alloc_error(data),
+    }
+}
+
+/// Frees values placed by this module through the same path as a node of an `XarcPool`.
+struct Release<T>(PhantomData<T>);
+
+impl<T: Send> Release<T> {
+    const HEADER: PoolHeader = PoolHeader {
+        release: release::<T>,
+        immediate: false,
+    };
+}
+
+unsafe fn release<T: Send>(_pool: *const PoolHeader, ptr: *mut XarcHeader) {
+    ptr::read(ptr as *mut XarcData<T>).reclaim();
+    dealloc(ptr as *mut u8, layout::<T>());
+}
+
+impl<T: Send> Xarc<T> {
+    /// Initialize the smart pointer with `value` in memory placed on NUMA node `node`. See `xarc::numa`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn new_on_node(value: T, node: usize) -> Self {
+        let layout = layout::<T>();
+        unsafe {
+            let address = alloc(layout);
+            if address.is_null() {
+                handle_alloc_error(layout);
+            }
+            bind(address, layout, node);
+            let data = address as *mut XarcData<T>;
+            data.write(XarcData::new(value));
+            (*data).header.pool = &Release::<T>::HEADER;
+            Xarc::init(thin(data))
+        }
+    }
+
+    /// Initialize the smart pointer with `value` in memory placed on the NUMA node of the calling thread, if it is known.
+    /// See `xarc::numa`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn new_local(value: T) -> Self {
+        match current_node() {
+            Some(node) => Xarc::new_on_node(value, node),
+            None => Xarc::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Xarc<T> {
+    /// The NUMA node holding the count, or None if the smart pointer is null or the kernel does not say.
+    /// This works for every `Xarc`, however it was allocated.
+    #[must_use]
+    pub fn numa_node(&self) -> Option<usize> {
+        if self.ptr.is_null() {
+            None
+        }
+        else {
+            node_of(self.ptr as *const u8)
+        }
+    }
+}
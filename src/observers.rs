@@ -0,0 +1,174 @@
+use super::{atomic::*, pointer::*};
+use alloc::vec::Vec;
+use core::{fmt, sync::atomic::{AtomicUsize, Ordering}};
+
+/// A callback registered with `Observers` for events of type `E`.
+pub type Observer<E> = dyn Fn(&E) + Send + Sync;
+
+/// Identifies a callback registered with `Observers`, to unregister it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ObserverId(usize);
+
+/// A registered callback and its id.
+struct Entry<E> {
+    id: ObserverId,
+    observer: Xarc<Observer<E>>,
+}
+
+/// `Observers` is a list of callbacks that can be registered and unregistered concurrently with events being dispatched to them.
+///
+/// The list is an immutable slice published through an `AtomicXarc`, and registering or unregistering replaces it with a compare-exchange.
+/// `dispatch` loads the current slice and calls every callback in it without holding anything that a registration waits on,
+/// so callbacks may themselves register or unregister callbacks, which take effect from the next dispatch.
+/// A callback unregistered during a dispatch may still be called by that dispatch, and stays alive until it returns.
+///
+/// Callbacks are `Xarc<dyn Fn(&E) + Send + Sync>`, so the same callback can be registered with several lists, or in several places.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use rayon::prelude::*;
+/// use xarc::Observers;
+///
+/// static TOTAL: AtomicUsize = AtomicUsize::new(0);
+/// static OBSERVERS: Observers<usize> = Observers::new();
+///
+/// let ids: Vec<_> = (0..4).into_par_iter().map(|_| OBSERVERS.register_fn(|event: &usize| {
+///     TOTAL.fetch_add(*event, Ordering::Relaxed);
+/// })).collect();
+/// assert_eq!(OBSERVERS.dispatch(&10), 4);
+/// assert_eq!(TOTAL.load(Ordering::Relaxed), 40);
+///
+/// assert!(OBSERVERS.unregister(ids[0]).is_some());
+/// assert!(OBSERVERS.unregister(ids[0]).is_none());
+/// assert_eq!(OBSERVERS.dispatch(&1), 3);
+/// assert_eq!(TOTAL.load(Ordering::Relaxed), 43);
+/// ```
+pub struct Observers<E> {
+    entries: AtomicXarc<[Entry<E>]>,
+    next_id: AtomicUsize,
+}
+
+impl<E> Observers<E> {
+    /// Initialize an empty list without allocating.
+    #[must_use]
+    pub const fn new() -> Self {
+        Observers {
+            entries: AtomicXarc::null(),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register `observer` to be called on every later dispatch, returning its id.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn register(&self, observer: &Xarc<Observer<E>>) -> ObserverId {
+        let id = ObserverId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let _ = self.entries.cas_loop(|current| {
+            let entries: Vec<Entry<E>> = current.iter()
+                .map(|entry| Entry {
+                    id: entry.id,
+                    observer: entry.observer.clone(),
+                })
+                .chain(core::iter::once(Entry {
+                    id,
+                    observer: observer.clone(),
+                }))
+                .collect();
+            Some(Xarc::from(entries))
+        }, Ordering::AcqRel, Ordering::Acquire);
+        id
+    }
+
+    /// Register the closure `f` to be called on every later dispatch, returning its id.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn register_fn<F: Fn(&E) + Send + Sync + 'static>(&self, f: F) -> ObserverId {
+        self.register(&crate::xarc_dyn!(f))
+    }
+
+    /// Unregister the callback with `id`, returning it, or None if it is not registered.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn unregister(&self, id: ObserverId) -> Option<Xarc<Observer<E>>> {
+        let mut removed = None;
+        let _ = self.entries.cas_loop(|current| {
+            removed = current.iter().find(|entry| entry.id == id).map(|entry| entry.observer.clone());
+            removed.as_ref()?;
+            let entries: Vec<Entry<E>> = current.iter()
+                .filter(|entry| entry.id != id)
+                .map(|entry| Entry {
+                    id: entry.id,
+                    observer: entry.observer.clone(),
+                })
+                .collect();
+            Some(if entries.is_empty() { Xarc::null() } else { Xarc::from(entries) })
+        }, Ordering::AcqRel, Ordering::Acquire);
+        removed
+    }
+
+    /// Call every registered callback with `event`, in the order they were registered, returning how many were called.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn dispatch(&self, event: &E) -> usize {
+        let entries = self.entries.load(Ordering::Acquire);
+        entries.iter().for_each(|entry| (entry.observer.non_null())(event));
+        entries.iter().len()
+    }
+
+    /// Get the number of registered callbacks.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.with(Ordering::Acquire, |entries| entries.map_or(0, <[Entry<E>]>::len))
+    }
+
+    /// Check whether no callbacks are registered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_null(Ordering::Acquire)
+    }
+}
+
+impl<E> Default for Observers<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> fmt::Debug for Observers<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observers").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_observers_mt_test() {
+        use alloc::{sync::Arc, vec::Vec};
+        use core::sync::atomic::AtomicUsize;
+        use rayon::prelude::*;
+
+        let observers = Observers::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ids: Vec<_> = (0..200).into_par_iter().map(|i| {
+            let calls = calls.clone();
+            let id = observers.register_fn(move |event: &usize| {
+                calls.fetch_add(*event, Ordering::Relaxed);
+            });
+            let _ = observers.dispatch(&0);
+            if i % 2 == 0 {
+                assert!(observers.unregister(id).is_some());
+            }
+            id
+        }).collect();
+        assert_eq!(observers.len(), 100);
+        assert_eq!(observers.dispatch(&1), 100);
+        assert_eq!(calls.load(Ordering::Relaxed), 100);
+
+        ids.par_iter().for_each(|id| {
+            let _ = observers.unregister(*id);
+        });
+        assert!(observers.is_empty());
+        assert_eq!(observers.dispatch(&1), 0);
+    }
+}
@@ -0,0 +1,123 @@
+use super::{atomic::*, pointee::*, pointer::*, strategy::{Eager, Strategy}};
+use core::sync::atomic::Ordering;
+
+/// `AtomicOptionXarc` is an `AtomicXarc` whose API speaks `Option<Xarc<T>>` rather than null.
+///
+/// Values loaded from it are `Some` exactly when they can be dereferenced,
+/// so call sites that adopt it never hold a null `Xarc` by accident.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicOptionXarc, Xarc};
+///
+/// let session = AtomicOptionXarc::none();
+/// assert!(session.load(Ordering::Acquire).is_none());
+///
+/// let token = Xarc::new("token");
+/// assert!(session.compare_exchange(None, Some(&token), Ordering::AcqRel, Ordering::Acquire).is_ok());
+/// assert_eq!(session.load(Ordering::Acquire).unwrap().maybe_deref(), Some(&"token"));
+/// assert_eq!(session.take(Ordering::AcqRel), Some(token));
+/// assert!(session.is_none(Ordering::Relaxed));
+/// ```
+#[derive(Debug)]
+pub struct AtomicOptionXarc<T: ?Sized + Send + Pointee, S: Strategy = Eager> {
+    slot: AtomicXarc<T, S>,
+}
+
+impl<T: Send> AtomicOptionXarc<T> {
+    /// Initialize the atomic smart pointer with `value`.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        AtomicOptionXarc {
+            slot: AtomicXarc::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> AtomicOptionXarc<T> {
+    /// Initialize the atomic smart pointer with `None`.
+    #[must_use]
+    pub const fn none() -> Self {
+        AtomicOptionXarc {
+            slot: AtomicXarc::null(),
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee, S: Strategy> AtomicOptionXarc<T, S> {
+    /// Initialize the atomic smart pointer with `pointer`, using `strategy`.
+    #[must_use]
+    pub fn with_strategy(pointer: Option<&Xarc<T>>, strategy: S) -> Self {
+        AtomicOptionXarc {
+            slot: match pointer {
+                Some(pointer) => AtomicXarc::with_strategy(pointer, strategy),
+                None => AtomicXarc::null_with_strategy(strategy),
+            },
+        }
+    }
+
+    /// Load the current value, if any.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> Option<Xarc<T>> {
+        self.slot.load(order).into_option()
+    }
+
+    /// Replace the current value with `new`.
+    pub fn store(&self, new: Option<&Xarc<T>>, order: Ordering) {
+        let _ = self.swap(new, order);
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`, returning the previous value, if any.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, new: Option<&Xarc<T>>, order: Ordering) -> Option<Xarc<T>> {
+        match new {
+            Some(new) => self.slot.swap(new, order),
+            None => self.slot.swap(&Xarc::null(), order),
+        }.into_option()
+    }
+
+    /// As an atomic operation, replace the contents of `self` with `None`, returning the previous value, if any.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn take(&self, order: Ordering) -> Option<Xarc<T>> {
+        self.swap(None, order)
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new` if `self == current`.
+    /// Returns the previous value of `self` in a Result indicating whether the operation succeeded or failed.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange(&self, current: Option<&Xarc<T>>, new: Option<&Xarc<T>>, success: Ordering, failure: Ordering) -> Result<Option<Xarc<T>>, Option<Xarc<T>>> {
+        let null = Xarc::null();
+        self.slot.compare_exchange(current.unwrap_or(&null), new.unwrap_or(&null), success, failure)
+            .map(Xarc::into_option)
+            .map_err(Xarc::into_option)
+    }
+
+    /// Check if the value is `None` without loading it.
+    #[must_use]
+    pub fn is_none(&self, order: Ordering) -> bool {
+        self.slot.is_null(order)
+    }
+
+    /// Give up the `Option` API for the `AtomicXarc` behind it.
+    #[must_use]
+    pub fn into_inner(self) -> AtomicXarc<T, S> {
+        self.slot
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Default for AtomicOptionXarc<T> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> From<Option<&Xarc<T>>> for AtomicOptionXarc<T> {
+    fn from(pointer: Option<&Xarc<T>>) -> Self {
+        Self::with_strategy(pointer, Eager)
+    }
+}
@@ -0,0 +1,91 @@
+//! Memory orderings as types, so that invalid orderings are compile errors rather than debug assertions.
+//!
+//! Each ordering is a zero-sized type named after its `core::sync::atomic::Ordering` counterpart.
+//! The `_ordered` methods of `AtomicXarc` accept them in place of `Ordering` and only compile for valid combinations:
+//! loads accept `Relaxed`, `Acquire` and `SeqCst`, and so does the failure ordering of a compare-exchange,
+//! whatever its success ordering, as with the standard atomics.
+//!
+//! # Examples
+//!
+//! ```
+//! use xarc::{AtomicXarc, Xarc, order::{AcqRel, Acquire, Release}};
+//!
+//! let atomic = AtomicXarc::new(42);
+//! let current = atomic.load_ordered(Acquire);
+//! assert!(atomic.compare_exchange_ordered(&current, &Xarc::new(43), AcqRel, Acquire).is_ok());
+//! assert!(atomic.compare_exchange_ordered(&current, &Xarc::new(44), Release, Acquire).is_err());
+//! ```
+//!
+//! A `Release` load does not compile.
+//!
+//! ```compile_fail
+//! use xarc::{AtomicXarc, order::Release};
+//!
+//! let atomic = AtomicXarc::new(42);
+//! let _ = atomic.load_ordered(Release);
+//! ```
+//!
+//! Neither does a `Release` failure ordering.
+//!
+//! ```compile_fail
+//! use xarc::{AtomicXarc, Xarc, order::{AcqRel, Acquire, Release}};
+//!
+//! let atomic = AtomicXarc::new(42);
+//! let current = atomic.load_ordered(Acquire);
+//! let _ = atomic.compare_exchange_ordered(&current, &Xarc::new(43), AcqRel, Release);
+//! ```
+
+use core::sync::atomic;
+
+/// An ordering valid for a read-modify-write such as a swap. Every ordering is.
+///
+/// This trait is sealed and cannot be implemented outside of `xarc`.
+pub trait RmwOrdering: sealed::Sealed + Copy {
+    /// The equivalent runtime ordering.
+    const ORDERING: atomic::Ordering;
+}
+
+/// An ordering valid for a load: `Relaxed`, `Acquire` or `SeqCst`.
+pub trait LoadOrdering: RmwOrdering {}
+
+/// An ordering valid for the load performed when a compare-exchange with success ordering `S` fails.
+pub trait FailureOrdering<S: RmwOrdering>: LoadOrdering {}
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! orderings {
+    ($($(#[$attr:meta])* $name:ident,)*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+            pub struct $name;
+
+            impl sealed::Sealed for $name {}
+
+            impl RmwOrdering for $name {
+                const ORDERING: atomic::Ordering = atomic::Ordering::$name;
+            }
+        )*
+    };
+}
+
+orderings! {
+    /// `Ordering::Relaxed` as a type.
+    Relaxed,
+    /// `Ordering::Release` as a type.
+    Release,
+    /// `Ordering::Acquire` as a type.
+    Acquire,
+    /// `Ordering::AcqRel` as a type.
+    AcqRel,
+    /// `Ordering::SeqCst` as a type.
+    SeqCst,
+}
+
+impl LoadOrdering for Relaxed {}
+impl LoadOrdering for Acquire {}
+impl LoadOrdering for SeqCst {}
+
+impl<S: RmwOrdering, L: LoadOrdering> FailureOrdering<S> for L {}
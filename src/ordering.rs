@@ -0,0 +1,62 @@
+use core::sync::atomic::Ordering;
+
+/// Upgrade `order` to `SeqCst` if the `force-seqcst` feature is enabled.
+/// Every ordering used by the crate, internal or user-provided, passes through here.
+#[inline]
+#[must_use]
+pub(crate) const fn force(order: Ordering) -> Ordering {
+    if cfg!(feature = "force-seqcst") {
+        Ordering::SeqCst
+    }
+    else {
+        order
+    }
+}
+
+/// Validate an ordering used to load, panicking in debug builds if it is invalid.
+#[inline]
+pub(crate) fn load(order: Ordering) -> Ordering {
+    debug_assert!(!matches!(order, Ordering::Release | Ordering::AcqRel),
+        "xarc: {:?} is not a valid ordering for a load", order);
+    force(order)
+}
+
+/// Any ordering is valid for a read-modify-write such as a swap.
+#[inline]
+pub(crate) fn swap(order: Ordering) -> Ordering {
+    force(order)
+}
+
+/// Validate the orderings used by a compare-exchange, panicking in debug builds if they are invalid.
+/// The failure ordering is a load, but may be stronger than the success ordering, as with the standard atomics.
+#[inline]
+pub(crate) fn compare_exchange(success: Ordering, failure: Ordering) -> (Ordering, Ordering) {
+    debug_assert!(!matches!(failure, Ordering::Release | Ordering::AcqRel),
+        "xarc: {:?} is not a valid failure ordering for a compare-exchange", failure);
+    (force(success), force(failure))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{atomic::AtomicXarc, pointer::Xarc};
+    use core::sync::atomic::Ordering;
+
+    #[test]
+    fn failure_ordering_st_test() {
+        // A failure ordering stronger than the success ordering is valid.
+        let shared = AtomicXarc::new(42);
+        let current = shared.load(Ordering::Acquire);
+        assert!(shared.compare_exchange(&current, &Xarc::new(43), Ordering::Release, Ordering::Acquire).is_ok());
+        assert!(shared.compare_exchange(&current, &Xarc::new(44), Ordering::AcqRel, Ordering::SeqCst).is_err());
+        assert_eq!(shared.load(Ordering::Acquire).maybe_deref(), Some(&43));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a valid failure ordering")]
+    fn invalid_failure_ordering_st_test() {
+        let shared = AtomicXarc::new(42);
+        let current = shared.load(Ordering::Acquire);
+        let _ = shared.compare_exchange(&current, &Xarc::null(), Ordering::AcqRel, Ordering::Release);
+    }
+}
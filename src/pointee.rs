@@ -0,0 +1,183 @@
+use super::internal::*;
+use alloc::{alloc::Layout, boxed::Box};
+use core::{any::Any, ptr};
+
+/// `Pointee` is implemented for every type that `Xarc` and `AtomicXarc` can point to:
+/// every sized type, slices, `str`, `dyn Any + Send`, `dyn Any + Send + Sync`, `dyn Fn(&E) + Send + Sync`,
+/// and any trait object passed to `dyn_pointee!`.
+/// 
+/// `Xarc` and `AtomicXarc` always store a thin pointer, so both remain a single machine word
+/// and `AtomicXarc` can be swapped without double-width atomics.
+/// Whatever else is needed to dereference the value lives in the allocation next to the count:
+/// the length for slices and `str`, and the coercion producing the vtable for trait objects.
+/// 
+/// This trait is sealed. It can only be implemented for trait objects, and only through `dyn_pointee!`.
+pub trait Pointee: sealed::Sealed {}
+
+pub(crate) mod sealed {
+    use super::*;
+
+    pub trait Sealed {
+        /// Recover the possibly fat pointer from a thin pointer.
+        /// 
+        /// # Safety
+        /// - `ptr` must be non-null and point to a live allocation holding a `Self`.
+        unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<Self>;
+
+        /// The layout of the allocation, read from the type and the metadata in the header without touching the value.
+        /// 
+        /// # Safety
+        /// - `ptr` must be non-null and point to an allocation holding a `Self`, which may already have been dropped.
+        unsafe fn layout(ptr: *mut XarcHeader) -> Layout;
+
+        /// Destroy the allocation, running the `on_reclaim` hook if there is one.
+        fn reclaim(data: Box<XarcData<Self>>) {
+            drop(data);
+        }
+
+        /// Drop the value in place, running the `on_reclaim` hook if there is one, leaving the allocation to a `WeakXarc`.
+        ///
+        /// # Safety
+        /// - `data` must point to a live value that is never used again.
+        unsafe fn drop_value(data: *mut XarcData<Self>) {
+            ptr::drop_in_place(ptr::addr_of_mut!((*data).value));
+        }
+    }
+}
+
+/// Implement `Pointee` for a trait object type so that it can be stored in `Xarc` and `AtomicXarc`.
+/// Values are then constructed with `xarc_dyn!`.
+/// 
+/// The trait object must be `Send` to satisfy the bounds of `Xarc`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Xarc, dyn_pointee, xarc_dyn};
+/// 
+/// trait Shape {
+///     fn area(&self) -> f64;
+/// }
+/// 
+/// struct Square(f64);
+/// 
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.0 * self.0
+///     }
+/// }
+/// 
+/// dyn_pointee!(dyn Shape + Send + Sync);
+/// 
+/// let shape: Xarc<dyn Shape + Send + Sync> = xarc_dyn!(Square(3.0));
+/// let atomic = AtomicXarc::from(&shape);
+/// assert_eq!(atomic.load(Ordering::Acquire).maybe_deref().unwrap().area(), 9.0);
+/// ```
+#[macro_export]
+macro_rules! dyn_pointee {
+    ($t:ty) => {
+        impl $crate::Pointee for $t {}
+
+        impl $crate::__private::Sealed for $t {
+            unsafe fn fat(ptr: *mut $crate::__private::XarcHeader) -> *mut $crate::__private::XarcData<$t> {
+                $crate::__private::dyn_fat(ptr)
+            }
+
+            unsafe fn layout(ptr: *mut $crate::__private::XarcHeader) -> $crate::__private::Layout {
+                $crate::__private::dyn_layout(ptr)
+            }
+        }
+    };
+}
+
+/// Construct an `Xarc` of a trait object from a sized value implementing the trait.
+/// The trait object type is inferred and must implement `Pointee` through `dyn_pointee!`.
+/// 
+/// See `dyn_pointee!` for an example.
+#[macro_export]
+macro_rules! xarc_dyn {
+    ($value:expr) => {
+        match $value {
+            // The value is bound outside of the `unsafe` block so that the caller's expression is still checked.
+            value => unsafe {
+                $crate::Xarc::new_unsize(value, |data| data)
+            },
+        }
+    };
+}
+
+impl<T> Pointee for T {}
+impl<T> Pointee for [T] {}
+impl Pointee for str {}
+impl Pointee for dyn Any + Send {}
+impl Pointee for dyn Any + Send + Sync {}
+impl<E> Pointee for dyn Fn(&E) + Send + Sync {}
+
+impl<T> sealed::Sealed for T {
+    unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<T> {
+        ptr as *mut XarcData<T>
+    }
+
+    unsafe fn layout(_ptr: *mut XarcHeader) -> Layout {
+        Layout::new::<XarcData<T>>()
+    }
+
+    fn reclaim(data: Box<XarcData<T>>) {
+        (*data).reclaim();
+    }
+
+    unsafe fn drop_value(data: *mut XarcData<T>) {
+        (*data).header.dispose(ptr::read(ptr::addr_of!((*data).value)));
+    }
+}
+
+impl<T> sealed::Sealed for [T] {
+    unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<[T]> {
+        ptr::slice_from_raw_parts_mut(ptr as *mut T, (*ptr).slice_len()) as *mut XarcData<[T]>
+    }
+
+    unsafe fn layout(ptr: *mut XarcHeader) -> Layout {
+        XarcData::<[T]>::layout((*ptr).slice_len())
+    }
+}
+
+impl sealed::Sealed for str {
+    unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<str> {
+        <[u8] as sealed::Sealed>::fat(ptr) as *mut XarcData<str>
+    }
+
+    unsafe fn layout(ptr: *mut XarcHeader) -> Layout {
+        <[u8] as sealed::Sealed>::layout(ptr)
+    }
+}
+
+impl sealed::Sealed for dyn Any + Send {
+    unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<dyn Any + Send> {
+        dyn_fat(ptr)
+    }
+
+    unsafe fn layout(ptr: *mut XarcHeader) -> Layout {
+        dyn_layout(ptr)
+    }
+}
+
+impl sealed::Sealed for dyn Any + Send + Sync {
+    unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<dyn Any + Send + Sync> {
+        dyn_fat(ptr)
+    }
+
+    unsafe fn layout(ptr: *mut XarcHeader) -> Layout {
+        dyn_layout(ptr)
+    }
+}
+
+impl<E> sealed::Sealed for dyn Fn(&E) + Send + Sync {
+    unsafe fn fat(ptr: *mut XarcHeader) -> *mut XarcData<dyn Fn(&E) + Send + Sync> {
+        dyn_fat(ptr)
+    }
+
+    unsafe fn layout(ptr: *mut XarcHeader) -> Layout {
+        dyn_layout(ptr)
+    }
+}
@@ -1,7 +1,6 @@
-use super::{internal::*};
-use alloc::boxed::Box;
-use core::{hash::*, ptr};
-use crossbeam_epoch::{Guard, pin};
+use super::{atomic::AtomicXarc, clones::XarcClones, error::AllocError, internal::*, pointee::*, weak::WeakXarc};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{any::Any, convert::TryFrom, fmt, hash::*, iter::FromIterator, marker::PhantomData, mem::{self, MaybeUninit}, num::NonZeroUsize, ops::Index, ptr, slice::{self, SliceIndex}};
 
 /// `Xarc` is a derefenceable atomically refcounted smart pointer.
 /// `Xarc` is roughly equivalent to `Arc` but is compatible with `AtomicXarc`.
@@ -31,65 +30,344 @@ use crossbeam_epoch::{Guard, pin};
 /// 
 /// When implementing a container you often need structures with an immutable part,
 /// such as a pointer to another part of the structure, and a separate value that
-/// you can `take` to return a value as you remove it. `UnsafeCell` comes to the rescue.
+/// you can `take` to return a value as you remove it. `Takeable` comes to the rescue.
 /// ```
-/// use core::{cell::UnsafeCell, mem};
-/// use xarc::Xarc;
+/// use xarc::{Takeable, Xarc};
 /// 
 /// struct Example {
 ///     immutable: i64,
-///     takeable: UnsafeCell<i64>,
+///     takeable: Takeable<i64>,
 /// }
 /// 
-/// let mut xarc = Xarc::new(Example {immutable: 0, takeable: UnsafeCell::new(42)});
+/// let xarc = Xarc::new(Example {immutable: 0, takeable: Takeable::new(42)});
 /// 
-/// let value = unsafe {
-///     // You had better know what you're doing at this point. 🙂
-///     core::mem::take(&mut *xarc.maybe_deref().unwrap().takeable.get())
-/// };
+/// assert_eq!(xarc.maybe_deref().unwrap().takeable.take(), Some(42));
+/// assert_eq!(xarc.maybe_deref().unwrap().takeable.take(), None);
+/// ```
 /// 
-/// assert_eq!(value, 42);
+/// Slices and `str` are supported as well, and `AtomicXarc<[T]>` remains a single word. See `Pointee`.
 /// ```
-
-#[derive(Debug, Eq)]
-pub struct Xarc<T: Send> {
-    pub(crate) ptr: *mut XarcData<T>,
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Xarc};
+/// 
+/// let slice: Xarc<[i64]> = Xarc::from(vec![1, 2, 3]);
+/// let atomic = AtomicXarc::from(&slice);
+/// assert_eq!(atomic.load(Ordering::Acquire).maybe_deref().unwrap(), &[1, 2, 3]);
+/// 
+/// let string: Xarc<str> = Xarc::from("xarc");
+/// assert_eq!(string.maybe_deref().unwrap(), "xarc");
+/// ```
+/// 
+/// So are trait objects, which are constructed with `xarc_dyn!`. See `dyn_pointee!` for your own traits.
+/// ```
+/// use core::any::Any;
+/// use xarc::{Xarc, xarc_dyn};
+/// 
+/// let any: Xarc<dyn Any + Send + Sync> = xarc_dyn!(42i64);
+/// assert_eq!(any.maybe_deref().unwrap().downcast_ref::<i64>(), Some(&42));
+/// assert_eq!(*any.downcast::<i64>().unwrap().maybe_deref().unwrap(), 42);
+/// ```
+/// 
+/// Dropping the head of a long chain of `Xarc` never recurses through the chain, however long it is.
+/// The teardown relies on the epoch: releasing the last reference to a value only defers its reclamation,
+/// and when the value is dropped, the references it held are released the same way, deferring the next link
+/// rather than dropping it inside this destructor. A chain is therefore torn down iteratively, one link per epoch advance.
+/// Values from an `ImmediateXarcPool` are reclaimed inside the release of their last reference instead,
+/// which is why they are `ImmediateXarc`s rather than `Xarc`s and recurse through a chain of them.
+pub struct Xarc<T: ?Sized + Send + Pointee> {
+    pub(crate) ptr: *mut XarcHeader,
+    #[cfg(feature = "debug-owners")]
+    owner: usize,
+    phantom: PhantomData<T>,
 }
 
 impl<T: Send> Xarc<T> {
     /// Initialize the smart pointer with `value`.
     #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn new(value: T) -> Self {
-        Xarc {
-            ptr: Box::into_raw(Box::new(XarcData::new(value))),
+        Xarc::init(thin(Box::into_raw(Box::new(XarcData::new(value)))))
+    }
+
+    /// Initialize the smart pointer with `value`, returning an error rather than aborting if allocation fails.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use xarc::Xarc;
+    /// 
+    /// let xarc = Xarc::try_new(42).expect("out of memory");
+    /// assert_eq!(*xarc.maybe_deref().unwrap(), 42);
+    /// ```
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        Ok(Xarc::init(thin(XarcData::new(value).try_allocate()?)))
+    }
+
+    /// Initialize the smart pointer with `value` and a hook to run when it is reclaimed.
+    /// `on_reclaim` receives the value once the last reference is gone *and* the deferred destruction
+    /// actually runs, rather than when the count merely hits zero.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use core::sync::atomic::{AtomicUsize, Ordering};
+    /// use xarc::Xarc;
+    /// 
+    /// static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+    /// 
+    /// let xarc = Xarc::with_on_reclaim(vec![0u8; 1024], |buffer| {
+    ///     RECLAIMED.fetch_add(buffer.len(), Ordering::Relaxed);
+    /// });
+    /// drop(xarc);
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn with_on_reclaim(value: T, on_reclaim: fn(T)) -> Self {
+        Xarc::init(thin(Box::into_raw(Box::new(XarcData::with_on_reclaim(value, on_reclaim)))))
+    }
+
+    /// The address of the value, or null.
+    /// It remains valid for as long as `self` or any other reference to the value does.
+    #[must_use]
+    pub fn as_raw(&self) -> *const T {
+        value_ptr(self.ptr)
+    }
+
+    /// Release `self` and, if it was the last reference, free the allocation at once and return the value
+    /// rather than deferring its reclamation through the epoch. Otherwise `self` is released as usual.
+    /// This is how collections tear themselves down when dropped, one link at a time.
+    ///
+    /// # Safety
+    /// - No other thread may be reading the value without a count of its own.
+    ///   Values that have only ever been stored in `AtomicXarc`s owned by a collection being dropped satisfy this.
+    #[must_use]
+    pub(crate) unsafe fn unwrap_unprotected(self) -> Option<T> {
+        let value = reclaim_now::<T>(self.ptr)?;
+        let _ = self.into_ptr();
+        Some(value)
+    }
+
+    /// Clone the value into a new allocation, giving an independent copy, or return null if `self` is null.
+    /// `clone` only copies the pointer and increments the count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::Xarc;
+    ///
+    /// let original = Xarc::new(vec![1, 2, 3]);
+    /// let copy = original.deep_clone();
+    /// assert_ne!(copy, original);
+    /// assert_eq!(copy.maybe_deref(), original.maybe_deref());
+    /// assert!(Xarc::<Vec<i32>>::null().deep_clone().is_null());
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn deep_clone(&self) -> Self where T: Clone {
+        match self.maybe_deref() {
+            Some(value) => Xarc::new(value.clone()),
+            None => Xarc::null(),
+        }
+    }
+
+    /// Move the value out if `self` is the only reference to it, and otherwise clone it, as `Arc::unwrap_or_clone` does.
+    ///
+    /// A value that has ever been stored in an `AtomicXarc` is always cloned,
+    /// since loads that hold no count of their own may still be reading it. See `AtomicXarc::load_owned`.
+    /// So is a value with a hook to run on reclamation or allocated from an `XarcPool`.
+    ///
+    /// # Panics
+    /// - If the pointer is null.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::Xarc;
+    ///
+    /// let config = Xarc::new(vec![1, 2, 3]);
+    /// let shared = config.clone();
+    /// let mut mine = config.unwrap_or_clone();
+    /// mine.push(4);
+    /// assert_eq!(*shared.maybe_deref().unwrap(), [1, 2, 3]);
+    /// assert_eq!(shared.unwrap_or_clone(), [1, 2, 3]);
+    /// ```
+    #[must_use]
+    pub fn unwrap_or_clone(self) -> T where T: Clone {
+        if claim_unique(self.ptr) {
+            unsafe {
+                free(self.into_ptr())
+            }
+        }
+        else {
+            self.non_null().clone()
         }
     }
 
+    /// Replace the value with `value`, reusing the allocation if `self` is the only reference to it.
+    /// The old value is dropped, or handed to the hook given to `with_on_reclaim`, immediately rather than deferred.
+    ///
+    /// Otherwise, including when `self` is null, this is `Xarc::new(value)` and `self` is released as usual.
+    /// A value that has ever been stored in an `AtomicXarc` is never reused,
+    /// since loads that hold no count of their own, such as `AtomicXarc::with`, may still be reading it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::Xarc;
+    ///
+    /// let frame = Xarc::new([0u8; 64]);
+    /// let address = frame.as_raw();
+    /// let frame = frame.recycle([1; 64]);
+    /// assert_eq!(frame.as_raw(), address);
+    ///
+    /// let shared = frame.clone();
+    /// let frame = frame.recycle([2; 64]);
+    /// assert_ne!(frame, shared);
+    /// assert_eq!(shared.maybe_deref().unwrap()[0], 1);
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn recycle(mut self, value: T) -> Self {
+        if is_unique(self.ptr) {
+            unsafe {
+                (*(self.ptr as *mut XarcData<T>)).replace(value);
+            }
+            self
+        }
+        else {
+            self.reset();
+            Xarc::new(value)
+        }
+    }
+}
+
+impl<T: Send> Xarc<[T]> {
+    /// Allocate a slice of `len` uninitialized elements.
+    /// See `XarcSliceWriter` for a safe alternative that tracks which elements are initialized.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use xarc::Xarc;
+    /// 
+    /// let mut uninit = Xarc::<[i64]>::new_uninit_slice(3);
+    /// let slice = unsafe {
+    ///     for (index, element) in uninit.unguarded_maybe_deref_mut().unwrap().iter_mut().enumerate() {
+    ///         element.write(index as i64);
+    ///     }
+    ///     uninit.assume_init()
+    /// };
+    /// assert_eq!(slice.maybe_deref().unwrap(), &[0, 1, 2]);
+    /// ```
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn new_uninit_slice(len: usize) -> Xarc<[MaybeUninit<T>]> {
+        Xarc::init(thin(unsafe { XarcData::<[MaybeUninit<T>]>::allocate(len) }))
+    }
+
+    /// Iterate over the elements. A null slice is treated as empty.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use xarc::Xarc;
+    /// 
+    /// let slice: Xarc<[i64]> = Xarc::from(vec![1, 2, 3]);
+    /// assert_eq!(slice.iter().sum::<i64>(), 6);
+    /// assert_eq!(slice[1], 2);
+    /// assert_eq!(&slice[1..], &[2, 3]);
+    /// for element in &slice {
+    ///     assert!(*element > 0);
+    /// }
+    /// assert_eq!(Xarc::<[i64]>::null().iter().count(), 0);
+    /// ```
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    #[must_use]
+    pub(crate) fn as_slice(&self) -> &[T] {
+        self.maybe_deref().unwrap_or(&[])
+    }
+}
+
+impl<T: Send> Xarc<[MaybeUninit<T>]> {
+    /// Convert to a slice of initialized elements.
+    /// 
+    /// # Safety
+    /// - Every element must be initialized.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub unsafe fn assume_init(self) -> Xarc<[T]> {
+        Xarc::init(self.into_ptr())
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Xarc<T> {
+    /// Initialize the smart pointer with `value`, to be pointed to as a `T`, typically a trait object.
+    /// Use `xarc_dyn!` rather than calling this directly.
+    ///
+    /// # Safety
+    /// - `coerce` must be the unsizing coercion `|data| data`, which is exactly what `xarc_dyn!` passes.
+    ///   It is stored in the header and called to recover the fat pointer every time the value is reached.
+    #[doc(hidden)]
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub unsafe fn new_unsize<C: Send>(value: C, coerce: fn(*mut XarcData<C>) -> *mut XarcData<T>) -> Self {
+        Xarc::init(thin(Box::into_raw(Box::new(XarcData::unsize(value, coerce)))))
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Xarc<T> {
     /// Initialize the smart pointer with null.
     #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     pub fn null() -> Self {
-        Xarc {
-            ptr: ptr::null_mut(),
-        }
+        Xarc::init(ptr::null_mut())
     }
 
     #[must_use]
-    pub(crate) fn init(ptr: *mut XarcData<T>) -> Self {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub(crate) fn init(ptr: *mut XarcHeader) -> Self {
         Xarc {
             ptr,
+            #[cfg(feature = "debug-owners")]
+            owner: super::debug::register(ptr, core::panic::Location::caller()),
+            phantom: PhantomData,
         }
     }
 
-    pub(crate) fn try_from(ptr: *mut XarcData<T>, guard: &Guard) -> Result<Self, ()> {
-        try_increment(ptr, guard)?;
-        Ok(Xarc::init(ptr))
+    /// Give up the reference without decrementing the count.
+    #[must_use]
+    pub(crate) fn into_ptr(self) -> *mut XarcHeader {
+        #[cfg(feature = "debug-owners")]
+        super::debug::unregister(self.owner);
+        let ptr = self.ptr;
+        mem::forget(self);
+        ptr
     }
 
     /// Reset the smart pointer to null.
     pub fn reset(&mut self) {
-        let guard = pin();
-        decrement(self.ptr, &guard);
+        release_reference::<T>(self.ptr);
         self.ptr = ptr::null_mut();
+        #[cfg(feature = "debug-owners")]
+        {
+            super::debug::unregister(self.owner);
+            self.owner = 0;
+        }
+    }
+
+    /// `None` if the smart pointer is null, and `Some(self)` otherwise.
+    #[must_use]
+    pub(crate) fn into_option(self) -> Option<Self> {
+        if self.is_null() {
+            None
+        }
+        else {
+            Some(self)
+        }
     }
 
     /// Check if the smart pointer is null.
@@ -98,13 +376,85 @@ impl<T: Send> Xarc<T> {
         self.ptr.is_null()
     }
 
+    /// Take `n` more references to the value with a single atomic increment of the count, for handing out to many consumers at once.
+    /// See `XarcClones`.
+    ///
+    /// # Panics
+    /// - If the count would exceed its maximum, leaving it unchanged.
+    #[must_use]
+    pub fn clone_n(&self, n: usize) -> XarcClones<T> {
+        XarcClones::new(self, n)
+    }
+
+    /// Move the reference into a new `AtomicXarc` without touching the count, unlike `AtomicXarc::from(&xarc)`,
+    /// which takes a reference of its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    /// use xarc::Xarc;
+    ///
+    /// let value = Xarc::new(42);
+    /// let shared = value.clone();
+    /// let atomic = value.into_atomic();
+    /// assert_eq!(atomic.load(Ordering::Acquire), shared);
+    /// ```
+    #[must_use]
+    pub fn into_atomic(self) -> AtomicXarc<T> {
+        AtomicXarc::init(self.into_ptr())
+    }
+
+    /// Take a weak reference to the value, which does not keep it alive. A null `Xarc` gives a `WeakXarc` that never upgrades.
+    /// 
+    /// # Panics
+    /// - If the value belongs to an `XarcPool` or an `IntrusiveXarc`, which free it themselves.
+    #[must_use]
+    pub fn downgrade(this: &Self) -> WeakXarc<T> {
+        WeakXarc::downgrade(this)
+    }
+
+    /// The number of `WeakXarc`s referring to the value, or 0 if the smart pointer is null.
+    #[must_use]
+    pub fn weak_count(this: &Self) -> usize {
+        if this.ptr.is_null() {
+            0
+        }
+        else {
+            unsafe {
+                (*this.ptr).count.load_weak()
+            }
+        }
+    }
+
+    /// Get an opaque identity for the allocation, or None if the smart pointer is null.
+    /// Every `Xarc` sharing an allocation has the same identity, so it can serve as a map key or to correlate log entries.
+    /// 
+    /// An identity is unique among live allocations, but may be reused once an allocation has been reclaimed.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use xarc::Xarc;
+    /// 
+    /// let a = Xarc::new(42);
+    /// let b = Xarc::new(42);
+    /// assert_eq!(a.id(), a.clone().id());
+    /// assert_ne!(a.id(), b.id());
+    /// assert_eq!(Xarc::<i32>::null().id(), None);
+    /// ```
+    #[must_use]
+    pub fn id(&self) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.ptr.addr())
+    }
+
     /// Dereference the pointer only if it is not null.
     /// None will be returned if it is null.
     #[must_use]
     pub fn maybe_deref(&self) -> Option<&T> {
         if !self.ptr.is_null() {
             unsafe {
-                Some(&(*self.ptr).value)
+                Some(&(*fat::<T>(self.ptr)).value)
             }
         }
         else {
@@ -112,17 +462,28 @@ impl<T: Send> Xarc<T> {
         }
     }
 
+    /// Dereference a pointer that is never null, such as a node that a collection allocates itself.
+    /// 
+    /// # Panics
+    /// - If the pointer is null.
+    #[must_use]
+    pub(crate) fn non_null(&self) -> &T {
+        self.maybe_deref().expect("Dereferenced a null Xarc!")
+    }
+
     /// Dereference the pointer only if it is not null.
     /// None will be returned if it is null.
     /// 
+    /// Store the value in an `XarcMutex` to modify it safely while others may have access to it.
+    /// 
     /// # Safety
     /// - This should be called only if you're absolutely,
-    /// 100% certain that nobody else could possibly have access to this data
-    /// or if you *really* know what you're doing.
+    ///   100% certain that nobody else could possibly have access to this data
+    ///   or if you *really* know what you're doing.
     #[must_use]
     pub unsafe fn unguarded_maybe_deref_mut(&mut self) -> Option<&mut T> {
         if !self.ptr.is_null() {
-            Some(&mut (*self.ptr).value)
+            Some(&mut (*fat::<T>(self.ptr)).value)
         }
         else {
             None
@@ -130,31 +491,250 @@ impl<T: Send> Xarc<T> {
     }
 }
 
-impl<T: Send> Clone for Xarc<T> {
+impl<T: ?Sized + Send + Pointee> Clone for Xarc<T> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
     fn clone(&self) -> Self {
         unguarded_increment(self.ptr);
         Xarc::init(self.ptr)
     }
 }
 
-impl<T: Send> Drop for Xarc<T> {
+impl<T: ?Sized + Send + Pointee> Drop for Xarc<T> {
     fn drop(&mut self) {
-        decrement(self.ptr, &pin());
+        #[cfg(feature = "debug-owners")]
+        super::debug::unregister(self.owner);
+        release_reference::<T>(self.ptr);
     }
 }
 
-impl<T: Send> Hash for Xarc<T> {
+/// Only with the `stable_deref_trait` feature, which requires `Deref`. Everywhere else, `maybe_deref`
+/// is the accessor, since an `Xarc` may be null.
+#[cfg(feature = "stable_deref_trait")]
+impl<T: ?Sized + Send + Pointee> core::ops::Deref for Xarc<T> {
+    type Target = T;
+
+    /// Dereference the pointer.
+    /// 
+    /// # Panics
+    /// - If the pointer is null. Use `maybe_deref` if it might be.
+    fn deref(&self) -> &T {
+        match self.maybe_deref() {
+            Some(value) => value,
+            None => panic!("Dereferenced a null Xarc!"),
+        }
+    }
+}
+
+/// The value never moves while any reference to it remains, and clones point to the same value.
+#[cfg(feature = "stable_deref_trait")]
+unsafe impl<T: ?Sized + Send + Pointee> stable_deref_trait::StableDeref for Xarc<T> {}
+
+#[cfg(feature = "stable_deref_trait")]
+unsafe impl<T: ?Sized + Send + Pointee> stable_deref_trait::CloneStableDeref for Xarc<T> {}
+
+/// Lets an `Xarc` be stored in `arc_swap::ArcSwapAny`. A null `Xarc` is stored as a null pointer,
+/// so an `ArcSwapAny<Xarc<T>>` holds it directly, with no need for `Option`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use arc_swap::ArcSwapAny;
+/// use xarc::Xarc;
+/// 
+/// let config: ArcSwapAny<Xarc<i32>> = ArcSwapAny::new(Xarc::new(42));
+/// config.store(Xarc::new(43));
+/// assert_eq!(config.load().maybe_deref(), Some(&43));
+/// 
+/// let previous = config.swap(Xarc::null());
+/// assert_eq!(previous.maybe_deref(), Some(&43));
+/// assert!(config.load().is_null());
+/// assert!(config.load_full().is_null());
+/// ```
+#[cfg(feature = "arc-swap")]
+unsafe impl<T: ?Sized + Send + Pointee> arc_swap::RefCnt for Xarc<T> {
+    type Base = XarcHeader;
+
+    fn into_ptr(me: Self) -> *mut XarcHeader {
+        // Loads from an `ArcSwapAny` may read the value without a count of their own, as they may from an `AtomicXarc`.
+        mark_published(me.ptr);
+        me.into_ptr()
+    }
+
+    fn as_ptr(me: &Self) -> *mut XarcHeader {
+        me.ptr
+    }
+
+    unsafe fn from_ptr(ptr: *const XarcHeader) -> Self {
+        Xarc::init(ptr as *mut XarcHeader)
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Hash for Xarc<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         ptr::hash(self.ptr, state);
     }
 }
 
-impl<T: Send> PartialEq for Xarc<T> {
-    #[must_use]
+impl<T: ?Sized + Send + Pointee> PartialEq for Xarc<T> {
     fn eq(&self, other: &Self) -> bool {
         self.ptr == other.ptr
     }
 }
 
-unsafe impl<T: Send> Send for Xarc<T> {}
-unsafe impl<T: Send> Sync for Xarc<T> {}
+impl<T: ?Sized + Send + Pointee> Eq for Xarc<T> {}
+
+impl<T: PartialEq + Send> PartialEq<T> for Xarc<T> {
+    /// Compare the value against `other`. A null `Xarc` is unequal to every value.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use xarc::Xarc;
+    /// 
+    /// assert_eq!(Xarc::new(42), 42);
+    /// assert_ne!(Xarc::null(), 42);
+    /// ```
+    fn eq(&self, other: &T) -> bool {
+        self.maybe_deref() == Some(other)
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> fmt::Debug for Xarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Xarc").field("ptr", &self.ptr).finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<T: ?Sized + Send + Pointee> defmt::Format for Xarc<T> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "Xarc {{ ptr: {=usize:#x} }}", self.ptr.addr());
+    }
+}
+
+impl<T: Send, I: SliceIndex<[T]>> Index<I> for Xarc<[T]> {
+    type Output = I::Output;
+
+    /// Index into the slice. A null slice is treated as empty.
+    fn index(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<'a, T: Send> IntoIterator for &'a Xarc<[T]> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Send> From<Vec<T>> for Xarc<[T]> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(values: Vec<T>) -> Self {
+        Xarc::init(thin(XarcData::from_vec(values)))
+    }
+}
+
+impl<T: Send, const N: usize> From<[T; N]> for Xarc<[T]> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(values: [T; N]) -> Self {
+        Xarc::init(thin(XarcData::from_array(values)))
+    }
+}
+
+impl<T: Send, const N: usize> TryFrom<Xarc<[T; N]>> for Xarc<[T]> {
+    type Error = Xarc<[T; N]>;
+
+    /// Reuse the allocation, since an array and a slice of the same length are laid out alike.
+    ///
+    /// The length is written to the header of the allocation, which other references read without synchronization,
+    /// so this is only possible while the reference is unique. Returns the array if there are other references to it,
+    /// if it has ever been stored in an `AtomicXarc`, or if it has a hook to run on reclamation,
+    /// which takes an array and so cannot be run on a slice. `Xarc::from` an array always allocates a slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use xarc::Xarc;
+    ///
+    /// let array = Xarc::new([1, 2, 3]);
+    /// let address = array.as_raw() as usize;
+    /// let shared = array.clone();
+    /// let array = Xarc::<[i32]>::try_from(array).unwrap_err();
+    /// drop(shared);
+    /// let slice = Xarc::<[i32]>::try_from(array).unwrap();
+    /// assert_eq!(slice.iter().as_slice().as_ptr() as usize, address);
+    /// assert_eq!(slice.maybe_deref(), Some(&[1, 2, 3][..]));
+    ///
+    /// let hooked = Xarc::with_on_reclaim([4, 5], drop);
+    /// assert!(Xarc::<[i32]>::try_from(hooked).is_err());
+    ///
+    /// let slice: Xarc<[i32]> = Xarc::from([4, 5]);
+    /// assert_eq!(slice.iter().len(), 2);
+    /// ```
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn try_from(array: Xarc<[T; N]>) -> Result<Self, Self::Error> {
+        if !array.ptr.is_null() {
+            unsafe {
+                if (*array.ptr).has_on_reclaim() || !is_unique(array.ptr) {
+                    return Err(array);
+                }
+                (*array.ptr).set_slice_len(N);
+            }
+        }
+        Ok(Xarc::init(array.into_ptr()))
+    }
+}
+
+impl<T: Clone + Send> From<&[T]> for Xarc<[T]> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(values: &[T]) -> Self {
+        Xarc::from(values.to_vec())
+    }
+}
+
+impl<T: Send> FromIterator<T> for Xarc<[T]> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Xarc::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl From<String> for Xarc<str> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(value: String) -> Self {
+        Xarc::init(thin(XarcData::from_vec(value.into_bytes())))
+    }
+}
+
+impl From<&str> for Xarc<str> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn from(value: &str) -> Self {
+        Xarc::init(thin(XarcData::from_vec(value.as_bytes().to_vec())))
+    }
+}
+
+macro_rules! downcast {
+    ($t:ty) => {
+        impl Xarc<$t> {
+            /// Convert the smart pointer into one to the concrete type `C` if that is what it points to.
+            /// The original is returned unchanged otherwise, including when it is null.
+            #[cfg_attr(feature = "debug-owners", track_caller)]
+            pub fn downcast<C: Any + Send>(self) -> Result<Xarc<C>, Self> {
+                match self.maybe_deref() {
+                    Some(value) if value.is::<C>() => Ok(Xarc::init(self.into_ptr())),
+                    _ => Err(self),
+                }
+            }
+        }
+    };
+}
+
+downcast!(dyn Any + Send);
+downcast!(dyn Any + Send + Sync);
+
+unsafe impl<T: ?Sized + Send + Pointee> Send for Xarc<T> {}
+unsafe impl<T: ?Sized + Send + Pointee> Sync for Xarc<T> {}
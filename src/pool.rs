@@ -0,0 +1,244 @@
+use super::{immediate::ImmediateXarc, internal::*, pointer::*};
+use core::{cell::UnsafeCell, fmt, mem::{self, MaybeUninit}, ptr, sync::atomic::{AtomicBool, Ordering}};
+
+/// The type-erased start of every `XarcPool`, which values allocated from it point back to so that they can be returned.
+#[repr(C)]
+pub(crate) struct PoolHeader {
+    /// Reclaims the value in a slot of the pool and frees the slot.
+    pub(crate) release: unsafe fn(*const PoolHeader, *mut XarcHeader),
+    /// Reclaim values as soon as their count reaches 0 rather than through the epoch. See `ImmediateXarcPool`.
+    pub(crate) immediate: bool,
+}
+
+/// `XarcPool` is a fixed-capacity pool of `N` nodes for `Xarc<T>`, meant to be declared as a `static`.
+///
+/// `try_new` places the value in a free node of the pool instead of allocating it, and fails, returning the value,
+/// when every node is in use. When the last reference is released, the node is returned to the pool
+/// after the same deferred reclamation as any other `Xarc`, running the value's destructor.
+/// The resulting `Xarc` is indistinguishable from one allocated on the heap and can be stored in any `AtomicXarc`.
+///
+/// Nodes are claimed by scanning for a free one starting at a position derived from the stack, so `try_new` takes O(N) steps at worst.
+///
+/// Values allocated from a pool never touch the allocator, but the epoch-based reclamation that they go through
+/// still allocates its own bookkeeping. An `ImmediateXarcPool` skips the epoch altogether,
+/// so that firmware without an allocator can use it.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, XarcPool};
+///
+/// static READINGS: XarcPool<[u16; 8], 4> = XarcPool::new();
+///
+/// let latest = AtomicXarc::null();
+/// let reading = READINGS.try_new([512; 8]).unwrap();
+/// let _ = latest.swap(&reading, Ordering::AcqRel);
+/// assert_eq!(READINGS.available(), 3);
+///
+/// let held: Vec<_> = (0..3).map(|_| READINGS.try_new([0; 8]).unwrap()).collect();
+/// assert_eq!(READINGS.try_new([0; 8]).unwrap_err(), [0; 8]);
+/// drop(held);
+/// ```
+#[repr(C)]
+pub struct XarcPool<T: Send, const N: usize> {
+    header: PoolHeader,
+    claimed: [AtomicBool; N],
+    nodes: [UnsafeCell<MaybeUninit<XarcData<T>>>; N],
+}
+
+impl<T: Send, const N: usize> XarcPool<T, N> {
+    /// Create a pool of `N` free nodes.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_reclamation(false)
+    }
+
+    #[must_use]
+    const fn with_reclamation(immediate: bool) -> Self {
+        XarcPool {
+            header: PoolHeader {
+                release: release::<T, N>,
+                immediate,
+            },
+            claimed: [const { AtomicBool::new(false) }; N],
+            nodes: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+        }
+    }
+
+    /// Initialize a smart pointer with `value` in a free node of the pool, or return `value` if every node is in use.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn try_new(&'static self, value: T) -> Result<Xarc<T>, T> {
+        let start = thread_hint();
+        let claimed = (0..N).map(|offset| start.wrapping_add(offset) % N).find(|&index| {
+            !self.claimed[index].load(Ordering::Relaxed)
+                && self.claimed[index].compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+        });
+        let Some(index) = claimed else {
+            return Err(value);
+        };
+        let mut data = XarcData::new(value);
+        data.header.pool = &self.header;
+        unsafe {
+            let node = self.nodes[index].get() as *mut XarcData<T>;
+            node.write(data);
+            Ok(Xarc::init(thin(node)))
+        }
+    }
+
+    /// Get the number of nodes not currently in use.
+    /// A node stays in use until the deferred reclamation of its value has run.
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.claimed.iter().filter(|claimed| !claimed.load(Ordering::Relaxed)).count()
+    }
+
+    /// Get the number of nodes in the pool.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Send, const N: usize> Default for XarcPool<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send, const N: usize> fmt::Debug for XarcPool<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XarcPool").field("capacity", &N).field("available", &self.available()).finish()
+    }
+}
+
+unsafe impl<T: Send, const N: usize> Sync for XarcPool<T, N> {}
+
+/// `ImmediateXarcPool` is an `XarcPool` whose values are reclaimed, and nodes returned to the pool,
+/// as soon as the last reference is released, without pinning or deferring anything through the epoch.
+///
+/// `try_new` returns an `ImmediateXarc`, which can only be stored in an `AtomicImmediateXarc`.
+/// Those take every reference under a lock and never borrow the value without one,
+/// so cloning, dropping, loading, swapping and compare-exchanging values from the pool never allocates.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicImmediateXarc, ImmediateXarcPool};
+///
+/// static FRAMES: ImmediateXarcPool<[u8; 64], 2> = ImmediateXarcPool::new();
+/// static CURRENT: AtomicImmediateXarc<[u8; 64]> = AtomicImmediateXarc::null();
+///
+/// CURRENT.store(&FRAMES.try_new([1; 64]).unwrap(), Ordering::Release);
+/// let reader = CURRENT.load(Ordering::Acquire);
+/// drop(CURRENT.swap(&FRAMES.try_new([2; 64]).unwrap(), Ordering::AcqRel));
+/// assert_eq!(FRAMES.available(), 0);
+/// // The first frame goes back to the pool the moment its last reference is released.
+/// drop(reader);
+/// ```
+#[repr(transparent)]
+pub struct ImmediateXarcPool<T: Send, const N: usize> {
+    pool: XarcPool<T, N>,
+}
+
+impl<T: Send, const N: usize> ImmediateXarcPool<T, N> {
+    /// Create a pool of `N` free nodes.
+    #[must_use]
+    pub const fn new() -> Self {
+        ImmediateXarcPool {
+            pool: XarcPool::with_reclamation(true),
+        }
+    }
+
+    /// Initialize a smart pointer with `value` in a free node of the pool, or return `value` if every node is in use.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn try_new(&'static self, value: T) -> Result<ImmediateXarc<T>, T> {
+        self.pool.try_new(value).map(ImmediateXarc::from_inner)
+    }
+
+    /// Get the number of nodes not currently in use.
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.pool.available()
+    }
+
+    /// Get the number of nodes in the pool.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Send, const N: usize> Default for ImmediateXarcPool<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send, const N: usize> fmt::Debug for ImmediateXarcPool<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImmediateXarcPool").field("capacity", &N).field("available", &self.available()).finish()
+    }
+}
+
+/// Reclaim the value in the node at `ptr`, which belongs to the `XarcPool<T, N>` at `pool`, and free the node.
+unsafe fn release<T: Send, const N: usize>(pool: *const PoolHeader, ptr: *mut XarcHeader) {
+    let pool = &*(pool as *const XarcPool<T, N>);
+    let index = (ptr.addr() - pool.nodes.as_ptr().addr()) / mem::size_of::<XarcData<T>>();
+    ptr::read(ptr as *mut XarcData<T>).reclaim();
+    pool.claimed[index].store(false, Ordering::Release);
+}
+
+#[cfg(all(test, not(feature = "no-reclaim")))]
+mod tests {
+    use super::*;
+    use crate::{atomic::AtomicXarc, immediate::AtomicImmediateXarc};
+    use rayon::prelude::*;
+
+    #[test]
+    fn xarc_pool_immediate_mt_test() {
+        static NODES: ImmediateXarcPool<[usize; 16], 8> = ImmediateXarcPool::new();
+        static SLOT: AtomicImmediateXarc<[usize; 16]> = AtomicImmediateXarc::null();
+
+        (0..4usize).into_par_iter().for_each(|thread| {
+            for i in 0..2000 {
+                let value = thread * 10_000 + i;
+                // A node reused while still reachable would show up as a value whose elements disagree.
+                let current = SLOT.load(Ordering::Acquire);
+                if let Some(values) = current.maybe_deref() {
+                    assert!(values.iter().all(|&v| v == values[0]));
+                }
+                if let Ok(new) = NODES.try_new([value; 16]) {
+                    if i % 2 == 0 {
+                        drop(SLOT.swap(&new, Ordering::AcqRel));
+                    }
+                    else {
+                        let _ = SLOT.compare_exchange(&current, &new, Ordering::AcqRel, Ordering::Acquire);
+                    }
+                }
+            }
+        });
+        drop(SLOT.swap(&ImmediateXarc::null(), Ordering::AcqRel));
+        // Every node is back in the pool without flushing the epoch.
+        assert_eq!(NODES.available(), 8);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-reclaim"))]
+    fn xarc_pool_st_test() {
+        static POOL: XarcPool<usize, 2> = XarcPool::new();
+
+        let shared = AtomicXarc::null();
+        let first = POOL.try_new(1).unwrap();
+        let _ = shared.swap(&first, Ordering::AcqRel);
+        let second = POOL.try_new(2).unwrap();
+        assert_eq!(POOL.try_new(3), Err(3));
+        drop((first, second));
+        let previous = shared.swap(&Xarc::null(), Ordering::AcqRel);
+        assert_eq!(previous.maybe_deref(), Some(&1));
+        drop(previous);
+        assert!(crate::quiescent::flush_until(|| POOL.available() == 2));
+        assert_eq!(POOL.try_new(4).unwrap().maybe_deref(), Some(&4));
+    }
+}
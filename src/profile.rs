@@ -0,0 +1,227 @@
+//! Contention profiling for `AtomicXarc`, enabled by the `profiling` feature.
+//!
+//! Every `AtomicXarc` counts how often the operations on it had to try again:
+//! - CAS failures, when a compare-exchange found another value than the one it expected.
+//! - Load retries, when a load lost the race to increment the count of the value it read.
+//! - Backoff escalations, when a retry loop exhausted spinning and started yielding to the scheduler.
+//!
+//! `AtomicXarc::contention` reads the counts of one slot and `stats` lists every slot that has been contended, hottest first,
+//! so that the slot responsible for contention in a large structure can be found.
+//! `AtomicXarc::label` gives a slot a name to report it by.
+//!
+//! Counts are kept in a fixed table keyed by the address of the slot and are only claimed once a slot is first contended or labelled,
+//! so an `AtomicXarc` that is dropped and replaced at the same address continues its predecessor's counts.
+//! Slots that do not find room in the table still contribute to `totals`.
+//!
+//! # Examples
+//!
+//! ```
+//! use core::sync::atomic::Ordering;
+//! use rayon::prelude::*;
+//! use xarc::{AtomicXarc, Xarc, profile};
+//!
+//! let counter = AtomicXarc::new(0);
+//! counter.label("counter");
+//! (0..1000).into_par_iter().for_each(|_| {
+//!     let _ = counter.cas_loop(|current| Some(Xarc::new(current.maybe_deref().unwrap() + 1)),
+//!                              Ordering::AcqRel, Ordering::Acquire);
+//! });
+//!
+//! let hot = profile::stats().into_iter().find(|slot| slot.label == Some("counter")).unwrap();
+//! assert_eq!(hot.contention, counter.contention());
+//! assert!(profile::totals().cas_failures >= hot.contention.cas_failures);
+//! ```
+
+use super::{atomic::AtomicXarc, pointee::Pointee, strategy::Strategy};
+use alloc::{boxed::Box, vec::Vec};
+use core::{ptr, sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering}};
+
+/// The number of slots that can be told apart.
+const SLOTS: usize = 4096;
+/// How many entries of the table to search for a slot before counting it only in the totals.
+const PROBES: usize = 32;
+
+/// Something that made an operation on a slot try again.
+#[derive(Clone, Copy)]
+pub(crate) enum Event {
+    CasFailure,
+    LoadRetry,
+    BackoffEscalation,
+}
+
+struct Entry {
+    address: AtomicUsize,
+    label: AtomicPtr<&'static str>,
+    counts: [AtomicU64; 3],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Entry = Entry {
+    address: AtomicUsize::new(0),
+    label: AtomicPtr::new(ptr::null_mut()),
+    counts: [ZERO; 3],
+};
+
+static TABLE: [Entry; SLOTS] = [EMPTY; SLOTS];
+static TOTALS: [AtomicU64; 3] = [ZERO; 3];
+
+/// How often the operations on a slot, or on every slot, had to try again. See `xarc::profile`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Contention {
+    /// Compare-exchanges that found another value than the one they expected, including spurious failures.
+    pub cas_failures: u64,
+    /// Loads that lost the race to increment the count of the value they read.
+    pub load_retries: u64,
+    /// Retries that came after spinning was exhausted, which yield to the scheduler with `std` or ask writers for help.
+    pub backoff_escalations: u64,
+}
+
+impl Contention {
+    /// The sum of the counts.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.cas_failures + self.load_retries + self.backoff_escalations
+    }
+
+    #[must_use]
+    fn read(counts: &[AtomicU64; 3]) -> Self {
+        Contention {
+            cas_failures: counts[0].load(Ordering::Relaxed),
+            load_retries: counts[1].load(Ordering::Relaxed),
+            backoff_escalations: counts[2].load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The contention of one slot, as listed by `stats`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SlotStats {
+    /// The address of the `AtomicXarc`.
+    pub address: usize,
+    /// The name given to the slot with `AtomicXarc::label`, if any.
+    pub label: Option<&'static str>,
+    /// How often operations on the slot had to try again.
+    pub contention: Contention,
+}
+
+/// Find the entry for the slot at `address`, claiming one if `claim` is set and the slot has none.
+#[must_use]
+fn entry(address: usize, claim: bool) -> Option<&'static Entry> {
+    let start = (address >> 7).wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize) % SLOTS;
+    for probe in 0..PROBES {
+        let entry = &TABLE[(start + probe) % SLOTS];
+        match entry.address.load(Ordering::Acquire) {
+            found if found == address => return Some(entry),
+            0 if claim => match entry.address.compare_exchange(0, address, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Some(entry),
+                Err(found) if found == address => return Some(entry),
+                Err(_) => {},
+            },
+            0 => return None,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Count `event` against the slot at `address`.
+pub(crate) fn record(address: usize, event: Event) {
+    TOTALS[event as usize].fetch_add(1, Ordering::Relaxed);
+    if let Some(entry) = entry(address, true) {
+        entry.counts[event as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Every slot that has been contended or labelled since the process started or since `reset`, hottest first.
+#[must_use]
+pub fn stats() -> Vec<SlotStats> {
+    let mut stats: Vec<SlotStats> = TABLE.iter()
+        .filter_map(|entry| {
+            let address = entry.address.load(Ordering::Acquire);
+            if address == 0 {
+                return None;
+            }
+            let label = entry.label.load(Ordering::Acquire);
+            Some(SlotStats {
+                address,
+                label: if label.is_null() { None } else { Some(unsafe { *label }) },
+                contention: Contention::read(&entry.counts),
+            })
+        })
+        .collect();
+    stats.sort_by_key(|slot| core::cmp::Reverse(slot.contention.total()));
+    stats
+}
+
+/// The contention of every slot combined, including those that found no room in the table.
+#[must_use]
+pub fn totals() -> Contention {
+    Contention::read(&TOTALS)
+}
+
+/// Forget every count and label. Counts recorded while this runs may survive it.
+pub fn reset() {
+    for entry in TABLE.iter() {
+        for count in entry.counts.iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+        entry.label.store(ptr::null_mut(), Ordering::Relaxed);
+        entry.address.store(0, Ordering::Release);
+    }
+    for count in TOTALS.iter() {
+        count.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<T: ?Sized + Send + Pointee, S: Strategy> AtomicXarc<T, S> {
+    /// Name the slot so that `xarc::profile::stats` reports it by `label`.
+    ///
+    /// Labels are never freed, so a slot should be labelled once rather than on every use.
+    /// Nothing is recorded if the table has no room for the slot.
+    pub fn label(&self, label: &'static str) {
+        if let Some(entry) = entry(self.address(), true) {
+            let current = entry.label.load(Ordering::Acquire);
+            if current.is_null() || !ptr::eq(unsafe { *current }, label) {
+                entry.label.store(Box::into_raw(Box::new(label)), Ordering::Release);
+            }
+        }
+    }
+
+    /// How often operations on the slot have had to try again. See `xarc::profile`.
+    #[must_use]
+    pub fn contention(&self) -> Contention {
+        entry(self.address(), false).map_or_else(Contention::default, |entry| Contention::read(&entry.counts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_profiling_mt_test() {
+        use crate::pointer::Xarc;
+        use core::sync::atomic::AtomicU64;
+        use rayon::prelude::*;
+
+        let shared = AtomicXarc::new(0);
+        shared.label("shared");
+        let before = shared.contention();
+        let attempts = AtomicU64::new(0);
+        (0..10000).into_par_iter().for_each(|_| {
+            let _ = shared.cas_loop(|current| {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Some(Xarc::new(current.maybe_deref().unwrap() + 1))
+            }, Ordering::AcqRel, Ordering::Acquire);
+        });
+        assert_eq!(*shared.load(Ordering::Acquire).maybe_deref().unwrap(), 10000);
+
+        let after = shared.contention();
+        assert_eq!(after.cas_failures - before.cas_failures, attempts.load(Ordering::Relaxed) - 10000);
+        let slot = stats().into_iter().find(|slot| slot.address == shared.address()).unwrap();
+        assert_eq!(slot.label, Some("shared"));
+        assert!(totals().cas_failures >= slot.contention.cas_failures);
+    }
+}
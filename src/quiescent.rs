@@ -0,0 +1,127 @@
+use super::repin;
+use core::marker::PhantomData;
+use crossbeam_epoch::{is_pinned, pin};
+
+/// Declare that this thread holds no guard and no reference it is in the middle of reading, letting reclamation advance.
+///
+/// Released values are not destroyed until every thread pinned at the time has unpinned, and the destructors a thread defers
+/// wait in a buffer of its own until it pins often enough to hand them on.
+/// A long-lived worker that rarely touches `xarc` and then blocks, in I/O for instance,
+/// can leave everything it released stranded in that buffer for as long as it blocks.
+/// Calling `quiescent` periodically, and before blocking, hands the buffer to the other threads
+/// and tries to advance the epoch, running whatever destructors have become safe to run.
+///
+/// A thread that is still pinned, such as inside `AtomicXarc::with` or while holding a `crossbeam_epoch::Guard`,
+/// holds up reclamation regardless, and in debug builds calling `quiescent` from one panics.
+/// Holding `Xarc`s is fine, since they keep their values alive by count rather than by pinning.
+/// The pin kept between operations under `xarc::set_repin_interval` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::{AtomicXarc, Xarc};
+/// use core::sync::atomic::Ordering;
+///
+/// let jobs = AtomicXarc::new(0);
+/// for job in 1..4 {
+///     drop(jobs.swap(&Xarc::new(job), Ordering::AcqRel));
+///     xarc::quiescent();
+/// }
+/// ```
+pub fn quiescent() {
+    repin::release();
+    debug_assert!(!is_pinned(), "xarc: quiescent called while the thread is pinned");
+    pin().flush();
+}
+
+/// Run `f`, during which this thread is expected to hold up nothing, such as a blocking read, between calls to `quiescent`.
+/// Values released before `f` are handed on before it starts, and those released inside it before it returns.
+///
+/// # Panics
+/// - If the thread is pinned, in debug builds. See `quiescent`.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::Xarc;
+///
+/// let buffer = Xarc::new(vec![0u8; 4096]);
+/// drop(buffer);
+/// let line = xarc::quiescent_while(|| {
+///     // A blocking read would go here.
+///     String::from("request")
+/// });
+/// assert_eq!(line, "request");
+/// ```
+pub fn quiescent_while<R, F: FnOnce() -> R>(f: F) -> R {
+    quiescent();
+    let result = f();
+    quiescent();
+    result
+}
+
+/// Register the calling thread with the epoch collector and initialize the thread-local state of `xarc` now,
+/// rather than inside the first operation on the thread, which would otherwise pay for both.
+///
+/// Whatever the thread releases afterwards is handed on and the pin it may have kept is dropped
+/// when the returned `ThreadRegistration` is dropped, at a point of the caller's choosing,
+/// rather than whenever the thread-local destructors happen to run as the thread exits.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use std::{sync::Arc, thread};
+/// use xarc::{AtomicXarc, Xarc};
+///
+/// let shared = Arc::new(AtomicXarc::new(0));
+/// let worker = {
+///     let shared = shared.clone();
+///     thread::spawn(move || {
+///         let _registration = xarc::register_thread();
+///         // Latency-sensitive work starts here, with registration already paid for.
+///         for i in 1..=100 {
+///             drop(shared.swap(&Xarc::new(i), Ordering::AcqRel));
+///         }
+///     })
+/// };
+/// worker.join().unwrap();
+/// assert_eq!(shared.load(Ordering::Acquire).maybe_deref(), Some(&100));
+/// ```
+#[must_use]
+pub fn register_thread() -> ThreadRegistration {
+    drop(pin());
+    repin::register();
+    #[cfg(all(feature = "std", not(feature = "no-reclaim")))]
+    let _ = super::collector::is_current();
+    ThreadRegistration {
+        phantom: PhantomData,
+    }
+}
+
+/// The registration of a thread, returned by `register_thread`.
+/// Dropping it calls `quiescent`, so it must not be dropped while the thread is pinned.
+#[derive(Debug)]
+pub struct ThreadRegistration {
+    /// The registration belongs to the thread that made it.
+    phantom: PhantomData<*const ()>,
+}
+
+impl Drop for ThreadRegistration {
+    fn drop(&mut self) {
+        quiescent();
+    }
+}
+
+/// Hand on deferred destructors until `done` holds, giving up once enough epochs have passed that it never will.
+/// Returns whether `done` held.
+#[cfg(test)]
+pub(crate) fn flush_until<F: Fn() -> bool>(done: F) -> bool {
+    for _ in 0..1024 {
+        if done() {
+            return true;
+        }
+        pin().flush();
+    }
+    done()
+}
@@ -0,0 +1,133 @@
+use super::{atomic::*, internal::fnv_hash, pointer::*};
+use core::{any::{Any, TypeId}, fmt, sync::atomic::Ordering};
+
+/// The number of buckets that registered types are spread across.
+const BUCKETS: usize = 16;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicXarc<Entry> = AtomicXarc::null();
+
+/// The hot-swappable value registered for one type. Entries are never unlinked before the `Registry` is dropped.
+struct Entry {
+    type_id: TypeId,
+    value: AtomicXarc<dyn Any + Send + Sync>,
+    next: Xarc<Entry>,
+}
+
+/// `Registry` holds at most one hot-swappable value of each type, keyed by `TypeId`.
+///
+/// It is const-constructible so that it can initialize a `static` and serve as the one process-wide place to publish swappable state.
+/// Each value is stored as an `Xarc<dyn Any + Send + Sync>` and downcast on the way out, so `get` never returns a value of the wrong type.
+/// Registering a type for the first time links a new entry into a bucket with a compare-exchange. Later swaps replace the value in place.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::Registry;
+///
+/// struct Config {
+///     verbose: bool,
+/// }
+///
+/// static REGISTRY: Registry = Registry::new();
+///
+/// assert!(REGISTRY.get::<Config>(Ordering::Acquire).is_none());
+/// assert!(REGISTRY.swap(Config {verbose: false}, Ordering::AcqRel).is_none());
+/// let previous = REGISTRY.swap(Config {verbose: true}, Ordering::AcqRel).unwrap();
+/// assert!(!previous.maybe_deref().unwrap().verbose);
+/// assert!(REGISTRY.get::<Config>(Ordering::Acquire).unwrap().maybe_deref().unwrap().verbose);
+/// ```
+pub struct Registry {
+    buckets: [AtomicXarc<Entry>; BUCKETS],
+}
+
+impl Registry {
+    /// Initialize an empty registry without allocating.
+    #[must_use]
+    pub const fn new() -> Self {
+        Registry {
+            buckets: [EMPTY; BUCKETS],
+        }
+    }
+
+    /// Load the value registered for `T`, or return `None` if none has been registered yet.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn get<T: Any + Send + Sync>(&self, order: Ordering) -> Option<Xarc<T>> {
+        let entry = self.find(&self.buckets[bucket::<T>()], TypeId::of::<T>())?;
+        entry.value.load(order).downcast().ok()
+    }
+
+    /// Check whether a value has been registered for `T`.
+    #[must_use]
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.find(&self.buckets[bucket::<T>()], TypeId::of::<T>()).is_some()
+    }
+
+    /// Allocate `value` and publish it as the value registered for `T`, returning the one it replaced, if any.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap<T: Any + Send + Sync>(&self, value: T, order: Ordering) -> Option<Xarc<T>> {
+        let new: Xarc<dyn Any + Send + Sync> = crate::xarc_dyn!(value);
+        let type_id = TypeId::of::<T>();
+        let head = &self.buckets[bucket::<T>()];
+        loop {
+            if let Some(entry) = self.find(head, type_id) {
+                return entry.value.swap(&new, order).downcast().ok();
+            }
+            let current = head.load(Ordering::Acquire);
+            let entry = Xarc::new(Entry {
+                type_id,
+                value: AtomicXarc::from(&new),
+                next: current.clone(),
+            });
+            // Another thread may register the same type first, in which case the search starts over and finds its entry.
+            if head.compare_exchange(&current, &entry, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                return None;
+            }
+        }
+    }
+
+    /// Find the entry for `type_id` in the bucket starting at `head`.
+    fn find(&self, head: &AtomicXarc<Entry>, type_id: TypeId) -> Option<&Entry> {
+        let mut next = head.load_raw(Ordering::Acquire);
+        while let Some(entry) = unsafe {
+            // Entries live as long as `self` since they are never unlinked.
+            next.as_ref()
+        } {
+            if entry.type_id == type_id {
+                return Some(entry);
+            }
+            next = entry.next.as_raw();
+        }
+        None
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len: usize = self.buckets.iter().map(|head| {
+            let mut len = 0;
+            let mut next = head.load_raw(Ordering::Acquire);
+            while let Some(entry) = unsafe {
+                next.as_ref()
+            } {
+                len += 1;
+                next = entry.next.as_raw();
+            }
+            len
+        }).sum();
+        f.debug_struct("Registry").field("len", &len).finish()
+    }
+}
+
+/// Pick the bucket for `T` by hashing its `TypeId`.
+fn bucket<T: Any>() -> usize {
+    fnv_hash(&TypeId::of::<T>()) as usize % BUCKETS
+}
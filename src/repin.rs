@@ -0,0 +1,165 @@
+// A WebAssembly module built without threads never keeps a pin between operations. See `PinGuard`.
+#![cfg_attr(all(target_family = "wasm", not(target_feature = "atomics")), allow(dead_code))]
+
+use crossbeam_epoch::Guard;
+#[cfg(all(target_family = "wasm", not(target_feature = "atomics")))]
+use core::{ops::Deref, sync::atomic::AtomicUsize as DepthCounter};
+#[cfg(feature = "std")]
+use core::{cell::RefCell, sync::atomic::{AtomicBool, AtomicUsize, Ordering}};
+#[cfg(feature = "std")]
+use std::thread_local;
+
+/// How many operations a thread performs under one pin of the epoch. 1 pins for every operation.
+#[cfg(feature = "std")]
+static INTERVAL: AtomicUsize = AtomicUsize::new(1);
+/// Set once an interval above 1 has been chosen, so that threads only look for a pin to drop after that.
+#[cfg(feature = "std")]
+static AMORTIZED: AtomicBool = AtomicBool::new(false);
+
+/// The pin a thread keeps between operations, and how many operations it has been used for.
+#[cfg(feature = "std")]
+struct Held {
+    guard: Guard,
+    operations: usize,
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static HELD: RefCell<Option<Held>> = const { RefCell::new(None) };
+}
+
+/// Keep each thread pinned across `interval` operations rather than pinning and unpinning for every one.
+///
+/// Pinning the epoch costs a `SeqCst` fence, but pinning a thread that is already pinned only bumps a counter.
+/// With an interval above 1, a thread stays pinned after an operation and unpins and pins again only every `interval` operations,
+/// as `crossbeam_epoch::Guard::repin_after` does for a single guard.
+/// In exchange, a thread holds up reclamation for every thread until its next repin, and indefinitely if it stops using `xarc`:
+/// call `xarc::quiescent` before a thread goes idle or blocks, which drops the pin it kept.
+///
+/// The interval applies to every thread and can be changed at any time, taking effect at each thread's next operation.
+/// An interval of 0 or 1, the default, restores pinning for every operation.
+/// Requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Xarc};
+///
+/// xarc::set_repin_interval(64);
+/// let counter = AtomicXarc::new(0);
+/// for _ in 0..1000 {
+///     let current = counter.load(Ordering::Acquire);
+///     let _ = counter.swap(&Xarc::new(current.maybe_deref().unwrap() + 1), Ordering::AcqRel);
+/// }
+/// xarc::set_repin_interval(1);
+/// xarc::quiescent();
+/// assert_eq!(counter.load(Ordering::Acquire).maybe_deref(), Some(&1000));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_repin_interval(interval: usize) {
+    if interval > 1 {
+        AMORTIZED.store(true, Ordering::Relaxed);
+    }
+    INTERVAL.store(interval.max(1), Ordering::Relaxed);
+}
+
+/// The interval chosen with `set_repin_interval`.
+#[cfg(feature = "std")]
+#[must_use]
+pub fn repin_interval() -> usize {
+    INTERVAL.load(Ordering::Relaxed)
+}
+
+/// How many operations are in progress on the only thread of a WebAssembly module built without threads.
+#[cfg(all(target_family = "wasm", not(target_feature = "atomics")))]
+static DEPTH: DepthCounter = DepthCounter::new(0);
+
+/// The pin for one operation on the only thread of a WebAssembly module built without threads.
+///
+/// With no other thread to read a value, only an operation still in progress on this one can hold it without a count,
+/// as `AtomicXarc::with` does while its closure runs, or a caller that has pinned the epoch itself, as `pin_scope` does.
+/// So an operation that starts while no other is in progress and the thread is not pinned skips pinning,
+/// and values it releases are reclaimed at once, while any other operation pins the epoch and defers as usual.
+#[cfg(all(target_family = "wasm", not(target_feature = "atomics")))]
+pub(crate) struct PinGuard {
+    guard: Option<Guard>,
+}
+
+#[cfg(all(target_family = "wasm", not(target_feature = "atomics")))]
+impl Deref for PinGuard {
+    type Target = Guard;
+
+    fn deref(&self) -> &Guard {
+        match &self.guard {
+            Some(guard) => guard,
+            // Deferring through the unprotected guard runs the destructor immediately.
+            None => unsafe { crossbeam_epoch::unprotected() },
+        }
+    }
+}
+
+#[cfg(all(target_family = "wasm", not(target_feature = "atomics")))]
+impl Drop for PinGuard {
+    fn drop(&mut self) {
+        DEPTH.fetch_sub(1, core::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Skip pinning if no other operation is in progress on the only thread and nothing else holds a pin. See `PinGuard`.
+#[cfg(all(target_family = "wasm", not(target_feature = "atomics")))]
+#[must_use]
+pub(crate) fn pin() -> PinGuard {
+    // A `PinScope`, a `ManualCollector` collecting, or a `Guard` the caller took may still reach what is released.
+    let nested = DEPTH.fetch_add(1, core::sync::atomic::Ordering::Relaxed) > 0 || crossbeam_epoch::is_pinned();
+    PinGuard {
+        guard: if nested { Some(crossbeam_epoch::pin()) } else { None },
+    }
+}
+
+/// Pin the epoch for one operation, reusing the pin the thread kept if the repin interval allows it.
+#[cfg(not(all(target_family = "wasm", not(target_feature = "atomics"))))]
+#[must_use]
+pub(crate) fn pin() -> Guard {
+    #[cfg(feature = "std")]
+    if AMORTIZED.load(Ordering::Relaxed) {
+        let interval = INTERVAL.load(Ordering::Relaxed);
+        // The thread-local may already be gone while the thread exits, in which case the operation just pins for itself.
+        let _ = HELD.try_with(|held| {
+            // Pinning may run deferred destructors that drop `Xarc`s and come back here, so nothing stays borrowed meanwhile.
+            let taken = held.borrow_mut().take();
+            let kept = match taken {
+                Some(_) if interval <= 1 => None,
+                Some(mut kept) => {
+                    kept.operations += 1;
+                    if kept.operations >= interval {
+                        kept.operations = 0;
+                        kept.guard.repin();
+                    }
+                    Some(kept)
+                },
+                None if interval > 1 => Some(Held {
+                    guard: crossbeam_epoch::pin(),
+                    operations: 0,
+                }),
+                None => None,
+            };
+            *held.borrow_mut() = kept;
+        });
+    }
+    crossbeam_epoch::pin()
+}
+
+/// Initialize the thread-local state, so that the first operation on the thread does not pay for it.
+pub(crate) fn register() {
+    #[cfg(feature = "std")]
+    let _ = HELD.try_with(|_| ());
+}
+
+/// Drop the pin the thread kept, if any, before waiting for the epoch to advance.
+pub(crate) fn release() {
+    #[cfg(feature = "std")]
+    if AMORTIZED.load(Ordering::Relaxed) {
+        let _ = HELD.try_with(|held| held.borrow_mut().take());
+    }
+}
@@ -0,0 +1,274 @@
+use super::{atomic::*, internal::back_off, ordering::force, pointer::*};
+use core::{fmt, ops::Deref, sync::atomic::{AtomicBool, Ordering}};
+use crossbeam_utils::{Backoff, CachePadded};
+
+/// `XarcRwLock` replaces a reader-writer lock for values that are read far more often than they are written.
+///
+/// Readers never block: `read` returns an `Xarc` snapshot of the current value, which stays valid and unchanged
+/// for as long as it is held, however many writes happen in the meantime.
+/// Writers never modify a value in place. They build a new one and publish it atomically, so readers see either all of a write or none of it.
+///
+/// Writes come in two kinds:
+/// - `update` is lock-free. It computes the new value from the current one and compare-exchanges it into place,
+///   calling `f` again with the latest value if another writer got there first.
+/// - `write` and `lock` are serialized against each other by a flag, so `f` sees every earlier serialized write
+///   and runs once unless an `update` or `replace` intervenes. They suit expensive writes that should rarely be retried,
+///   at the cost of waiting for the writer ahead.
+///
+/// `replace` publishes a value unconditionally. A serialized write publishes with a compare-exchange against the value it was computed from,
+/// so one that races with `update` or `replace` is computed again from their value rather than discarding it.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::XarcRwLock;
+///
+/// let routes = XarcRwLock::new(vec!["/"]);
+/// let before = routes.read();
+///
+/// routes.write(|current| {
+///     let mut next = current.clone();
+///     next.push("/health");
+///     next
+/// });
+/// routes.update(|current| current.iter().copied().filter(|route| *route != "/").collect());
+///
+/// assert_eq!(*before.maybe_deref().unwrap(), ["/"]);
+/// assert_eq!(*routes.read().maybe_deref().unwrap(), ["/health"]);
+/// ```
+pub struct XarcRwLock<T: Send> {
+    writer: CachePadded<AtomicBool>,
+    slot: AtomicXarc<T>,
+}
+
+impl<T: Send> XarcRwLock<T> {
+    /// Initialize the lock with `value`.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        XarcRwLock {
+            writer: CachePadded::new(AtomicBool::new(false)),
+            slot: AtomicXarc::new(value),
+        }
+    }
+
+    /// Load a snapshot of the current value.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn read(&self) -> Xarc<T> {
+        self.slot.load(Ordering::Acquire)
+    }
+
+    /// Run `f` on the current value without taking a reference to it.
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        self.slot.with(Ordering::Acquire, |value| f(value.unwrap()))
+    }
+
+    /// Publish `value` unconditionally, returning the value it replaced.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn replace(&self, value: T) -> Xarc<T> {
+        self.slot.swap(&Xarc::new(value), Ordering::AcqRel)
+    }
+
+    /// Publish the result of `f` on the current value, retrying with the latest value if another writer intervenes.
+    /// `f` may be called several times. Returns the value that was replaced.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn update<F: FnMut(&T) -> T>(&self, mut f: F) -> Xarc<T> {
+        match self.slot.cas_loop(|current| Some(Xarc::new(f(current.maybe_deref().unwrap()))), Ordering::AcqRel, Ordering::Acquire) {
+            Ok(previous) => previous,
+            Err(_) => unreachable!("the replacement is never declined"),
+        }
+    }
+
+    /// Wait for any other serialized writer to finish, then publish the result of `f` on the current value.
+    /// `f` is called again with the latest value if `update` or `replace` publishes one in the meantime.
+    /// Returns the value that was replaced.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn write<F: FnMut(&T) -> T>(&self, mut f: F) -> Xarc<T> {
+        let mut guard = self.lock();
+        loop {
+            let value = f(&guard);
+            match guard.publish(value) {
+                Ok(previous) => return previous,
+                Err(latest) => guard = latest,
+            }
+        }
+    }
+
+    /// Wait for any other serialized writer to finish and hold off the next one until the guard is published or dropped.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn lock(&self) -> XarcRwLockWriteGuard<'_, T> {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            while self.writer.load(force(Ordering::Relaxed)) {
+                back_off(&backoff);
+            }
+        }
+    }
+
+    /// As `lock`, but return None rather than waiting if another serialized writer is in progress.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn try_lock(&self) -> Option<XarcRwLockWriteGuard<'_, T>> {
+        if self.writer.swap(true, force(Ordering::Acquire)) {
+            None
+        }
+        else {
+            Some(XarcRwLockWriteGuard {
+                lock: self,
+                current: self.read(),
+            })
+        }
+    }
+
+    /// Give up the lock for the `AtomicXarc` behind it.
+    #[must_use]
+    pub fn into_inner(self) -> AtomicXarc<T> {
+        self.slot
+    }
+}
+
+impl<T: Send + Default> Default for XarcRwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Send> From<T> for XarcRwLock<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for XarcRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with(|value| f.debug_tuple("XarcRwLock").field(value).finish())
+    }
+}
+
+/// The right to make the next serialized write to an `XarcRwLock`, returned by `lock`.
+///
+/// It dereferences to the value that was current when it was taken, which only `update` and `replace` can change while it is held,
+/// in which case `publish` reports the conflict rather than overwriting their value.
+/// Publishing a new value or dropping the guard lets the next serialized writer in.
+pub struct XarcRwLockWriteGuard<'a, T: Send> {
+    lock: &'a XarcRwLock<T>,
+    current: Xarc<T>,
+}
+
+impl<T: Send> XarcRwLockWriteGuard<'_, T> {
+    /// The value that was current when the guard was taken.
+    #[must_use]
+    pub fn current(&self) -> &Xarc<T> {
+        &self.current
+    }
+
+    /// Publish `value` if `current` is still the current value, returning the value it replaced, and let the next serialized writer in.
+    ///
+    /// If `update` or `replace` has published another value since, `value` is dropped and the guard is returned, still holding off
+    /// other serialized writers, with `current` refreshed to the latest value so that the write can be computed again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xarc::XarcRwLock;
+    ///
+    /// let counter = XarcRwLock::new(0);
+    /// let guard = counter.lock();
+    /// let _ = counter.update(|count| count + 10);
+    ///
+    /// let next = *guard + 1;
+    /// let guard = guard.publish(next).unwrap_err();
+    /// assert_eq!(guard.current().maybe_deref(), Some(&10));
+    /// let next = *guard + 1;
+    /// assert_eq!(guard.publish(next).unwrap().maybe_deref(), Some(&10));
+    /// assert_eq!(counter.read().maybe_deref(), Some(&11));
+    /// ```
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn publish(mut self, value: T) -> Result<Xarc<T>, Self> {
+        match self.lock.slot.compare_exchange(&self.current, &Xarc::new(value), Ordering::AcqRel, Ordering::Acquire) {
+            Ok(previous) => Ok(previous),
+            Err(latest) => {
+                self.current = latest;
+                Err(self)
+            },
+        }
+    }
+}
+
+impl<T: Send> Deref for XarcRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.current.non_null()
+    }
+}
+
+impl<T: Send> Drop for XarcRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.writer.store(false, force(Ordering::Release));
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for XarcRwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("XarcRwLockWriteGuard").field(self.current.non_null()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_rw_lock_publish_mt_test() {
+        extern crate std;
+        use std::thread;
+
+        // Serialized and unserialized writes to the same lock race, and neither may lose the other's increments.
+        let lock = XarcRwLock::new(0);
+        thread::scope(|scope| {
+            for serialized in [false, true, false, true] {
+                let lock = &lock;
+                scope.spawn(move || {
+                    for _ in 0..500 {
+                        let _ = if serialized {
+                            lock.write(|count| count + 1)
+                        }
+                        else {
+                            lock.update(|count| count + 1)
+                        };
+                    }
+                });
+            }
+        });
+        assert_eq!(lock.read().maybe_deref(), Some(&2000));
+    }
+
+    #[test]
+    fn xarc_rw_lock_mt_test() {
+        use rayon::prelude::*;
+
+        let serialized = XarcRwLock::new(0);
+        let unserialized = XarcRwLock::new(0);
+        (0..10000).into_par_iter().for_each(|i| {
+            match i % 10 {
+                0 => {
+                    let _ = serialized.write(|count| count + 1);
+                },
+                1 => {
+                    let _ = unserialized.update(|count| count + 1);
+                },
+                _ => {
+                    assert!(serialized.read().maybe_deref().is_some_and(|value| *value <= 1000));
+                    assert!(unserialized.read().maybe_deref().is_some_and(|value| *value <= 1000));
+                },
+            }
+        });
+        assert_eq!(serialized.read().maybe_deref(), Some(&1000));
+        assert_eq!(unserialized.read().maybe_deref(), Some(&1000));
+    }
+}
@@ -0,0 +1,271 @@
+//! Recording and replaying the order of atomic operations, enabled by the `schedule` feature.
+//!
+//! A bug in lock-free code often shows up only under one interleaving of threads, which may never recur on a developer's machine.
+//! While recording, every successful load, swap and compare-exchange on an `AtomicXarc` is appended to a ring buffer
+//! as a `Step` naming the thread, the slot and the value.
+//! A schedule taken from a failing run, such as one saved by a production canary, can then be replayed:
+//! each operation first waits at a yield point until the next step of the schedule belongs to its thread,
+//! so that successful operations happen in the recorded order.
+//!
+//! Threads are told apart by ids given with `set_thread`, which should be the same in the replaying run as in the recorded one.
+//! A thread that never calls it is numbered from 2^31 in the order it first reaches a yield point.
+//! Slots and values are identified by address, which differs from run to run, so they are recorded for diagnosis and not matched.
+//!
+//! A replay that cannot follow the schedule, because a thread performs an operation other than the one recorded
+//! or because no thread takes the next step within the patience given to `replay`, stops waiting and lets every thread run freely.
+//! `finish` reports where that happened. A schedule from a ring buffer that wrapped starts partway through the run
+//! and diverges unless the replay starts at the same point.
+//!
+//! # Examples
+//!
+//! ```
+//! use core::sync::atomic::Ordering;
+//! use std::{sync::Arc, thread, time::Duration};
+//! use xarc::{AtomicXarc, Xarc, schedule};
+//!
+//! fn run() -> i32 {
+//!     let atomic = Arc::new(AtomicXarc::new(0));
+//!     let threads: Vec<_> = (0..2).map(|id| {
+//!         let atomic = atomic.clone();
+//!         thread::spawn(move || {
+//!             schedule::set_thread(id);
+//!             let current = atomic.load(Ordering::Acquire);
+//!             let _ = atomic.compare_exchange(&current, &Xarc::new(10 * current.maybe_deref().unwrap() + id as i32 + 1), Ordering::AcqRel, Ordering::Acquire);
+//!         })
+//!     }).collect();
+//!     threads.into_iter().for_each(|thread| thread.join().unwrap());
+//!     let result = *atomic.load(Ordering::Acquire).maybe_deref().unwrap();
+//!     result
+//! }
+//!
+//! schedule::record(1024);
+//! let recorded = run();
+//! let steps = schedule::take();
+//!
+//! schedule::replay(steps, Duration::from_secs(10));
+//! let replayed = run();
+//! assert!(schedule::finish().is_ok());
+//! assert_eq!(replayed, recorded);
+//! ```
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{cell::Cell, fmt, sync::atomic::{AtomicU32, AtomicU8, Ordering}, time::Duration};
+use std::{sync::{Mutex, MutexGuard}, thread_local, time::Instant};
+
+/// An atomic operation that can be recorded.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Op {
+    /// A load into an `Xarc`.
+    Load,
+    /// A swap.
+    Swap,
+    /// A successful compare-exchange.
+    CompareExchange,
+}
+
+/// One successful atomic operation in a schedule.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Step {
+    /// The id of the thread that performed the operation. See `set_thread`.
+    pub thread: u32,
+    /// The operation.
+    pub op: Op,
+    /// The address of the `AtomicXarc`.
+    pub slot: usize,
+    /// The address of the value that was loaded or stored, or 0 for null.
+    pub pointer: usize,
+}
+
+/// The error returned by `finish` when a replay could not follow its schedule.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Diverged {
+    /// The index of the first step that could not be reproduced.
+    pub step: usize,
+}
+
+impl fmt::Display for Diverged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "replay diverged from its schedule at step {}", self.step)
+    }
+}
+
+impl std::error::Error for Diverged {}
+
+const IDLE: u8 = 0;
+const RECORDING: u8 = 1;
+const REPLAYING: u8 = 2;
+
+/// Checked before taking the lock so that operations cost one load while nothing is recorded or replayed.
+static MODE: AtomicU8 = AtomicU8::new(IDLE);
+static NEXT_THREAD: AtomicU32 = AtomicU32::new(1 << 31);
+
+struct State {
+    capacity: usize,
+    recorded: VecDeque<Step>,
+    schedule: Vec<Step>,
+    cursor: usize,
+    patience: Duration,
+    diverged: Option<usize>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    capacity: 0,
+    recorded: VecDeque::new(),
+    schedule: Vec::new(),
+    cursor: 0,
+    patience: Duration::ZERO,
+    diverged: None,
+});
+
+thread_local! {
+    static THREAD: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+fn lock() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Give the calling thread the id that its steps are recorded and replayed under.
+pub fn set_thread(id: u32) {
+    THREAD.with(|thread| thread.set(Some(id)));
+}
+
+#[must_use]
+fn thread() -> u32 {
+    THREAD.with(|thread| match thread.get() {
+        Some(id) => id,
+        None => {
+            let id = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+            thread.set(Some(id));
+            id
+        },
+    })
+}
+
+/// Start recording, keeping the last `capacity` steps and discarding any recorded before.
+/// Stops a replay in progress.
+pub fn record(capacity: usize) {
+    let mut state = lock();
+    state.capacity = capacity;
+    state.recorded = VecDeque::with_capacity(capacity);
+    state.schedule = Vec::new();
+    state.diverged = None;
+    MODE.store(RECORDING, Ordering::Relaxed);
+}
+
+/// Stop recording and return the steps recorded, oldest first.
+#[must_use]
+pub fn take() -> Vec<Step> {
+    let mut state = lock();
+    if MODE.load(Ordering::Relaxed) == RECORDING {
+        MODE.store(IDLE, Ordering::Relaxed);
+    }
+    state.recorded.drain(..).collect()
+}
+
+/// Start replaying `schedule`, waiting up to `patience` at each yield point for the step that is due before giving up on it.
+/// Stops a recording in progress.
+pub fn replay(schedule: Vec<Step>, patience: Duration) {
+    let mut state = lock();
+    state.recorded.clear();
+    state.schedule = schedule;
+    state.cursor = 0;
+    state.patience = patience;
+    state.diverged = None;
+    MODE.store(REPLAYING, Ordering::Relaxed);
+}
+
+/// Stop replaying, reporting whether every step of the schedule was reproduced in order.
+pub fn finish() -> Result<(), Diverged> {
+    let state = lock();
+    if MODE.load(Ordering::Relaxed) == REPLAYING {
+        MODE.store(IDLE, Ordering::Relaxed);
+    }
+    match state.diverged {
+        Some(step) => Err(Diverged { step }),
+        None if state.cursor < state.schedule.len() => Err(Diverged { step: state.cursor }),
+        None => Ok(()),
+    }
+}
+
+/// The yield point before an operation on a slot: wait until the next step of a replayed schedule belongs to the calling thread.
+pub(crate) fn turn() {
+    if MODE.load(Ordering::Relaxed) != REPLAYING {
+        return;
+    }
+    let thread = thread();
+    let mut deadline = None;
+    loop {
+        {
+            let mut state = lock();
+            if state.diverged.is_some() || state.schedule.get(state.cursor).is_none_or(|step| step.thread == thread) {
+                return;
+            }
+            let patience = state.patience;
+            if Instant::now() >= *deadline.get_or_insert_with(|| Instant::now() + patience) {
+                state.diverged = Some(state.cursor);
+                return;
+            }
+        }
+        std::thread::yield_now();
+    }
+}
+
+/// Note that `op` has just succeeded on the slot at `slot`, leaving or returning the value at `pointer`.
+pub(crate) fn done(op: Op, slot: usize, pointer: usize) {
+    match MODE.load(Ordering::Relaxed) {
+        RECORDING => {
+            let step = Step { thread: thread(), op, slot, pointer };
+            let mut state = lock();
+            if state.recorded.len() == state.capacity {
+                state.recorded.pop_front();
+            }
+            if state.capacity > 0 {
+                state.recorded.push_back(step);
+            }
+        },
+        REPLAYING => {
+            let thread = thread();
+            let mut state = lock();
+            if state.diverged.is_some() {
+                return;
+            }
+            match state.schedule.get(state.cursor) {
+                Some(step) if step.thread == thread && step.op == op => state.cursor += 1,
+                Some(_) => state.diverged = Some(state.cursor),
+                None => {},
+            }
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{atomic::AtomicXarc, pointer::Xarc};
+    use alloc::vec;
+
+    #[test]
+    fn schedule_record_replay_st_test() {
+        // Other tests run alongside and are recorded too, so only the steps of this thread on this slot are looked at.
+        set_thread(u32::MAX);
+        let atomic = AtomicXarc::new(0);
+        record(1 << 16);
+        let stale = atomic.load(Ordering::Acquire);
+        let _ = atomic.swap(&Xarc::new(1), Ordering::AcqRel);
+        assert!(atomic.compare_exchange(&stale, &Xarc::new(2), Ordering::AcqRel, Ordering::Acquire).is_err());
+        let current = atomic.load(Ordering::Acquire);
+        assert!(atomic.compare_exchange(&current, &Xarc::new(3), Ordering::AcqRel, Ordering::Acquire).is_ok());
+        let steps: Vec<Step> = take().into_iter().filter(|step| step.thread == u32::MAX && step.slot == atomic.address()).collect();
+        // A failed compare-exchange is not a step, since replaying it would not change anything.
+        assert_eq!(steps.iter().map(|step| step.op).collect::<Vec<_>>(), [Op::Load, Op::Swap, Op::Load, Op::CompareExchange]);
+        assert_eq!(steps[1].pointer, steps[2].pointer);
+
+        // A step due from a thread that never runs holds this thread up until the patience runs out, and then the replay gives up.
+        replay(vec![Step { thread: u32::MAX - 1, op: Op::Swap, slot: 0, pointer: 0 }], Duration::from_millis(10));
+        let _ = atomic.swap(&Xarc::new(4), Ordering::AcqRel);
+        assert_eq!(finish(), Err(Diverged { step: 0 }));
+        assert_eq!(atomic.load(Ordering::Acquire).maybe_deref(), Some(&4));
+    }
+}
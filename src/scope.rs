@@ -0,0 +1,67 @@
+use crossbeam_epoch::{Guard, pin};
+
+/// A pinned epoch held across many operations by `pin_scope`.
+///
+/// Every load, swap and drop pins the epoch, which costs a fence unless the thread is already pinned,
+/// in which case pinning again only bumps a counter. While a scope is alive the thread is pinned,
+/// so the operations inside it share that one pin rather than each paying for its own.
+pub struct PinScope {
+    guard: Guard,
+}
+
+impl PinScope {
+    /// Unpin and pin again, letting reclamation advance past this thread partway through a long scope.
+    /// Anything read through a borrowed pointer rather than an `Xarc` must not be used across this call.
+    pub fn repin(&mut self) {
+        self.guard.repin();
+    }
+
+    /// Hand the destructors this thread has deferred to other threads and try to advance the epoch,
+    /// as `xarc::quiescent` does outside a scope. Values cannot be reclaimed until the scope ends or is repinned.
+    pub fn flush(&self) {
+        self.guard.flush();
+    }
+
+    /// Run `f` once every thread pinned now, this scope included, has unpinned. See `xarc::defer`.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn defer<F: FnOnce() + Send + 'static>(&self, f: F) {
+        crate::defer(f);
+    }
+}
+
+impl core::fmt::Debug for PinScope {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PinScope").finish_non_exhaustive()
+    }
+}
+
+/// Run `f` with the epoch pinned once for its whole duration, so that the loads, swaps and drops inside it skip pinning their own.
+///
+/// Nothing released by any thread can be reclaimed while the scope is pinned, so keep scopes to a bounded batch of work,
+/// or call `PinScope::repin` between batches. Operations that wait for the epoch to advance,
+/// `AtomicXarc::load_owned` and `xarc::quiescent`, must not be called inside a scope, and panic in debug builds if they are.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Xarc};
+///
+/// let counter = AtomicXarc::new(0);
+/// xarc::pin_scope(|scope| {
+///     for batch in 0..10 {
+///         for _ in 0..1000 {
+///             let current = counter.load(Ordering::Acquire);
+///             let _ = counter.swap(&Xarc::new(current.maybe_deref().unwrap() + 1), Ordering::AcqRel);
+///         }
+///         scope.repin();
+///     }
+/// });
+/// assert_eq!(counter.load(Ordering::Acquire).maybe_deref(), Some(&10000));
+/// ```
+pub fn pin_scope<R, F: FnOnce(&mut PinScope) -> R>(f: F) -> R {
+    let mut scope = PinScope {
+        guard: pin(),
+    };
+    f(&mut scope)
+}
@@ -0,0 +1,165 @@
+use super::{ordering::force, pointer::*};
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering, fence};
+use crossbeam_utils::CachePadded;
+
+struct ShardedData<T: Send> {
+    active: AtomicUsize,
+    shards: Box<[CachePadded<AtomicUsize>]>,
+    pointer: Xarc<T>,
+}
+
+/// `ShardedXarc` is a dereferenceable smart pointer whose count is split across cache-padded shards.
+///
+/// It is intended for a small number of extremely hot objects, such as global configuration,
+/// where every thread cloning and dropping handles on one count cache line becomes the bottleneck.
+/// Each handle belongs to one shard and its clones and drops only touch that shard.
+/// The shards are only reconciled when a shard drops to zero.
+///
+/// `no_std` offers no notion of the current core, so the caller picks the shard with `clone_to`,
+/// typically using a worker index. `clone` stays on the shard of the handle being cloned.
+/// Use `to_xarc` to store the value in an `AtomicXarc`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::ShardedXarc;
+///
+/// let config = ShardedXarc::new(42, 8);
+/// let handles: Vec<ShardedXarc<i64>> = (0..8).map(|worker| config.clone_to(worker)).collect();
+/// handles.par_iter().for_each(|handle| {
+///     for _ in 0..1000 {
+///         assert_eq!(*handle.clone().maybe_deref().unwrap(), 42);
+///     }
+/// });
+/// ```
+pub struct ShardedXarc<T: Send> {
+    data: *mut ShardedData<T>,
+    shard: usize,
+}
+
+impl<T: Send> ShardedXarc<T> {
+    /// Initialize the smart pointer with `value` and a count split across `shards` shards.
+    ///
+    /// # Panics
+    /// - If `shards` is 0.
+    #[must_use]
+    pub fn new(value: T, shards: usize) -> Self {
+        assert!(shards > 0, "ShardedXarc requires at least one shard!");
+        let shards: Vec<CachePadded<AtomicUsize>> = (0..shards).map(|shard| CachePadded::new(AtomicUsize::new(if shard == 0 { 1 } else { 0 }))).collect();
+        ShardedXarc {
+            data: Box::into_raw(Box::new(ShardedData {
+                active: AtomicUsize::new(1),
+                shards: shards.into_boxed_slice(),
+                pointer: Xarc::new(value),
+            })),
+            shard: 0,
+        }
+    }
+
+    /// Clone the handle onto shard `shard` modulo the number of shards.
+    #[must_use]
+    pub fn clone_to(&self, shard: usize) -> Self {
+        let data = self.data();
+        let shard = shard % data.shards.len();
+        let count = &data.shards[shard];
+        let mut current = count.load(force(Ordering::Relaxed));
+        loop {
+            if current > 0 {
+                match count.compare_exchange_weak(current, current + 1, force(Ordering::Relaxed), force(Ordering::Relaxed)) {
+                    Ok(_) => break,
+                    Err(c) => current = c,
+                }
+            }
+            else {
+                // Activate the shard before it can be observed as nonzero so that a concurrent
+                // deactivation can never bring `active` down to zero while handles remain.
+                data.active.fetch_add(1, force(Ordering::Relaxed));
+                match count.compare_exchange(0, 1, force(Ordering::Release), force(Ordering::Relaxed)) {
+                    Ok(_) => break,
+                    Err(c) => {
+                        data.active.fetch_sub(1, force(Ordering::Relaxed));
+                        current = c;
+                    },
+                }
+            }
+        }
+        ShardedXarc {
+            data: self.data,
+            shard,
+        }
+    }
+
+    /// The shard this handle belongs to.
+    #[must_use]
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
+    /// The number of shards the count is split across.
+    #[must_use]
+    pub fn shards(&self) -> usize {
+        self.data().shards.len()
+    }
+
+    /// Dereference the pointer.
+    /// None is never returned since a `ShardedXarc` cannot be null, but the signature mirrors `Xarc`.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        self.data().pointer.maybe_deref()
+    }
+
+    /// Produce an `Xarc` pointing to the same value.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn to_xarc(&self) -> Xarc<T> {
+        self.data().pointer.clone()
+    }
+
+    #[must_use]
+    fn data(&self) -> &ShardedData<T> {
+        unsafe {
+            &*self.data
+        }
+    }
+}
+
+impl<T: Send> Clone for ShardedXarc<T> {
+    fn clone(&self) -> Self {
+        self.data().shards[self.shard].fetch_add(1, force(Ordering::Relaxed));
+        ShardedXarc {
+            data: self.data,
+            shard: self.shard,
+        }
+    }
+}
+
+impl<T: Send> Drop for ShardedXarc<T> {
+    fn drop(&mut self) {
+        let data = self.data();
+        if data.shards[self.shard].fetch_sub(1, force(Ordering::AcqRel)) == 1 && data.active.fetch_sub(1, force(Ordering::Release)) == 1 {
+            fence(force(Ordering::Acquire));
+            unsafe {
+                drop(Box::from_raw(self.data));
+            }
+        }
+    }
+}
+
+impl<T: Send> PartialEq for ShardedXarc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl<T: Send> Eq for ShardedXarc<T> {}
+
+impl<T: Send> core::fmt::Debug for ShardedXarc<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ShardedXarc").field("data", &self.data).field("shard", &self.shard).finish()
+    }
+}
+
+unsafe impl<T: Send> Send for ShardedXarc<T> {}
+unsafe impl<T: Send> Sync for ShardedXarc<T> {}
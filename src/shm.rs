@@ -0,0 +1,574 @@
+use super::error::{AllocError, ShmError};
+use core::{fmt, marker::PhantomData, mem, ptr, sync::atomic::{AtomicU32, AtomicU64, Ordering, fence}};
+
+/// Identifies a formatted segment, so that `attach` can reject memory that was never formatted.
+const MAGIC: u64 = 0x7861_7263_5F73_686D;
+
+/// The number of roots in every segment.
+pub const SHM_ROOTS: usize = 16;
+
+/// Blocks, and the values in them, are aligned to this many bytes relative to the start of the segment.
+const ALIGN: usize = 16;
+
+/// The start of every segment. Everything in it is addressed by offset, so it means the same wherever the segment is mapped.
+#[repr(C)]
+struct SegmentHeader {
+    /// Written last by `create`, so a segment that carries it is fully formatted.
+    magic: AtomicU64,
+    len: u64,
+    block_size: u32,
+    first_block: u32,
+    /// The offset of the first free block in the low half and a tag incremented by every change in the high half,
+    /// so that a block freed and reallocated between reading the head and replacing it cannot be mistaken for the same head.
+    free: AtomicU64,
+    roots: [AtomicU64; SHM_ROOTS],
+}
+
+/// The start of every block. The value follows at the next multiple of `ALIGN`.
+#[repr(C)]
+struct BlockHeader {
+    count: AtomicU32,
+    /// Incremented every time the block is allocated, so that a handle to an earlier value never matches a later one.
+    generation: AtomicU32,
+    /// The offset of the next free block while this one is free.
+    next: AtomicU32,
+}
+
+/// Pack the offset of a block and its generation into a handle. The null handle is 0, since no block lies at offset 0.
+#[must_use]
+fn pack(offset: u32, generation: u32) -> u64 {
+    u64::from(offset) | (u64::from(generation) << 32)
+}
+
+#[must_use]
+fn offset(handle: u64) -> u32 {
+    handle as u32
+}
+
+#[must_use]
+fn generation(handle: u64) -> u32 {
+    (handle >> 32) as u32
+}
+
+#[must_use]
+const fn round_up(size: usize) -> usize {
+    size.div_ceil(ALIGN) * ALIGN
+}
+
+/// A type that can be stored in a `ShmSegment` and read by another process.
+///
+/// # Safety
+/// The type must be `Copy`, since no destructor runs when a value is released, and must not contain pointers or references,
+/// since they would mean nothing in another process. Every bit pattern written by one process must be valid in the other,
+/// so both must be built with the same definition of the type.
+pub unsafe trait ShmValue: Copy + Send + Sync + 'static {}
+
+macro_rules! shm_value {
+    ($($t:ty),*) => {
+        $(unsafe impl ShmValue for $t {})*
+    };
+}
+
+shm_value!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+
+unsafe impl<T: ShmValue, const N: usize> ShmValue for [T; N] {}
+
+/// `ShmSegment` is a region of memory, typically shared between processes, that holds `XarcShm` values and `AtomicXarcShm` roots.
+///
+/// The region is split into a header and fixed-size blocks, each holding one reference-counted value.
+/// Every reference within the region is an offset from its start rather than an address, and every count lives in the region,
+/// so processes that map the same region at different addresses can swap and share values through its roots.
+/// Blocks are recycled through a lock-free free list in the header.
+///
+/// Memory is never returned from a block to its process, so the epochs that `Xarc` relies on are not needed:
+/// a load that races with the release of a value may increment the count of a block that has since been reused,
+/// but notices that its generation has changed and lets go again.
+/// A process that exits while holding references leaks the blocks they refer to.
+///
+/// Offsets are 32 bits, so a segment may be at most 4 GiB.
+///
+/// # Examples
+///
+/// ```
+/// use std::alloc::{Layout, alloc_zeroed, dealloc};
+/// use xarc::{ShmSegment, XarcShm};
+///
+/// let layout = Layout::from_size_align(4096, 4096).unwrap();
+/// let (first, second) = unsafe { (alloc_zeroed(layout), alloc_zeroed(layout)) };
+///
+/// let segment = unsafe { ShmSegment::create(first, 4096, 64).unwrap() };
+/// let config = unsafe { segment.root::<[u32; 4]>(0) };
+/// let _ = config.swap(&segment.try_new([1, 2, 3, 4]).unwrap());
+///
+/// // Another process would map the same memory at an address of its own.
+/// unsafe { std::ptr::copy_nonoverlapping(first, second, 4096) };
+/// let other = unsafe { ShmSegment::attach(second, 4096).unwrap() };
+/// let config = unsafe { other.root::<[u32; 4]>(0) };
+/// assert_eq!(config.load().maybe_deref(), Some(&[1, 2, 3, 4]));
+///
+/// unsafe { dealloc(first, layout) };
+/// unsafe { dealloc(second, layout) };
+/// ```
+pub struct ShmSegment {
+    base: *mut u8,
+    len: usize,
+}
+
+impl ShmSegment {
+    /// Format the `len` bytes at `base` as a segment whose blocks can hold values of up to `value_size` bytes.
+    /// Any roots are null.
+    ///
+    /// # Safety
+    /// The region must be valid for reads and writes for as long as the segment and every handle into it live,
+    /// and no other process may use it until this returns.
+    pub unsafe fn create(base: *mut u8, len: usize, value_size: usize) -> Result<Self, ShmError> {
+        if !base.addr().is_multiple_of(ALIGN) {
+            return Err(ShmError::Misaligned);
+        }
+        let first_block = round_up(mem::size_of::<SegmentHeader>());
+        let block_size = round_up(mem::size_of::<BlockHeader>()) + round_up(value_size.max(1));
+        if len > u32::MAX as usize || len < first_block + block_size {
+            return Err(ShmError::BadSize);
+        }
+        let blocks = (len - first_block) / block_size;
+        for index in 0..blocks {
+            let next = if index + 1 < blocks {
+                first_block + (index + 1) * block_size
+            }
+            else {
+                0
+            };
+            ptr::write(base.add(first_block + index * block_size) as *mut BlockHeader, BlockHeader {
+                count: AtomicU32::new(0),
+                generation: AtomicU32::new(0),
+                next: AtomicU32::new(next as u32),
+            });
+        }
+        let header = base as *mut SegmentHeader;
+        ptr::write(header, SegmentHeader {
+            magic: AtomicU64::new(0),
+            len: len as u64,
+            block_size: block_size as u32,
+            first_block: first_block as u32,
+            free: AtomicU64::new(pack(first_block as u32, 0)),
+            roots: [(); SHM_ROOTS].map(|_| AtomicU64::new(0)),
+        });
+        (*header).magic.store(MAGIC, Ordering::Release);
+        Ok(ShmSegment {
+            base,
+            len,
+        })
+    }
+
+    /// Use the `len` bytes at `base` as a segment that another process has already formatted with `create`.
+    ///
+    /// # Safety
+    /// The region must be valid for reads and writes for as long as the segment and every handle into it live,
+    /// and must either be formatted or be at least as large as a segment header.
+    pub unsafe fn attach(base: *mut u8, len: usize) -> Result<Self, ShmError> {
+        if !base.addr().is_multiple_of(ALIGN) {
+            return Err(ShmError::Misaligned);
+        }
+        let header = &*(base as *const SegmentHeader);
+        if header.magic.load(Ordering::Acquire) != MAGIC || header.len != len as u64 {
+            return Err(ShmError::NotFormatted);
+        }
+        Ok(ShmSegment {
+            base,
+            len,
+        })
+    }
+
+    /// Move `value` into a free block, or fail if every block is in use.
+    ///
+    /// # Panics
+    /// - If `T` is larger than the blocks of the segment or needs alignment to more than 16 bytes.
+    pub fn try_new<T: ShmValue>(&self, value: T) -> Result<XarcShm<'_, T>, AllocError> {
+        assert!(mem::size_of::<T>() <= self.header().block_size as usize - round_up(mem::size_of::<BlockHeader>())
+            && mem::align_of::<T>() <= ALIGN, "xarc: value does not fit in a block of this segment");
+        let offset = self.pop().ok_or(AllocError)?;
+        let block = self.block(offset);
+        let generation = block.generation.load(Ordering::Relaxed).wrapping_add(1);
+        // A load holding an old handle to this block checks the generation after incrementing the count,
+        // so the generation must change before the count does.
+        block.generation.store(generation, Ordering::Relaxed);
+        unsafe {
+            ptr::write(self.value::<T>(offset) as *mut T, value);
+        }
+        block.count.store(1, Ordering::Release);
+        Ok(XarcShm {
+            segment: self,
+            handle: pack(offset, generation),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Get the root at `index`, through which processes share a value.
+    ///
+    /// # Safety
+    /// Every process must use the root with the same `T`.
+    ///
+    /// # Panics
+    /// - If `index` is not less than `SHM_ROOTS`.
+    #[must_use]
+    pub unsafe fn root<T: ShmValue>(&self, index: usize) -> AtomicXarcShm<'_, T> {
+        AtomicXarcShm {
+            segment: self,
+            slot: &self.header().roots[index],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of blocks in the segment, which is the number of values it can hold at once.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        let header = self.header();
+        (self.len - header.first_block as usize) / header.block_size as usize
+    }
+
+    #[must_use]
+    fn header(&self) -> &SegmentHeader {
+        unsafe {
+            &*(self.base as *const SegmentHeader)
+        }
+    }
+
+    #[must_use]
+    fn block(&self, offset: u32) -> &BlockHeader {
+        unsafe {
+            &*(self.base.add(offset as usize) as *const BlockHeader)
+        }
+    }
+
+    #[must_use]
+    fn value<T>(&self, offset: u32) -> *const T {
+        unsafe {
+            self.base.add(offset as usize + round_up(mem::size_of::<BlockHeader>())) as *const T
+        }
+    }
+
+    /// Take a block from the free list.
+    #[must_use]
+    fn pop(&self) -> Option<u32> {
+        let free = &self.header().free;
+        let mut head = free.load(Ordering::Acquire);
+        loop {
+            if offset(head) == 0 {
+                return None;
+            }
+            // The block may have been taken and reused since `head` was read, in which case the tag has changed and the exchange fails.
+            let next = self.block(offset(head)).next.load(Ordering::Relaxed);
+            match free.compare_exchange_weak(head, pack(next, generation(head).wrapping_add(1)), Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Some(offset(head)),
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Return a block whose count has reached zero to the free list.
+    fn push(&self, offset: u32) {
+        let free = &self.header().free;
+        let mut head = free.load(Ordering::Relaxed);
+        loop {
+            self.block(offset).next.store(self::offset(head), Ordering::Relaxed);
+            match free.compare_exchange_weak(head, pack(offset, generation(head).wrapping_add(1)), Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Take a reference to the value behind `handle`, unless it has been released, in which case nothing changes.
+    #[must_use]
+    fn try_acquire(&self, handle: u64) -> bool {
+        let block = self.block(offset(handle));
+        let mut count = block.count.load(Ordering::Relaxed);
+        loop {
+            if count == 0 {
+                return false;
+            }
+            match block.count.compare_exchange_weak(count, count + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => count = current,
+            }
+        }
+        if block.generation.load(Ordering::Acquire) == generation(handle) {
+            true
+        }
+        else {
+            // The block has been reused for another value, and the reference just taken belongs to it.
+            self.release(offset(handle));
+            false
+        }
+    }
+
+    /// Give up a reference to the value in the block at `offset`, freeing the block if it was the last.
+    fn release(&self, offset: u32) {
+        if self.block(offset).count.fetch_sub(1, Ordering::Release) == 1 {
+            fence(Ordering::Acquire);
+            self.push(offset);
+        }
+    }
+}
+
+impl fmt::Debug for ShmSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmSegment").field("base", &self.base).field("len", &self.len).finish()
+    }
+}
+
+unsafe impl Send for ShmSegment {}
+unsafe impl Sync for ShmSegment {}
+
+/// `XarcShm` is a reference to a value in a `ShmSegment`, counted in the segment so that other processes see it. See `ShmSegment`.
+pub struct XarcShm<'s, T: ShmValue> {
+    segment: &'s ShmSegment,
+    handle: u64,
+    phantom: PhantomData<T>,
+}
+
+impl<'s, T: ShmValue> XarcShm<'s, T> {
+    /// Initialize the smart pointer with null.
+    #[must_use]
+    pub fn null(segment: &'s ShmSegment) -> Self {
+        XarcShm {
+            segment,
+            handle: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Check if the pointer is null.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.handle == 0
+    }
+
+    /// Dereference the pointer only if it is not null.
+    /// None will be returned if it is null.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        if self.is_null() {
+            None
+        }
+        else {
+            unsafe {
+                Some(&*self.segment.value::<T>(offset(self.handle)))
+            }
+        }
+    }
+
+    /// Get the offset of the value's block from the start of the segment, which is the same in every process, or 0 if it is null.
+    #[must_use]
+    pub fn offset(&self) -> u32 {
+        offset(self.handle)
+    }
+}
+
+impl<T: ShmValue> Clone for XarcShm<'_, T> {
+    fn clone(&self) -> Self {
+        if !self.is_null() {
+            self.segment.block(offset(self.handle)).count.fetch_add(1, Ordering::Relaxed);
+        }
+        XarcShm {
+            segment: self.segment,
+            handle: self.handle,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ShmValue> Drop for XarcShm<'_, T> {
+    fn drop(&mut self) {
+        if !self.is_null() {
+            self.segment.release(offset(self.handle));
+        }
+    }
+}
+
+impl<T: ShmValue> PartialEq for XarcShm<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl<T: ShmValue> Eq for XarcShm<'_, T> {}
+
+impl<T: ShmValue + fmt::Debug> fmt::Debug for XarcShm<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("XarcShm").field(&self.maybe_deref()).finish()
+    }
+}
+
+unsafe impl<T: ShmValue> Send for XarcShm<'_, T> {}
+unsafe impl<T: ShmValue> Sync for XarcShm<'_, T> {}
+
+/// `AtomicXarcShm` is one of the roots of a `ShmSegment`, through which processes share a value. See `ShmSegment::root`.
+///
+/// Every operation uses acquire and release orderings, and the root holds its own reference to its value as `AtomicXarc` does.
+pub struct AtomicXarcShm<'s, T: ShmValue> {
+    segment: &'s ShmSegment,
+    slot: &'s AtomicU64,
+    phantom: PhantomData<T>,
+}
+
+impl<'s, T: ShmValue> AtomicXarcShm<'s, T> {
+    /// Load the value into an `XarcShm`, retrying if it is released before a reference can be taken.
+    #[must_use]
+    pub fn load(&self) -> XarcShm<'s, T> {
+        loop {
+            let handle = self.slot.load(Ordering::Acquire);
+            if handle == 0 || self.segment.try_acquire(handle) {
+                return self.wrap(handle);
+            }
+        }
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`.
+    /// Returns the previous value.
+    ///
+    /// # Panics
+    /// - If `new` belongs to another segment.
+    #[must_use]
+    pub fn swap(&self, new: &XarcShm<'s, T>) -> XarcShm<'s, T> {
+        let new = self.check(new).clone();
+        let previous = self.slot.swap(new.handle, Ordering::AcqRel);
+        mem::forget(new);
+        self.wrap(previous)
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new` if `self == current`.
+    /// Returns the previous value on success or the current value on failure.
+    ///
+    /// # Panics
+    /// - If `current` or `new` belongs to another segment.
+    pub fn compare_exchange(&self, current: &XarcShm<'s, T>, new: &XarcShm<'s, T>) -> Result<XarcShm<'s, T>, XarcShm<'s, T>> {
+        let new = self.check(new).clone();
+        match self.slot.compare_exchange(self.check(current).handle, new.handle, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(previous) => {
+                mem::forget(new);
+                Ok(self.wrap(previous))
+            },
+            Err(_) => Err(self.load()),
+        }
+    }
+
+    /// Check if the value is null without loading it.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.slot.load(Ordering::Acquire) == 0
+    }
+
+    #[must_use]
+    fn wrap(&self, handle: u64) -> XarcShm<'s, T> {
+        XarcShm {
+            segment: self.segment,
+            handle,
+            phantom: PhantomData,
+        }
+    }
+
+    #[must_use]
+    fn check<'a>(&self, pointer: &'a XarcShm<'s, T>) -> &'a XarcShm<'s, T> {
+        assert!(ptr::eq(pointer.segment, self.segment), "xarc: XarcShm used with a root of another segment");
+        pointer
+    }
+}
+
+impl<T: ShmValue> fmt::Debug for AtomicXarcShm<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicXarcShm").field("handle", &self.slot.load(Ordering::Relaxed)).finish()
+    }
+}
+
+unsafe impl<T: ShmValue> Send for AtomicXarcShm<'_, T> {}
+unsafe impl<T: ShmValue> Sync for AtomicXarcShm<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{vec, vec::Vec};
+
+    /// Zeroed memory aligned for a segment of `len` bytes.
+    fn memory(len: usize) -> Vec<u128> {
+        vec![0; len / mem::size_of::<u128>()]
+    }
+
+    #[test]
+    fn shm_free_list_reuse_st_test() {
+        let mut memory = memory(4096);
+        let segment = unsafe { ShmSegment::create(memory.as_mut_ptr() as *mut u8, 4096, 8).unwrap() };
+        let values: Vec<_> = (0..segment.capacity()).map(|i| segment.try_new(i as u64).unwrap()).collect();
+        assert!(segment.try_new(0u64).is_err());
+
+        let offset = values[3].offset();
+        let clone = values[3].clone();
+        drop(values);
+        // The clone still holds its block, so it stays out of the free list.
+        assert!((0..segment.capacity() - 1).map(|i| segment.try_new(i as u64).unwrap()).all(|value| value.offset() != offset));
+        drop(clone);
+        let reused: Vec<_> = (0..segment.capacity()).map(|i| segment.try_new(i as u64).unwrap()).collect();
+        assert!(reused.iter().any(|value| value.offset() == offset));
+        assert!(segment.try_new(0u64).is_err());
+    }
+
+    #[test]
+    fn shm_generation_mismatch_st_test() {
+        let mut memory = memory(1024);
+        let segment = unsafe { ShmSegment::create(memory.as_mut_ptr() as *mut u8, 1024, 8).unwrap() };
+        let old = segment.try_new(1u64).unwrap();
+        let stale = old.handle;
+        drop(old);
+        let new = segment.try_new(2u64).unwrap();
+        assert_eq!(offset(new.handle), offset(stale));
+        assert_ne!(new.handle, stale);
+
+        // A load holding the old handle must not take a reference to the new value.
+        assert!(!segment.try_acquire(stale));
+        assert_eq!(segment.block(new.offset()).count.load(Ordering::Relaxed), 1);
+        assert!(segment.try_acquire(new.handle));
+        segment.release(new.offset());
+        assert_eq!(new.maybe_deref(), Some(&2));
+    }
+
+    #[test]
+    fn shm_load_release_mt_test() {
+        extern crate std;
+        use std::thread;
+
+        const LEN: usize = 4096;
+        let mut memory = memory(LEN);
+        let segment = unsafe { ShmSegment::create(memory.as_mut_ptr() as *mut u8, LEN, 32).unwrap() };
+        let root = unsafe { segment.root::<[u64; 4]>(0) };
+        let _ = root.swap(&segment.try_new([0; 4]).unwrap());
+
+        // Writers release values as fast as they replace them, so loads race with blocks being freed and reused.
+        thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let (segment, root) = (&segment, &root);
+                scope.spawn(move || {
+                    for i in 0..2000 {
+                        if thread % 2 == 0 {
+                            if let Ok(new) = segment.try_new([thread * 10_000 + i; 4]) {
+                                let _ = root.swap(&new);
+                            }
+                        }
+                        else {
+                            let current = root.load();
+                            let values = current.maybe_deref().unwrap();
+                            // A block reused under a load would show up as a value written by two allocations.
+                            assert!(values.iter().all(|value| *value == values[0]));
+                            let _ = root.compare_exchange(&current, &current);
+                        }
+                    }
+                });
+            }
+        });
+
+        let _ = root.swap(&XarcShm::null(&segment));
+        assert!(root.is_null());
+        // Every block has been returned to the free list.
+        let all: Vec<_> = (0..segment.capacity()).map(|_| segment.try_new([0u64; 4]).unwrap()).collect();
+        assert_eq!(all.len(), segment.capacity());
+    }
+}
@@ -0,0 +1,209 @@
+use super::pointer::*;
+use core::{fmt, hash::{Hash, Hasher}, ops::{Bound, Deref, Range, RangeBounds}};
+
+/// `XarcSlice` is a view of a sub-range of an `Xarc<[T]>` that keeps the whole allocation alive, returned by `Xarc::slice`.
+///
+/// It holds a reference to the slice along with the offset and length of its window, so it is created without copying anything
+/// and cloned with a single increment, like the `Xarc` itself. Windows of one published buffer can be handed out independently,
+/// and published in turn as `Xarc<XarcSlice<T>>` to be swapped through an `AtomicXarc`.
+/// The allocation is only reclaimed once the `Xarc` and every view of it are gone, however small the views are.
+///
+/// Unlike `Xarc`, which compares by identity, views compare and hash by their elements, as the slices they dereference to.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::Xarc;
+///
+/// let buffer: Xarc<[u8]> = Xarc::from(&b"GET /index.html HTTP/1.1"[..]);
+/// let method = buffer.slice(..3);
+/// let path = buffer.slice(4..15);
+/// drop(buffer);
+///
+/// assert_eq!(&*method, b"GET");
+/// assert_eq!(&*path, b"/index.html");
+/// assert_eq!(&*path.slice(1..6), b"index");
+/// assert_eq!(path.range(), 4..15);
+/// ```
+pub struct XarcSlice<T: Send> {
+    owner: Xarc<[T]>,
+    start: usize,
+    end: usize,
+}
+
+impl<T: Send> XarcSlice<T> {
+    /// View `range` of `owner`. See `Xarc::slice`.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub(crate) fn new<R: RangeBounds<usize>>(owner: &Xarc<[T]>, range: R) -> Self {
+        let (start, end) = bounds(range, owner.iter().len());
+        XarcSlice {
+            owner: owner.clone(),
+            start,
+            end,
+        }
+    }
+
+    /// View `range` of this view, relative to its start, sharing the same allocation.
+    ///
+    /// # Panics
+    /// - If `range` is out of bounds or decreasing, as slice indexing would.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Self {
+        let (start, end) = bounds(range, self.len());
+        XarcSlice {
+            owner: self.owner.clone(),
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// The slice this views a range of.
+    #[must_use]
+    pub fn owner(&self) -> &Xarc<[T]> {
+        &self.owner
+    }
+
+    /// The range of the owner this views.
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Give up the view for the slice it views a range of.
+    #[must_use]
+    pub fn into_owner(self) -> Xarc<[T]> {
+        self.owner
+    }
+
+    /// Narrow the view to `start..end`, relative to its start. The caller has checked the bounds.
+    pub(crate) fn narrow(&mut self, start: usize, end: usize) {
+        self.end = self.start + end;
+        self.start += start;
+    }
+}
+
+/// Resolve `range` against a slice of `len` elements.
+///
+/// # Panics
+/// - If `range` is out of bounds or decreasing, with the messages slice indexing would give.
+#[must_use]
+pub(crate) fn bounds<R: RangeBounds<usize>>(range: R, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).expect("attempted to index slice from after maximum usize"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).expect("attempted to index slice up to maximum usize"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end, "slice index starts at {} but ends at {}", start, end);
+    assert!(end <= len, "range end index {} out of range for slice of length {}", end, len);
+    (start, end)
+}
+
+impl<T: Send> Xarc<[T]> {
+    /// View `range` of the slice without copying it, keeping the whole allocation alive for as long as the view is.
+    /// A null `Xarc` is viewed as an empty slice.
+    ///
+    /// # Panics
+    /// - If `range` is out of bounds or decreasing, as slice indexing would.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> XarcSlice<T> {
+        XarcSlice::new(self, range)
+    }
+}
+
+impl<T: Send> Deref for XarcSlice<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.owner.iter().as_slice()[self.start..self.end]
+    }
+}
+
+impl<T: Send> AsRef<[T]> for XarcSlice<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T: Send> Clone for XarcSlice<T> {
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn clone(&self) -> Self {
+        XarcSlice {
+            owner: self.owner.clone(),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<T: Send> From<Xarc<[T]>> for XarcSlice<T> {
+    /// View the whole slice.
+    fn from(owner: Xarc<[T]>) -> Self {
+        let end = owner.iter().len();
+        XarcSlice {
+            owner,
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl<T: Send + PartialEq> PartialEq for XarcSlice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Send + PartialEq> PartialEq<[T]> for XarcSlice<T> {
+    fn eq(&self, other: &[T]) -> bool {
+        **self == *other
+    }
+}
+
+impl<T: Send + Eq> Eq for XarcSlice<T> {}
+
+impl<T: Send + Hash> Hash for XarcSlice<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for XarcSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xarc_slice_mt_test() {
+        use crate::atomic::AtomicXarc;
+        use core::sync::atomic::Ordering;
+        use rayon::prelude::*;
+
+        let buffer: Xarc<[usize]> = (0..1000).collect();
+        let window = AtomicXarc::new(buffer.slice(..));
+        drop(buffer);
+        (1..500).into_par_iter().for_each(|i| {
+            let loaded = window.load(Ordering::Acquire);
+            let current = loaded.maybe_deref().unwrap();
+            let narrowed: XarcSlice<usize> = current.slice(1..current.len() - 1);
+            assert_eq!(narrowed.first().copied(), current.get(1).copied());
+            let _ = window.compare_exchange(&loaded, &Xarc::new(narrowed.owner().slice(i..1000 - i)), Ordering::AcqRel, Ordering::Acquire);
+        });
+        let last = window.load(Ordering::Acquire);
+        let last = last.maybe_deref().unwrap();
+        assert!(last.iter().copied().eq(last.range()));
+        assert_eq!(last.owner().iter().len(), 1000);
+    }
+}
@@ -0,0 +1,39 @@
+use super::{atomic::*, internal::back_off, pointer::*};
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+use crossbeam_utils::Backoff;
+
+/// Load every slot in `slots` such that the returned values were all present at a single point in time.
+/// 
+/// This is a double-collect: all slots are loaded, then every slot is checked again,
+/// and the collection is repeated until no slot changed in between.
+/// Since the loaded handles keep their allocations alive, no other allocation can reuse those addresses.
+/// The one thing a double-collect cannot detect is the *same* `Xarc` being swapped out and back into a slot
+/// between the two reads. If your algorithm republishes old handles, version the slots as well.
+/// 
+/// Writers that never stop may cause `snapshot` to retry indefinitely.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, snapshot};
+/// 
+/// let head = AtomicXarc::new(1);
+/// let tail = AtomicXarc::new(2);
+/// 
+/// let values = snapshot(&[&head, &tail]);
+/// assert_eq!(values[0], head.load(Ordering::Acquire));
+/// assert_eq!(values[1], tail.load(Ordering::Acquire));
+/// ```
+#[must_use]
+pub fn snapshot<T: Send>(slots: &[&AtomicXarc<T>]) -> Vec<Xarc<T>> {
+    let backoff = Backoff::new();
+    loop {
+        let collected: Vec<Xarc<T>> = slots.iter().map(|slot| slot.load(Ordering::SeqCst)).collect();
+        if slots.iter().zip(collected.iter()).all(|(slot, pointer)| slot.current(Ordering::SeqCst) == pointer.ptr) {
+            return collected;
+        }
+        back_off(&backoff);
+    }
+}
@@ -0,0 +1,336 @@
+use super::{internal::*, ordering::force, repin::pin};
+use core::{ptr, sync::atomic::{AtomicBool, AtomicPtr, Ordering, fence}};
+use crossbeam_utils::{Backoff, CachePadded};
+
+/// `Strategy` selects how an `AtomicXarc` protects the object it is loading until its count has been incremented.
+/// It is chosen at the type level, `AtomicXarc<T, S>`, so tuning it never changes call sites.
+/// 
+/// - `Eager`, the default, increments the count directly with a compare-exchange loop while an epoch is pinned.
+///   Every write is as cheap as a single atomic operation.
+/// - `ReadMostly` first records a debt, as `arc-swap` does, so loads never contend on the count with a compare-exchange loop
+///   and never pin an epoch. In exchange every successful write scans the debt list to pay off the debts of concurrent loads.
+/// - `Locked` takes a spinlock around every load and write, for targets where compare-exchange is emulated and slow.
+/// 
+/// This trait is sealed and cannot be implemented outside of `xarc`.
+pub trait Strategy: sealed::Sealed {}
+
+pub(crate) mod sealed {
+    use super::*;
+
+    pub trait Sealed {
+        /// Whether a reference may only be taken by a load under the slot's lock, never from a pointer read earlier,
+        /// so that values that are reclaimed as soon as their count reaches 0 can be stored in the slot.
+        const LOADS_UNDER_LOCK: bool = false;
+
+        /// Whether the slot may hold a `compare_exchange_all` in progress, which a write must help to finish rather than overwrite.
+        const TRANSACTIONAL: bool = false;
+
+        /// Make one attempt to load a pointer with `load` and take a reference to it.
+        fn try_acquire<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()>;
+
+        /// As `try_acquire`, but in a bounded number of steps: the count is incremented with at most one compare-exchange.
+        fn try_acquire_once<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()>;
+
+        /// Called after `ptr` has been replaced, while the caller still holds the reference the `AtomicXarc` held.
+        fn replaced(ptr: *mut XarcHeader);
+
+        /// Run `f`, which loads from or writes to `slot`, excluding others doing the same to it if the strategy requires.
+        /// Every `try_acquire` and `try_acquire_once` runs inside it.
+        #[inline]
+        fn exclusive<R, F: FnOnce() -> R>(_slot: &AtomicPtr<XarcHeader>, f: F) -> R {
+            f()
+        }
+
+        /// As `exclusive`, but fails rather than waiting, for interrupt handlers that may have interrupted whoever they would wait for.
+        #[inline]
+        fn try_exclusive<R, F: FnOnce() -> R>(_slot: &AtomicPtr<XarcHeader>, f: F) -> Option<R> {
+            Some(f())
+        }
+    }
+}
+
+/// The default `Strategy`, which increments the count as it loads. See `Strategy`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Eager;
+
+/// A `Strategy` for values that are loaded far more often than they are replaced. See `Strategy`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, ReadMostly, Xarc};
+/// 
+/// let atomic = AtomicXarc::with_strategy(&Xarc::new(42), ReadMostly);
+/// assert_eq!(*atomic.load(Ordering::Acquire).maybe_deref().unwrap(), 42);
+/// let _ = atomic.swap(&Xarc::new(43), Ordering::AcqRel);
+/// assert_eq!(*atomic.load(Ordering::Acquire).maybe_deref().unwrap(), 43);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ReadMostly;
+
+/// A `Strategy` for targets where compare-exchange on pointers is emulated and slow, such as with a critical section per operation.
+/// See `Strategy`.
+///
+/// Every load and write of an `AtomicXarc` takes a spinlock, one of a fixed number shared between slots by address.
+/// A load then increments the count with a single atomic add, with neither a compare-exchange loop nor a pinned epoch,
+/// and a write holds the lock only for the swap itself. Loads therefore never fail or ask writers for help,
+/// but a thread preempted while holding a lock stalls every slot sharing it, so the operations are no longer lock-free.
+///
+/// `try_swap_isr`, `try_load_bounded` and `compare_exchange_bounded` fail rather than waiting if the lock is held,
+/// so they stay bounded and safe to use in interrupt handlers.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, Locked, Xarc};
+///
+/// static SETTINGS: AtomicXarc<u32, Locked> = AtomicXarc::null_with_strategy(Locked);
+///
+/// let _ = SETTINGS.swap(&Xarc::new(115_200), Ordering::AcqRel);
+/// assert_eq!(SETTINGS.load(Ordering::Acquire).maybe_deref(), Some(&115_200));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Locked;
+
+impl Strategy for Eager {}
+impl Strategy for ReadMostly {}
+impl Strategy for Locked {}
+
+impl sealed::Sealed for Eager {
+    const TRANSACTIONAL: bool = true;
+
+    fn try_acquire<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()> {
+        let guard = pin();
+        let ptr = load();
+        #[cfg(feature = "fault-injection")]
+        super::fault::delay();
+        try_increment(ptr, &guard).map(|_| ptr)
+    }
+
+    fn try_acquire_once<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()> {
+        let guard = pin();
+        let ptr = load();
+        #[cfg(feature = "fault-injection")]
+        super::fault::delay();
+        try_increment_once(ptr, &guard).map(|_| ptr)
+    }
+
+    #[inline]
+    fn replaced(_ptr: *mut XarcHeader) {}
+}
+
+/// The number of loads that can hold a debt at once. Further concurrent loads fall back on `Eager`.
+const DEBTS: usize = 64;
+
+/// Marks a debt that was paid by a writer but not yet released by the load that owns the slot.
+const PAID: *mut XarcHeader = ptr::NonNull::dangling().as_ptr();
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: CachePadded<AtomicPtr<XarcHeader>> = CachePadded::new(AtomicPtr::new(ptr::null_mut()));
+
+/// Pointers that loads are in the process of taking references to without having incremented their counts.
+static DEBT_LIST: [CachePadded<AtomicPtr<XarcHeader>>; DEBTS] = [EMPTY; DEBTS];
+
+impl ReadMostly {
+    /// Make one attempt to load a pointer with `load` and take a reference to it using only a debt, never pinning an epoch.
+    /// Returns None if every debt slot is taken.
+    pub(crate) fn try_acquire_with_debt<F: Fn() -> *mut XarcHeader>(load: &F) -> Option<Result<*mut XarcHeader, ()>> {
+        let ptr = load();
+        if ptr.is_null() {
+            return Some(Ok(ptr));
+        }
+        let debt = claim(ptr)?;
+        // Pairs with the fence in `replaced`: either the writer sees the debt or this load sees the replacement.
+        fence(Ordering::SeqCst);
+        if load() == ptr {
+            // The debt keeps the count from reaching 0, so it can be incremented unconditionally.
+            unguarded_increment(ptr);
+            if release(debt, ptr) {
+                unsafe {
+                    let _ = (*ptr).count.decrement(1);
+                }
+            }
+            Some(Ok(ptr))
+        }
+        else if release(debt, ptr) {
+            // Replaced in the meantime, but a writer paid the debt, so the reference is ours anyway.
+            Some(Ok(ptr))
+        }
+        else {
+            Some(Err(()))
+        }
+    }
+}
+
+impl sealed::Sealed for ReadMostly {
+    fn try_acquire<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()> {
+        match ReadMostly::try_acquire_with_debt(&load) {
+            Some(result) => result,
+            None => Eager::try_acquire(load),
+        }
+    }
+
+    fn try_acquire_once<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()> {
+        match ReadMostly::try_acquire_with_debt(&load) {
+            Some(result) => result,
+            None => Eager::try_acquire_once(load),
+        }
+    }
+
+    fn replaced(ptr: *mut XarcHeader) {
+        if ptr.is_null() {
+            return;
+        }
+        fence(Ordering::SeqCst);
+        for debt in DEBT_LIST.iter() {
+            if debt.load(force(Ordering::Relaxed)) == ptr {
+                unguarded_increment(ptr);
+                if debt.compare_exchange(ptr, PAID, force(Ordering::Release), force(Ordering::Relaxed)).is_err() {
+                    // The load released the debt itself. The reference held by the caller keeps the count above 0.
+                    unsafe {
+                        let _ = (*ptr).count.decrement(1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The number of spinlocks shared between `AtomicXarc`s using `Locked`.
+const LOCKS: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const UNLOCKED: CachePadded<AtomicBool> = CachePadded::new(AtomicBool::new(false));
+
+static LOCK_LIST: [CachePadded<AtomicBool>; LOCKS] = [UNLOCKED; LOCKS];
+
+/// The spinlock guarding `slot`. Each `AtomicXarc` is padded to its own cache line, so the low bits of the address are skipped.
+fn lock_for(slot: &AtomicPtr<XarcHeader>) -> &'static AtomicBool {
+    &LOCK_LIST[((slot as *const AtomicPtr<XarcHeader>).addr() >> 7) % LOCKS]
+}
+
+impl sealed::Sealed for Locked {
+    const LOADS_UNDER_LOCK: bool = true;
+
+    fn try_acquire<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()> {
+        // The lock keeps the slot from being replaced, so the reference it holds keeps the count above 0.
+        let ptr = load();
+        unguarded_increment(ptr);
+        Ok(ptr)
+    }
+
+    fn try_acquire_once<F: Fn() -> *mut XarcHeader>(load: F) -> Result<*mut XarcHeader, ()> {
+        Locked::try_acquire(load)
+    }
+
+    #[inline]
+    fn replaced(_ptr: *mut XarcHeader) {}
+
+    fn exclusive<R, F: FnOnce() -> R>(slot: &AtomicPtr<XarcHeader>, f: F) -> R {
+        let lock = lock_for(slot);
+        let backoff = Backoff::new();
+        while lock.swap(true, force(Ordering::Acquire)) {
+            while lock.load(force(Ordering::Relaxed)) {
+                back_off(&backoff);
+            }
+        }
+        let result = f();
+        lock.store(false, force(Ordering::Release));
+        result
+    }
+
+    fn try_exclusive<R, F: FnOnce() -> R>(slot: &AtomicPtr<XarcHeader>, f: F) -> Option<R> {
+        let lock = lock_for(slot);
+        if lock.swap(true, force(Ordering::Acquire)) {
+            return None;
+        }
+        let result = f();
+        lock.store(false, force(Ordering::Release));
+        Some(result)
+    }
+}
+
+/// Record a debt for `ptr` in a free slot, starting the search at a position derived from the stack so that threads spread out.
+fn claim(ptr: *mut XarcHeader) -> Option<&'static AtomicPtr<XarcHeader>> {
+    let start = thread_hint();
+    (0..DEBTS).map(|offset| &*DEBT_LIST[start.wrapping_add(offset) % DEBTS]).find(|debt| {
+        debt.load(force(Ordering::Relaxed)).is_null()
+            && debt.compare_exchange(ptr::null_mut(), ptr, force(Ordering::Relaxed), force(Ordering::Relaxed)).is_ok()
+    })
+}
+
+/// The number of debts on `ptr` that have not been paid, for `DebugState`.
+#[must_use]
+pub(crate) fn debts(ptr: *mut XarcHeader) -> usize {
+    if ptr.is_null() {
+        return 0;
+    }
+    DEBT_LIST.iter().filter(|debt| debt.load(Ordering::Relaxed) == ptr).count()
+}
+
+/// Release the debt for `ptr`. Returns true if a writer paid it, in which case the caller owns an additional reference.
+fn release(debt: &AtomicPtr<XarcHeader>, ptr: *mut XarcHeader) -> bool {
+    match debt.compare_exchange(ptr, ptr::null_mut(), force(Ordering::Relaxed), force(Ordering::Acquire)) {
+        Ok(_) => false,
+        Err(_) => {
+            debt.store(ptr::null_mut(), force(Ordering::Release));
+            true
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sealed::Sealed, *};
+    use crate::{atomic::AtomicXarc, pointer::Xarc};
+
+    #[test]
+    fn locked_bounded_st_test() {
+        let slot = AtomicXarc::with_strategy(&Xarc::new(1), Locked);
+        let current = slot.load(Ordering::Acquire);
+        // While the lock is held, the bounded operations fail rather than spinning on it.
+        Locked::exclusive(&slot.ptr, || {
+            assert!(slot.try_load_bounded(Ordering::Acquire, 8).is_err());
+            assert!(slot.compare_exchange_bounded(&current, &Xarc::new(2), Ordering::AcqRel, Ordering::Acquire).is_err());
+            assert!(slot.cas_loop_bounded(|_| Some(Xarc::new(3)), Ordering::AcqRel, Ordering::Acquire, 8).unwrap_err().is_none());
+        });
+        assert_eq!(slot.try_load_bounded(Ordering::Acquire, 1).unwrap(), current);
+        assert!(slot.compare_exchange_bounded(&current, &Xarc::new(2), Ordering::AcqRel, Ordering::Acquire).is_ok());
+        assert_eq!(slot.load(Ordering::Acquire).maybe_deref(), Some(&2));
+    }
+
+    #[test]
+    fn xarc_read_mostly_mt_test() {
+        use rayon::prelude::*;
+
+        let shared = AtomicXarc::with_strategy(&Xarc::new(0), ReadMostly);
+        (0..10000).into_par_iter().for_each(|i| {
+            if i % 10 == 0 {
+                let _ = shared.swap(&Xarc::new(i), Ordering::AcqRel);
+            }
+            else {
+                let local = shared.load(Ordering::Acquire);
+                assert_eq!(*local.maybe_deref().unwrap() % 10, 0);
+            }
+        });
+    }
+
+    #[test]
+    fn xarc_locked_mt_test() {
+        use rayon::prelude::*;
+
+        let shared = AtomicXarc::with_strategy(&Xarc::new(0), Locked);
+        (0..10000).into_par_iter().for_each(|i| {
+            if i % 10 == 0 {
+                let current = shared.load(Ordering::Acquire);
+                let _ = shared.compare_exchange(&current, &Xarc::new(i), Ordering::AcqRel, Ordering::Acquire);
+            }
+            else {
+                let local = shared.load(Ordering::Acquire);
+                assert_eq!(*local.maybe_deref().unwrap() % 10, 0);
+            }
+        });
+    }
+}
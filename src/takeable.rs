@@ -0,0 +1,111 @@
+use super::ordering::force;
+use core::{cell::UnsafeCell, fmt, sync::atomic::{AtomicBool, Ordering}};
+
+/// `Takeable` holds a value that whoever shares it can move out once, such as the value in a node of a lock-free container
+/// that is returned by whichever thread removes the node.
+///
+/// An atomic flag decides which caller of `take` gets the value. Every other caller gets None,
+/// so the value can be shared through an `Xarc` without resorting to `UnsafeCell<Option<T>>` and `mem::take`.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::{Takeable, Xarc};
+///
+/// struct Node {
+///     key: u64,
+///     value: Takeable<String>,
+/// }
+///
+/// let shared = Xarc::new(Node {key: 7, value: Takeable::new(String::from("seven"))});
+/// let node = shared.maybe_deref().unwrap();
+/// let taken: Vec<String> = (0..8).into_par_iter().filter_map(|_| node.value.take()).collect();
+/// assert_eq!(taken, ["seven"]);
+/// assert_eq!(node.key, 7);
+/// assert!(node.value.is_taken());
+/// ```
+pub struct Takeable<T> {
+    taken: AtomicBool,
+    value: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for Takeable<T> {}
+unsafe impl<T: Send> Sync for Takeable<T> {}
+
+impl<T> Takeable<T> {
+    /// Store `value` to be taken.
+    #[must_use]
+    pub const fn new(value: T) -> Self {
+        Takeable {
+            taken: AtomicBool::new(false),
+            value: UnsafeCell::new(Some(value)),
+        }
+    }
+
+    /// Store nothing, as if the value had already been taken.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Takeable {
+            taken: AtomicBool::new(true),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Move the value out, or return None if it has already been taken.
+    pub fn take(&self) -> Option<T> {
+        if self.taken.load(force(Ordering::Relaxed)) || self.taken.swap(true, force(Ordering::Acquire)) {
+            None
+        }
+        else {
+            unsafe {
+                (*self.value.get()).take()
+            }
+        }
+    }
+
+    /// Check if the value has been taken.
+    #[must_use]
+    pub fn is_taken(&self) -> bool {
+        self.taken.load(force(Ordering::Relaxed))
+    }
+
+    /// Access the value, if it has not been taken. Exclusive access to the `Takeable` makes this a plain read.
+    #[must_use]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut().as_mut()
+    }
+
+    /// Move the value out, if it has not been taken.
+    #[must_use]
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+}
+
+impl<T> From<T> for Takeable<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> From<Option<T>> for Takeable<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Self::new(value),
+            None => Self::empty(),
+        }
+    }
+}
+
+impl<T> Default for Takeable<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<T> fmt::Debug for Takeable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Takeable").field("taken", &self.is_taken()).finish_non_exhaustive()
+    }
+}
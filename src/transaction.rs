@@ -0,0 +1,231 @@
+use super::{atomic::*, error::TransactionConflict, internal::*, mcas, pointer::*};
+use alloc::vec::Vec;
+use core::{ptr, sync::atomic::{AtomicPtr, Ordering}};
+use crossbeam_utils::Backoff;
+
+/// Run `f` as a transaction over `AtomicXarc`s, so that invariants spanning several slots,
+/// such as the head and tail of a deque, can be updated without hand-written helping code.
+///
+/// `f` reads slots with `Transaction::read` and buffers writes with `Transaction::write`. Nothing is written until `f` returns Ok,
+/// at which point every slot it touched is checked to still hold the value it first saw there, and its writes are stored,
+/// all as one `compare_exchange_all`. If a slot changed in the meantime, the writes are discarded and `f` is called again,
+/// as it is if `read` or `f` itself returns `TransactionConflict`.
+/// Since `read` fails as soon as an earlier read is overwritten, `f` never sees a mix of values from before and after another write.
+///
+/// Transactions are lock-free, like `compare_exchange_all`: one only starts over because another write succeeded.
+/// Plain writes to the same slots stay correct, and a transaction that read a slot fails if one gets in first.
+///
+/// As with `snapshot`, a read is checked by address, so the same `Xarc` being swapped out and back into a slot goes undetected.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::{AtomicXarc, Xarc, transaction};
+///
+/// // The deque holds the indices from `head` up to `tail`.
+/// let head = AtomicXarc::new(0);
+/// let tail = AtomicXarc::new(3);
+///
+/// let popped = transaction(|tx| {
+///     let (front, back) = (tx.read(&head)?, tx.read(&tail)?);
+///     let (front, back) = (*front.maybe_deref().unwrap(), *back.maybe_deref().unwrap());
+///     if front == back {
+///         return Ok(None);
+///     }
+///     tx.write(&head, &Xarc::new(front + 1));
+///     Ok(Some(front))
+/// });
+/// assert_eq!(popped, Some(0));
+/// assert_eq!(transaction(|tx| Ok(tx.read(&tail)?.maybe_deref().unwrap() - tx.read(&head)?.maybe_deref().unwrap())), 2);
+/// ```
+#[cfg_attr(feature = "debug-owners", track_caller)]
+pub fn transaction<'a, R, F: FnMut(&mut Transaction<'a>) -> Result<R, TransactionConflict>>(mut f: F) -> R {
+    let backoff = Backoff::new();
+    loop {
+        let mut tx = Transaction {
+            accesses: Vec::new(),
+        };
+        if let Ok(result) = f(&mut tx) {
+            if tx.commit() {
+                return result;
+            }
+        }
+        back_off(&backoff);
+    }
+}
+
+/// The reads and buffered writes of one attempt at a `transaction`.
+pub struct Transaction<'a> {
+    accesses: Vec<Access<'a>>,
+}
+
+/// A slot read or written by a `Transaction`. Each value is held with a reference of its own.
+struct Access<'a> {
+    slot: &'a AtomicPtr<XarcHeader>,
+    /// The value the slot held when it was first touched, which it must still hold for the transaction to commit.
+    /// The reference keeps the address from being reused by another value while the transaction runs.
+    read: *mut XarcHeader,
+    /// The value to store in the slot, if it was written.
+    write: Option<*mut XarcHeader>,
+    /// Drops a reference with the type of the slot.
+    release: fn(*mut XarcHeader),
+}
+
+impl<'a> Transaction<'a> {
+    /// Load the value of `slot`, or the value this transaction has written to it.
+    ///
+    /// Fails if a slot touched earlier in the transaction has been written since, in which case `f` should return the error with `?`.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn read<T: Send>(&mut self, slot: &'a AtomicXarc<T>) -> Result<Xarc<T>, TransactionConflict> {
+        if let Some(access) = self.find(slot) {
+            let ptr = access.write.unwrap_or(access.read);
+            unguarded_increment(ptr);
+            return Ok(Xarc::init(ptr));
+        }
+        let value = slot.load(Ordering::Acquire);
+        if !self.accesses.iter().all(Access::unchanged) {
+            return Err(TransactionConflict);
+        }
+        self.touch(slot, &value);
+        Ok(value)
+    }
+
+    /// Buffer a write of `value` to `slot`, to be stored if the transaction commits. A later write to the same slot replaces it.
+    ///
+    /// A slot that has not been read yet is read first, so the write commits only if nothing else writes to the slot in the meantime.
+    pub fn write<T: Send>(&mut self, slot: &'a AtomicXarc<T>, value: &Xarc<T>) {
+        if self.find(slot).is_none() {
+            self.touch(slot, &slot.load(Ordering::Acquire));
+        }
+        unguarded_increment(value.ptr);
+        let access = self.accesses.iter_mut().find(|access| ptr::eq(access.slot, &*slot.ptr)).unwrap();
+        if let Some(previous) = access.write.replace(value.ptr) {
+            (access.release)(previous);
+        }
+    }
+
+    fn find<T: Send>(&self, slot: &AtomicXarc<T>) -> Option<&Access<'a>> {
+        self.accesses.iter().find(|access| ptr::eq(access.slot, &*slot.ptr))
+    }
+
+    /// Record that `slot` held `value` when first touched.
+    fn touch<T: Send>(&mut self, slot: &'a AtomicXarc<T>, value: &Xarc<T>) {
+        unguarded_increment(value.ptr);
+        self.accesses.push(Access {
+            slot: &slot.ptr,
+            read: value.ptr,
+            write: None,
+            release: release_reference::<T>,
+        });
+    }
+
+    /// Store the buffered writes if every slot touched still holds the value it held then. Returns whether they were stored.
+    fn commit(&self) -> bool {
+        // Every read checked the ones before it, so the values read were all present together at the last of them.
+        if self.accesses.iter().all(|access| access.write.is_none()) {
+            return true;
+        }
+        mcas::compare_exchange_entries(self.accesses.iter().map(|access| mcas::Entry {
+            slot: access.slot,
+            current: access.read,
+            new: access.write.unwrap_or(access.read),
+            release: access.release,
+        }).collect())
+    }
+}
+
+impl Access<'_> {
+    fn unchanged(&self) -> bool {
+        mcas::read(self.slot, self.slot.load(Ordering::Acquire)) == self.read
+    }
+}
+
+impl Drop for Access<'_> {
+    fn drop(&mut self) {
+        for ptr in [Some(self.read), self.write].iter().flatten() {
+            (self.release)(*ptr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transaction_read_own_write_st_test() {
+        let slot = AtomicXarc::new(1);
+        let other = AtomicXarc::null();
+        let (before, after) = transaction(|tx| {
+            let before = tx.read(&slot)?;
+            tx.write(&slot, &Xarc::new(2));
+            tx.write(&slot, &Xarc::new(3));
+            tx.write(&other, &before);
+            Ok((*before.maybe_deref().unwrap(), *tx.read(&slot)?.maybe_deref().unwrap()))
+        });
+        assert_eq!((before, after), (1, 3));
+        assert_eq!(slot.load(Ordering::Acquire).maybe_deref(), Some(&3));
+        assert_eq!(other.load(Ordering::Acquire).maybe_deref(), Some(&1));
+        // Only the slots hold references once the transaction is done, besides the one loaded here.
+        let loaded = other.load(Ordering::Acquire);
+        assert_eq!(unsafe { (*loaded.ptr).count.load() }, 2);
+    }
+
+    #[test]
+    fn transaction_conflict_st_test() {
+        let slot = AtomicXarc::new(1);
+        let other = AtomicXarc::new(10);
+        let mut attempts = 0;
+        let seen = transaction(|tx| {
+            attempts += 1;
+            let value = *tx.read(&slot)?.maybe_deref().unwrap();
+            if attempts == 1 {
+                // Another writer gets in between the reads, so the second one must fail rather than return a mix.
+                let _ = slot.swap(&Xarc::new(2), Ordering::AcqRel);
+                assert_eq!(tx.read(&other), Err(TransactionConflict));
+            }
+            tx.write(&other, &Xarc::new(value * 10));
+            Ok(value)
+        });
+        assert_eq!((seen, attempts), (2, 2));
+        assert_eq!(other.load(Ordering::Acquire).maybe_deref(), Some(&20));
+    }
+
+    #[test]
+    fn transaction_transfer_mt_test() {
+        extern crate std;
+        use std::thread;
+        const ACCOUNTS: usize = 8;
+        const TRANSFERS: usize = 2000;
+
+        // Every transfer keeps the total constant, so a reader that sees it change saw a torn write.
+        let accounts: Vec<AtomicXarc<i64>> = (0..ACCOUNTS).map(|_| AtomicXarc::new(100)).collect();
+        let balance = |account: Xarc<i64>| *account.maybe_deref().unwrap();
+        thread::scope(|scope| {
+            for thread in 0..4 {
+                let accounts = &accounts;
+                scope.spawn(move || {
+                    for i in 0..TRANSFERS {
+                        let (from, to) = ((thread + i) % ACCOUNTS, (thread * 3 + i * 5 + 1) % ACCOUNTS);
+                        if from == to {
+                            continue;
+                        }
+                        transaction(|tx| {
+                            let (debit, credit) = (balance(tx.read(&accounts[from])?), balance(tx.read(&accounts[to])?));
+                            tx.write(&accounts[from], &Xarc::new(debit - 1));
+                            tx.write(&accounts[to], &Xarc::new(credit + 1));
+                            Ok(())
+                        });
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..TRANSFERS {
+                    let total = transaction(|tx| accounts.iter().map(|account| tx.read(account).map(balance)).sum::<Result<i64, _>>());
+                    assert_eq!(total, 100 * ACCOUNTS as i64);
+                }
+            });
+        });
+        assert_eq!(accounts.iter().map(|account| balance(account.load(Ordering::Acquire))).sum::<i64>(), 100 * ACCOUNTS as i64);
+    }
+}
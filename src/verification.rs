@@ -0,0 +1,85 @@
+//! Kani proof harnesses for the reference count protocol. Run them with `cargo kani`.
+//!
+//! Kani does not model threads, so the harnesses that involve races explore every interleaving of the atomic steps
+//! of the participating threads by choosing nondeterministically which thread takes the next step.
+//! Epoch pinning, which keeps the header readable while a loader calls `try_increment`, is assumed rather than modeled.
+
+use crate::internal::XarcCount;
+
+/// `try_increment` succeeds exactly when the count is nonzero, so a count that has reached zero is never revived.
+#[kani::proof]
+#[kani::unwind(3)]
+fn try_increment_never_from_zero() {
+    let initial: usize = kani::any();
+    kani::assume(initial < XarcCount::MAX);
+    let count = XarcCount::new(initial);
+    match count.try_increment() {
+        Ok(previous) => {
+            assert!(previous > 0);
+            assert_eq!(previous, initial);
+            assert_eq!(count.load(), initial + 1);
+        },
+        Err(current) => {
+            assert_eq!(initial, 0);
+            assert_eq!(current, 0);
+            assert_eq!(count.load(), 0);
+        },
+    }
+}
+
+/// When holders of different weights release their references in either order, exactly one observes the release of the last reference.
+#[kani::proof]
+fn weighted_release_reclaims_once() {
+    let first: usize = kani::any();
+    let second: usize = kani::any();
+    kani::assume(first > 0 && second > 0 && first.checked_add(second).map_or(false, |count| count <= XarcCount::MAX));
+    let count = XarcCount::new(first + second);
+    let (a, b) = if kani::any() { (first, second) } else { (second, first) };
+    let reclaimed_a = count.decrement(a) == a;
+    let reclaimed_b = count.decrement(b) == b;
+    assert!(!reclaimed_a);
+    assert!(reclaimed_b);
+    assert_eq!(count.load(), 0);
+}
+
+/// A load racing with the release of every other reference either fails or keeps the value alive until it releases its own reference.
+/// In every interleaving the value is reclaimed exactly once, and never before a successful load is done with it.
+#[kani::proof]
+#[kani::unwind(7)]
+fn load_races_release() {
+    let owners: usize = kani::any();
+    kani::assume((1..=3).contains(&owners));
+    let count = XarcCount::new(owners);
+
+    let mut released = 0;
+    let mut attempted = false;
+    let mut holding = false;
+    let mut reclaimed = 0;
+    while released < owners || !attempted || holding {
+        let step: u8 = kani::any();
+        if step == 0 && released < owners {
+            released += 1;
+            if count.decrement(1) == 1 {
+                reclaimed += 1;
+            }
+        }
+        else if step == 1 && !attempted {
+            attempted = true;
+            if count.try_increment().is_ok() {
+                assert_eq!(reclaimed, 0);
+                holding = true;
+            }
+        }
+        else if step == 2 && holding {
+            holding = false;
+            if count.decrement(1) == 1 {
+                reclaimed += 1;
+            }
+        }
+        else {
+            kani::assume(false);
+        }
+        assert!(reclaimed == 0 || !holding);
+    }
+    assert_eq!(reclaimed, 1);
+}
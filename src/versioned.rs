@@ -0,0 +1,167 @@
+use super::{atomic::*, pointer::*};
+use core::sync::atomic::Ordering;
+
+/// `VersionedAtomicXarc` is an `AtomicXarc` paired with a version that is incremented by every successful write.
+///
+/// Optimistic algorithms can remember the cheap integer version of what they read rather than holding a handle,
+/// and validate with `version` or `compare_exchange_versioned` later.
+/// Unlike pointer comparisons, versions are immune to ABA since they never repeat.
+///
+/// The slot holds a small node pairing each value with its version, so a write replaces both with a single compare-exchange
+/// and every operation is lock-free. In exchange, every write allocates a node.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::{VersionedAtomicXarc, Xarc};
+///
+/// let atomic = VersionedAtomicXarc::new(42);
+/// let (value, version) = atomic.load_versioned();
+/// assert_eq!(*value.maybe_deref().unwrap(), 42);
+///
+/// let (previous, next_version) = atomic.compare_exchange_versioned(version, &Xarc::new(43)).unwrap();
+/// assert_eq!(previous, value);
+/// assert_eq!(next_version, version + 1);
+/// assert!(atomic.compare_exchange_versioned(version, &Xarc::new(44)).is_err());
+/// ```
+#[derive(Debug)]
+pub struct VersionedAtomicXarc<T: Send> {
+    slot: AtomicXarc<Versioned<T>>,
+}
+
+/// A value and the version it was published at. Never null in the slot.
+#[derive(Debug)]
+struct Versioned<T: Send> {
+    version: u64,
+    value: Xarc<T>,
+}
+
+impl<T: Send> VersionedAtomicXarc<T> {
+    /// Initialize the atomic smart pointer with `value` at version 0.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self::from(&Xarc::new(value))
+    }
+
+    /// Initialize the atomic smart pointer with null at version 0.
+    #[must_use]
+    pub fn null() -> Self {
+        Self::from(&Xarc::null())
+    }
+
+    /// The current version.
+    #[must_use]
+    pub fn version(&self) -> u64 {
+        self.slot.with(Ordering::Acquire, |current| current.unwrap().version)
+    }
+
+    /// Load the value into an `Xarc` without regard for the version.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load(&self, order: Ordering) -> Xarc<T> {
+        self.slot.with(order, |current| current.unwrap().value.clone())
+    }
+
+    /// Load the value into an `Xarc` along with the version it was published at.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn load_versioned(&self) -> (Xarc<T>, u64) {
+        self.slot.with(Ordering::Acquire, |current| {
+            let current = current.unwrap();
+            (current.value.clone(), current.version)
+        })
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new` if the version is still `expected_version`.
+    /// Returns the previous value and the new version on success.
+    /// Returns the current value and version on failure.
+    #[allow(clippy::type_complexity)]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn compare_exchange_versioned(&self, expected_version: u64, new: &Xarc<T>) -> Result<(Xarc<T>, u64), (Xarc<T>, u64)> {
+        let mut current = self.slot.load(Ordering::Acquire);
+        loop {
+            let node = current.maybe_deref().unwrap();
+            if node.version != expected_version {
+                return Err((node.value.clone(), node.version));
+            }
+            match self.write(&current, new) {
+                Ok(written) => return Ok(written),
+                // A node is never reused while a reference to it is held, so another write got in first and bumped the version.
+                Err(latest) => current = latest,
+            }
+        }
+    }
+
+    /// As an atomic operation, swap the contents of `self` with `new`.
+    /// Returns the previous value and the new version.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn swap(&self, new: &Xarc<T>) -> (Xarc<T>, u64) {
+        let mut current = self.slot.load(Ordering::Acquire);
+        loop {
+            match self.write(&current, new) {
+                Ok(written) => return written,
+                Err(latest) => current = latest,
+            }
+        }
+    }
+
+    /// Replace `current` with a node holding `new` at the next version, or return the node that replaced `current` first.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    fn write(&self, current: &Xarc<Versioned<T>>, new: &Xarc<T>) -> Result<(Xarc<T>, u64), Xarc<Versioned<T>>> {
+        let node = current.maybe_deref().unwrap();
+        let next = Xarc::new(Versioned {
+            version: node.version + 1,
+            value: new.clone(),
+        });
+        self.slot.compare_exchange(current, &next, Ordering::AcqRel, Ordering::Acquire).map(|_| (node.value.clone(), node.version + 1))
+    }
+}
+
+impl<T: Send> From<&Xarc<T>> for VersionedAtomicXarc<T> {
+    fn from(pointer: &Xarc<T>) -> Self {
+        VersionedAtomicXarc {
+            slot: AtomicXarc::new(Versioned {
+                version: 0,
+                value: pointer.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versioned_compare_exchange_mt_test() {
+        extern crate std;
+        use alloc::vec::Vec;
+        use std::thread;
+        const THREADS: usize = 4;
+        const WRITES: usize = 1000;
+
+        // Every successful write takes the next version, so no two writers may ever be told the same one.
+        let atomic = VersionedAtomicXarc::new(0usize);
+        let mut versions: Vec<u64> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS).map(|_| scope.spawn(|| {
+                let mut versions = Vec::new();
+                while versions.len() < WRITES {
+                    let (value, version) = atomic.load_versioned();
+                    if let Ok((previous, next)) = atomic.compare_exchange_versioned(version, &Xarc::new(*value.maybe_deref().unwrap() + 1)) {
+                        assert_eq!(previous, value);
+                        versions.push(next);
+                    }
+                }
+                versions
+            })).collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        });
+        versions.sort_unstable();
+        assert!(versions.into_iter().eq(1..=(THREADS * WRITES) as u64));
+        let (value, version) = atomic.load_versioned();
+        assert_eq!((*value.maybe_deref().unwrap(), version), (THREADS * WRITES, (THREADS * WRITES) as u64));
+        assert_eq!(atomic.swap(&Xarc::null()).1, version + 1);
+        assert!(atomic.load(Ordering::Acquire).is_null());
+    }
+}
@@ -0,0 +1,105 @@
+use super::ordering::force;
+use core::sync::atomic::{AtomicUsize, Ordering, fence};
+use crossbeam_utils::CachePadded;
+use std::{sync::Mutex, thread::{self, Thread}, time::Instant, vec::Vec};
+
+/// The number of buckets that waiting threads are spread over by the address they wait on.
+const BUCKETS: usize = 64;
+
+/// The threads waiting on the addresses that hash to one bucket.
+struct Bucket {
+    /// Number of threads currently registered as waiting in this bucket.
+    /// Writers only take the lock when this is nonzero, so writes to slots nobody waits on stay lock-free.
+    waiting: AtomicUsize,
+    waiters: Mutex<Vec<(usize, Thread)>>,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: CachePadded<Bucket> = CachePadded::new(Bucket {
+    waiting: AtomicUsize::new(0),
+    waiters: Mutex::new(Vec::new()),
+});
+
+static BUCKET_LIST: [CachePadded<Bucket>; BUCKETS] = [EMPTY; BUCKETS];
+
+/// The bucket for `address`, mixing its bits so that neighbouring slots land in different buckets.
+fn bucket_for(address: usize) -> &'static Bucket {
+    let hash = (address as u64 >> 3).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    &BUCKET_LIST[(hash >> 32) as usize % BUCKETS]
+}
+
+/// Park the current thread until `changed` returns true, waking on `notify(address)`.
+/// Returns false if `deadline` passes first.
+pub(crate) fn wait_until<F: Fn() -> bool>(address: usize, changed: F, deadline: Option<Instant>) -> bool {
+    let bucket = bucket_for(address);
+    while !changed() {
+        let timeout = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) => Some(timeout),
+                None => return false,
+            },
+            None => None,
+        };
+        bucket.waiters.lock().unwrap_or_else(|e| e.into_inner()).push((address, thread::current()));
+        bucket.waiting.fetch_add(1, Ordering::SeqCst);
+        // Pairs with the fence in `notify`: either the writer sees us waiting or we see its write.
+        if !changed() {
+            match timeout {
+                Some(timeout) => thread::park_timeout(timeout),
+                None => thread::park(),
+            }
+        }
+        bucket.waiting.fetch_sub(1, force(Ordering::Relaxed));
+        let id = thread::current().id();
+        let mut waiters = bucket.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = waiters.iter().position(|(a, t)| *a == address && t.id() == id) {
+            waiters.swap_remove(index);
+        }
+    }
+    true
+}
+
+/// Wake every thread waiting on `address`. Only takes a lock if a thread is waiting on an address in the same bucket.
+#[inline]
+pub(crate) fn notify(address: usize) {
+    fence(Ordering::SeqCst);
+    let bucket = bucket_for(address);
+    if bucket.waiting.load(force(Ordering::Relaxed)) > 0 {
+        for (_, thread) in bucket.waiters.lock().unwrap_or_else(|e| e.into_inner()).iter().filter(|(a, _)| *a == address) {
+            thread.unpark();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{atomic::AtomicXarc, pointer::Xarc};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn wait_buckets_st_test() {
+        let slots: Vec<AtomicXarc<usize>> = (0..64).map(AtomicXarc::new).collect();
+        let buckets: BTreeSet<*const Bucket> = slots.iter().map(|slot| bucket_for(slot.address()) as *const Bucket).collect();
+        assert!(buckets.len() > BUCKETS / 4);
+    }
+
+    #[test]
+    fn wait_notify_mt_test() {
+        let slots: Vec<AtomicXarc<usize>> = (0..16).map(AtomicXarc::new).collect();
+        let current = slots[0].load(Ordering::Acquire);
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| slots[0].wait_until_changed(&current, Ordering::Acquire));
+            while bucket_for(slots[0].address()).waiting.load(Ordering::SeqCst) == 0 {
+                thread::yield_now();
+            }
+            // Writes to the other slots wake nobody waiting on the first.
+            for slot in &slots[1..] {
+                let _ = slot.swap(&Xarc::new(0), Ordering::AcqRel);
+            }
+            assert!(!waiter.is_finished());
+            let _ = slots[0].swap(&Xarc::new(1), Ordering::AcqRel);
+            assert_eq!(waiter.join().unwrap().maybe_deref(), Some(&1));
+        });
+    }
+}
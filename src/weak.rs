@@ -0,0 +1,207 @@
+use super::{internal::*, pointee::*, pointer::*};
+use core::{fmt, marker::PhantomData, ptr};
+
+/// `WeakXarc` is a reference to the value of an `Xarc` that does not keep the value alive, as `Weak` is to `Arc`.
+/// It is created with `Xarc::downgrade` and turned back into an `Xarc` with `upgrade` for as long as the value lives.
+///
+/// The value is dropped when the last `Xarc` is reclaimed, and the allocation is freed when the last `WeakXarc` is dropped after that.
+/// Both counts share one atomic word, so cloning and dropping either kind of reference, and upgrading, are each a single atomic operation.
+/// There can be up to `u16::MAX - 1` weak references to a value, or `u8::MAX - 1` with `count-u32` or 32-bit pointers,
+/// and taking one more panics.
+///
+/// Values allocated from an `XarcPool` or reached through an `IntrusiveXarc` are freed by their owners and cannot be downgraded.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::{WeakXarc, Xarc};
+///
+/// let parent = Xarc::new(String::from("root"));
+/// let weak = Xarc::downgrade(&parent);
+/// assert_eq!(weak.upgrade().maybe_deref().map(String::as_str), Some("root"));
+/// assert_eq!(Xarc::weak_count(&parent), 1);
+///
+/// drop(parent);
+/// xarc::quiescent();
+/// // Reclamation may be deferred until other threads advance the epoch.
+/// let upgraded = weak.upgrade();
+/// assert!(upgraded.maybe_deref().is_none_or(|value| value == "root"));
+/// assert!(WeakXarc::<String>::new().upgrade().is_null());
+/// ```
+pub struct WeakXarc<T: ?Sized + Send + Pointee> {
+    ptr: *mut XarcHeader,
+    phantom: PhantomData<Xarc<T>>,
+}
+
+impl<T: ?Sized + Send + Pointee> WeakXarc<T> {
+    /// A weak reference to nothing, which never upgrades.
+    #[must_use]
+    pub const fn new() -> Self {
+        WeakXarc {
+            ptr: ptr::null_mut(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Take a weak reference to the value of `pointer`, or to nothing if it is null. See `Xarc::downgrade`.
+    #[must_use]
+    pub(crate) fn downgrade(pointer: &Xarc<T>) -> Self {
+        if pointer.is_null() {
+            return Self::new();
+        }
+        unsafe {
+            if !(*pointer.ptr).pool.is_null() {
+                panic!("Downgraded an Xarc that does not own its allocation!");
+            }
+            (*pointer.ptr).count.increment_weak();
+        }
+        WeakXarc {
+            ptr: pointer.ptr,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Take a strong reference to the value if it has not been dropped yet, or return null otherwise.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn upgrade(&self) -> Xarc<T> {
+        if !self.ptr.is_null() && unsafe { (*self.ptr).count.try_increment() }.is_ok() {
+            Xarc::init(self.ptr)
+        }
+        else {
+            Xarc::null()
+        }
+    }
+
+    /// The number of `Xarc`s that point to the value, or 0 if it has been dropped.
+    #[must_use]
+    pub fn strong_count(&self) -> usize {
+        if self.ptr.is_null() {
+            0
+        }
+        else {
+            unsafe {
+                (*self.ptr).count.load()
+            }
+        }
+    }
+
+    /// Check if `self` and `other` refer to the same allocation, or are both made with `new`.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Clone for WeakXarc<T> {
+    fn clone(&self) -> Self {
+        if !self.ptr.is_null() {
+            unsafe {
+                (*self.ptr).count.increment_weak();
+            }
+        }
+        WeakXarc {
+            ptr: self.ptr,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Default for WeakXarc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> Drop for WeakXarc<T> {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            release_weak::<T>(self.ptr);
+        }
+    }
+}
+
+impl<T: ?Sized + Send + Pointee> fmt::Debug for WeakXarc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeakXarc").field("ptr", &self.ptr).finish()
+    }
+}
+
+unsafe impl<T: ?Sized + Send + Pointee> Send for WeakXarc<T> {}
+unsafe impl<T: ?Sized + Send + Pointee> Sync for WeakXarc<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn xarc_weak_many_st_test() {
+        // Thousands of weak references fit even in a 32-bit count.
+        let value = Xarc::new(7);
+        let weak: Vec<WeakXarc<i32>> = (0..4000).map(|_| Xarc::downgrade(&value)).collect();
+        assert_eq!(Xarc::weak_count(&value), 4000);
+        assert!(weak.iter().all(|weak| weak.upgrade() == value));
+        drop(value);
+        assert!(weak.iter().all(|weak| weak.upgrade().is_null()));
+    }
+
+    #[test]
+    fn xarc_weak_outlives_value_st_test() {
+        use alloc::{string::String, vec};
+        use core::any::Any;
+
+        // The last weak reference frees each allocation with a layout computed without touching the dropped value.
+        let sized = Xarc::new([String::from("a"), String::from("b"), String::from("c")]);
+        let slice: Xarc<[String]> = Xarc::from(vec![String::from("d"); 5]);
+        let string: Xarc<str> = Xarc::from("efg");
+        let object: Xarc<dyn Any + Send + Sync> = crate::xarc_dyn!((0u8, String::from("h"), [0u64; 3]));
+        let weak = (Xarc::downgrade(&sized), Xarc::downgrade(&slice), Xarc::downgrade(&string), Xarc::downgrade(&object));
+        drop((sized, slice, string, object));
+        if cfg!(not(feature = "no-reclaim")) {
+            assert!(crate::quiescent::flush_until(|| weak.3.upgrade().is_null()));
+        }
+        drop(weak);
+    }
+
+    #[test]
+    fn xarc_weak_mt_test() {
+        use alloc::{string::String, vec};
+        use crate::atomic::AtomicXarc;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        use rayon::prelude::*;
+
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        struct Tracked;
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let shared = AtomicXarc::new(Tracked);
+        let weak = Xarc::downgrade(&shared.load(Ordering::Acquire));
+        (0..10000).into_par_iter().for_each(|_| {
+            let weak = weak.clone();
+            let upgraded = weak.upgrade();
+            assert!(Xarc::weak_count(&upgraded) >= 2);
+            drop(weak);
+            assert!(Xarc::downgrade(&upgraded).strong_count() >= 2);
+        });
+        assert_eq!(Xarc::weak_count(&shared.load(Ordering::Acquire)), 1);
+        assert_eq!(Xarc::weak_count(&Xarc::<Tracked>::null()), 0);
+        // Release the last strong reference here, so that its reclamation is deferred to this thread.
+        let _ = shared.swap(&Xarc::null(), Ordering::AcqRel);
+        if cfg!(not(feature = "no-reclaim")) {
+            assert!(crate::quiescent::flush_until(|| DROPPED.load(Ordering::Relaxed) == 1));
+            assert!(weak.upgrade().is_null());
+            assert_eq!(weak.strong_count(), 0);
+        }
+
+        let slice: Xarc<[String]> = Xarc::from(vec![String::from("a"), String::from("b")]);
+        let weak_slice = Xarc::downgrade(&slice);
+        assert_eq!(weak_slice.upgrade().iter().len(), 2);
+        drop(slice);
+        drop(weak_slice);
+    }
+}
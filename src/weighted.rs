@@ -0,0 +1,150 @@
+use super::{internal::*, pointer::*, repin::pin};
+use alloc::boxed::Box;
+use core::{cell::Cell, ptr};
+
+/// The weight given to a freshly created handle and added whenever a handle runs out of weight to split.
+/// It is scaled down where the strong count is narrow, so that the count has room for at least 2^14 refills.
+const WEIGHT: usize = if XarcCount::MAX >> 14 < 1 << 16 { (XarcCount::MAX >> 14) + 1 } else { 1 << 16 };
+
+/// `WeightedXarc` is a dereferenceable smart pointer using weighted reference counting.
+///
+/// Every handle carries a weight and the shared count holds the sum of the weights of all live handles.
+/// Cloning splits the weight of the handle being cloned without touching the shared count,
+/// so it is a non-atomic operation unless the handle has run out of weight to split.
+/// Dropping returns the weight of the handle to the shared count with a single atomic operation.
+///
+/// An `Xarc` is simply a handle with a weight of 1, so `WeightedXarc` interoperates with `Xarc`
+/// and `AtomicXarc` through `to_xarc` and `From<Xarc<T>>`.
+///
+/// Since cloning mutates the handle being cloned, `WeightedXarc` is `Send` but not `Sync`.
+/// Give each thread its own clone rather than sharing one handle by reference.
+///
+/// # Examples
+///
+/// ```
+/// use core::sync::atomic::Ordering;
+/// use xarc::{AtomicXarc, WeightedXarc};
+///
+/// let weighted = WeightedXarc::new(42);
+/// let clones: Vec<WeightedXarc<i64>> = (0..1000).map(|_| weighted.clone()).collect();
+/// assert!(clones.iter().all(|clone| *clone.maybe_deref().unwrap() == 42));
+///
+/// let atomic = AtomicXarc::from(&weighted.to_xarc());
+/// assert_eq!(atomic.load(Ordering::Acquire), weighted.to_xarc());
+/// ```
+#[derive(Debug)]
+pub struct WeightedXarc<T: Send> {
+    ptr: *mut XarcData<T>,
+    weight: Cell<usize>,
+}
+
+impl<T: Send> WeightedXarc<T> {
+    /// Initialize the smart pointer with `value`.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        WeightedXarc {
+            ptr: Box::into_raw(Box::new(XarcData::with_count(value, WEIGHT))),
+            weight: Cell::new(WEIGHT),
+        }
+    }
+
+    /// Initialize the smart pointer with null.
+    #[must_use]
+    pub fn null() -> Self {
+        WeightedXarc {
+            ptr: ptr::null_mut(),
+            weight: Cell::new(0),
+        }
+    }
+
+    /// Check if the smart pointer is null.
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Dereference the pointer only if it is not null.
+    /// None will be returned if it is null.
+    #[must_use]
+    pub fn maybe_deref(&self) -> Option<&T> {
+        if !self.ptr.is_null() {
+            unsafe {
+                Some(&(*self.ptr).value)
+            }
+        }
+        else {
+            None
+        }
+    }
+
+    /// Produce an `Xarc` pointing to the same value by splitting off a weight of 1.
+    #[must_use]
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn to_xarc(&self) -> Xarc<T> {
+        Xarc::init(thin(self.split(1)))
+    }
+
+    #[must_use]
+    fn split(&self, amount: usize) -> *mut XarcData<T> {
+        if !self.ptr.is_null() {
+            if self.weight.get() <= amount {
+                unguarded_increment_by(thin(self.ptr), WEIGHT);
+                self.weight.set(self.weight.get() + WEIGHT);
+            }
+            self.weight.set(self.weight.get() - amount);
+        }
+        self.ptr
+    }
+}
+
+impl<T: Send> Clone for WeightedXarc<T> {
+    fn clone(&self) -> Self {
+        let amount = if self.weight.get() > 1 { self.weight.get() / 2 } else { WEIGHT / 2 };
+        WeightedXarc {
+            ptr: self.split(amount),
+            weight: Cell::new(if self.ptr.is_null() { 0 } else { amount }),
+        }
+    }
+}
+
+impl<T: Send> Drop for WeightedXarc<T> {
+    fn drop(&mut self) {
+        decrement_by::<T>(thin(self.ptr), self.weight.get(), &pin());
+    }
+}
+
+impl<T: Send> From<Xarc<T>> for WeightedXarc<T> {
+    fn from(pointer: Xarc<T>) -> Self {
+        let ptr = pointer.into_ptr() as *mut XarcData<T>;
+        WeightedXarc {
+            ptr,
+            weight: Cell::new(if ptr.is_null() { 0 } else { 1 }),
+        }
+    }
+}
+
+impl<T: Send> PartialEq for WeightedXarc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T: Send> Eq for WeightedXarc<T> {}
+
+unsafe impl<T: Send> Send for WeightedXarc<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn xarc_weighted_clones_st_test() {
+        // Each handle that runs out of weight refills from the count, which must not overflow even with 32 bits.
+        let weighted = WeightedXarc::new(1);
+        let clones: Vec<WeightedXarc<i32>> = (0..20_000).map(|_| weighted.clone()).collect();
+        assert!(clones.iter().all(|clone| *clone == weighted));
+        let nested: Vec<WeightedXarc<i32>> = clones.iter().map(WeightedXarc::clone).collect();
+        assert_eq!(nested.len(), 20_000);
+    }
+}
@@ -0,0 +1,173 @@
+use super::{internal::*, pointer::*};
+use alloc::boxed::Box;
+use core::{fmt, mem::{self, MaybeUninit}, ptr, slice};
+
+/// `XarcSliceWriter` fills a slice in place before it is frozen into an `Xarc<[T]>` and published.
+///
+/// The allocation is made up front with room for exactly `capacity` elements and the writer tracks how many have been initialized,
+/// so large buffers are never built in a `Vec` only to be copied.
+/// Elements are appended with `push` or `extend`, or written directly through `spare_capacity_mut`,
+/// which also allows filling the remaining elements in parallel.
+/// Once every element is initialized, `finish` produces the `Xarc`.
+/// Dropping an unfinished writer drops the initialized elements and frees the allocation.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use xarc::{Xarc, XarcSliceWriter};
+///
+/// let mut writer = XarcSliceWriter::with_capacity(1024);
+/// writer.push(0);
+/// writer.spare_capacity_mut().par_iter_mut().enumerate().for_each(|(index, element)| {
+///     element.write(index + 1);
+/// });
+/// unsafe {
+///     writer.set_len(1024);
+/// }
+///
+/// let slice: Xarc<[usize]> = writer.finish().unwrap();
+/// assert!(slice.maybe_deref().unwrap().iter().enumerate().all(|(index, element)| *element == index));
+/// ```
+pub struct XarcSliceWriter<T: Send> {
+    ptr: *mut XarcData<[T]>,
+    len: usize,
+}
+
+impl<T: Send> XarcSliceWriter<T> {
+    /// Allocate room for exactly `capacity` elements, none of which are initialized.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        XarcSliceWriter {
+            ptr: unsafe { XarcData::allocate(capacity) },
+            len: 0,
+        }
+    }
+
+    /// The number of initialized elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if no elements are initialized.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of elements the finished slice will have.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        unsafe {
+            (*self.ptr).header.meta.len
+        }
+    }
+
+    /// Check if every element is initialized so that `finish` will succeed.
+    #[must_use]
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    /// Initialize the next element with `value`.
+    /// `value` is returned if the writer is already full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        match self.spare_capacity_mut().first_mut() {
+            Some(element) => {
+                element.write(value);
+                self.len += 1;
+                Ok(())
+            },
+            None => Err(value),
+        }
+    }
+
+    /// The initialized elements.
+    #[must_use]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe {
+            slice::from_raw_parts(self.elements(), self.len)
+        }
+    }
+
+    /// The initialized elements.
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            slice::from_raw_parts_mut(self.elements(), self.len)
+        }
+    }
+
+    /// The elements that are not yet initialized.
+    /// Call `set_len` after writing to them.
+    #[must_use]
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe {
+            slice::from_raw_parts_mut(self.elements().add(self.len) as *mut MaybeUninit<T>, self.capacity() - self.len)
+        }
+    }
+
+    /// Set the number of initialized elements.
+    ///
+    /// # Safety
+    /// - `len` must not exceed the capacity.
+    /// - The first `len` elements must be initialized.
+    pub unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.capacity());
+        self.len = len;
+    }
+
+    /// Freeze the slice into an `Xarc` once every element is initialized.
+    /// The writer is returned unchanged otherwise.
+    #[cfg_attr(feature = "debug-owners", track_caller)]
+    pub fn finish(self) -> Result<Xarc<[T]>, Self> {
+        if self.is_full() {
+            let ptr = self.ptr;
+            mem::forget(self);
+            Ok(Xarc::init(thin(ptr)))
+        }
+        else {
+            Err(self)
+        }
+    }
+
+    #[must_use]
+    fn elements(&self) -> *mut T {
+        unsafe {
+            ptr::addr_of_mut!((*self.ptr).value) as *mut T
+        }
+    }
+}
+
+impl<T: Send> Extend<T> for XarcSliceWriter<T> {
+    /// Initialize the next elements from `iter`.
+    ///
+    /// # Panics
+    /// - If `iter` yields more elements than there is room for.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            if self.push(value).is_err() {
+                panic!("XarcSliceWriter capacity exceeded!");
+            }
+        }
+    }
+}
+
+impl<T: Send> Drop for XarcSliceWriter<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.as_mut_slice());
+            drop(Box::from_raw(self.ptr as *mut XarcData<[MaybeUninit<T>]>));
+        }
+    }
+}
+
+impl<T: Send> fmt::Debug for XarcSliceWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XarcSliceWriter").field("len", &self.len).field("capacity", &self.capacity()).finish()
+    }
+}
+
+unsafe impl<T: Send> Send for XarcSliceWriter<T> {}
+unsafe impl<T: Send + Sync> Sync for XarcSliceWriter<T> {}
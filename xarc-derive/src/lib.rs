@@ -0,0 +1,148 @@
+//! # xarc-derive
+//!
+//! Derive macros for `xarc`. Enable the `derive` feature of `xarc` and use them from there.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, ext::IdentExt, parse_macro_input};
+
+/// Generate a hot-swappable mirror of a struct with named fields.
+///
+/// For `struct Config`, this generates `AtomicConfig`, which stores each field in its own `AtomicXarc`, and `ConfigSnapshot`,
+/// which holds an `Xarc` for every field.
+///
+/// - `AtomicConfig::new(Config)` and `From<Config>` initialize the mirror.
+/// - `load_x()` loads field `x` into an `Xarc` without coordinating with any other field.
+/// - `store_x(value)` allocates `value` and publishes it as field `x`.
+/// - `load()` returns a `ConfigSnapshot` of the fields as they were at a single point in time.
+/// - `swap(Config)` replaces every field at once and returns a `ConfigSnapshot` of the fields it replaced.
+///
+/// Loads are `Acquire` and writes are `AcqRel`.
+/// Writes and whole-snapshot loads are serialized by a sequence as in a seqlock,
+/// so a writer that is preempted while swapping briefly stalls them. Single-field loads never wait.
+///
+/// The accessors for each field share its visibility. Generic structs are not supported.
+///
+/// # Examples
+///
+/// ```
+/// use xarc::HotSwap;
+///
+/// #[derive(HotSwap)]
+/// struct Config {
+///     verbose: bool,
+///     name: String,
+/// }
+///
+/// let config = AtomicConfig::new(Config {verbose: false, name: "first".into()});
+/// config.store_verbose(true);
+/// assert!(*config.load_verbose().maybe_deref().unwrap());
+///
+/// let previous = config.swap(Config {verbose: false, name: "second".into()});
+/// assert!(*previous.verbose.maybe_deref().unwrap());
+/// assert_eq!(previous.name.maybe_deref().unwrap(), "first");
+///
+/// let current = config.load();
+/// assert!(!*current.verbose.maybe_deref().unwrap());
+/// assert_eq!(current.name.maybe_deref().unwrap(), "second");
+/// ```
+#[proc_macro_derive(HotSwap)]
+pub fn derive_hot_swap(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    hot_swap(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn hot_swap(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "`HotSwap` does not support generic structs"));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&data.fields, "`HotSwap` requires named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "`HotSwap` can only be derived for structs")),
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let atomic = format_ident!("Atomic{}", name);
+    let snapshot = format_ident!("{}Snapshot", name);
+    let names: Vec<_> = fields.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let field_vis: Vec<_> = fields.iter().map(|field| &field.vis).collect();
+    let loads: Vec<_> = names.iter().map(|name| format_ident!("load_{}", name.unraw())).collect();
+    let stores: Vec<_> = names.iter().map(|name| format_ident!("store_{}", name.unraw())).collect();
+
+    let atomic_doc = format!("A hot-swappable mirror of `{}`, generated by `#[derive(HotSwap)]`.", name);
+    let snapshot_doc = format!("The fields of `{}` as they were at a single point in time, generated by `#[derive(HotSwap)]`.", atomic);
+    let load_docs = names.iter().map(|name| format!("Load field `{}` into an `Xarc`.", name.unraw()));
+    let store_docs = names.iter().map(|name| format!("Allocate `value` and publish it as field `{}`.", name.unraw()));
+
+    Ok(quote! {
+        #[doc = #atomic_doc]
+        #vis struct #atomic {
+            #(#names: ::xarc::AtomicXarc<#types>,)*
+            __sequence: ::xarc::__private::HotSwapSequence,
+        }
+
+        #[doc = #snapshot_doc]
+        #[derive(Clone)]
+        #vis struct #snapshot {
+            #(#field_vis #names: ::xarc::Xarc<#types>,)*
+        }
+
+        impl #atomic {
+            /// Initialize every field from `value`.
+            #[must_use]
+            #vis fn new(value: #name) -> Self {
+                let #name {#(#names),*} = value;
+                #atomic {
+                    #(#names: ::xarc::AtomicXarc::new(#names),)*
+                    __sequence: ::xarc::__private::HotSwapSequence::new(),
+                }
+            }
+
+            /// Load every field such that the returned values were all present at a single point in time.
+            #[must_use]
+            #vis fn load(&self) -> #snapshot {
+                self.__sequence.read(|| #snapshot {
+                    #(#names: self.#names.load(::core::sync::atomic::Ordering::Acquire),)*
+                })
+            }
+
+            /// As an atomic operation, replace every field with those of `value`.
+            /// Returns the fields that were replaced.
+            #vis fn swap(&self, value: #name) -> #snapshot {
+                let #name {#(#names),*} = value;
+                #(let #names = ::xarc::Xarc::new(#names);)*
+                self.__sequence.write(|| #snapshot {
+                    #(#names: self.#names.swap(&#names, ::core::sync::atomic::Ordering::AcqRel),)*
+                })
+            }
+
+            #(
+                #[doc = #load_docs]
+                #[must_use]
+                #field_vis fn #loads(&self) -> ::xarc::Xarc<#types> {
+                    self.#names.load(::core::sync::atomic::Ordering::Acquire)
+                }
+
+                #[doc = #store_docs]
+                #field_vis fn #stores(&self, value: #types) {
+                    let value = ::xarc::Xarc::new(value);
+                    let _previous = self.__sequence.write(|| self.#names.swap(&value, ::core::sync::atomic::Ordering::AcqRel));
+                }
+            )*
+        }
+
+        impl ::core::convert::From<#name> for #atomic {
+            fn from(value: #name) -> Self {
+                #atomic::new(value)
+            }
+        }
+    })
+}